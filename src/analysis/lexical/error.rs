@@ -2,6 +2,7 @@ use colored::Colorize;
 
 pub enum LexingError {
     UnknownCharacter { line: usize, column: usize },
+    UnterminatedString { line: usize, column: usize },
 }
 
 impl std::fmt::Display for LexingError {
@@ -16,6 +17,15 @@ impl std::fmt::Display for LexingError {
                     format!("column {}", column).bold().red()
                 )
             }
+            LexingError::UnterminatedString { line, column } => {
+                write!(
+                    f,
+                    "{} starting at {}, {}",
+                    "unterminated string".bold().red(),
+                    format!("line {}", line).bold().red(),
+                    format!("column {}", column).bold().red()
+                )
+            }
         }
     }
 }
@@ -24,7 +34,7 @@ pub fn err_msg(error: LexingError) -> String {
     let prefix = "Failed in the lexing process:";
 
     let err_msg = match error {
-        LexingError::UnknownCharacter { .. } => {
+        LexingError::UnknownCharacter { .. } | LexingError::UnterminatedString { .. } => {
             format!("{error}")
         }
     };
@@ -2,12 +2,15 @@
 
 use std::{iter::Peekable, str::Chars};
 
-use crate::structures::tokens::Token;
+use crate::structures::{
+    parse_tree::{NumericType, Span},
+    tokens::Token,
+};
 
-mod error;
+pub mod error;
 use error::{err_msg, LexingError};
 
-pub fn lex(input: &str) -> Vec<Token> {
+pub fn lex(input: &str) -> Vec<(Token, Span)> {
     match Lexer::new(input).lex() {
         Ok(program) => program,
         Err(e) => {
@@ -20,24 +23,32 @@ pub fn lex(input: &str) -> Vec<Token> {
 pub struct Lexer<'s> {
     source: Peekable<Chars<'s>>,
     tokens: Vec<Token>,
+    /// Where each of the tokens starts.
+    spans: Vec<Span>,
     position: usize,
     line: usize,
     column: usize,
 }
 
-impl<'s> Lexer<'_> {
-    pub fn new(input: &str) -> Lexer {
+impl Lexer<'_> {
+    pub fn new(input: &str) -> Lexer<'_> {
         Lexer {
             source: input.chars().peekable(),
             tokens: vec![],
+            spans: vec![],
             position: 0,
             line: 1,
             column: 1,
         }
     }
 
-    fn lex(&mut self) -> Result<Vec<Token>, LexingError> {
+    pub fn lex(&mut self) -> Result<Vec<(Token, Span)>, LexingError> {
         while let Some(&c) = self.source.peek() {
+            let start = Span {
+                line: self.line,
+                column: self.column,
+            };
+            let count = self.tokens.len();
             if c.is_whitespace() {
                 // ignore whitespace
                 self.consume_char();
@@ -50,6 +61,9 @@ impl<'s> Lexer<'_> {
             } else if c.is_numeric() {
                 // scan number
                 self.consume_number();
+            } else if c == '"' {
+                // scan string
+                self.consume_string()?;
             } else {
                 match c {
                     '=' => {
@@ -58,6 +72,9 @@ impl<'s> Lexer<'_> {
                         if let Some('=') = self.source.peek() {
                             self.consume_char();
                             self.tokens.push(Token::Equal);
+                        } else if let Some('>') = self.source.peek() {
+                            self.consume_char();
+                            self.tokens.push(Token::FatArrow);
                         } else {
                             self.tokens.push(Token::Assignment);
                         }
@@ -93,6 +110,10 @@ impl<'s> Lexer<'_> {
                         self.consume_char();
                         self.tokens.push(Token::Colon);
                     }
+                    '?' => {
+                        self.consume_char();
+                        self.tokens.push(Token::QuestionMark);
+                    }
                     ';' => {
                         self.consume_char();
                         self.tokens.push(Token::SemiColon);
@@ -101,6 +122,10 @@ impl<'s> Lexer<'_> {
                         self.consume_char();
                         self.tokens.push(Token::Comma);
                     }
+                    '.' => {
+                        self.consume_char();
+                        self.tokens.push(Token::Dot);
+                    }
                     '+' => {
                         self.consume_char();
                         self.tokens.push(Token::Plus);
@@ -118,6 +143,10 @@ impl<'s> Lexer<'_> {
                         self.consume_char();
                         self.tokens.push(Token::Asterisk);
                     }
+                    '&' => {
+                        self.consume_char();
+                        self.tokens.push(Token::Ampersand);
+                    }
                     '/' => {
                         self.consume_char();
                         self.tokens.push(Token::Slash);
@@ -138,6 +167,14 @@ impl<'s> Lexer<'_> {
                         self.consume_char();
                         self.tokens.push(Token::CloseParen);
                     }
+                    '[' => {
+                        self.consume_char();
+                        self.tokens.push(Token::OpenBracket);
+                    }
+                    ']' => {
+                        self.consume_char();
+                        self.tokens.push(Token::CloseBracket);
+                    }
                     _ => {
                         return Err(LexingError::UnknownCharacter {
                             line: self.line,
@@ -146,8 +183,19 @@ impl<'s> Lexer<'_> {
                     }
                 }
             }
+
+            // whitespace and comments don't make a token
+            if self.tokens.len() > count {
+                self.spans.push(start);
+            }
         }
-        Ok(self.tokens.clone())
+
+        Ok(self
+            .tokens
+            .iter()
+            .cloned()
+            .zip(self.spans.iter().copied())
+            .collect())
     }
 
     fn consume_char(&mut self) -> Option<char> {
@@ -176,7 +224,7 @@ impl<'s> Lexer<'_> {
     fn consume_identifier(&mut self) {
         let mut ident = String::new();
         while let Some(&c) = self.source.peek() {
-            if c.is_alphabetic() || c == '_' {
+            if c.is_alphanumeric() || c == '_' {
                 ident.push(self.consume_char().unwrap());
             } else {
                 break;
@@ -187,25 +235,70 @@ impl<'s> Lexer<'_> {
             "nil" => self.tokens.push(Token::Nil),
             "let" => self.tokens.push(Token::Let),
             "const" => self.tokens.push(Token::Const),
+            "capture" => self.tokens.push(Token::Capture),
+            "where" => self.tokens.push(Token::Where),
             "if" => self.tokens.push(Token::If),
             "else" => self.tokens.push(Token::Else),
+            "true" => self.tokens.push(Token::True),
+            "false" => self.tokens.push(Token::False),
             "number" => self.tokens.push(Token::NumberKeyword),
             "int" => self.tokens.push(Token::IntKeyword),
+            "bool" => self.tokens.push(Token::BoolKeyword),
             "fn" => self.tokens.push(Token::FnKeyword),
-            _ => self.tokens.push(Token::Identifier(ident)),
+            "struct" => self.tokens.push(Token::Struct),
+            "enum" => self.tokens.push(Token::Enum),
+            "match" => self.tokens.push(Token::Match),
+            "comptime" => self.tokens.push(Token::Comptime),
+            "type" => self.tokens.push(Token::TypeKeyword),
+            "as" => self.tokens.push(Token::As),
+            "pub" => self.tokens.push(Token::PubKeyword),
+            "inline" => self.tokens.push(Token::Inline),
+            "import" => self.tokens.push(Token::Import),
+            _ => match NumericType::from_name(&ident) {
+                Some(numeric) => self.tokens.push(Token::NumericKeyword(numeric)),
+                None => self.tokens.push(Token::Identifier(ident)),
+            },
         }
     }
 
     fn consume_number(&mut self) {
+        // a number following a dot is a tuple index, like the `1` in `pair.1`
+        let is_index = matches!(self.tokens.last(), Some(Token::Dot));
+
         // scan number
         let mut number = String::new();
         while let Some(&c) = self.source.peek() {
             if c.is_numeric() {
                 number.push(self.consume_char().unwrap());
+            } else if c == '.' && !is_index && !number.contains('.') && self.digit_after_point() {
+                // decimal point of a floating point literal
+                number.push(self.consume_char().unwrap());
             } else {
                 break;
             }
         }
         self.tokens.push(Token::NumberLiteral(number));
     }
+
+    fn consume_string(&mut self) -> Result<(), LexingError> {
+        let (line, column) = (self.line, self.column);
+        self.consume_char(); // consume the opening quote
+
+        let mut string = String::new();
+        loop {
+            match self.consume_char() {
+                Some('"') => break,
+                Some('\n') | None => return Err(LexingError::UnterminatedString { line, column }),
+                Some(c) => string.push(c),
+            }
+        }
+        self.tokens.push(Token::StringLiteral(string));
+        Ok(())
+    }
+
+    fn digit_after_point(&self) -> bool {
+        let mut lookahead = self.source.clone();
+        lookahead.next(); // skip the '.'
+        matches!(lookahead.peek(), Some(c) if c.is_numeric())
+    }
 }
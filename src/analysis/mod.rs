@@ -1,3 +1,4 @@
 pub mod lexical;
-pub mod syntactic;
+pub mod modules;
 pub mod semantic;
+pub mod syntactic;
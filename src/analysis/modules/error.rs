@@ -0,0 +1,90 @@
+use colored::Colorize;
+
+use crate::analysis::lexical::error::LexingError;
+
+pub enum ModuleError {
+    NotFound { path: String, reason: String },
+    ImportCycle { cycle: Vec<String> },
+    Lexing { path: String, error: LexingError },
+    Parsing { path: String, error: String },
+    UnknownMember { module: String, member: String },
+    PrivateItem { module: String, item: String },
+    NameClash { name: String },
+}
+
+impl std::fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModuleError::NotFound { path, reason } => {
+                write!(
+                    f,
+                    "{} '{}': {}",
+                    "could not load module".bold().red(),
+                    path.bold(),
+                    reason
+                )
+            }
+            ModuleError::ImportCycle { cycle } => {
+                write!(
+                    f,
+                    "{} {}",
+                    "modules import each other:".bold().red(),
+                    cycle.join(" -> ").bold()
+                )
+            }
+            ModuleError::Lexing { path, error } => {
+                write!(
+                    f,
+                    "{} '{}': {}",
+                    "in module".bold().red(),
+                    path.bold(),
+                    error
+                )
+            }
+            ModuleError::Parsing { path, error } => {
+                write!(
+                    f,
+                    "{} '{}': {}",
+                    "in module".bold().red(),
+                    path.bold(),
+                    error
+                )
+            }
+            ModuleError::UnknownMember { module, member } => {
+                write!(
+                    f,
+                    "{} '{}' {} '{}'",
+                    "module".bold().red(),
+                    module.bold(),
+                    "has no item named".bold().red(),
+                    member.bold()
+                )
+            }
+            ModuleError::PrivateItem { module, item } => {
+                write!(
+                    f,
+                    "{} '{}' {} '{}' {}",
+                    "item".bold().red(),
+                    item.bold(),
+                    "of module".bold().red(),
+                    module.bold(),
+                    "is not public".bold().red()
+                )
+            }
+            ModuleError::NameClash { name } => {
+                write!(
+                    f,
+                    "{} '{}' {}",
+                    "module".bold().red(),
+                    name.bold(),
+                    "has the same name as another import or item".bold().red()
+                )
+            }
+        }
+    }
+}
+
+pub fn err_msg(error: ModuleError) -> String {
+    let prefix = "Failed to load the modules:";
+    format!("{} {}", prefix, error)
+}
@@ -0,0 +1,219 @@
+// MODULE LOADING //
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::structures::parse_tree::{Program, Statement};
+
+use super::{lexical::Lexer, syntactic::Parser};
+
+pub mod error;
+mod qualify;
+use error::{err_msg, ModuleError};
+use qualify::Qualifier;
+
+#[cfg(test)]
+mod tests;
+
+/// Loads the modules imported by `program`, the contents of the file at `path`, and links
+/// them into a single program.
+pub fn link(path: &str, program: &Program) -> Program {
+    match Linker::new().link(Path::new(path), program) {
+        Ok(program) => program,
+        Err(e) => {
+            println!("{}", err_msg(e));
+            std::process::exit(1);
+        }
+    }
+}
+
+/// A loaded module, whose top-level items are named `namespace.item` in the linked program.
+pub struct Module {
+    /// The namespace of the module, `None` for the root module whose items keep their names.
+    namespace: Option<String>,
+    /// The top-level items of the module, mapped to whether they are public.
+    items: HashMap<String, bool>,
+}
+
+impl Module {
+    /// The name of one of the module's items in the linked program.
+    fn qualified(&self, item: &str) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{namespace}.{item}"),
+            None => item.to_string(),
+        }
+    }
+}
+
+pub struct Linker {
+    /// The modules loaded so far, by canonical path.
+    modules: HashMap<PathBuf, Module>,
+    /// The modules currently being loaded, importers before the modules they import.
+    loading: Vec<PathBuf>,
+    namespaces: HashSet<String>,
+    /// The statements of the linked program, with those of a module after its imports'.
+    statements: Vec<Statement>,
+}
+
+impl Linker {
+    pub fn new() -> Self {
+        Linker {
+            modules: HashMap::new(),
+            loading: vec![],
+            namespaces: HashSet::new(),
+            statements: vec![],
+        }
+    }
+
+    pub fn link(mut self, path: &Path, program: &Program) -> Result<Program, ModuleError> {
+        let path = canonical(path)?;
+        self.load(path, program, None)?;
+
+        Ok(Program {
+            statements: self.statements,
+        })
+    }
+
+    fn load(
+        &mut self,
+        path: PathBuf,
+        program: &Program,
+        namespace: Option<String>,
+    ) -> Result<(), ModuleError> {
+        self.loading.push(path.clone());
+
+        // imports are resolved relative to the importing module
+        let directory = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut imports = HashMap::new();
+        for statement in &program.statements {
+            let Statement::Import { path, name } = statement else {
+                continue;
+            };
+            let imported = self.import(&directory.join(path), name)?;
+            if imports.insert(name.clone(), imported).is_some() {
+                return Err(ModuleError::NameClash { name: name.clone() });
+            }
+        }
+
+        let mut items = HashMap::new();
+        for statement in &program.statements {
+            for (name, public) in top_level_items(statement) {
+                if imports.contains_key(&name) {
+                    return Err(ModuleError::NameClash { name });
+                }
+                items.insert(name, public);
+            }
+        }
+
+        let module = Module { namespace, items };
+        let imports = imports
+            .into_iter()
+            .map(|(name, path)| (name, &self.modules[&path]))
+            .collect();
+        let mut qualifier = Qualifier::new(&module, imports);
+
+        let mut statements = vec![];
+        for statement in &program.statements {
+            if matches!(statement, Statement::Import { .. }) {
+                continue;
+            }
+            let mut statement = statement.clone();
+            qualifier.top_level(&mut statement)?;
+            statements.push(statement);
+        }
+
+        self.statements.append(&mut statements);
+        self.modules.insert(path, module);
+        self.loading.pop();
+        Ok(())
+    }
+
+    /// Loads the module at `path` unless it has been loaded before, returning its canonical
+    /// path.
+    fn import(&mut self, path: &Path, name: &str) -> Result<PathBuf, ModuleError> {
+        let canonical = canonical(path)?;
+
+        if let Some(start) = self.loading.iter().position(|path| *path == canonical) {
+            let cycle = self.loading[start..]
+                .iter()
+                .chain(std::iter::once(&canonical))
+                .map(|path| file_name(path))
+                .collect();
+            return Err(ModuleError::ImportCycle { cycle });
+        }
+
+        if !self.modules.contains_key(&canonical) {
+            let program = read_module(&canonical)?;
+            let namespace = self.namespace(name);
+            self.load(canonical.clone(), &program, Some(namespace))?;
+        }
+        Ok(canonical)
+    }
+
+    /// A namespace for a module imported as `name`, distinct from those of other modules.
+    fn namespace(&mut self, name: &str) -> String {
+        let mut namespace = name.to_string();
+        let mut suffix = 1;
+        while self.namespaces.contains(&namespace) {
+            suffix += 1;
+            namespace = format!("{name}{suffix}");
+        }
+        self.namespaces.insert(namespace.clone());
+        namespace
+    }
+}
+
+impl Default for Linker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn canonical(path: &Path) -> Result<PathBuf, ModuleError> {
+    path.canonicalize().map_err(|e| ModuleError::NotFound {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn read_module(path: &Path) -> Result<Program, ModuleError> {
+    let source = std::fs::read_to_string(path).map_err(|e| ModuleError::NotFound {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    })?;
+
+    let tokens = Lexer::new(&source)
+        .lex()
+        .map_err(|error| ModuleError::Lexing {
+            path: file_name(path),
+            error,
+        })?;
+
+    Parser::new(tokens)
+        .parse_program()
+        .map_err(|error| ModuleError::Parsing {
+            path: file_name(path),
+            error,
+        })
+}
+
+/// The names a top-level statement declares, along with whether they are public.
+fn top_level_items(statement: &Statement) -> Vec<(String, bool)> {
+    match statement {
+        Statement::Declaration { public, name, .. }
+        | Statement::FuncDeclaration { public, name, .. }
+        | Statement::TypeDeclaration { public, name, .. } => vec![(name.clone(), *public)],
+        Statement::Destructuring { names, .. } => {
+            names.iter().map(|name| (name.clone(), false)).collect()
+        }
+        _ => vec![],
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analysis::semantic::patterns::bindings,
+    structures::parse_tree::{
+        Block, ConditionalBlock, Expression, If, Param, Pattern, Statement, Type, TypeDefinition,
+    },
+};
+
+use super::{error::ModuleError, Module};
+
+/// Renames the items of a module and the references to them to their names in the linked
+/// program, resolving `module.item` through the module's imports.
+pub struct Qualifier<'m> {
+    module: &'m Module,
+    imports: HashMap<String, &'m Module>,
+    /// The names bound inside of functions and blocks, which shadow items and imports.
+    locals: Vec<HashSet<String>>,
+}
+
+impl<'m> Qualifier<'m> {
+    pub fn new(module: &'m Module, imports: HashMap<String, &'m Module>) -> Self {
+        Qualifier {
+            module,
+            imports,
+            locals: vec![],
+        }
+    }
+
+    pub fn top_level(&mut self, statement: &mut Statement) -> Result<(), ModuleError> {
+        match statement {
+            Statement::Declaration { name, .. }
+            | Statement::FuncDeclaration { name, .. }
+            | Statement::TypeDeclaration { name, .. } => *name = self.module.qualified(name),
+            Statement::Destructuring { names, .. } => {
+                for name in names {
+                    *name = self.module.qualified(name);
+                }
+            }
+            _ => {}
+        }
+        self.statement(statement)
+    }
+
+    fn is_local(&self, name: &str) -> bool {
+        self.locals.iter().any(|scope| scope.contains(name))
+    }
+
+    fn declare(&mut self, name: &str) {
+        if let Some(scope) = self.locals.last_mut() {
+            scope.insert(name.to_string());
+        }
+    }
+
+    /// The name an unqualified reference refers to.
+    fn own(&self, name: &str) -> String {
+        if !self.is_local(name) && self.module.items.contains_key(name) {
+            self.module.qualified(name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// The name `module.member` refers to, if `module` is an import.
+    fn member(&self, module: &str, member: &str) -> Result<Option<String>, ModuleError> {
+        if self.is_local(module) {
+            return Ok(None);
+        }
+        let Some(imported) = self.imports.get(module) else {
+            return Ok(None);
+        };
+
+        match imported.items.get(member) {
+            Some(true) => Ok(Some(imported.qualified(member))),
+            Some(false) => Err(ModuleError::PrivateItem {
+                module: module.to_string(),
+                item: member.to_string(),
+            }),
+            None => Err(ModuleError::UnknownMember {
+                module: module.to_string(),
+                member: member.to_string(),
+            }),
+        }
+    }
+
+    /// The name a type name, qualified like `shapes.Circle` or not, refers to.
+    fn type_name(&self, name: &str) -> Result<String, ModuleError> {
+        match name.split_once('.') {
+            Some((module, member)) => Ok(self.member(module, member)?.unwrap_or(name.to_string())),
+            None => Ok(self.own(name)),
+        }
+    }
+
+    fn statements(&mut self, statements: &mut [Statement]) -> Result<(), ModuleError> {
+        // functions and types may be used before they are declared
+        for statement in statements.iter() {
+            if let Statement::FuncDeclaration { name, .. }
+            | Statement::TypeDeclaration { name, .. } = statement
+            {
+                self.declare(name);
+            }
+        }
+
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &mut Statement) -> Result<(), ModuleError> {
+        match statement {
+            Statement::Declaration {
+                name,
+                datatype,
+                value,
+                ..
+            } => {
+                self.datatype(datatype)?;
+                self.expression(value)?;
+                self.declare(name);
+            }
+            Statement::Destructuring {
+                names,
+                datatype,
+                value,
+                ..
+            } => {
+                self.datatype(datatype)?;
+                self.expression(value)?;
+                for name in names {
+                    self.declare(name);
+                }
+            }
+            Statement::PlaceAssignment { target, value } => {
+                self.expression(target)?;
+                self.expression(value)?;
+            }
+            Statement::ReAssignment { name, value, .. } => {
+                *name = self.own(name);
+                self.expression(value)?;
+            }
+            Statement::FuncDeclaration {
+                parameters,
+                return_type,
+                body,
+                ..
+            } => self.function(parameters, return_type, body)?,
+            Statement::TypeDeclaration { definition, .. } => self.definition(definition)?,
+            Statement::ExpressionStatement(expression) => self.expression(expression)?,
+            Statement::IfStatement(if_statement) => self.if_expression(if_statement)?,
+            Statement::Import { .. } => unreachable!("imports only appear at the top level"),
+        }
+        Ok(())
+    }
+
+    fn function(
+        &mut self,
+        parameters: &mut [Param],
+        return_type: &mut Type,
+        body: &mut Block,
+    ) -> Result<(), ModuleError> {
+        // parameters may be types used by the other parameters and the return type
+        self.locals.push(HashSet::new());
+        for Param::Parameter { name, .. } in parameters.iter() {
+            self.declare(name);
+        }
+        for Param::Parameter { datatype, .. } in parameters {
+            self.datatype(datatype)?;
+        }
+        self.datatype(return_type)?;
+        self.block(body)?;
+        self.locals.pop();
+        Ok(())
+    }
+
+    fn block(&mut self, block: &mut Block) -> Result<(), ModuleError> {
+        self.locals.push(HashSet::new());
+        self.statements(&mut block.statements)?;
+        self.expression(&mut block.return_value)?;
+        self.locals.pop();
+        Ok(())
+    }
+
+    fn if_expression(&mut self, if_expression: &mut If) -> Result<(), ModuleError> {
+        let conditional_blocks = std::iter::once(&mut if_expression.if_block)
+            .chain(if_expression.else_if_blocks.iter_mut());
+        for ConditionalBlock { condition, block } in conditional_blocks {
+            self.expression(condition)?;
+            self.block(block)?;
+        }
+        if let Some(else_block) = &mut if_expression.else_block {
+            self.block(else_block)?;
+        }
+        Ok(())
+    }
+
+    fn definition(&mut self, definition: &mut TypeDefinition) -> Result<(), ModuleError> {
+        match definition {
+            TypeDefinition::Struct { fields, methods } => {
+                for field in fields {
+                    self.datatype(&mut field.datatype)?;
+                }
+                self.methods(methods)
+            }
+            TypeDefinition::Enum { variants, methods } => {
+                for payload in variants
+                    .iter_mut()
+                    .filter_map(|variant| variant.payload.as_mut())
+                {
+                    self.datatype(payload)?;
+                }
+                self.methods(methods)
+            }
+            TypeDefinition::Alias(datatype) => self.datatype(datatype),
+        }
+    }
+
+    fn methods(&mut self, methods: &mut [Statement]) -> Result<(), ModuleError> {
+        for method in methods {
+            if let Statement::FuncDeclaration {
+                parameters,
+                return_type,
+                body,
+                ..
+            } = method
+            {
+                self.function(parameters, return_type, body)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn pattern(&mut self, pattern: &mut Pattern) -> Result<(), ModuleError> {
+        match pattern {
+            Pattern::Variant {
+                enum_name, payload, ..
+            } => {
+                if let Some(enum_name) = enum_name {
+                    *enum_name = self.type_name(enum_name)?;
+                }
+                if let Some(payload) = payload {
+                    self.pattern(payload)?;
+                }
+            }
+            Pattern::Tuple(elements) => {
+                for element in elements {
+                    self.pattern(element)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn datatype(&mut self, datatype: &mut Type) -> Result<(), ModuleError> {
+        match datatype {
+            Type::Named(name) => *name = self.type_name(name)?,
+            Type::Instance(call) => {
+                call.factory = self.type_name(&call.factory)?;
+                for argument in &mut call.arguments {
+                    self.datatype(argument)?;
+                }
+            }
+            Type::Tuple(fields) => {
+                for field in fields {
+                    self.datatype(&mut field.datatype)?;
+                }
+            }
+            Type::Optional(datatype) | Type::Comptime(datatype) => self.datatype(datatype)?,
+            Type::Array { element, .. } => self.datatype(element)?,
+            Type::Pointer { pointee, .. } => self.datatype(pointee)?,
+            Type::FuncType {
+                parameters,
+                return_type,
+            } => {
+                for Param::Parameter { datatype, .. } in parameters {
+                    self.datatype(datatype)?;
+                }
+                self.datatype(return_type)?;
+            }
+            Type::Numeric(_)
+            | Type::Bool
+            | Type::Nil
+            | Type::Struct(_)
+            | Type::Enum(_)
+            | Type::Type => {}
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &mut Expression) -> Result<(), ModuleError> {
+        match expression {
+            Expression::Identifier { name, .. } => *name = self.own(name),
+            Expression::FieldAccess { value, field } => {
+                if let Expression::Identifier { name: module, span } = &**value {
+                    if let Some(name) = self.member(module, field)? {
+                        *expression = Expression::Identifier { name, span: *span };
+                        return Ok(());
+                    }
+                }
+                self.expression(value)?;
+            }
+            Expression::MethodCall {
+                receiver,
+                method,
+                arguments,
+                span,
+            } => {
+                for argument in arguments.iter_mut() {
+                    self.expression(argument)?;
+                }
+                if let Expression::Identifier { name: module, .. } = &**receiver {
+                    if let Some(name) = self.member(module, method)? {
+                        *expression = Expression::FunctionCall {
+                            name,
+                            arguments: std::mem::take(arguments),
+                            span: *span,
+                            tail: false,
+                        };
+                        return Ok(());
+                    }
+                }
+                self.expression(receiver)?;
+            }
+            Expression::FunctionCall {
+                name, arguments, ..
+            } => {
+                *name = self.own(name);
+                for argument in arguments {
+                    self.expression(argument)?;
+                }
+            }
+            Expression::StructLiteral { name, fields } => {
+                if let Some(name) = name {
+                    *name = self.type_name(name)?;
+                }
+                for field in fields {
+                    self.expression(&mut field.value)?;
+                }
+            }
+            Expression::Variant {
+                enum_name, payload, ..
+            } => {
+                *enum_name = self.type_name(enum_name)?;
+                if let Some(payload) = payload {
+                    self.expression(payload)?;
+                }
+            }
+            Expression::Addition {
+                augend: left,
+                addend: right,
+                ..
+            }
+            | Expression::Subtraction {
+                minuend: left,
+                subtrahend: right,
+                ..
+            }
+            | Expression::Multiplication {
+                multiplicant: left,
+                multiplier: right,
+                ..
+            }
+            | Expression::Division {
+                dividend: left,
+                divisor: right,
+                ..
+            }
+            | Expression::LessThan { left, right }
+            | Expression::GreaterThan { left, right }
+            | Expression::LessThanOrEqual { left, right }
+            | Expression::GreaterThanOrEqual { left, right }
+            | Expression::Equal { left, right }
+            | Expression::NotEqual { left, right }
+            | Expression::Default {
+                value: left,
+                fallback: right,
+            }
+            | Expression::Index {
+                value: left,
+                index: right,
+                ..
+            } => {
+                self.expression(left)?;
+                self.expression(right)?;
+            }
+            Expression::Cast { value, datatype } => {
+                self.expression(value)?;
+                self.datatype(datatype)?;
+            }
+            Expression::Tuple(elements) => {
+                for element in elements {
+                    self.expression(&mut element.value)?;
+                }
+            }
+            Expression::Array(elements) => {
+                for element in elements {
+                    self.expression(element)?;
+                }
+            }
+            Expression::AddressOf(value) | Expression::Deref(value) => self.expression(value)?,
+            Expression::Block {
+                statements,
+                return_value,
+            } => {
+                self.locals.push(HashSet::new());
+                self.statements(statements)?;
+                self.expression(return_value)?;
+                self.locals.pop();
+            }
+            Expression::Function {
+                parameters,
+                return_type,
+                body,
+                ..
+            } => self.function(parameters, return_type, body)?,
+            Expression::If(if_expression) => self.if_expression(if_expression)?,
+            Expression::Match(match_expression) => {
+                self.expression(&mut match_expression.value)?;
+                for arm in &mut match_expression.arms {
+                    self.pattern(&mut arm.pattern)?;
+                    self.locals
+                        .push(bindings(&arm.pattern).into_iter().cloned().collect());
+                    self.expression(&mut arm.value)?;
+                    self.locals.pop();
+                }
+            }
+            Expression::Comptime(block) => self.block(block)?,
+            Expression::Type(definition) => self.definition(definition)?,
+            Expression::NumberLiteral(_)
+            | Expression::Number(_)
+            | Expression::Boolean(_)
+            | Expression::Nil => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+
+use crate::{
+    analysis::{lexical::lex, semantic::Validator, syntactic::parser::Parser},
+    structures::parse_tree::{Program, Statement},
+};
+
+use super::{error::ModuleError, Linker};
+
+/// Writes the files of a program to a directory of their own, returning the directory.
+fn write_files(directory: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("honey-{}-{}", directory, std::process::id()));
+    std::fs::create_dir_all(&directory).expect("the directory is created");
+    for (name, source) in files {
+        std::fs::write(directory.join(name), source).expect("the file is written");
+    }
+    directory
+}
+
+/// Links the program in `main.hon` with the modules it imports, all of which must parse.
+fn link(directory: &str, files: &[(&str, &str)]) -> Result<Program, ModuleError> {
+    let path = write_files(directory, files).join("main.hon");
+    let source = std::fs::read_to_string(&path).expect("the file is read");
+    let program = Parser::new(lex(&source))
+        .parse_program()
+        .expect("the program parses");
+    Linker::new().link(&path, &program)
+}
+
+fn linked(directory: &str, files: &[(&str, &str)]) -> Program {
+    match link(directory, files) {
+        Ok(program) => program,
+        Err(e) => panic!("the modules fail to link: {}", e),
+    }
+}
+
+fn names(program: &Program) -> Vec<&str> {
+    program
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Declaration { name, .. }
+            | Statement::FuncDeclaration { name, .. }
+            | Statement::TypeDeclaration { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+const SHAPES: &str = "
+pub const Pt = struct { x: u64 };
+pub fn origin(): Pt { .{ x = 0 } }
+fn helper(): u64 { 1 }
+pub const unit: u64 = helper();
+";
+
+#[test]
+fn imported_items_are_qualified_by_their_module() {
+    let program = linked(
+        "qualified",
+        &[
+            ("shapes.hon", SHAPES),
+            (
+                "main.hon",
+                "import \"shapes.hon\" as shapes;
+fn helper(p: shapes.Pt): u64 { p.x + shapes.unit }
+fn main(): nil { const y: u64 = helper(shapes.origin()); }
+",
+            ),
+        ],
+    );
+    // the items of the importing module keep their names, with no clash between helpers
+    assert_eq!(
+        names(&program),
+        [
+            "shapes.Pt",
+            "shapes.origin",
+            "shapes.helper",
+            "shapes.unit",
+            "helper",
+            "main"
+        ]
+    );
+    if let Err(e) = Validator::new().validate(&program) {
+        panic!("the linked program is invalid: {}", e);
+    }
+}
+
+#[test]
+fn private_items_stay_in_their_module() {
+    let error = link(
+        "private",
+        &[
+            ("shapes.hon", SHAPES),
+            (
+                "main.hon",
+                "import \"shapes.hon\" as shapes;
+fn main(): nil { const y: u64 = shapes.helper(); }
+",
+            ),
+        ],
+    );
+    assert!(matches!(
+        error,
+        Err(ModuleError::PrivateItem { module, item }) if module == "shapes" && item == "helper"
+    ));
+
+    let error = link(
+        "unknown",
+        &[
+            ("shapes.hon", SHAPES),
+            (
+                "main.hon",
+                "import \"shapes.hon\" as shapes;
+fn main(): nil { const y: u64 = shapes.nothing(); }
+",
+            ),
+        ],
+    );
+    assert!(matches!(
+        error,
+        Err(ModuleError::UnknownMember { module, member }) if module == "shapes" && member == "nothing"
+    ));
+}
+
+#[test]
+fn imports_are_checked() {
+    let error = link(
+        "cycle",
+        &[
+            (
+                "a.hon",
+                "import \"main.hon\" as main;\npub fn f(): u64 { 1 }\n",
+            ),
+            ("main.hon", "import \"a.hon\" as a;\nfn main(): nil {}\n"),
+        ],
+    );
+    assert!(matches!(
+        error,
+        Err(ModuleError::ImportCycle { cycle }) if cycle == ["main.hon", "a.hon", "main.hon"]
+    ));
+
+    let error = link(
+        "clash",
+        &[
+            ("shapes.hon", SHAPES),
+            (
+                "main.hon",
+                "import \"shapes.hon\" as shapes;\nfn shapes(): nil {}\n",
+            ),
+        ],
+    );
+    assert!(matches!(error, Err(ModuleError::NameClash { name }) if name == "shapes"));
+
+    let error = link("missing", &[("main.hon", "import \"nowhere.hon\" as n;\n")]);
+    assert!(matches!(error, Err(ModuleError::NotFound { .. })));
+}
@@ -0,0 +1,606 @@
+// COMPILE-TIME EVALUATION //
+
+use std::collections::HashMap;
+
+use crate::structures::parse_tree::{
+    Block, Expression, FactoryCall, If, NumericType, Param, Span, Statement, TupleElement, Type,
+    TypeDefinition,
+};
+
+use super::{
+    error::SemanticError,
+    folding::{cast_number, fold_binary, fold_literal, integer, type_of_constant, unsigned_value},
+    patterns::{bind_constant, bindings},
+};
+
+/// The number of expressions a single `comptime` block may evaluate before it is given up on.
+pub const STEP_LIMIT: usize = 1_000_000;
+/// The number of nested calls a single `comptime` block may make.
+pub const DEPTH_LIMIT: usize = 1_000;
+/// The stack size of the thread evaluating `comptime` blocks, which has to fit `DEPTH_LIMIT`
+/// nested calls.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// A function computing a type at compile time from `comptime type` parameters, like
+/// `fn Maybe(T: comptime type): type { enum { Some(T), None } }`.
+#[derive(Debug, Clone)]
+pub struct Factory {
+    pub parameters: Vec<String>,
+    pub definition: TypeDefinition,
+}
+
+/// A struct or an enum created by a type factory, along with the types its parameters were
+/// bound to when creating it.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub name: String,
+    pub definition: TypeDefinition,
+    pub arguments: Vec<(String, Type)>,
+}
+
+/// Whether a statement declares a type factory, which has no runtime representation.
+pub fn is_type_factory(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::FuncDeclaration {
+            return_type: Type::Type,
+            ..
+        }
+    )
+}
+
+/// The name and the factory a statement declares, if it declares one.
+pub fn type_factory(statement: &Statement) -> Result<Option<(&String, Factory)>, SemanticError> {
+    let Statement::FuncDeclaration {
+        name,
+        parameters,
+        return_type: Type::Type,
+        body,
+        ..
+    } = statement
+    else {
+        return Ok(None);
+    };
+    let Expression::Type(definition) = &*body.return_value else {
+        unreachable!("the bodies of type factories are parsed as types")
+    };
+
+    let mut names = vec![];
+    for Param::Parameter {
+        name: parameter,
+        datatype,
+    } in parameters
+    {
+        if *datatype != Type::Comptime(Box::new(Type::Type)) {
+            return Err(SemanticError::ComptimeParameter {
+                function: name.clone(),
+                parameter: parameter.clone(),
+            });
+        }
+        names.push(parameter.clone());
+    }
+
+    Ok(Some((
+        name,
+        Factory {
+            parameters: names,
+            definition: (**definition).clone(),
+        },
+    )))
+}
+
+/// The name of the type a factory creates for some arguments, like `Maybe(int)`.
+pub fn instance_name(factory: &str, arguments: &[Type]) -> String {
+    Type::Instance(Box::new(FactoryCall {
+        factory: factory.to_string(),
+        arguments: arguments.to_vec(),
+    }))
+    .to_string()
+}
+
+/// A validated function, which `comptime` blocks may call.
+#[derive(Debug, Clone)]
+pub struct ComptimeFunction {
+    pub parameters: Vec<String>,
+    pub body: Block,
+}
+
+/// The functions `comptime` blocks may call, looked up by name when they are called.
+pub trait Functions {
+    fn function(&mut self, name: &str) -> Result<Option<ComptimeFunction>, SemanticError>;
+}
+
+/// Evaluates a validated `comptime` block to a constant. On failure, the error carries the
+/// calls that led to it, innermost first.
+pub fn evaluate(
+    block: &Block,
+    functions: &mut (impl Functions + Send),
+) -> Result<Expression, SemanticError> {
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, || interpret(block, functions))
+            .expect("failed to spawn the compile-time evaluator")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+fn interpret(block: &Block, functions: &mut dyn Functions) -> Result<Expression, SemanticError> {
+    let mut interpreter = Interpreter {
+        functions,
+        calls: vec![Call {
+            name: "comptime block".to_string(),
+            scopes: vec![HashMap::new()],
+        }],
+        steps: 0,
+    };
+
+    let value = interpreter.block(block).and_then(|value| match value {
+        value if value.is_constant() => Ok(value),
+        value => Err(SemanticError::NotComptime {
+            what: describe(&value),
+        }),
+    });
+    value.map_err(|error| SemanticError::ComptimeFailure {
+        error: Box::new(error),
+        stack: interpreter
+            .calls
+            .iter()
+            .rev()
+            .map(|call| call.name.clone())
+            .collect(),
+    })
+}
+
+/// A function being evaluated, with the scopes of its variables, innermost last.
+struct Call {
+    /// The function along with its arguments, for diagnostics.
+    name: String,
+    scopes: Vec<HashMap<String, Expression>>,
+}
+
+/// Step of the path from a variable to the element assigned to.
+enum Place {
+    Element(usize),
+    Field(usize),
+}
+
+/// A tree-walking interpreter over validated code, where values are constant expressions like
+/// the ones the folder produces, plus functions.
+///
+/// Calls and scopes are only left on success, so the call stack is still intact when an error
+/// is returned.
+struct Interpreter<'a> {
+    functions: &'a mut dyn Functions,
+    calls: Vec<Call>,
+    steps: usize,
+}
+
+impl Interpreter<'_> {
+    fn call_frame(&mut self) -> &mut Call {
+        self.calls.last_mut().expect("there is always a call")
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Expression> {
+        let call = self.calls.last().expect("there is always a call");
+        call.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Result<&mut Expression, SemanticError> {
+        self.call_frame()
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+            // anything outside of the current call is state a pure function can't change
+            .ok_or_else(|| SemanticError::NotComptime {
+                what: format!("'{}'", name),
+            })
+    }
+
+    fn declare(&mut self, name: &str, value: Expression) {
+        let scope = self.call_frame().scopes.last_mut();
+        scope
+            .expect("there is always a scope")
+            .insert(name.to_string(), value);
+    }
+
+    fn block(&mut self, block: &Block) -> Result<Expression, SemanticError> {
+        self.call_frame().scopes.push(HashMap::new());
+        for statement in &block.statements {
+            self.statement(statement)?;
+        }
+        let value = self.expression(&block.return_value)?;
+        self.call_frame().scopes.pop();
+        Ok(value)
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), SemanticError> {
+        match statement {
+            Statement::Declaration { name, value, .. } => {
+                let value = self.expression(value)?;
+                self.declare(name, value);
+            }
+            Statement::Destructuring { names, value, .. } => {
+                let Expression::Tuple(elements) = self.expression(value)? else {
+                    unreachable!("only tuples are destructured")
+                };
+                for (name, element) in names.iter().zip(elements) {
+                    self.declare(name, element.value);
+                }
+            }
+            Statement::ReAssignment { name, value, .. } => {
+                let value = self.expression(value)?;
+                *self.lookup_mut(name)? = value;
+            }
+            Statement::PlaceAssignment { target, value } => {
+                let value = self.expression(value)?;
+                let (name, path) = self.place(target)?;
+                let mut place = self.lookup_mut(&name)?;
+                for step in path {
+                    place = match (step, place) {
+                        (Place::Element(index), Expression::Array(elements)) => {
+                            let length = elements.len();
+                            elements.get_mut(index).ok_or_else(|| {
+                                SemanticError::IndexOutOfBounds {
+                                    index: index.to_string(),
+                                    length: Some(length),
+                                }
+                            })?
+                        }
+                        (
+                            Place::Field(index),
+                            Expression::Tuple(elements)
+                            | Expression::StructLiteral {
+                                fields: elements, ..
+                            },
+                        ) => &mut elements[index].value,
+                        _ => unreachable!("the validator only allows assigning to elements"),
+                    };
+                }
+                *place = value;
+            }
+            Statement::ExpressionStatement(expression) => {
+                self.expression(expression)?;
+            }
+            Statement::IfStatement(if_statement) => {
+                self.if_expression(if_statement)?;
+            }
+            // validated functions are looked up by name, types don't do anything at runtime
+            Statement::FuncDeclaration { .. } | Statement::TypeDeclaration { .. } => {}
+            Statement::Import { .. } => unreachable!("imports are resolved by the module loader"),
+        }
+        Ok(())
+    }
+
+    /// Finds the variable and the path to the element an index assignment assigns to.
+    fn place(&mut self, target: &Expression) -> Result<(String, Vec<Place>), SemanticError> {
+        match target {
+            Expression::Identifier { name, .. } => Ok((name.clone(), vec![])),
+            Expression::Index { value, index, .. } => {
+                let index = self.index(index)?;
+                let (name, mut path) = self.place(value)?;
+                path.push(Place::Element(index));
+                Ok((name, path))
+            }
+            Expression::FieldAccess { value, field } => {
+                let (name, mut path) = self.place(value)?;
+                path.push(Place::Field(field_index(field)));
+                Ok((name, path))
+            }
+            Expression::Deref(_) => Err(SemanticError::NotComptime {
+                what: "a pointer".to_string(),
+            }),
+            _ => unreachable!("the validator only allows assigning to elements of variables"),
+        }
+    }
+
+    fn index(&mut self, index: &Expression) -> Result<usize, SemanticError> {
+        match self.expression(index)? {
+            Expression::Number(number) => Ok(unsigned_value(&number)
+                .and_then(|index| usize::try_from(index).ok())
+                .expect("indices are converted to usize")),
+            _ => unreachable!("indices are numbers"),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<Expression, SemanticError> {
+        self.steps += 1;
+        if self.steps > STEP_LIMIT {
+            return Err(SemanticError::ComptimeStepLimit { limit: STEP_LIMIT });
+        }
+
+        if let Some((operator, left, right)) = expression.as_binary() {
+            let left = self.expression(left)?;
+            let right = self.expression(right)?;
+            return Ok(fold_binary(operator, &left, &right, expression.span())?
+                .expect("the operands of binary operators are constants"));
+        }
+
+        match expression {
+            Expression::NumberLiteral(literal) => fold_literal(literal),
+            Expression::Number(_) | Expression::Boolean(_) | Expression::Nil => {
+                Ok(expression.clone())
+            }
+            Expression::Identifier { name, .. } => match self.lookup(name) {
+                Some(value) => Ok(value.clone()),
+                // functions are values referring to them by name
+                None => match self.functions.function(name)? {
+                    Some(_) => Ok(expression.clone()),
+                    None => Err(SemanticError::NotComptime {
+                        what: format!("'{}'", name),
+                    }),
+                },
+            },
+            Expression::Cast { value, datatype } => match (self.expression(value)?, datatype) {
+                (Expression::Number(number), Type::Numeric(to)) => {
+                    Ok(Expression::Number(cast_number(&number, *to)))
+                }
+                (Expression::Boolean(boolean), Type::Numeric(to)) => Ok(Expression::Number(
+                    integer(*to, boolean as i128).expect("booleans fit any integer"),
+                )),
+                (value, _) => Ok(value),
+            },
+            Expression::Default { value, fallback } => match self.expression(value)? {
+                Expression::Nil => self.expression(fallback),
+                value => Ok(value),
+            },
+            Expression::Tuple(elements) => Ok(Expression::Tuple(self.elements(elements)?)),
+            Expression::StructLiteral { name, fields } => Ok(Expression::StructLiteral {
+                name: name.clone(),
+                fields: self.elements(fields)?,
+            }),
+            Expression::Array(elements) => elements
+                .iter()
+                .map(|element| self.expression(element))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Expression::Array),
+            Expression::Index { value, index, .. } => {
+                let Expression::Array(mut elements) = self.expression(value)? else {
+                    unreachable!("only arrays are indexed")
+                };
+                let index = self.index(index)?;
+                if index >= elements.len() {
+                    return Err(SemanticError::IndexOutOfBounds {
+                        index: index.to_string(),
+                        length: Some(elements.len()),
+                    });
+                }
+                Ok(elements.swap_remove(index))
+            }
+            Expression::FieldAccess { value, field } => match self.expression(value)? {
+                Expression::Array(elements) => Ok(Expression::Number(
+                    integer(NumericType::USize, elements.len() as i128).expect("lengths fit usize"),
+                )),
+                Expression::Tuple(mut elements)
+                | Expression::StructLiteral {
+                    fields: mut elements,
+                    ..
+                } => Ok(elements.swap_remove(field_index(field)).value),
+                _ => unreachable!("fields are only accessed on tuples, structs and arrays"),
+            },
+            Expression::FunctionCall {
+                name,
+                arguments,
+                span,
+                ..
+            } => {
+                let callee = self
+                    .lookup(name)
+                    .cloned()
+                    .unwrap_or_else(|| Expression::Identifier {
+                        name: name.clone(),
+                        span: *span,
+                    });
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.expression(argument))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call(name, callee, arguments)
+            }
+            Expression::Block {
+                statements,
+                return_value,
+            } => self.block(&Block {
+                statements: statements.clone(),
+                return_value: return_value.clone(),
+            }),
+            Expression::Function {
+                parameters,
+                return_type,
+                body,
+                by_reference,
+                captures,
+            } => {
+                if *by_reference && !captures.is_empty() {
+                    return Err(SemanticError::NotComptime {
+                        what: "a closure capturing by reference".to_string(),
+                    });
+                }
+
+                // the captured values are bound in front of the body
+                let mut statements = vec![];
+                for capture in captures {
+                    let value = self.expression(&Expression::Identifier {
+                        name: capture.name.clone(),
+                        span: Span::default(),
+                    })?;
+                    statements.push(Statement::Declaration {
+                        public: false,
+                        mutable: false,
+                        name: capture.name.clone(),
+                        datatype: type_of_constant(&value).unwrap_or(Type::Nil),
+                        value,
+                    });
+                }
+                statements.extend(body.statements.iter().cloned());
+
+                Ok(Expression::Function {
+                    parameters: parameters.clone(),
+                    return_type: return_type.clone(),
+                    body: Block {
+                        statements,
+                        return_value: body.return_value.clone(),
+                    },
+                    by_reference: false,
+                    captures: vec![],
+                })
+            }
+            Expression::If(if_expression) => self.if_expression(if_expression),
+            Expression::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => Ok(Expression::Variant {
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                payload: match payload {
+                    Some(payload) => Some(Box::new(self.expression(payload)?)),
+                    None => None,
+                },
+            }),
+            Expression::Match(match_expression) => {
+                let value = self.expression(&match_expression.value)?;
+                for arm in &match_expression.arms {
+                    if let Some(values) = bind_constant(&arm.pattern, &value) {
+                        self.call_frame().scopes.push(HashMap::new());
+                        for (name, value) in bindings(&arm.pattern).into_iter().zip(values) {
+                            self.declare(name, value);
+                        }
+                        let value = self.expression(&arm.value)?;
+                        self.call_frame().scopes.pop();
+                        return Ok(value);
+                    }
+                }
+                unreachable!("matches are exhaustive")
+            }
+            Expression::Comptime(block) => self.block(block),
+            Expression::AddressOf(_) | Expression::Deref(_) => Err(SemanticError::NotComptime {
+                what: "a pointer".to_string(),
+            }),
+            Expression::Type(_) => Err(SemanticError::ComptimeOnly {
+                datatype: Type::Type,
+            }),
+            _ => unreachable!("method calls are lowered, binary operations are handled above"),
+        }
+    }
+
+    fn elements(&mut self, elements: &[TupleElement]) -> Result<Vec<TupleElement>, SemanticError> {
+        elements
+            .iter()
+            .map(|element| {
+                Ok(TupleElement {
+                    name: element.name.clone(),
+                    value: self.expression(&element.value)?,
+                })
+            })
+            .collect()
+    }
+
+    fn if_expression(&mut self, if_expression: &If) -> Result<Expression, SemanticError> {
+        let branches =
+            std::iter::once(&if_expression.if_block).chain(&if_expression.else_if_blocks);
+        for branch in branches {
+            if let Expression::Boolean(true) = self.expression(&branch.condition)? {
+                return self.block(&branch.block);
+            }
+        }
+        match &if_expression.else_block {
+            Some(block) => self.block(block),
+            None => Ok(Expression::Nil),
+        }
+    }
+
+    /// Calls a function, given either by name or as a closure value.
+    fn call(
+        &mut self,
+        name: &str,
+        callee: Expression,
+        arguments: Vec<Expression>,
+    ) -> Result<Expression, SemanticError> {
+        let (parameters, body) = match callee {
+            Expression::Function {
+                parameters, body, ..
+            } => (
+                parameters
+                    .into_iter()
+                    .map(|Param::Parameter { name, .. }| name)
+                    .collect(),
+                body,
+            ),
+            Expression::Identifier { name: function, .. } => {
+                match self.functions.function(&function)? {
+                    Some(function) => (function.parameters, function.body),
+                    None => {
+                        return Err(SemanticError::NotComptime {
+                            what: format!("function '{}'", function),
+                        })
+                    }
+                }
+            }
+            _ => unreachable!("only functions are called"),
+        };
+
+        if self.calls.len() > DEPTH_LIMIT {
+            return Err(SemanticError::ComptimeDepthLimit { limit: DEPTH_LIMIT });
+        }
+        let shown = arguments.iter().map(describe).collect::<Vec<_>>();
+        self.calls.push(Call {
+            name: format!("{}({})", name, shown.join(", ")),
+            scopes: vec![parameters.into_iter().zip(arguments).collect()],
+        });
+        let value = self.block(&body)?;
+        self.calls.pop();
+        Ok(value)
+    }
+}
+
+fn field_index(field: &str) -> usize {
+    field
+        .parse()
+        .expect("the validator turns fields into positions")
+}
+
+/// Renders a value for diagnostics.
+fn describe(value: &Expression) -> String {
+    let elements = |elements: &[TupleElement]| {
+        elements
+            .iter()
+            .map(|element| match &element.name {
+                Some(name) => format!("{} = {}", name, describe(&element.value)),
+                None => describe(&element.value),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match value {
+        Expression::Number(number) => number.to_string(),
+        Expression::Boolean(boolean) => boolean.to_string(),
+        Expression::Nil => "nil".to_string(),
+        Expression::Identifier { name, .. } => name.clone(),
+        Expression::Tuple(fields) => format!("({})", elements(fields)),
+        Expression::StructLiteral { name, fields } => {
+            format!(
+                "{}.{{ {} }}",
+                name.as_deref().unwrap_or(""),
+                elements(fields)
+            )
+        }
+        Expression::Array(values) => {
+            let values = values.iter().map(describe).collect::<Vec<_>>();
+            format!("[{}]", values.join(", "))
+        }
+        Expression::Variant {
+            enum_name,
+            variant,
+            payload,
+        } => match payload {
+            Some(payload) => format!("{}.{}({})", enum_name, variant, describe(payload)),
+            None => format!("{}.{}", enum_name, variant),
+        },
+        Expression::Function { .. } => "a closure".to_string(),
+        _ => "a value".to_string(),
+    }
+}
@@ -0,0 +1,725 @@
+use colored::Colorize;
+
+use crate::structures::parse_tree::{BinaryOperator, Span, Type};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SemanticError {
+    UndefinedIdentifier {
+        name: String,
+    },
+    AssignmentToConstant {
+        name: String,
+    },
+    InvalidNumberLiteral {
+        literal: String,
+    },
+    MismatchedOperands {
+        operator: BinaryOperator,
+        left: Type,
+        right: Type,
+    },
+    InvalidOperands {
+        operator: BinaryOperator,
+        operand: Type,
+    },
+    NonBooleanCondition {
+        found: Type,
+    },
+    MismatchedTypes {
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+    LossyConversion {
+        from: Type,
+        to: Type,
+        span: Span,
+    },
+    ConstantOutOfRange {
+        value: String,
+        datatype: Type,
+    },
+    InvalidCast {
+        from: Type,
+        to: Type,
+    },
+    NotCallable {
+        name: String,
+        datatype: Type,
+        span: Span,
+    },
+    UndefinedFunction {
+        name: String,
+        span: Span,
+    },
+    DuplicateFunction {
+        name: String,
+    },
+    DuplicateName {
+        name: String,
+    },
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    DuplicateParameter {
+        parameter: String,
+    },
+    MismatchedArgument {
+        function: String,
+        parameter: String,
+        expected: Box<Type>,
+        found: Box<Type>,
+        span: Span,
+    },
+    CaptureInFunction {
+        function: String,
+        name: String,
+        span: Span,
+    },
+    AssignmentToCapture {
+        name: String,
+        span: Span,
+    },
+    EscapingClosure {
+        name: String,
+        span: Span,
+    },
+    EscapingAddress {
+        name: String,
+        function: String,
+    },
+    UnknownField {
+        datatype: Type,
+        field: String,
+    },
+    DestructuringMismatch {
+        names: usize,
+        datatype: Type,
+    },
+    UndefinedType {
+        name: String,
+    },
+    DuplicateType {
+        name: String,
+    },
+    CyclicType {
+        cycle: Vec<String>,
+    },
+    MissingField {
+        datatype: Type,
+        field: String,
+    },
+    DuplicateField {
+        field: String,
+    },
+    UnknownMethod {
+        datatype: Type,
+        method: String,
+    },
+    UnknownVariant {
+        datatype: Type,
+        variant: String,
+    },
+    DuplicateVariant {
+        name: String,
+    },
+    UnreachablePattern {
+        pattern: String,
+        span: Span,
+    },
+    NonExhaustiveMatch {
+        datatype: Type,
+        missing: String,
+        span: Span,
+    },
+    NotAMethod {
+        name: String,
+    },
+    NotOptional {
+        datatype: Type,
+    },
+    UnwrappedOptional {
+        found: Type,
+        expected: Type,
+    },
+    NilToNonOptional {
+        datatype: Type,
+    },
+    InvalidOptional {
+        datatype: Type,
+    },
+    NotIndexable {
+        datatype: Type,
+    },
+    InvalidIndex {
+        datatype: Type,
+    },
+    IndexOutOfBounds {
+        index: String,
+        length: Option<usize>,
+    },
+    NotAssignable,
+    NotAPointer {
+        datatype: Type,
+    },
+    AssignmentThroughConst {
+        datatype: Type,
+    },
+    AddressOfTemporary,
+    ComptimeOnly {
+        datatype: Type,
+    },
+    ComptimeParameter {
+        function: String,
+        parameter: String,
+    },
+    NotComptime {
+        what: String,
+    },
+    ComptimeStepLimit {
+        limit: usize,
+    },
+    ComptimeDepthLimit {
+        limit: usize,
+    },
+    /// An error while evaluating a `comptime` block, with the calls leading to it, innermost
+    /// first.
+    ComptimeFailure {
+        error: Box<SemanticError>,
+        stack: Vec<String>,
+    },
+    DivisionByZero {
+        span: Span,
+    },
+    IntegerOverflow {
+        operator: BinaryOperator,
+        span: Span,
+    },
+}
+
+impl std::fmt::Display for SemanticError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SemanticError::UndefinedIdentifier { name } => {
+                write!(
+                    f,
+                    "{} '{}'",
+                    "undefined identifier".bold().red(),
+                    name.bold()
+                )
+            }
+            SemanticError::AssignmentToConstant { name } => {
+                write!(
+                    f,
+                    "{} '{}'",
+                    "cannot assign to constant".bold().red(),
+                    name.bold()
+                )
+            }
+            SemanticError::InvalidNumberLiteral { literal } => {
+                write!(
+                    f,
+                    "{} '{}'",
+                    "invalid number literal".bold().red(),
+                    literal.bold()
+                )
+            }
+            SemanticError::MismatchedOperands {
+                operator,
+                left,
+                right,
+            } => {
+                write!(
+                    f,
+                    "{} '{}' between {} and {}",
+                    "mismatched operands for".bold().red(),
+                    operator.to_string().bold(),
+                    left.to_string().bold(),
+                    right.to_string().bold()
+                )
+            }
+            SemanticError::InvalidOperands { operator, operand } => {
+                write!(
+                    f,
+                    "{} '{}' on {}",
+                    "invalid operands for".bold().red(),
+                    operator.to_string().bold(),
+                    operand.to_string().bold()
+                )
+            }
+            SemanticError::NonBooleanCondition { found } => {
+                write!(
+                    f,
+                    "{}, found {}",
+                    "expected a boolean condition".bold().red(),
+                    found.to_string().bold()
+                )
+            }
+            SemanticError::MismatchedTypes {
+                expected,
+                found,
+                span,
+            } => {
+                write!(
+                    f,
+                    "{}{}, expected {} but found {}",
+                    "mismatched types".bold().red(),
+                    at(span),
+                    expected.to_string().bold(),
+                    found.to_string().bold()
+                )
+            }
+            SemanticError::LossyConversion { from, to, span } => {
+                write!(
+                    f,
+                    "{} from {} to {}{}, use an explicit '{}' cast",
+                    "lossy implicit conversion".bold().red(),
+                    from.to_string().bold(),
+                    to.to_string().bold(),
+                    at(span),
+                    "as".bold()
+                )
+            }
+            SemanticError::ConstantOutOfRange { value, datatype } => {
+                write!(
+                    f,
+                    "{} '{}' for {}",
+                    "constant out of range".bold().red(),
+                    value.bold(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::InvalidCast { from, to } => {
+                write!(
+                    f,
+                    "{} from {} to {}",
+                    "invalid cast".bold().red(),
+                    from.to_string().bold(),
+                    to.to_string().bold()
+                )
+            }
+            SemanticError::NotCallable {
+                name,
+                datatype,
+                span,
+            } => {
+                write!(
+                    f,
+                    "{} '{}' of type {}{}",
+                    "cannot call".bold().red(),
+                    name.bold(),
+                    datatype.to_string().bold(),
+                    at(span)
+                )
+            }
+            SemanticError::UndefinedFunction { name, span } => {
+                write!(
+                    f,
+                    "{} '{}'{}",
+                    "call to undefined function".bold().red(),
+                    name.bold(),
+                    at(span)
+                )
+            }
+            SemanticError::DuplicateFunction { name } => {
+                write!(
+                    f,
+                    "{} '{}'",
+                    "function defined twice".bold().red(),
+                    name.bold()
+                )
+            }
+            SemanticError::DuplicateName { name } => {
+                write!(
+                    f,
+                    "{} '{}' in the same scope",
+                    "name defined twice".bold().red(),
+                    name.bold()
+                )
+            }
+            SemanticError::ArityMismatch {
+                name,
+                expected,
+                found,
+                span,
+            } => {
+                write!(
+                    f,
+                    "{} '{}' takes {} but {} supplied{}",
+                    "wrong number of arguments:".bold().red(),
+                    name.bold(),
+                    format!("{} argument(s)", expected).bold(),
+                    format!("{} were", found).bold(),
+                    at(span)
+                )
+            }
+            SemanticError::DuplicateParameter { parameter } => {
+                write!(
+                    f,
+                    "{} '{}'",
+                    "parameter defined twice".bold().red(),
+                    parameter.bold()
+                )
+            }
+            SemanticError::MismatchedArgument {
+                function,
+                parameter,
+                expected,
+                found,
+                span,
+            } => {
+                write!(
+                    f,
+                    "{} '{}' of '{}'{}, expected {} but found {}",
+                    "mismatched type for parameter".bold().red(),
+                    parameter.bold(),
+                    function.bold(),
+                    at(span),
+                    expected.to_string().bold(),
+                    found.to_string().bold()
+                )
+            }
+            SemanticError::CaptureInFunction {
+                function,
+                name,
+                span,
+            } => {
+                write!(
+                    f,
+                    "{} '{}' in function '{}'{}, use a closure instead",
+                    "cannot capture".bold().red(),
+                    name.bold(),
+                    function.bold(),
+                    at(span)
+                )
+            }
+            SemanticError::AssignmentToCapture { name, span } => {
+                write!(
+                    f,
+                    "{} '{}'{}, declare the closure with '{}' to capture it by reference",
+                    "cannot assign to variable captured by value".bold().red(),
+                    name.bold(),
+                    at(span),
+                    "capture".bold()
+                )
+            }
+            SemanticError::EscapingClosure { name, span } => {
+                write!(
+                    f,
+                    "{} '{}'{}, it captures variables by reference and can only be called",
+                    "closure used as a value".bold().red(),
+                    name.bold(),
+                    at(span)
+                )
+            }
+            SemanticError::EscapingAddress { name, function } => {
+                write!(
+                    f,
+                    "{} '{}' from '{}', which frees it on returning",
+                    "address of a variable returned".bold().red(),
+                    name.bold(),
+                    function.bold()
+                )
+            }
+            SemanticError::UnknownField { datatype, field } => {
+                write!(
+                    f,
+                    "{} '{}' on {}",
+                    "no field".bold().red(),
+                    field.bold(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::DestructuringMismatch { names, datatype } => {
+                write!(
+                    f,
+                    "{} {} into {}",
+                    "cannot destructure".bold().red(),
+                    datatype.to_string().bold(),
+                    format!("{} variable(s)", names).bold()
+                )
+            }
+            SemanticError::UndefinedType { name } => {
+                write!(f, "{} '{}'", "undefined type".bold().red(), name.bold())
+            }
+            SemanticError::DuplicateType { name } => {
+                write!(f, "{} '{}'", "type defined twice".bold().red(), name.bold())
+            }
+            SemanticError::CyclicType { cycle } => {
+                write!(
+                    f,
+                    "{} {}",
+                    "type alias refers to itself:".bold().red(),
+                    cycle.join(" -> ").bold()
+                )
+            }
+            SemanticError::MissingField { datatype, field } => {
+                write!(
+                    f,
+                    "{} '{}' in literal of {}",
+                    "missing field".bold().red(),
+                    field.bold(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::DuplicateField { field } => {
+                write!(f, "{} '{}'", "field given twice".bold().red(), field.bold())
+            }
+            SemanticError::UnknownMethod { datatype, method } => {
+                write!(
+                    f,
+                    "{} '{}' on {}",
+                    "no method".bold().red(),
+                    method.bold(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::UnknownVariant { datatype, variant } => {
+                write!(
+                    f,
+                    "{} '{}' in {}",
+                    "no variant".bold().red(),
+                    variant.bold(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::DuplicateVariant { name } => {
+                write!(
+                    f,
+                    "{} '{}'",
+                    "variant defined twice".bold().red(),
+                    name.bold()
+                )
+            }
+            SemanticError::UnreachablePattern { pattern, span } => {
+                write!(
+                    f,
+                    "{} '{}' in the match{}, it is covered by the arms before it",
+                    "unreachable pattern".bold().red(),
+                    pattern.bold(),
+                    at(span)
+                )
+            }
+            SemanticError::NonExhaustiveMatch {
+                datatype,
+                missing,
+                span,
+            } => {
+                write!(
+                    f,
+                    "{} on {}{}, '{}' is not covered",
+                    "non-exhaustive match".bold().red(),
+                    datatype.to_string().bold(),
+                    at(span),
+                    missing.bold()
+                )
+            }
+            SemanticError::NotAMethod { name } => {
+                write!(
+                    f,
+                    "{} '{}' takes no '{}', call it on the type instead",
+                    "cannot call on a value:".bold().red(),
+                    name.bold(),
+                    "self".bold()
+                )
+            }
+            SemanticError::NotOptional { datatype } => {
+                write!(
+                    f,
+                    "{} on {}, which is never nil",
+                    "default value with '!'".bold().red(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::UnwrappedOptional { found, expected } => {
+                write!(
+                    f,
+                    "{} {} where {} is required, provide a default with '{}'",
+                    "optional".bold().red(),
+                    found.to_string().bold(),
+                    expected.to_string().bold(),
+                    "!".bold()
+                )
+            }
+            SemanticError::NilToNonOptional { datatype } => {
+                write!(
+                    f,
+                    "{} to {}, declare it as {} instead",
+                    "cannot convert nil".bold().red(),
+                    datatype.to_string().bold(),
+                    format!("{}?", datatype).bold()
+                )
+            }
+            SemanticError::InvalidOptional { datatype } => {
+                write!(
+                    f,
+                    "{} {} optional",
+                    "cannot make".bold().red(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::NotIndexable { datatype } => {
+                write!(
+                    f,
+                    "{} {}",
+                    "cannot index into".bold().red(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::InvalidIndex { datatype } => {
+                write!(
+                    f,
+                    "{}, found {}",
+                    "expected an integer index".bold().red(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::IndexOutOfBounds { index, length } => match length {
+                Some(length) => write!(
+                    f,
+                    "{} '{}' for length {}",
+                    "index out of bounds".bold().red(),
+                    index.bold(),
+                    length.to_string().bold()
+                ),
+                None => write!(
+                    f,
+                    "{} '{}'",
+                    "index out of bounds".bold().red(),
+                    index.bold()
+                ),
+            },
+            SemanticError::NotAssignable => {
+                write!(
+                    f,
+                    "{}, only elements of variables can be assigned to",
+                    "cannot assign to a temporary value".bold().red()
+                )
+            }
+            SemanticError::NotAPointer { datatype } => {
+                write!(
+                    f,
+                    "{} {}",
+                    "cannot dereference".bold().red(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::AssignmentThroughConst { datatype } => {
+                write!(
+                    f,
+                    "{} {}",
+                    "cannot assign through".bold().red(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::AddressOfTemporary => {
+                write!(
+                    f,
+                    "{}, only variables and their elements have one",
+                    "cannot take the address of a temporary value".bold().red()
+                )
+            }
+            SemanticError::ComptimeOnly { datatype } => {
+                write!(
+                    f,
+                    "{} {} only exists at compile time",
+                    "cannot use at runtime:".bold().red(),
+                    datatype.to_string().bold()
+                )
+            }
+            SemanticError::ComptimeParameter {
+                function,
+                parameter,
+            } => {
+                write!(
+                    f,
+                    "{} '{}' of type factory '{}' must be a '{}'",
+                    "parameter".bold().red(),
+                    parameter.bold(),
+                    function.bold(),
+                    "comptime type".bold()
+                )
+            }
+            SemanticError::NotComptime { what } => {
+                write!(
+                    f,
+                    "{} is {}",
+                    what.bold(),
+                    "not known at compile time".bold().red()
+                )
+            }
+            SemanticError::ComptimeStepLimit { limit } => {
+                write!(
+                    f,
+                    "{} of {} steps",
+                    "exceeded the compile-time evaluation limit".bold().red(),
+                    limit.to_string().bold()
+                )
+            }
+            SemanticError::ComptimeDepthLimit { limit } => {
+                write!(
+                    f,
+                    "{} of {} nested calls",
+                    "exceeded the compile-time recursion limit".bold().red(),
+                    limit.to_string().bold()
+                )
+            }
+            SemanticError::ComptimeFailure { error, stack } => {
+                write!(
+                    f,
+                    "{} {}",
+                    "in compile-time evaluation:".bold().red(),
+                    error
+                )?;
+                write_stack(f, stack.iter().map(|call| call.bold()))
+            }
+            SemanticError::DivisionByZero { span } => {
+                write!(f, "{}{}", "division by zero".bold().red(), at(span))
+            }
+            SemanticError::IntegerOverflow { operator, span } => {
+                write!(
+                    f,
+                    "{} in '{}'{}",
+                    "integer overflow".bold().red(),
+                    operator.to_string().bold(),
+                    at(span)
+                )
+            }
+        }
+    }
+}
+
+/// Where an error was found, for the parts of the source code which keep track of that.
+fn at(span: &Span) -> String {
+    match *span == Span::default() {
+        true => String::new(),
+        false => format!(" at {}", span),
+    }
+}
+
+/// Writes the calls that led to an error, innermost first, one per line. Deep recursion is
+/// shown by its innermost and outermost calls only.
+pub fn write_stack(
+    f: &mut std::fmt::Formatter<'_>,
+    stack: impl ExactSizeIterator<Item = impl std::fmt::Display>,
+) -> std::fmt::Result {
+    let shown = 5;
+    let calls = stack.len();
+    for (depth, call) in stack.enumerate() {
+        if calls > 2 * shown && depth == shown {
+            write!(f, "\n    ... {} more calls", calls - 2 * shown)?;
+        }
+        if calls <= 2 * shown || depth < shown || depth >= calls - shown {
+            write!(f, "\n    in {}", call)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn err_msg(error: SemanticError) -> String {
+    let prefix = "Failed in the semantic analysis:";
+    format!("{} {}", prefix, error)
+}
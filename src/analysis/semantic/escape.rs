@@ -0,0 +1,85 @@
+// ESCAPING ADDRESSES //
+
+use crate::structures::parse_tree::{Block, Expression, Param, Statement};
+
+use super::patterns::bindings;
+
+/// The variable of a checked function whose address the value of its body holds, going into
+/// blocks, `if` branches, `match` arms and the values built from them. Its parameters and
+/// variables are freed once it returns, so the address would dangle.
+///
+/// Addresses passed through calls or read through pointers are not followed.
+pub fn escaping_address<'a>(parameters: &'a [Param], body: &'a Block) -> Option<&'a str> {
+    let mut locals = parameters
+        .iter()
+        .map(|Param::Parameter { name, .. }| name.as_str())
+        .collect();
+    escapes_block(&body.statements, &body.return_value, &mut locals)
+}
+
+fn escapes_block<'a>(
+    statements: &'a [Statement],
+    return_value: &'a Expression,
+    locals: &mut Vec<&'a str>,
+) -> Option<&'a str> {
+    let outer = locals.len();
+    locals.extend(statements.iter().flat_map(declared));
+    let escaping = escapes(return_value, locals);
+    locals.truncate(outer);
+    escaping
+}
+
+fn escapes<'a>(expression: &'a Expression, locals: &mut Vec<&'a str>) -> Option<&'a str> {
+    match expression {
+        Expression::AddressOf(place) => {
+            let name = root(place)?;
+            locals.contains(&name).then_some(name)
+        }
+        Expression::Block {
+            statements,
+            return_value,
+        } => escapes_block(statements, return_value, locals),
+        Expression::If(if_expression) => std::iter::once(&if_expression.if_block)
+            .chain(&if_expression.else_if_blocks)
+            .map(|branch| &branch.block)
+            .chain(&if_expression.else_block)
+            .find_map(|block| escapes_block(&block.statements, &block.return_value, locals)),
+        Expression::Match(match_expression) => match_expression.arms.iter().find_map(|arm| {
+            let outer = locals.len();
+            locals.extend(bindings(&arm.pattern).into_iter().map(String::as_str));
+            let escaping = escapes(&arm.value, locals);
+            locals.truncate(outer);
+            escaping
+        }),
+        Expression::Tuple(elements)
+        | Expression::StructLiteral {
+            fields: elements, ..
+        } => elements
+            .iter()
+            .find_map(|element| escapes(&element.value, locals)),
+        Expression::Array(elements) => elements.iter().find_map(|element| escapes(element, locals)),
+        Expression::Variant {
+            payload: Some(payload),
+            ..
+        } => escapes(payload, locals),
+        _ => None,
+    }
+}
+
+/// The variable a place is part of, unless it is reached through a pointer.
+fn root(place: &Expression) -> Option<&str> {
+    match place {
+        Expression::Identifier { name, .. } => Some(name),
+        Expression::Index { value, .. } | Expression::FieldAccess { value, .. } => root(value),
+        _ => None,
+    }
+}
+
+/// The variables a statement declares.
+fn declared(statement: &Statement) -> Vec<&str> {
+    match statement {
+        Statement::Declaration { name, .. } => vec![name],
+        Statement::Destructuring { names, .. } => names.iter().map(String::as_str).collect(),
+        _ => vec![],
+    }
+}
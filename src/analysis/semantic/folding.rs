@@ -0,0 +1,405 @@
+// CONSTANT FOLDING //
+
+use crate::structures::parse_tree::{
+    BinaryOperator, Expression, Floating, Integer, Number, NumericType, Signed, Span, TupleField,
+    Type, Unsigned,
+};
+
+use super::error::SemanticError;
+
+/// Builds a number of the given type from a primitive, using `as` conversion semantics.
+macro_rules! number_as {
+    ($datatype:expr, $value:expr) => {
+        match $datatype {
+            NumericType::I8 => Number::Int(Integer::Signed(Signed::Int8($value as i8))),
+            NumericType::I16 => Number::Int(Integer::Signed(Signed::Int16($value as i16))),
+            NumericType::I32 => Number::Int(Integer::Signed(Signed::Int32($value as i32))),
+            NumericType::I64 => Number::Int(Integer::Signed(Signed::Int64($value as i64))),
+            NumericType::I128 => Number::Int(Integer::Signed(Signed::Int128($value as i128))),
+            NumericType::ISize => Number::Int(Integer::Signed(Signed::ISize($value as isize))),
+            NumericType::U8 => Number::Int(Integer::Unsigned(Unsigned::UInt8($value as u8))),
+            NumericType::U16 => Number::Int(Integer::Unsigned(Unsigned::UInt16($value as u16))),
+            NumericType::U32 => Number::Int(Integer::Unsigned(Unsigned::UInt32($value as u32))),
+            NumericType::U64 => Number::Int(Integer::Unsigned(Unsigned::UInt64($value as u64))),
+            NumericType::U128 => Number::Int(Integer::Unsigned(Unsigned::UInt128($value as u128))),
+            NumericType::USize => Number::Int(Integer::Unsigned(Unsigned::USize($value as usize))),
+            NumericType::F32 => Number::Float(Floating::Float($value as f32)),
+            NumericType::F64 => Number::Float(Floating::Double($value as f64)),
+        }
+    };
+}
+
+/// The exact value of an integer, if it fits in an `i128`.
+pub fn signed_value(number: &Number) -> Option<i128> {
+    match number {
+        Number::Int(Integer::Signed(signed)) => Some(match signed {
+            Signed::Int8(v) => *v as i128,
+            Signed::Int16(v) => *v as i128,
+            Signed::Int32(v) => *v as i128,
+            Signed::Int64(v) => *v as i128,
+            Signed::Int128(v) => *v,
+            Signed::ISize(v) => *v as i128,
+        }),
+        Number::Int(Integer::Unsigned(Unsigned::UInt128(v))) => i128::try_from(*v).ok(),
+        Number::Int(Integer::Unsigned(unsigned)) => {
+            unsigned_value(&Number::Int(Integer::Unsigned(unsigned.clone()))).map(|v| v as i128)
+        }
+        Number::Float(_) => None,
+    }
+}
+
+/// The exact value of an integer, if it is non-negative.
+pub fn unsigned_value(number: &Number) -> Option<u128> {
+    match number {
+        Number::Int(Integer::Unsigned(unsigned)) => Some(match unsigned {
+            Unsigned::UInt8(v) => *v as u128,
+            Unsigned::UInt16(v) => *v as u128,
+            Unsigned::UInt32(v) => *v as u128,
+            Unsigned::UInt64(v) => *v as u128,
+            Unsigned::UInt128(v) => *v,
+            Unsigned::USize(v) => *v as u128,
+        }),
+        Number::Int(Integer::Signed(_)) => {
+            signed_value(number).and_then(|v| u128::try_from(v).ok())
+        }
+        Number::Float(_) => None,
+    }
+}
+
+/// The value of any number as an `f64`, rounding wide integers.
+pub fn float_value(number: &Number) -> f64 {
+    match number {
+        Number::Float(Floating::Float(v)) => *v as f64,
+        Number::Float(Floating::Double(v)) => *v,
+        _ => match signed_value(number) {
+            Some(v) => v as f64,
+            None => unsigned_value(number).unwrap() as f64,
+        },
+    }
+}
+
+/// An integer of the given type, if the value fits.
+pub fn integer(datatype: NumericType, value: i128) -> Option<Number> {
+    let fits = match datatype {
+        NumericType::I8 => i8::try_from(value).is_ok(),
+        NumericType::I16 => i16::try_from(value).is_ok(),
+        NumericType::I32 => i32::try_from(value).is_ok(),
+        NumericType::I64 => i64::try_from(value).is_ok(),
+        NumericType::I128 => true,
+        NumericType::ISize => isize::try_from(value).is_ok(),
+        NumericType::U8 => u8::try_from(value).is_ok(),
+        NumericType::U16 => u16::try_from(value).is_ok(),
+        NumericType::U32 => u32::try_from(value).is_ok(),
+        NumericType::U64 => u64::try_from(value).is_ok(),
+        NumericType::U128 => value >= 0,
+        NumericType::USize => usize::try_from(value).is_ok(),
+        NumericType::F32 | NumericType::F64 => false,
+    };
+
+    fits.then_some(number_as!(datatype, value))
+}
+
+fn unsigned(datatype: NumericType, value: u128) -> Option<Number> {
+    match datatype {
+        NumericType::U128 => Some(number_as!(datatype, value)),
+        _ => integer(datatype, i128::try_from(value).ok()?),
+    }
+}
+
+/// A floating point number of the given type, rounding to its precision.
+pub fn float(datatype: NumericType, value: f64) -> Number {
+    number_as!(datatype, value)
+}
+
+/// Parses a number literal into the default constant representing it.
+pub fn fold_literal(literal: &str) -> Result<Expression, SemanticError> {
+    let number = if literal.contains('.') {
+        literal
+            .parse::<f64>()
+            .ok()
+            .map(|v| float(NumericType::F64, v))
+    } else if let Ok(v) = literal.parse::<i64>() {
+        Some(number_as!(NumericType::I64, v))
+    } else if let Ok(v) = literal.parse::<i128>() {
+        Some(number_as!(NumericType::I128, v))
+    } else {
+        literal
+            .parse::<u128>()
+            .ok()
+            .map(|v| number_as!(NumericType::U128, v))
+    };
+
+    number
+        .map(Expression::Number)
+        .ok_or_else(|| SemanticError::InvalidNumberLiteral {
+            literal: literal.to_string(),
+        })
+}
+
+/// Converts a constant implicitly, provided its value is preserved.
+///
+/// Floating point constants may lose precision (but not magnitude) when narrowed, so that
+/// literals like `3.14` can initialise an `f32`.
+pub fn convert_exact(number: &Number, to: NumericType) -> Option<Number> {
+    let from = number.datatype();
+    if from == to {
+        return Some(number.clone());
+    }
+
+    match (from.is_float(), to.is_float()) {
+        (false, false) => match signed_value(number) {
+            Some(v) => integer(to, v),
+            None => unsigned(to, unsigned_value(number)?),
+        },
+        (false, true) => {
+            let v = signed_value(number)?;
+            let exact = match to {
+                NumericType::F32 => v as f32 as i128 == v,
+                _ => v as f64 as i128 == v,
+            };
+            exact.then_some(number_as!(to, v))
+        }
+        (true, false) => {
+            let v = float_value(number);
+            if v.fract() != 0.0 || !v.is_finite() || v.abs() >= i128::MAX as f64 {
+                return None;
+            }
+            integer(to, v as i128)
+        }
+        (true, true) => {
+            let v = float_value(number);
+            let narrowed = float(to, v);
+            (!v.is_finite() || float_value(&narrowed).is_finite()).then_some(narrowed)
+        }
+    }
+}
+
+/// Converts a constant explicitly, with the semantics of an `as` cast.
+pub fn cast_number(number: &Number, to: NumericType) -> Number {
+    if number.datatype().is_float() {
+        return number_as!(to, float_value(number));
+    }
+
+    match signed_value(number) {
+        Some(v) => number_as!(to, v),
+        None => number_as!(to, unsigned_value(number).unwrap()),
+    }
+}
+
+/// The type of a folded constant.
+pub fn type_of_constant(constant: &Expression) -> Option<Type> {
+    match constant {
+        Expression::Number(number) => Some(Type::Numeric(number.datatype())),
+        Expression::Boolean(_) => Some(Type::Bool),
+        Expression::Nil => Some(Type::Nil),
+        Expression::Tuple(elements) => elements
+            .iter()
+            .map(|element| {
+                Some(TupleField {
+                    name: element.name.clone(),
+                    datatype: type_of_constant(&element.value)?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(Type::Tuple),
+        Expression::StructLiteral {
+            name: Some(name), ..
+        } => Some(Type::Struct(name.clone())),
+        Expression::Variant { enum_name, .. } => Some(Type::Enum(enum_name.clone())),
+        Expression::Array(elements) => Some(Type::Array {
+            element: Box::new(match elements.first() {
+                Some(element) => type_of_constant(element)?,
+                None => Type::Nil,
+            }),
+            length: Some(elements.len()),
+        }),
+        _ => None,
+    }
+}
+
+/// Folds a binary operation at `span`, yielding `None` if either operand isn't constant.
+///
+/// Numeric operands are expected to have been converted to a common type already.
+pub fn fold_binary(
+    operator: BinaryOperator,
+    left: &Expression,
+    right: &Expression,
+    span: Span,
+) -> Result<Option<Expression>, SemanticError> {
+    let folded = match (left, right) {
+        (Expression::Number(left), Expression::Number(right)) => {
+            fold_numbers(operator, left, right, span)?
+        }
+        (Expression::Boolean(left), Expression::Boolean(right)) => match operator {
+            BinaryOperator::Equal => Expression::Boolean(left == right),
+            BinaryOperator::NotEqual => Expression::Boolean(left != right),
+            _ => {
+                return Err(SemanticError::InvalidOperands {
+                    operator,
+                    operand: Type::Bool,
+                })
+            }
+        },
+        (Expression::Nil, Expression::Nil) => match operator {
+            BinaryOperator::Equal => Expression::Boolean(true),
+            BinaryOperator::NotEqual => Expression::Boolean(false),
+            _ => {
+                return Err(SemanticError::InvalidOperands {
+                    operator,
+                    operand: Type::Nil,
+                })
+            }
+        },
+        // only optionals are compared with nil
+        (Expression::Nil, value) | (value, Expression::Nil) if value.is_constant() => {
+            match operator {
+                BinaryOperator::Equal => Expression::Boolean(false),
+                BinaryOperator::NotEqual => Expression::Boolean(true),
+                _ => {
+                    return Err(SemanticError::InvalidOperands {
+                        operator,
+                        operand: Type::Nil,
+                    })
+                }
+            }
+        }
+        (left, right) if left.is_constant() && right.is_constant() => {
+            return Err(SemanticError::MismatchedOperands {
+                operator,
+                left: type_of_constant(left).unwrap(),
+                right: type_of_constant(right).unwrap(),
+            })
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(folded))
+}
+
+fn fold_numbers(
+    operator: BinaryOperator,
+    left: &Number,
+    right: &Number,
+    span: Span,
+) -> Result<Expression, SemanticError> {
+    let datatype = left.datatype();
+    if datatype != right.datatype() {
+        return Err(SemanticError::MismatchedOperands {
+            operator,
+            left: Type::Numeric(datatype),
+            right: Type::Numeric(right.datatype()),
+        });
+    }
+
+    if datatype.is_float() {
+        return fold_floats(
+            operator,
+            datatype,
+            float_value(left),
+            float_value(right),
+            span,
+        );
+    }
+
+    match (signed_value(left), signed_value(right)) {
+        (Some(l), Some(r)) if datatype != NumericType::U128 => {
+            fold_integers(operator, l, r, span, |v| integer(datatype, v))
+        }
+        _ => fold_integers(
+            operator,
+            unsigned_value(left).unwrap(),
+            unsigned_value(right).unwrap(),
+            span,
+            |v| unsigned(datatype, v),
+        ),
+    }
+}
+
+trait CheckedArithmetic: Copy + PartialOrd + Default {
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn checked_div(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_checked_arithmetic {
+    ($($primitive:ty),*) => {
+        $(impl CheckedArithmetic for $primitive {
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$primitive>::checked_add(self, other)
+            }
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                <$primitive>::checked_sub(self, other)
+            }
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                <$primitive>::checked_mul(self, other)
+            }
+            fn checked_div(self, other: Self) -> Option<Self> {
+                <$primitive>::checked_div(self, other)
+            }
+        })*
+    };
+}
+
+impl_checked_arithmetic!(i128, u128);
+
+/// Folds integer operands, `narrow` fitting the result back into the operand type.
+fn fold_integers<T: CheckedArithmetic>(
+    operator: BinaryOperator,
+    left: T,
+    right: T,
+    span: Span,
+    narrow: impl Fn(T) -> Option<Number>,
+) -> Result<Expression, SemanticError> {
+    let overflow = SemanticError::IntegerOverflow { operator, span };
+    let result = match operator {
+        BinaryOperator::Add => left.checked_add(right),
+        BinaryOperator::Subtract => left.checked_sub(right),
+        BinaryOperator::Multiply => left.checked_mul(right),
+        BinaryOperator::Divide => {
+            if right == T::default() {
+                return Err(SemanticError::DivisionByZero { span });
+            }
+            left.checked_div(right)
+        }
+        BinaryOperator::LessThan => return Ok(Expression::Boolean(left < right)),
+        BinaryOperator::GreaterThan => return Ok(Expression::Boolean(left > right)),
+        BinaryOperator::LessThanOrEqual => return Ok(Expression::Boolean(left <= right)),
+        BinaryOperator::GreaterThanOrEqual => return Ok(Expression::Boolean(left >= right)),
+        BinaryOperator::Equal => return Ok(Expression::Boolean(left == right)),
+        BinaryOperator::NotEqual => return Ok(Expression::Boolean(left != right)),
+    };
+
+    result
+        .and_then(narrow)
+        .map(Expression::Number)
+        .ok_or(overflow)
+}
+
+fn fold_floats(
+    operator: BinaryOperator,
+    datatype: NumericType,
+    left: f64,
+    right: f64,
+    span: Span,
+) -> Result<Expression, SemanticError> {
+    let result = match operator {
+        BinaryOperator::Add => left + right,
+        BinaryOperator::Subtract => left - right,
+        BinaryOperator::Multiply => left * right,
+        BinaryOperator::Divide => {
+            if right == 0.0 {
+                return Err(SemanticError::DivisionByZero { span });
+            }
+            left / right
+        }
+        BinaryOperator::LessThan => return Ok(Expression::Boolean(left < right)),
+        BinaryOperator::GreaterThan => return Ok(Expression::Boolean(left > right)),
+        BinaryOperator::LessThanOrEqual => return Ok(Expression::Boolean(left <= right)),
+        BinaryOperator::GreaterThanOrEqual => return Ok(Expression::Boolean(left >= right)),
+        BinaryOperator::Equal => return Ok(Expression::Boolean(left == right)),
+        BinaryOperator::NotEqual => return Ok(Expression::Boolean(left != right)),
+    };
+
+    Ok(Expression::Number(float(datatype, result)))
+}
@@ -1,2 +1,13 @@
+pub mod comptime;
+pub mod error;
+pub mod escape;
+pub mod folding;
 pub mod parser;
+pub mod patterns;
+pub mod scope;
+pub mod tail;
+pub mod types;
 pub use parser::*;
+
+#[cfg(test)]
+mod tests;
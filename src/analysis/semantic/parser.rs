@@ -1,114 +1,2602 @@
+// SEMANTIC ANALYSIS //
+
+use std::collections::{HashMap, HashSet};
+
 use crate::structures::parse_tree::{
-    Block, ConditionalBlock, Expression, Floating, If, Integer, Number, Param, Program, Signed,
-    Statement, Type,
+    BinaryOperator, Block, Capture, ConditionalBlock, Expression, If, Match, MatchArm, NumericType,
+    Param, Pattern, Program, Span, Statement, StructField, TupleElement, TupleField, Type,
+    TypeDefinition, Variant,
+};
+
+use super::{
+    comptime::{
+        evaluate, instance_name, is_type_factory, type_factory, ComptimeFunction, Factory,
+        Functions, Instance,
+    },
+    error::{err_msg, SemanticError},
+    escape::escaping_address,
+    folding::{
+        cast_number, convert_exact, float_value, fold_binary, fold_literal, integer, unsigned_value,
+    },
+    patterns::{bind_constant, bindings, covered, uncovered},
+    scope::{FunctionFrame, Scopes, Symbol},
+    tail::mark_tail_calls,
+    types::{compatible_names, join, same_type, tuple_field_index, widens},
 };
 
-pub fn validate(program: &Program) -> Result<Program, String> {
-    Validator {}.validate(program)
+pub fn validate(program: &Program) -> Program {
+    match Validator::new().validate(program) {
+        Ok(program) => program,
+        Err(e) => {
+            println!("{}", err_msg(e));
+            std::process::exit(1);
+        }
+    }
 }
 
-pub struct Validator {}
+#[derive(Clone)]
+pub struct Validator {
+    scopes: Scopes,
+    /// The functions whose bodies are currently being validated, innermost last.
+    functions: Vec<FunctionFrame>,
+    /// The types declared by name, with aliases resolved to the types they stand for.
+    types: HashMap<String, Type>,
+    /// The aliases whose target has not been resolved yet.
+    aliases: HashMap<String, Type>,
+    /// The type factories, which are evaluated whenever a type like `Maybe(int)` is used.
+    factories: HashMap<String, Factory>,
+    /// The types created by type factories so far, by names like `Maybe(int)`.
+    instances: HashMap<String, Type>,
+    /// The instances whose methods are still to be validated.
+    pending_instances: Vec<Instance>,
+    /// The functions validated so far, which `comptime` blocks may call.
+    comptime_functions: HashMap<String, ComptimeFunction>,
+    /// The functions and methods of the top level not validated yet, along with the type of
+    /// their `self`, which `comptime` blocks may call before they are declared.
+    unchecked: HashMap<String, (Statement, Option<Type>)>,
+    /// The functions of the top level validated early because a `comptime` block called them.
+    checked_ahead: HashMap<String, Statement>,
+    /// The aliases being resolved, innermost last, to detect aliases referring to themselves.
+    resolving: Vec<String>,
+    /// The fields of every declared struct.
+    structs: HashMap<String, Vec<StructField>>,
+    /// The variants of every declared enum.
+    enums: HashMap<String, Vec<Variant>>,
+    /// The type whose methods are being declared or validated, which `Self` refers to.
+    self_type: Option<Type>,
+}
 
 impl Validator {
-    fn validate(&mut self, program: &Program) -> Result<Program, String> {
-        Ok(self.visit_program(program))
+    pub fn new() -> Self {
+        Self {
+            scopes: Scopes::new(),
+            functions: vec![],
+            types: HashMap::new(),
+            aliases: HashMap::new(),
+            resolving: vec![],
+            factories: HashMap::new(),
+            instances: HashMap::new(),
+            pending_instances: vec![],
+            comptime_functions: HashMap::new(),
+            unchecked: HashMap::new(),
+            checked_ahead: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            self_type: None,
+        }
+    }
+
+    pub fn validate(&mut self, program: &Program) -> Result<Program, SemanticError> {
+        self.visit_program(program)
+    }
+
+    /// Validates an expression as if it was the value of a top-level declaration, returning
+    /// it along with its type and the declarations of the type instances it created.
+    pub fn validate_expression(
+        &mut self,
+        expression: &Expression,
+    ) -> Result<(Vec<Statement>, Expression, Type), SemanticError> {
+        let (expression, datatype) = self.check_expression(expression)?;
+        Ok((self.check_instances()?, expression, datatype))
+    }
+
+    fn lookup(&self, name: &str) -> Result<&Symbol, SemanticError> {
+        self.scopes
+            .lookup(name)
+            .ok_or_else(|| SemanticError::UndefinedIdentifier {
+                name: name.to_string(),
+            })
+    }
+
+    /// Looks up a symbol used at `span`, recording it as a capture of every closure it is used
+    /// from.
+    fn resolve(
+        &mut self,
+        name: &str,
+        assigning: bool,
+        span: Span,
+    ) -> Result<Symbol, SemanticError> {
+        let (depth, symbol) = self.scopes.lookup_with_depth(name).ok_or_else(|| {
+            SemanticError::UndefinedIdentifier {
+                name: name.to_string(),
+            }
+        })?;
+        let symbol = symbol.clone();
+
+        // globals, functions and propagated constants are available everywhere
+        if depth == 0 || symbol.function || symbol.value.is_some() {
+            return Ok(symbol);
+        }
+
+        for frame in self.functions.iter_mut().rev() {
+            if depth >= frame.boundary {
+                break;
+            }
+
+            if !frame.closure {
+                return Err(SemanticError::CaptureInFunction {
+                    function: frame.name.clone(),
+                    name: name.to_string(),
+                    span,
+                });
+            }
+
+            let by_reference = frame.by_reference && symbol.mutable;
+            if assigning && !by_reference {
+                return Err(SemanticError::AssignmentToCapture {
+                    name: name.to_string(),
+                    span,
+                });
+            }
+
+            if !frame.captures.iter().any(|capture| capture.name == name) {
+                frame.captures.push(Capture {
+                    name: name.to_string(),
+                    by_reference,
+                });
+            }
+        }
+
+        Ok(symbol)
+    }
+
+    /// Implicitly converts an expression of type `from` into one of type `to`.
+    ///
+    /// Constants convert if their value is preserved, other numeric values only along the
+    /// promotion lattice, in which case the widening is made explicit as a cast.
+    fn coerce(
+        &self,
+        expression: Expression,
+        from: &Type,
+        to: &Type,
+    ) -> Result<Expression, SemanticError> {
+        if same_type(from, to) {
+            return Ok(expression);
+        }
+        let span = expression.span();
+
+        // an optional holds either nil or a value of the type it wraps
+        match (from, to) {
+            (Type::Nil, Type::Optional(_)) => return Ok(expression),
+            (Type::Optional(from_inner), Type::Optional(to_inner)) => {
+                return match &expression {
+                    Expression::Nil => Ok(expression),
+                    _ if expression.is_constant() => self.coerce(expression, from_inner, to_inner),
+                    _ => Err(SemanticError::MismatchedTypes {
+                        expected: to.clone(),
+                        found: from.clone(),
+                        span,
+                    }),
+                };
+            }
+            (_, Type::Optional(inner)) => return self.coerce(expression, from, inner),
+            (Type::Optional(_), _) => {
+                return Err(SemanticError::UnwrappedOptional {
+                    found: from.clone(),
+                    expected: to.clone(),
+                })
+            }
+            (Type::Nil, _) => {
+                return Err(SemanticError::NilToNonOptional {
+                    datatype: to.clone(),
+                })
+            }
+            // what may be changed through a pointer may also just be read through it
+            (
+                Type::Pointer {
+                    pointee: from_pointee,
+                    mutable: true,
+                },
+                Type::Pointer {
+                    pointee: to_pointee,
+                    mutable: false,
+                },
+            ) if same_type(from_pointee, to_pointee) => return Ok(expression),
+            _ => {}
+        }
+
+        match (from, to, &expression) {
+            (
+                Type::Numeric(numeric_from),
+                Type::Numeric(numeric_to),
+                Expression::Number(number),
+            ) => match convert_exact(number, *numeric_to) {
+                Some(converted) => Ok(Expression::Number(converted)),
+                None if !numeric_from.is_float() && !numeric_to.is_float() => {
+                    Err(SemanticError::ConstantOutOfRange {
+                        value: number.to_string(),
+                        datatype: to.clone(),
+                    })
+                }
+                None => Err(SemanticError::LossyConversion {
+                    from: from.clone(),
+                    to: to.clone(),
+                    span,
+                }),
+            },
+            (Type::Tuple(from_fields), Type::Tuple(to_fields), Expression::Tuple(_))
+                if from_fields.len() == to_fields.len() =>
+            {
+                let Expression::Tuple(elements) = expression else {
+                    unreachable!()
+                };
+
+                let mut converted = vec![];
+                for ((element, from_field), to_field) in
+                    elements.into_iter().zip(from_fields).zip(to_fields)
+                {
+                    if !compatible_names(&from_field.name, &to_field.name) {
+                        return Err(SemanticError::MismatchedTypes {
+                            expected: to.clone(),
+                            found: from.clone(),
+                            span,
+                        });
+                    }
+
+                    converted.push(TupleElement {
+                        name: to_field.name.clone(),
+                        value: self.coerce(
+                            element.value,
+                            &from_field.datatype,
+                            &to_field.datatype,
+                        )?,
+                    });
+                }
+
+                Ok(Expression::Tuple(converted))
+            }
+            // struct literals are checked as tagged tuples until their struct is known
+            (Type::Tuple(from_fields), Type::Struct(name), Expression::Tuple(_)) => {
+                let Expression::Tuple(elements) = expression else {
+                    unreachable!()
+                };
+                self.struct_literal(name, elements, from_fields, span)
+            }
+            // arrays convert element by element, or to slices of the same elements
+            (
+                Type::Array {
+                    element: from_element,
+                    length: from_length,
+                },
+                Type::Array {
+                    element: to_element,
+                    length: to_length,
+                },
+                _,
+            ) if to_length.is_none() || to_length == from_length => match expression {
+                Expression::Array(elements) => elements
+                    .into_iter()
+                    .map(|element| self.coerce(element, from_element, to_element))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(Expression::Array),
+                _ if same_type(from_element, to_element) => Ok(expression),
+                _ => Err(SemanticError::MismatchedTypes {
+                    expected: to.clone(),
+                    found: from.clone(),
+                    span,
+                }),
+            },
+            (Type::Numeric(numeric_from), Type::Numeric(numeric_to), _) => {
+                if widens(*numeric_from, *numeric_to) {
+                    Ok(Expression::Cast {
+                        value: Box::new(expression),
+                        datatype: to.clone(),
+                    })
+                } else {
+                    Err(SemanticError::LossyConversion {
+                        from: from.clone(),
+                        to: to.clone(),
+                        span,
+                    })
+                }
+            }
+            _ => Err(SemanticError::MismatchedTypes {
+                expected: to.clone(),
+                found: from.clone(),
+                span,
+            }),
+        }
+    }
+
+    /// Orders the fields of a literal like those of its struct, converting each of them.
+    fn struct_literal(
+        &self,
+        name: &str,
+        elements: Vec<TupleElement>,
+        from_fields: &[TupleField],
+        span: Span,
+    ) -> Result<Expression, SemanticError> {
+        let datatype = Type::Struct(name.to_string());
+        let fields = &self.structs[name];
+
+        let mut values = vec![None; fields.len()];
+        for (element, from_field) in elements.into_iter().zip(from_fields) {
+            let Some(field) = element.name else {
+                return Err(SemanticError::MismatchedTypes {
+                    expected: datatype,
+                    found: Type::Tuple(from_fields.to_vec()),
+                    span,
+                });
+            };
+            let index = fields
+                .iter()
+                .position(|struct_field| struct_field.name == field)
+                .ok_or_else(|| SemanticError::UnknownField {
+                    datatype: datatype.clone(),
+                    field: field.clone(),
+                })?;
+
+            values[index] =
+                Some(self.coerce(element.value, &from_field.datatype, &fields[index].datatype)?);
+        }
+
+        let mut converted = vec![];
+        for (field, value) in fields.iter().zip(values) {
+            // optional fields may be left out
+            let value = match (value, &field.datatype) {
+                (Some(value), _) => value,
+                (None, Type::Optional(_)) => Expression::Nil,
+                (None, _) => {
+                    return Err(SemanticError::MissingField {
+                        datatype: datatype.clone(),
+                        field: field.name.clone(),
+                    })
+                }
+            };
+            converted.push(TupleElement {
+                name: Some(field.name.clone()),
+                value,
+            });
+        }
+
+        Ok(Expression::StructLiteral {
+            name: Some(name.to_string()),
+            fields: converted,
+        })
+    }
+
+    /// Brings both operands of a binary operation to a common type.
+    fn unify_operands(
+        &self,
+        operator: BinaryOperator,
+        (left, left_type): (Expression, Type),
+        (right, right_type): (Expression, Type),
+    ) -> Result<(Expression, Expression, Type), SemanticError> {
+        if left_type == right_type {
+            return Ok((left, right, left_type));
+        }
+
+        // optionals are compared with nil or values of the type they wrap
+        if let Type::Optional(_) = left_type {
+            if let Ok(right) = self.coerce(right.clone(), &right_type, &left_type) {
+                return Ok((left, right, left_type));
+            }
+        }
+        if let Type::Optional(_) = right_type {
+            if let Ok(left) = self.coerce(left.clone(), &left_type, &right_type) {
+                return Ok((left, right, right_type));
+            }
+        }
+
+        let mismatch = SemanticError::MismatchedOperands {
+            operator,
+            left: left_type.clone(),
+            right: right_type.clone(),
+        };
+
+        let (Type::Numeric(numeric_left), Type::Numeric(numeric_right)) = (&left_type, &right_type)
+        else {
+            return Err(mismatch);
+        };
+
+        // constants adapt to the type of the other operand when they can
+        if right.is_constant() {
+            if let Ok(right) = self.coerce(right.clone(), &right_type, &left_type) {
+                return Ok((left, right, left_type));
+            }
+        }
+        if left.is_constant() {
+            if let Ok(left) = self.coerce(left.clone(), &left_type, &right_type) {
+                return Ok((left, right, right_type));
+            }
+        }
+
+        let joined = Type::Numeric(join(*numeric_left, *numeric_right).ok_or(mismatch)?);
+        Ok((
+            self.coerce(left, &left_type, &joined)?,
+            self.coerce(right, &right_type, &joined)?,
+            joined,
+        ))
+    }
+
+    fn check_binary(
+        &mut self,
+        operator: BinaryOperator,
+        left: &Expression,
+        right: &Expression,
+        span: Span,
+    ) -> Result<(Expression, Type), SemanticError> {
+        let left = self.check_expression(left)?;
+        let right = self.check_expression(right)?;
+        let (left, right, operand_type) = self.unify_operands(operator, left, right)?;
+
+        let equality = matches!(operator, BinaryOperator::Equal | BinaryOperator::NotEqual);
+        let valid = match &operand_type {
+            Type::Numeric(_) => true,
+            Type::Bool | Type::Nil => equality,
+            // any optional may be compared with nil, but only the values of numbers and booleans
+            Type::Optional(inner) => {
+                equality
+                    && (matches!(**inner, Type::Numeric(_) | Type::Bool)
+                        || matches!(left, Expression::Nil)
+                        || matches!(right, Expression::Nil))
+            }
+            _ => false,
+        };
+        if !valid {
+            match operand_type {
+                Type::Optional(inner) if !equality => {
+                    return Err(SemanticError::UnwrappedOptional {
+                        found: Type::Optional(inner.clone()),
+                        expected: *inner,
+                    })
+                }
+                _ => {}
+            }
+            return Err(SemanticError::InvalidOperands {
+                operator,
+                operand: operand_type,
+            });
+        }
+
+        let datatype = if operator.is_comparison() {
+            Type::Bool
+        } else {
+            operand_type
+        };
+
+        let expression = match fold_binary(operator, &left, &right, span)? {
+            Some(folded) => folded,
+            // a divisor known to be zero fails whatever the dividend turns out to be
+            None if operator == BinaryOperator::Divide
+                && matches!(&right, Expression::Number(divisor) if float_value(divisor) == 0.0) =>
+            {
+                return Err(SemanticError::DivisionByZero { span })
+            }
+            None => Expression::binary(operator, left, right, span),
+        };
+
+        Ok((expression, datatype))
+    }
+
+    fn check_cast(
+        &mut self,
+        value: &Expression,
+        datatype: &Type,
+    ) -> Result<(Expression, Type), SemanticError> {
+        let (value, value_type) = self.check_expression(value)?;
+        let datatype = self.visit_type(datatype)?;
+
+        let folded = match (&value, &value_type, &datatype) {
+            (_, from, to) if from == to => value,
+            (Expression::Number(number), _, Type::Numeric(to)) => {
+                Expression::Number(cast_number(number, *to))
+            }
+            (Expression::Boolean(boolean), _, Type::Numeric(to)) if !to.is_float() => {
+                Expression::Number(integer(*to, *boolean as i128).unwrap())
+            }
+            (_, Type::Numeric(_), Type::Numeric(_)) => Expression::Cast {
+                value: Box::new(value),
+                datatype: datatype.clone(),
+            },
+            (_, Type::Bool, Type::Numeric(to)) if !to.is_float() => Expression::Cast {
+                value: Box::new(value),
+                datatype: datatype.clone(),
+            },
+            _ => {
+                return Err(SemanticError::InvalidCast {
+                    from: value_type,
+                    to: datatype,
+                })
+            }
+        };
+
+        Ok((folded, datatype))
+    }
+
+    /// Checks `value ! fallback`, which is optional itself only if the fallback is.
+    fn check_default(
+        &mut self,
+        value: &Expression,
+        fallback: &Expression,
+    ) -> Result<(Expression, Type), SemanticError> {
+        let (value, value_type) = self.check_expression(value)?;
+        let Type::Optional(inner) = &value_type else {
+            return Err(SemanticError::NotOptional {
+                datatype: value_type,
+            });
+        };
+
+        let (fallback, fallback_type) = self.check_expression(fallback)?;
+        let datatype = match fallback_type {
+            Type::Optional(_) | Type::Nil => value_type.clone(),
+            _ => *inner.clone(),
+        };
+        let fallback = self.coerce(fallback, &fallback_type, &datatype)?;
+
+        let expression = match value {
+            Expression::Nil => fallback,
+            value if value.is_constant() => value,
+            value => Expression::Default {
+                value: Box::new(value),
+                fallback: Box::new(fallback),
+            },
+        };
+
+        Ok((expression, datatype))
+    }
+
+    fn check_expression(
+        &mut self,
+        expression: &Expression,
+    ) -> Result<(Expression, Type), SemanticError> {
+        if let Some((operator, left, right)) = expression.as_binary() {
+            return self.check_binary(operator, left, right, expression.span());
+        }
+
+        match expression {
+            Expression::Nil => Ok((Expression::Nil, Type::Nil)),
+            Expression::Boolean(_) => Ok((expression.clone(), Type::Bool)),
+            Expression::Number(number) => {
+                Ok((expression.clone(), Type::Numeric(number.datatype())))
+            }
+            Expression::NumberLiteral(literal) => {
+                let number = fold_literal(literal)?;
+                self.check_expression(&number)
+            }
+            Expression::Identifier { name, span } => {
+                let symbol = self.resolve(name, false, *span)?;
+                if symbol.borrowing {
+                    return Err(SemanticError::EscapingClosure {
+                        name: name.clone(),
+                        span: *span,
+                    });
+                }
+                let value = symbol.value.unwrap_or_else(|| expression.clone());
+                Ok((value, symbol.datatype))
+            }
+            Expression::Cast { value, datatype } => self.check_cast(value, datatype),
+            Expression::Default { value, fallback } => self.check_default(value, fallback),
+            Expression::Tuple(elements) => self.check_tuple(elements),
+            Expression::Array(elements) => self.check_array(elements),
+            Expression::Index {
+                value, index, span, ..
+            } => self.check_index(value, index, *span),
+            Expression::StructLiteral { name, fields } => {
+                let (value, datatype) = self.check_tuple(fields)?;
+                match name {
+                    Some(name) => {
+                        let struct_type = Type::Struct(name.clone());
+                        Ok((self.coerce(value, &datatype, &struct_type)?, struct_type))
+                    }
+                    None => Ok((value, datatype)),
+                }
+            }
+            Expression::FieldAccess { value, field } => self.check_field_access(value, field),
+            Expression::AddressOf(value) => {
+                self.borrow();
+                let (place, datatype, immutable) = match self.check_place(value, false) {
+                    Err(SemanticError::NotAssignable) => {
+                        return Err(SemanticError::AddressOfTemporary)
+                    }
+                    place => place?,
+                };
+                Ok((
+                    Expression::AddressOf(Box::new(place)),
+                    Type::Pointer {
+                        pointee: Box::new(datatype),
+                        mutable: immutable.is_none(),
+                    },
+                ))
+            }
+            Expression::Deref(value) => match self.check_expression(value)? {
+                (value, Type::Pointer { pointee, .. }) => {
+                    Ok((Expression::Deref(Box::new(value)), *pointee))
+                }
+                (_, datatype) => Err(SemanticError::NotAPointer { datatype }),
+            },
+            Expression::FunctionCall {
+                name,
+                arguments,
+                span,
+                ..
+            } => self.check_call(name, arguments, *span),
+            Expression::MethodCall {
+                receiver,
+                method,
+                arguments,
+                span,
+            } => self.check_method_call(receiver, method, arguments, *span),
+            Expression::Block {
+                statements,
+                return_value,
+            } => {
+                let (block, datatype) = self.check_block(&Block {
+                    statements: statements.clone(),
+                    return_value: return_value.clone(),
+                })?;
+                Ok((block_expression(block), datatype))
+            }
+            Expression::Function { .. } => {
+                let (closure, datatype) = self.check_closure(expression)?;
+                // only a declared closure can be kept from escaping, by allowing nothing but calls
+                if self.borrows_variables(&closure) {
+                    return Err(SemanticError::EscapingClosure {
+                        name: "closure".to_string(),
+                        span: Span::default(),
+                    });
+                }
+                Ok((closure, datatype))
+            }
+            Expression::If(if_expression) => self.check_if(if_expression),
+            Expression::Variant { enum_name, .. } => {
+                Ok((expression.clone(), Type::Enum(enum_name.clone())))
+            }
+            Expression::Match(match_expression) => self.check_match(match_expression),
+            Expression::Comptime(block) => {
+                let (block, datatype) = self.check_block(block)?;
+                Ok((evaluate(&block, self)?, datatype))
+            }
+            Expression::Type(_) => Err(SemanticError::ComptimeOnly {
+                datatype: Type::Type,
+            }),
+            _ => unreachable!("binary expressions are checked above"),
+        }
+    }
+
+    fn check_tuple(
+        &mut self,
+        elements: &[TupleElement],
+    ) -> Result<(Expression, Type), SemanticError> {
+        let mut checked_elements = vec![];
+        let mut fields = vec![];
+        for TupleElement { name, value } in elements {
+            if name.is_some() && fields.iter().any(|field: &TupleField| field.name == *name) {
+                return Err(SemanticError::DuplicateField {
+                    field: name.clone().unwrap(),
+                });
+            }
+
+            let (value, datatype) = self.check_expression(value)?;
+            fields.push(TupleField {
+                name: name.clone(),
+                datatype,
+            });
+            checked_elements.push(TupleElement {
+                name: name.clone(),
+                value,
+            });
+        }
+
+        Ok((Expression::Tuple(checked_elements), Type::Tuple(fields)))
+    }
+
+    fn check_array(
+        &mut self,
+        elements: &[Expression],
+    ) -> Result<(Expression, Type), SemanticError> {
+        let checked = elements
+            .iter()
+            .map(|element| self.check_expression(element))
+            .collect::<Result<Vec<_>, _>>()?;
+        let element_type =
+            self.join_values(checked.iter().map(|(value, datatype)| (value, datatype)))?;
+
+        let mut elements = vec![];
+        for (value, datatype) in checked {
+            elements.push(self.coerce(value, &datatype, &element_type)?);
+        }
+
+        let datatype = Type::Array {
+            element: Box::new(element_type),
+            length: Some(elements.len()),
+        };
+        Ok((Expression::Array(elements), datatype))
+    }
+
+    /// Checks an index into an array or a slice, converting the index to `usize`.
+    ///
+    /// Constant indices into arrays of known length are checked statically, and elements of
+    /// constant arrays are folded; every other index is left to be checked at runtime.
+    fn check_index(
+        &mut self,
+        value: &Expression,
+        index: &Expression,
+        span: Span,
+    ) -> Result<(Expression, Type), SemanticError> {
+        let (value, datatype) = self.check_expression(value)?;
+        let (value, datatype) = dereference(value, datatype);
+        self.index_into(value, datatype, index, span)
+    }
+
+    /// Checks an index into an already checked array or slice.
+    fn index_into(
+        &mut self,
+        value: Expression,
+        datatype: Type,
+        index: &Expression,
+        span: Span,
+    ) -> Result<(Expression, Type), SemanticError> {
+        let Type::Array { element, length } = &datatype else {
+            return Err(SemanticError::NotIndexable { datatype });
+        };
+
+        let (index, index_type) = self.check_expression(index)?;
+        let index = match (index, &index_type) {
+            (_, Type::Numeric(numeric)) if numeric.is_float() => {
+                return Err(SemanticError::InvalidIndex {
+                    datatype: index_type,
+                })
+            }
+            (Expression::Number(number), _) => match convert_exact(&number, NumericType::USize) {
+                Some(index) => Expression::Number(index),
+                None => {
+                    return Err(SemanticError::IndexOutOfBounds {
+                        index: number.to_string(),
+                        length: *length,
+                    })
+                }
+            },
+            (index, Type::Numeric(NumericType::USize)) => index,
+            (index, Type::Numeric(numeric)) if !numeric.is_float() => Expression::Cast {
+                value: Box::new(index),
+                datatype: Type::Numeric(NumericType::USize),
+            },
+            _ => {
+                return Err(SemanticError::InvalidIndex {
+                    datatype: index_type,
+                })
+            }
+        };
+
+        let known_length = match &value {
+            Expression::Array(elements) => Some(elements.len()),
+            _ => *length,
+        };
+        let bounds_check = match (&index, known_length) {
+            (Expression::Number(number), Some(known_length)) => {
+                let position = unsigned_value(number).expect("converted to usize") as usize;
+                if position >= known_length {
+                    return Err(SemanticError::IndexOutOfBounds {
+                        index: position.to_string(),
+                        length: Some(known_length),
+                    });
+                }
+
+                if let Expression::Array(elements) = &value {
+                    if value.is_constant() {
+                        return Ok((elements[position].clone(), *element.clone()));
+                    }
+                }
+                false
+            }
+            _ => true,
+        };
+
+        Ok((
+            Expression::Index {
+                value: Box::new(value),
+                index: Box::new(index),
+                bounds_check,
+                span,
+            },
+            *element.clone(),
+        ))
+    }
+
+    /// Checks a field access, resolving named tuple components and struct fields to their
+    /// position.
+    ///
+    /// Arrays and slices have their length as `len`, which is constant for arrays.
+    fn check_field_access(
+        &mut self,
+        value: &Expression,
+        field: &str,
+    ) -> Result<(Expression, Type), SemanticError> {
+        if let Some(Type::Enum(name)) = self.type_receiver(value) {
+            return self.check_variant(&name, field, None, value.span());
+        }
+
+        let (value, datatype) = self.check_expression(value)?;
+        let (value, datatype) = dereference(value, datatype);
+        self.field_of(value, datatype, field)
+    }
+
+    /// Checks access to a field of an already checked value.
+    fn field_of(
+        &self,
+        value: Expression,
+        datatype: Type,
+        field: &str,
+    ) -> Result<(Expression, Type), SemanticError> {
+        let unknown_field = || SemanticError::UnknownField {
+            datatype: datatype.clone(),
+            field: field.to_string(),
+        };
+        let (index, field_type) = match &datatype {
+            Type::Array { length, .. } if field == "len" => {
+                let usize_type = Type::Numeric(NumericType::USize);
+                let value = match length {
+                    Some(length) => Expression::Number(
+                        integer(NumericType::USize, *length as i128).expect("lengths fit usize"),
+                    ),
+                    None => Expression::FieldAccess {
+                        value: Box::new(value),
+                        field: field.to_string(),
+                    },
+                };
+                return Ok((value, usize_type));
+            }
+            Type::Tuple(fields) => {
+                let index = tuple_field_index(fields, field).ok_or_else(unknown_field)?;
+                (index, fields[index].datatype.clone())
+            }
+            Type::Struct(name) => {
+                let fields = &self.structs[name];
+                let index = fields
+                    .iter()
+                    .position(|struct_field| struct_field.name == field)
+                    .ok_or_else(unknown_field)?;
+                (index, fields[index].datatype.clone())
+            }
+            _ => return Err(unknown_field()),
+        };
+
+        // components of constant tuples and structs are folded
+        if let Expression::Tuple(elements)
+        | Expression::StructLiteral {
+            fields: elements, ..
+        } = &value
+        {
+            if value.is_constant() {
+                return Ok((elements[index].value.clone(), field_type));
+            }
+        }
+
+        Ok((
+            Expression::FieldAccess {
+                value: Box::new(value),
+                field: index.to_string(),
+            },
+            field_type,
+        ))
+    }
+
+    /// Checks a place, which is a variable, an element or field of one, or what a pointer points
+    /// to. Along with it comes the error to report if it is assigned to, if it can't be.
+    ///
+    /// Unlike values, places are never folded, as they have to stay around to be changed.
+    fn check_place(
+        &mut self,
+        place: &Expression,
+        assigning: bool,
+    ) -> Result<(Expression, Type, Option<SemanticError>), SemanticError> {
+        match place {
+            Expression::Identifier { name, span } => {
+                let symbol = self.lookup(name)?;
+                if symbol.function {
+                    return Err(SemanticError::NotAssignable);
+                }
+                let immutable = (!symbol.mutable)
+                    .then(|| SemanticError::AssignmentToConstant { name: name.clone() });
+                let datatype = self.resolve(name, assigning, *span)?.datatype;
+                Ok((place.clone(), datatype, immutable))
+            }
+            Expression::Deref(pointer) => {
+                let (pointer, datatype) = self.check_expression(pointer)?;
+                pointee_place(pointer, datatype)
+            }
+            Expression::Index {
+                value, index, span, ..
+            } => {
+                let (value, datatype, immutable) = self.place_through_pointer(value, assigning)?;
+                let (element, datatype) = self.index_into(value, datatype, index, *span)?;
+                Ok((element, datatype, immutable))
+            }
+            Expression::FieldAccess { value, field } => {
+                let (value, datatype, immutable) = self.place_through_pointer(value, assigning)?;
+                match self.field_of(value, datatype, field)? {
+                    (field @ Expression::FieldAccess { .. }, datatype) => {
+                        Ok((field, datatype, immutable))
+                    }
+                    // like the length of an array
+                    _ => Err(SemanticError::NotAssignable),
+                }
+            }
+            _ => Err(SemanticError::NotAssignable),
+        }
+    }
+
+    /// Checks the place an element or field belongs to, which may also be a pointer to it.
+    fn place_through_pointer(
+        &mut self,
+        place: &Expression,
+        assigning: bool,
+    ) -> Result<(Expression, Type, Option<SemanticError>), SemanticError> {
+        let (place, datatype, immutable) = self.check_place(place, assigning)?;
+        match datatype {
+            Type::Pointer { .. } => pointee_place(place, datatype),
+            datatype => Ok((place, datatype, immutable)),
+        }
+    }
+
+    fn check_call(
+        &mut self,
+        name: &str,
+        arguments: &[Expression],
+        span: Span,
+    ) -> Result<(Expression, Type), SemanticError> {
+        let symbol = match self.resolve(name, false, span) {
+            Err(SemanticError::UndefinedIdentifier { name }) => {
+                return Err(SemanticError::UndefinedFunction { name, span })
+            }
+            symbol => symbol?,
+        };
+        let (parameters, return_type) = match &symbol.datatype {
+            Type::FuncType {
+                parameters,
+                return_type,
+            } => (parameters.clone(), *return_type.clone()),
+            datatype => {
+                return Err(SemanticError::NotCallable {
+                    name: name.to_string(),
+                    datatype: datatype.clone(),
+                    span,
+                })
+            }
+        };
+
+        if arguments.len() != parameters.len() {
+            return Err(SemanticError::ArityMismatch {
+                name: name.to_string(),
+                expected: parameters.len(),
+                found: arguments.len(),
+                span,
+            });
+        }
+
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.check_expression(argument))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.check_arguments(name, &parameters, return_type, arguments, span)
+    }
+
+    /// The type a receiver like the `Pet` in `Pet.init()` names, unless it is a value.
+    fn type_receiver(&self, receiver: &Expression) -> Option<Type> {
+        match receiver {
+            Expression::Identifier { name, .. } if self.scopes.lookup(name).is_none() => {
+                self.types.get(name).cloned()
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks a call like `pet.rename(name)`, lowering it to a call of the method with the
+    /// receiver as its first argument, or `Pet.init(name)` to a call of the function `Pet.init`.
+    fn check_method_call(
+        &mut self,
+        receiver: &Expression,
+        method: &str,
+        arguments: &[Expression],
+        span: Span,
+    ) -> Result<(Expression, Type), SemanticError> {
+        if let Some(datatype) = self.type_receiver(receiver) {
+            if let Type::Enum(name) = &datatype {
+                if self.enums[name]
+                    .iter()
+                    .any(|variant| variant.name == method)
+                {
+                    return self.check_variant(name, method, Some(arguments), span);
+                }
+            }
+
+            let function = method_name(&datatype.to_string(), method);
+            if self.scopes.lookup(&function).is_none() {
+                return Err(SemanticError::UnknownMethod {
+                    datatype,
+                    method: method.to_string(),
+                });
+            }
+            return self.check_call(&function, arguments, span);
+        }
+
+        let (checked_receiver, receiver_type) = self.check_expression(receiver)?;
+        let (_, value_type) = dereference(Expression::Nil, receiver_type.clone());
+        let unknown_method = || SemanticError::UnknownMethod {
+            datatype: receiver_type.clone(),
+            method: method.to_string(),
+        };
+        if !matches!(value_type, Type::Struct(_) | Type::Enum(_)) {
+            return Err(unknown_method());
+        }
+        let function = method_name(&value_type.to_string(), method);
+        let symbol = self.scopes.lookup(&function).ok_or_else(unknown_method)?;
+        let Type::FuncType {
+            parameters,
+            return_type,
+        } = symbol.datatype.clone()
+        else {
+            unreachable!("methods are declared as functions")
+        };
+
+        if !matches!(parameters.first(), Some(Param::Parameter { name, .. }) if name == "self") {
+            return Err(SemanticError::NotAMethod { name: function });
+        }
+        if arguments.len() + 1 != parameters.len() {
+            return Err(SemanticError::ArityMismatch {
+                name: function,
+                expected: parameters.len() - 1,
+                found: arguments.len(),
+                span,
+            });
+        }
+
+        // the receiver is dereferenced or has its address taken to match `self`
+        let Param::Parameter {
+            datatype: self_type,
+            ..
+        } = &parameters[0];
+        let receiver = match (self_type, &receiver_type) {
+            (Type::Pointer { .. }, Type::Pointer { .. }) => (checked_receiver, receiver_type),
+            (Type::Pointer { mutable, .. }, _) => {
+                let (place, _, immutable) = self
+                    .check_place(receiver, false)
+                    .map_err(|_| SemanticError::AddressOfTemporary)?;
+                match immutable {
+                    Some(error) if *mutable => return Err(error),
+                    _ => {}
+                }
+                self.borrow();
+                (
+                    Expression::AddressOf(Box::new(place)),
+                    Type::Pointer {
+                        pointee: Box::new(receiver_type),
+                        mutable: *mutable,
+                    },
+                )
+            }
+            _ => dereference(checked_receiver, receiver_type),
+        };
+
+        let mut checked_arguments = vec![receiver];
+        for argument in arguments {
+            checked_arguments.push(self.check_expression(argument)?);
+        }
+        self.check_arguments(
+            &function,
+            &parameters,
+            *return_type,
+            checked_arguments,
+            span,
+        )
+    }
+
+    /// Checks a value of an enum like `Shape.Square` or `Shape.Circle(radius)` at `span`, where
+    /// several arguments make up a tuple payload.
+    fn check_variant(
+        &mut self,
+        enum_name: &str,
+        variant: &str,
+        arguments: Option<&[Expression]>,
+        span: Span,
+    ) -> Result<(Expression, Type), SemanticError> {
+        let datatype = Type::Enum(enum_name.to_string());
+        let definition = self.enums[enum_name]
+            .iter()
+            .find(|definition| definition.name == variant)
+            .cloned()
+            .ok_or_else(|| SemanticError::UnknownVariant {
+                datatype: datatype.clone(),
+                variant: variant.to_string(),
+            })?;
+        let arity_mismatch = |expected, found| SemanticError::ArityMismatch {
+            name: method_name(enum_name, variant),
+            expected,
+            found,
+            span,
+        };
+
+        let payload = match (&definition.payload, arguments) {
+            (None, None) => None,
+            (None, Some([])) => None,
+            (None, Some(arguments)) => return Err(arity_mismatch(0, arguments.len())),
+            (Some(_), None) => return Err(arity_mismatch(1, 0)),
+            (Some(_), Some([])) => return Err(arity_mismatch(1, 0)),
+            (Some(payload_type), Some(arguments)) => {
+                let argument = match arguments {
+                    [argument] => argument.clone(),
+                    _ => Expression::Tuple(
+                        arguments
+                            .iter()
+                            .map(|argument| TupleElement {
+                                name: None,
+                                value: argument.clone(),
+                            })
+                            .collect(),
+                    ),
+                };
+                let (value, value_type) = self.check_expression(&argument)?;
+                Some(Box::new(self.coerce(value, &value_type, payload_type)?))
+            }
+        };
+
+        Ok((
+            Expression::Variant {
+                enum_name: enum_name.to_string(),
+                variant: variant.to_string(),
+                payload,
+            },
+            datatype,
+        ))
+    }
+
+    /// Checks a match, in which every value has to be covered by an arm and every arm has to
+    /// be reachable. Matches on constants are folded to the arm taken.
+    fn check_match(
+        &mut self,
+        match_expression: &Match,
+    ) -> Result<(Expression, Type), SemanticError> {
+        let (value, value_type) = self.check_expression(&match_expression.value)?;
+
+        let mut arms: Vec<(Pattern, Vec<Type>, Expression, Type)> = vec![];
+        for arm in &match_expression.arms {
+            self.scopes.enter();
+            let checked = self
+                .check_pattern(&arm.pattern, &value_type, match_expression.span)
+                .and_then(|(pattern, binding_types)| {
+                    let (arm_value, arm_type) = self.check_expression(&arm.value)?;
+                    Ok((pattern, binding_types, arm_value, arm_type))
+                });
+            self.scopes.exit();
+            let checked = checked?;
+
+            let previous = arms.iter().map(|(pattern, ..)| pattern).collect::<Vec<_>>();
+            if covered(&previous, &checked.0, &value_type, &self.enums) {
+                return Err(SemanticError::UnreachablePattern {
+                    pattern: checked.0.to_string(),
+                    span: match_expression.span,
+                });
+            }
+            arms.push(checked);
+        }
+
+        let patterns = arms.iter().map(|(pattern, ..)| pattern).collect::<Vec<_>>();
+        if let Some(missing) = uncovered(&patterns, &value_type, &self.enums) {
+            return Err(SemanticError::NonExhaustiveMatch {
+                datatype: value_type,
+                missing: missing.to_string(),
+                span: match_expression.span,
+            });
+        }
+
+        let datatype = self.join_values(
+            arms.iter()
+                .map(|(_, _, arm_value, arm_type)| (arm_value, arm_type)),
+        )?;
+
+        let mut checked_arms = vec![];
+        for (pattern, binding_types, arm_value, arm_type) in arms {
+            let arm_value = self.coerce(arm_value, &arm_type, &datatype)?;
+
+            // on a constant, the first matching arm is taken with its bindings as constants
+            if value.is_constant() {
+                if let Some(values) = bind_constant(&pattern, &value) {
+                    let statements = bindings(&pattern)
+                        .into_iter()
+                        .zip(binding_types)
+                        .zip(values)
+                        .map(|((name, binding_type), value)| Statement::Declaration {
+                            public: false,
+                            mutable: false,
+                            name: name.clone(),
+                            datatype: binding_type,
+                            value,
+                        })
+                        .collect();
+                    let block = Block {
+                        statements,
+                        return_value: Box::new(arm_value),
+                    };
+                    return Ok((block_expression(block), datatype));
+                }
+                continue;
+            }
+
+            checked_arms.push(MatchArm {
+                pattern,
+                value: arm_value,
+            });
+        }
+
+        Ok((
+            Expression::Match(Match {
+                value: Box::new(value),
+                arms: checked_arms,
+                span: match_expression.span,
+            }),
+            datatype,
+        ))
+    }
+
+    /// Checks a pattern of the match at `span` against the type of the values it is matched
+    /// with, declaring its bindings in the current scope. The types of the bindings are returned
+    /// in order.
+    fn check_pattern(
+        &mut self,
+        pattern: &Pattern,
+        datatype: &Type,
+        span: Span,
+    ) -> Result<(Pattern, Vec<Type>), SemanticError> {
+        match pattern {
+            Pattern::Wildcard => Ok((Pattern::Wildcard, vec![])),
+            Pattern::Binding(name) => {
+                self.scopes.declare(
+                    name,
+                    Symbol {
+                        datatype: datatype.clone(),
+                        mutable: false,
+                        function: false,
+                        value: None,
+                        borrowing: false,
+                    },
+                )?;
+                Ok((pattern.clone(), vec![datatype.clone()]))
+            }
+            Pattern::Literal(literal) => {
+                let (literal, literal_type) = self.check_expression(literal)?;
+                let literal = self.coerce(literal, &literal_type, datatype)?;
+                Ok((Pattern::Literal(literal), vec![]))
+            }
+            Pattern::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                let Type::Enum(name) = datatype else {
+                    return Err(SemanticError::MismatchedTypes {
+                        expected: datatype.clone(),
+                        found: match enum_name {
+                            Some(enum_name) => self.named_type(enum_name)?,
+                            None => Type::Named(format!(".{}", variant)),
+                        },
+                        span,
+                    });
+                };
+                if let Some(enum_name) = enum_name {
+                    let found = self.named_type(enum_name)?;
+                    if found != *datatype {
+                        return Err(SemanticError::MismatchedTypes {
+                            expected: datatype.clone(),
+                            found,
+                            span,
+                        });
+                    }
+                }
+
+                let definition = self.enums[name]
+                    .iter()
+                    .find(|definition| definition.name == *variant)
+                    .cloned()
+                    .ok_or_else(|| SemanticError::UnknownVariant {
+                        datatype: datatype.clone(),
+                        variant: variant.clone(),
+                    })?;
+
+                // leaving out the payload of a variant ignores it
+                let (payload, binding_types) = match (payload, &definition.payload) {
+                    (None, None) => (None, vec![]),
+                    (None, Some(_)) => (Some(Box::new(Pattern::Wildcard)), vec![]),
+                    (Some(_), None) => {
+                        return Err(SemanticError::ArityMismatch {
+                            name: method_name(name, variant),
+                            expected: 0,
+                            found: 1,
+                            span,
+                        })
+                    }
+                    (Some(payload), Some(payload_type)) => {
+                        // a payload of several fields may be matched whole or field by field
+                        let fields = match payload_type {
+                            Type::Tuple(fields) => fields.len(),
+                            _ => 1,
+                        };
+                        if let Pattern::Tuple(elements) = &**payload {
+                            if elements.len() != fields {
+                                return Err(SemanticError::ArityMismatch {
+                                    name: method_name(name, variant),
+                                    expected: fields,
+                                    found: elements.len(),
+                                    span,
+                                });
+                            }
+                        }
+                        let (payload, binding_types) =
+                            self.check_pattern(payload, payload_type, span)?;
+                        (Some(Box::new(payload)), binding_types)
+                    }
+                };
+
+                Ok((
+                    Pattern::Variant {
+                        enum_name: Some(name.clone()),
+                        variant: variant.clone(),
+                        payload,
+                    },
+                    binding_types,
+                ))
+            }
+            Pattern::Tuple(elements) => {
+                let fields = match datatype {
+                    Type::Tuple(fields) if fields.len() == elements.len() => fields,
+                    _ => {
+                        return Err(SemanticError::DestructuringMismatch {
+                            names: elements.len(),
+                            datatype: datatype.clone(),
+                        })
+                    }
+                };
+
+                let mut checked_elements = vec![];
+                let mut binding_types = vec![];
+                for (element, field) in elements.iter().zip(fields) {
+                    let (element, element_types) =
+                        self.check_pattern(element, &field.datatype, span)?;
+                    checked_elements.push(element);
+                    binding_types.extend(element_types);
+                }
+                Ok((Pattern::Tuple(checked_elements), binding_types))
+            }
+        }
+    }
+
+    /// Converts the already checked arguments of a call to the types of the parameters.
+    fn check_arguments(
+        &self,
+        name: &str,
+        parameters: &[Param],
+        return_type: Type,
+        arguments: Vec<(Expression, Type)>,
+        span: Span,
+    ) -> Result<(Expression, Type), SemanticError> {
+        let mut checked_arguments = vec![];
+        for (
+            (argument, argument_type),
+            Param::Parameter {
+                name: parameter,
+                datatype,
+            },
+        ) in arguments.into_iter().zip(parameters)
+        {
+            let argument = self
+                .coerce(argument, &argument_type, datatype)
+                .map_err(|error| match error {
+                    // other errors, like constants out of range, say more than a mismatch
+                    SemanticError::MismatchedTypes { .. } => SemanticError::MismatchedArgument {
+                        function: name.to_string(),
+                        parameter: parameter.clone(),
+                        expected: Box::new(datatype.clone()),
+                        found: Box::new(argument_type),
+                        span,
+                    },
+                    error => error,
+                })?;
+            checked_arguments.push(argument);
+        }
+
+        Ok((
+            Expression::FunctionCall {
+                name: name.to_string(),
+                arguments: checked_arguments,
+                span,
+                tail: false,
+            },
+            return_type,
+        ))
+    }
+
+    /// Declares the structs, enums and the signatures of all functions in a statement list up
+    /// front, so that they may be used before their definition and refer to each other.
+    fn declare_items(&mut self, statements: &[Statement]) -> Result<(), SemanticError> {
+        // type names come first, so that fields, variants and signatures may use any of them
+        for statement in statements {
+            if let Some((name, factory)) = type_factory(statement)? {
+                if self.declares_type(name) {
+                    return Err(SemanticError::DuplicateType { name: name.clone() });
+                }
+                self.factories.insert(name.clone(), factory);
+            }
+
+            let Statement::TypeDeclaration {
+                name, definition, ..
+            } = statement
+            else {
+                continue;
+            };
+            if self.declares_type(name) {
+                return Err(SemanticError::DuplicateType { name: name.clone() });
+            }
+            match definition {
+                TypeDefinition::Struct { .. } => {
+                    self.types.insert(name.clone(), Type::Struct(name.clone()));
+                }
+                TypeDefinition::Enum { .. } => {
+                    self.types.insert(name.clone(), Type::Enum(name.clone()));
+                }
+                TypeDefinition::Alias(datatype) => {
+                    self.aliases.insert(name.clone(), datatype.clone());
+                }
+            }
+        }
+
+        for statement in statements {
+            let Statement::TypeDeclaration {
+                name, definition, ..
+            } = statement
+            else {
+                continue;
+            };
+            match definition {
+                // resolved here even if unused, so that every alias is checked
+                TypeDefinition::Alias(_) => {
+                    self.named_type(name)?;
+                }
+                definition => self.define_type(name, definition)?,
+            }
+        }
+
+        let mut declared = HashSet::new();
+        let top_level = self.at_top_level();
+        for statement in statements {
+            if let Statement::FuncDeclaration {
+                name,
+                parameters,
+                return_type,
+                ..
+            } = statement
+            {
+                if !is_type_factory(statement) {
+                    self.declare_function(&mut declared, name, parameters, return_type, false)?;
+                    if top_level {
+                        self.unchecked
+                            .insert(name.clone(), (statement.clone(), None));
+                    }
+                }
+            }
+
+            if let Some((name, datatype, methods)) = type_declaration(statement) {
+                self.declare_methods(&mut declared, name, datatype.clone(), methods, false)?;
+                if top_level {
+                    for method in methods {
+                        let method = renamed_method(name, method);
+                        let Statement::FuncDeclaration { name, .. } = &method else {
+                            unreachable!("types only contain methods")
+                        };
+                        self.unchecked
+                            .insert(name.clone(), (method, Some(datatype.clone())));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the statements being validated are those of the top level of the program.
+    fn at_top_level(&self) -> bool {
+        self.scopes.depth() == 1 && self.functions.is_empty()
+    }
+
+    /// Validates a function of the top level ahead of its declaration, in the scope of the top
+    /// level, for a `comptime` block calling it.
+    fn check_ahead(
+        &mut self,
+        declaration: Statement,
+        self_type: Option<Type>,
+    ) -> Result<(), SemanticError> {
+        let scopes = self.scopes.leave_to_global();
+        let functions = std::mem::take(&mut self.functions);
+        let outer = std::mem::replace(&mut self.self_type, self_type);
+        let checked = self.visit_statement(&declaration);
+        self.scopes.restore(scopes);
+        self.functions = functions;
+        self.self_type = outer;
+
+        let checked = checked?;
+        if let Statement::FuncDeclaration { name, .. } = &checked {
+            self.checked_ahead.insert(name.clone(), checked.clone());
+        }
+        Ok(())
+    }
+
+    /// Resolves the fields of a struct or the variants of an enum.
+    fn define_type(
+        &mut self,
+        name: &str,
+        definition: &TypeDefinition,
+    ) -> Result<(), SemanticError> {
+        match definition {
+            TypeDefinition::Struct { fields, .. } => {
+                let mut checked_fields: Vec<StructField> = vec![];
+                for field in fields {
+                    if checked_fields
+                        .iter()
+                        .any(|checked| checked.name == field.name)
+                    {
+                        return Err(SemanticError::DuplicateField {
+                            field: field.name.clone(),
+                        });
+                    }
+                    checked_fields.push(StructField {
+                        name: field.name.clone(),
+                        datatype: self.visit_type(&field.datatype)?,
+                    });
+                }
+                self.structs.insert(name.to_string(), checked_fields);
+            }
+            TypeDefinition::Enum { variants, .. } => {
+                let mut checked_variants: Vec<Variant> = vec![];
+                for variant in variants {
+                    if checked_variants
+                        .iter()
+                        .any(|checked| checked.name == variant.name)
+                    {
+                        return Err(SemanticError::DuplicateVariant {
+                            name: variant.name.clone(),
+                        });
+                    }
+                    let payload = match &variant.payload {
+                        Some(payload) => Some(self.visit_type(payload)?),
+                        None => None,
+                    };
+                    checked_variants.push(Variant {
+                        name: variant.name.clone(),
+                        payload,
+                    });
+                }
+                self.enums.insert(name.to_string(), checked_variants);
+            }
+            TypeDefinition::Alias(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Declares the signatures of the methods of a struct or an enum. Those of instances of type
+    /// factories are declared globally, since instances may be created from anywhere.
+    fn declare_methods(
+        &mut self,
+        declared: &mut HashSet<String>,
+        name: &str,
+        datatype: Type,
+        methods: &[Statement],
+        global: bool,
+    ) -> Result<(), SemanticError> {
+        let outer = self.self_type.replace(datatype);
+        let result = methods.iter().try_for_each(|method| {
+            let Statement::FuncDeclaration {
+                name: method,
+                parameters,
+                return_type,
+                ..
+            } = method
+            else {
+                unreachable!("types only contain methods")
+            };
+            let function = method_name(name, method);
+            self.declare_function(declared, &function, parameters, return_type, global)
+        });
+        self.self_type = outer;
+        result
+    }
+
+    /// Whether a name is already taken by a type, an alias or a type factory.
+    fn declares_type(&self, name: &str) -> bool {
+        self.types.contains_key(name)
+            || self.aliases.contains_key(name)
+            || self.factories.contains_key(name)
+    }
+
+    /// Validates the methods of a struct or an enum, declared under names like `Pet.init`.
+    fn check_methods(
+        &mut self,
+        name: &str,
+        datatype: Type,
+        methods: &[Statement],
+    ) -> Result<Vec<Statement>, SemanticError> {
+        let outer = self.self_type.replace(datatype);
+        let methods = methods
+            .iter()
+            .map(|method| self.visit_statement(&renamed_method(name, method)))
+            .collect::<Result<Vec<_>, _>>();
+        self.self_type = outer;
+        methods
+    }
+
+    fn declare_function(
+        &mut self,
+        declared: &mut HashSet<String>,
+        name: &str,
+        parameters: &[Param],
+        return_type: &Type,
+        global: bool,
+    ) -> Result<(), SemanticError> {
+        if !declared.insert(name.to_string()) {
+            return Err(SemanticError::DuplicateFunction {
+                name: name.to_string(),
+            });
+        }
+
+        let datatype = self.function_type(parameters, return_type)?;
+        let symbol = Symbol {
+            datatype,
+            mutable: false,
+            function: true,
+            value: None,
+            borrowing: false,
+        };
+        if global {
+            self.scopes.declare_global(name, symbol);
+        } else {
+            self.scopes.declare(name, symbol)?;
+        }
+
+        Ok(())
+    }
+
+    /// Resolves a type referred to by name, following aliases until they reach a type that is
+    /// not an alias.
+    fn named_type(&mut self, name: &str) -> Result<Type, SemanticError> {
+        match (&self.self_type, self.types.get(name)) {
+            (Some(self_type), _) if name == "Self" => return Ok(self_type.clone()),
+            (_, Some(datatype)) => return Ok(datatype.clone()),
+            _ => {}
+        }
+
+        let Some(target) = self.aliases.get(name).cloned() else {
+            return Err(match self.factories.get(name) {
+                Some(factory) => SemanticError::ArityMismatch {
+                    name: name.to_string(),
+                    expected: factory.parameters.len(),
+                    found: 0,
+                    span: Span::default(),
+                },
+                None => SemanticError::UndefinedType {
+                    name: name.to_string(),
+                },
+            });
+        };
+
+        let resolved = self.resolve_alias(name, &target)?;
+        self.aliases.remove(name);
+        self.types.insert(name.to_string(), resolved.clone());
+        Ok(resolved)
+    }
+
+    /// Resolves the type an alias stands for, failing if resolving it requires the alias itself.
+    fn resolve_alias(&mut self, name: &str, target: &Type) -> Result<Type, SemanticError> {
+        if let Some(start) = self.resolving.iter().position(|alias| alias == name) {
+            let mut cycle = self.resolving[start..].to_vec();
+            cycle.push(name.to_string());
+            return Err(SemanticError::CyclicType { cycle });
+        }
+
+        self.resolving.push(name.to_string());
+        let resolved = self.visit_type(target);
+        self.resolving.pop();
+        resolved
+    }
+
+    /// Evaluates a type factory, creating a type once for every distinct list of arguments.
+    fn instantiate(&mut self, name: &str, arguments: &[Type]) -> Result<Type, SemanticError> {
+        let factory =
+            self.factories
+                .get(name)
+                .cloned()
+                .ok_or_else(|| SemanticError::UndefinedType {
+                    name: name.to_string(),
+                })?;
+        if arguments.len() != factory.parameters.len() {
+            return Err(SemanticError::ArityMismatch {
+                name: name.to_string(),
+                expected: factory.parameters.len(),
+                found: arguments.len(),
+                span: Span::default(),
+            });
+        }
+
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.visit_type(argument))
+            .collect::<Result<Vec<_>, _>>()?;
+        let instance = instance_name(name, &arguments);
+        if let Some(datatype) = self.instances.get(&instance) {
+            return Ok(datatype.clone());
+        }
+
+        let arguments: Vec<_> = factory.parameters.into_iter().zip(arguments).collect();
+        let outer = self.bind_types(&arguments);
+        let datatype = match &factory.definition {
+            TypeDefinition::Alias(target) => self.resolve_alias(&instance, target),
+            definition => {
+                let datatype = match definition {
+                    TypeDefinition::Struct { .. } => Type::Struct(instance.clone()),
+                    _ => Type::Enum(instance.clone()),
+                };
+                // registered up front, so that the definition may refer to its own instance
+                self.instances.insert(instance.clone(), datatype.clone());
+                self.define_instance(&instance, datatype, definition, arguments)
+            }
+        };
+        self.unbind_types(outer);
+
+        let datatype = datatype?;
+        self.instances.insert(instance, datatype.clone());
+        Ok(datatype)
+    }
+
+    /// Resolves the fields or variants of an instance and declares its methods, leaving the
+    /// methods themselves to be validated at the end of the program.
+    fn define_instance(
+        &mut self,
+        name: &str,
+        datatype: Type,
+        definition: &TypeDefinition,
+        arguments: Vec<(String, Type)>,
+    ) -> Result<Type, SemanticError> {
+        self.define_type(name, definition)?;
+        if let TypeDefinition::Struct { methods, .. } | TypeDefinition::Enum { methods, .. } =
+            definition
+        {
+            self.declare_methods(&mut HashSet::new(), name, datatype.clone(), methods, true)?;
+        }
+
+        self.pending_instances.push(Instance {
+            name: name.to_string(),
+            definition: definition.clone(),
+            arguments,
+        });
+        Ok(datatype)
+    }
+
+    /// Binds the parameters of a type factory to their arguments, returning what the names
+    /// referred to before.
+    fn bind_types(&mut self, arguments: &[(String, Type)]) -> Vec<(String, Option<Type>)> {
+        arguments
+            .iter()
+            .map(|(name, datatype)| {
+                let outer = self.types.insert(name.clone(), datatype.clone());
+                (name.clone(), outer)
+            })
+            .collect()
+    }
+
+    fn unbind_types(&mut self, outer: Vec<(String, Option<Type>)>) {
+        for (name, datatype) in outer.into_iter().rev() {
+            match datatype {
+                Some(datatype) => self.types.insert(name, datatype),
+                None => self.types.remove(&name),
+            };
+        }
+    }
+
+    /// Validates the methods of the instances of type factories, which may create further
+    /// instances. Their declarations are returned in the order they were created in.
+    fn check_instances(&mut self) -> Result<Vec<Statement>, SemanticError> {
+        let mut declarations = vec![];
+        while !self.pending_instances.is_empty() {
+            let Instance {
+                name,
+                definition,
+                arguments,
+            } = self.pending_instances.remove(0);
+
+            let outer = self.bind_types(&arguments);
+            let declaration = self.visit_statement(&Statement::TypeDeclaration {
+                public: false,
+                name,
+                definition,
+            });
+            self.unbind_types(outer);
+            declarations.push(declaration?);
+        }
+        Ok(declarations)
+    }
+
+    fn function_type(
+        &mut self,
+        parameters: &[Param],
+        return_type: &Type,
+    ) -> Result<Type, SemanticError> {
+        let mut checked_parameters: Vec<Param> = vec![];
+        for Param::Parameter { name, datatype } in parameters {
+            if checked_parameters
+                .iter()
+                .any(|Param::Parameter { name: checked, .. }| checked == name)
+            {
+                return Err(SemanticError::DuplicateParameter {
+                    parameter: name.clone(),
+                });
+            }
+            checked_parameters.push(Param::Parameter {
+                name: name.clone(),
+                datatype: self.visit_type(datatype)?,
+            });
+        }
+
+        Ok(Type::FuncType {
+            parameters: checked_parameters,
+            return_type: Box::new(self.visit_type(return_type)?),
+        })
+    }
+
+    /// Checks a closure, recording the variables its body captures.
+    fn check_closure(&mut self, closure: &Expression) -> Result<(Expression, Type), SemanticError> {
+        let Expression::Function {
+            parameters,
+            return_type,
+            body,
+            by_reference,
+            ..
+        } = closure
+        else {
+            unreachable!("only closures are checked as closures")
+        };
+        let datatype = self.function_type(parameters, return_type)?;
+        let return_type = self.visit_type(return_type)?;
+        if *by_reference {
+            self.borrow();
+        }
+
+        let frame = FunctionFrame {
+            name: "closure".to_string(),
+            boundary: self.scopes.depth(),
+            closure: true,
+            by_reference: *by_reference,
+            captures: vec![],
+            borrows: false,
+        };
+        let (parameters, body, captures) =
+            self.check_function_body(frame, parameters, &return_type, body)?;
+
+        Ok((
+            Expression::Function {
+                parameters,
+                return_type: Box::new(return_type),
+                body,
+                by_reference: *by_reference,
+                captures,
+            },
+            datatype,
+        ))
+    }
+
+    /// Checks the body of a function in its own scope, returning the checked parameters and
+    /// body along with the variables captured by it.
+    fn check_function_body(
+        &mut self,
+        frame: FunctionFrame,
+        parameters: &[Param],
+        return_type: &Type,
+        body: &Block,
+    ) -> Result<(Vec<Param>, Block, Vec<Capture>), SemanticError> {
+        self.functions.push(frame);
+        self.scopes.enter();
+        let signature_and_body = parameters
+            .iter()
+            .map(|param| self.visit_param(param))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|parameters| Ok((parameters, self.check_block_in_scope(body)?)));
+        self.scopes.exit();
+        let frame = self.functions.pop().expect("pushed above");
+        let (parameters, (body, body_type)) = signature_and_body?;
+
+        // the value of a body returning `nil` is discarded
+        let mut body = if *return_type == Type::Nil {
+            body
+        } else {
+            self.coerce_block(body, &body_type, return_type)?
+        };
+        if let Some(name) = escaping_address(&parameters, &body) {
+            return Err(SemanticError::EscapingAddress {
+                name: name.to_string(),
+                function: frame.name,
+            });
+        }
+
+        // calling itself can't reuse the variables of a function which may be pointed to
+        if !frame.closure && !frame.borrows {
+            mark_tail_calls(&frame.name, &parameters, &mut body);
+        }
+
+        Ok((parameters, body, frame.captures))
+    }
+
+    /// The function the value of a function-typed declaration names, like the `dbl` of
+    /// `const g: (x: int) -> int = dbl;` or the call of `const f: () -> int = make();`, which
+    /// the parser made the body of a closure not knowing its type already is the declared one.
+    fn function_valued<'a>(
+        &self,
+        value: &'a Expression,
+        datatype: &Type,
+    ) -> Option<&'a Expression> {
+        let Expression::Function { body, .. } = value else {
+            return None;
+        };
+        if !body.statements.is_empty() {
+            return None;
+        }
+
+        let function = &*body.return_value;
+        let value_type = match function {
+            Expression::Identifier { name, .. } => self.scopes.lookup(name)?.datatype.clone(),
+            Expression::FunctionCall { name, .. } => match &self.scopes.lookup(name)?.datatype {
+                Type::FuncType { return_type, .. } => *return_type.clone(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        same_type(&value_type, datatype).then_some(function)
+    }
+
+    /// Whether a value is a closure using variables of the function being checked by reference,
+    /// itself or through the closures it calls.
+    fn borrows_variables(&self, value: &Expression) -> bool {
+        let Expression::Function { captures, .. } = value else {
+            return false;
+        };
+        captures.iter().any(|capture| {
+            capture.by_reference
+                || self
+                    .scopes
+                    .lookup(&capture.name)
+                    .is_some_and(|symbol| symbol.borrowing)
+        })
+    }
+
+    /// Notes that the function being checked takes the address of a variable.
+    fn borrow(&mut self) {
+        if let Some(frame) = self.functions.last_mut() {
+            frame.borrows = true;
+        }
+    }
+
+    fn check_block(&mut self, block: &Block) -> Result<(Block, Type), SemanticError> {
+        self.scopes.enter();
+        let block = self.check_block_in_scope(block);
+        self.scopes.exit();
+        block
+    }
+
+    /// Checks the statements of a block in the current scope.
+    fn check_block_in_scope(&mut self, block: &Block) -> Result<(Block, Type), SemanticError> {
+        self.declare_items(&block.statements)?;
+
+        let mut statements = vec![];
+        for stmt in &block.statements {
+            if !is_type_factory(stmt) {
+                statements.push(self.visit_statement(stmt)?);
+            }
+        }
+        let (return_value, datatype) = self.check_expression(&block.return_value)?;
+
+        Ok((
+            Block {
+                statements,
+                return_value: Box::new(return_value),
+            },
+            datatype,
+        ))
+    }
+
+    fn check_conditional_block(
+        &mut self,
+        conditional_block: &ConditionalBlock,
+    ) -> Result<(ConditionalBlock, Type), SemanticError> {
+        let (condition, condition_type) = self.check_expression(&conditional_block.condition)?;
+        if condition_type != Type::Bool {
+            return Err(SemanticError::NonBooleanCondition {
+                found: condition_type,
+            });
+        }
+
+        let (block, datatype) = self.check_block(&conditional_block.block)?;
+
+        Ok((
+            ConditionalBlock {
+                condition: Box::new(condition),
+                block,
+            },
+            datatype,
+        ))
+    }
+
+    fn check_if(&mut self, if_expression: &If) -> Result<(Expression, Type), SemanticError> {
+        let mut branches = vec![];
+        let conditional_blocks =
+            std::iter::once(&if_expression.if_block).chain(&if_expression.else_if_blocks);
+        for conditional_block in conditional_blocks {
+            branches.push(self.check_conditional_block(conditional_block)?);
+        }
+
+        let else_block = match &if_expression.else_block {
+            Some(block) => Some(self.check_block(block)?),
+            None => None,
+        };
+
+        // without an else branch the value of the if is discarded
+        let datatype = match &else_block {
+            Some(else_block) => self.join_values(
+                std::iter::once((&else_block.0, &else_block.1))
+                    .chain(
+                        branches
+                            .iter()
+                            .map(|(branch, branch_type)| (&branch.block, branch_type)),
+                    )
+                    .map(|(block, datatype)| (&*block.return_value, datatype)),
+            )?,
+            None => Type::Nil,
+        };
+
+        let mut kept = vec![];
+        let mut taken = None;
+        for (mut conditional_block, branch_type) in branches {
+            if else_block.is_some() {
+                conditional_block.block =
+                    self.coerce_block(conditional_block.block, &branch_type, &datatype)?;
+            }
+
+            match *conditional_block.condition {
+                // never taken, the branch is dropped
+                Expression::Boolean(false) => {}
+                // always taken, the remaining branches are unreachable
+                Expression::Boolean(true) => {
+                    taken = Some(conditional_block.block);
+                    break;
+                }
+                _ => kept.push(conditional_block),
+            }
+        }
+
+        let else_block = match (taken, else_block) {
+            (Some(block), _) => Some(block),
+            (None, Some((block, else_type))) => {
+                Some(self.coerce_block(block, &else_type, &datatype)?)
+            }
+            (None, None) => None,
+        };
+
+        if kept.is_empty() {
+            let value = else_block.map_or(Expression::Nil, block_expression);
+            return Ok((value, datatype));
+        }
+
+        let if_block = kept.remove(0);
+        Ok((
+            Expression::If(If {
+                if_block,
+                else_if_blocks: kept,
+                else_block,
+            }),
+            datatype,
+        ))
+    }
+
+    /// Finds the type all the values of branches can be converted to, where constants only
+    /// take part if they don't fit the type of the other branches.
+    fn join_values<'a>(
+        &self,
+        values: impl Iterator<Item = (&'a Expression, &'a Type)> + Clone,
+    ) -> Result<Type, SemanticError> {
+        let (constants, values): (Vec<_>, Vec<_>) =
+            values.partition(|(value, _)| value.is_constant());
+
+        let mut joined: Option<Type> = None;
+        for (value, datatype) in &values {
+            joined = Some(match joined {
+                Some(joined) => self.join_branches(&joined, datatype, value.span())?,
+                None => (*datatype).clone(),
+            });
+        }
+        for (position, (value, datatype)) in constants.iter().enumerate() {
+            let absorbs = |datatype: &Type, (value, from): &(&Expression, &Type)| {
+                self.coerce((*value).clone(), from, datatype).is_ok()
+            };
+            joined = Some(match joined {
+                Some(joined) if absorbs(&joined, &(value, datatype)) => joined,
+                // a constant may just as well take in the constants before it, as `2.5` does `1`
+                Some(_)
+                    if values.is_empty()
+                        && constants[..position]
+                            .iter()
+                            .all(|constant| absorbs(datatype, constant)) =>
+                {
+                    (*datatype).clone()
+                }
+                Some(joined) => self.join_branches(&joined, datatype, value.span())?,
+                None => (*datatype).clone(),
+            });
+        }
+
+        Ok(joined.unwrap_or(Type::Nil))
+    }
+
+    fn join_branches(&self, left: &Type, right: &Type, span: Span) -> Result<Type, SemanticError> {
+        match (left, right) {
+            _ if same_type(left, right) => Ok(left.clone()),
+            // branches which may be nil make the value optional
+            (Type::Nil, Type::Optional(_)) => Ok(right.clone()),
+            (Type::Optional(_), Type::Nil) => Ok(left.clone()),
+            (Type::Nil, datatype) | (datatype, Type::Nil) => {
+                Ok(Type::Optional(Box::new(datatype.clone())))
+            }
+            (Type::Optional(left), Type::Optional(right)) => Ok(Type::Optional(Box::new(
+                self.join_branches(left, right, span)?,
+            ))),
+            (Type::Optional(inner), datatype) | (datatype, Type::Optional(inner)) => Ok(
+                Type::Optional(Box::new(self.join_branches(inner, datatype, span)?)),
+            ),
+            (Type::Numeric(numeric_left), Type::Numeric(numeric_right)) => {
+                match join(*numeric_left, *numeric_right) {
+                    Some(joined) => Ok(Type::Numeric(joined)),
+                    None => Err(SemanticError::MismatchedTypes {
+                        expected: left.clone(),
+                        found: right.clone(),
+                        span,
+                    }),
+                }
+            }
+            _ => Err(SemanticError::MismatchedTypes {
+                expected: left.clone(),
+                found: right.clone(),
+                span,
+            }),
+        }
+    }
+
+    fn coerce_block(&self, block: Block, from: &Type, to: &Type) -> Result<Block, SemanticError> {
+        Ok(Block {
+            statements: block.statements,
+            return_value: Box::new(self.coerce(*block.return_value, from, to)?),
+        })
+    }
+}
+
+impl Default for Validator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A method of a type as the function it is declared as, named like `Pet.init`.
+fn renamed_method(datatype: &str, method: &Statement) -> Statement {
+    let Statement::FuncDeclaration {
+        public,
+        inline,
+        name,
+        parameters,
+        return_type,
+        body,
+    } = method
+    else {
+        unreachable!("types only contain methods")
+    };
+    Statement::FuncDeclaration {
+        public: *public,
+        inline: *inline,
+        name: method_name(datatype, name),
+        parameters: parameters.clone(),
+        return_type: return_type.clone(),
+        body: body.clone(),
+    }
+}
+
+/// The name a method is declared under, like `Pet.init`.
+fn method_name(datatype: &str, method: &str) -> String {
+    format!("{}.{}", datatype, method)
+}
+
+/// What a checked pointer points to as a place, which can only be assigned to through `*T`.
+fn pointee_place(
+    pointer: Expression,
+    datatype: Type,
+) -> Result<(Expression, Type, Option<SemanticError>), SemanticError> {
+    let Type::Pointer { pointee, mutable } = &datatype else {
+        return Err(SemanticError::NotAPointer { datatype });
+    };
+    let pointee = *pointee.clone();
+    let immutable = (!mutable).then_some(SemanticError::AssignmentThroughConst { datatype });
+    Ok((Expression::Deref(Box::new(pointer)), pointee, immutable))
+}
+
+/// Dereferences a pointer, leaving any other value as it is.
+fn dereference(value: Expression, datatype: Type) -> (Expression, Type) {
+    match datatype {
+        Type::Pointer { pointee, .. } => (Expression::Deref(Box::new(value)), *pointee),
+        datatype => (value, datatype),
+    }
+}
+
+/// The name, type and methods of a struct or an enum declaration.
+fn type_declaration(statement: &Statement) -> Option<(&String, Type, &[Statement])> {
+    match statement {
+        Statement::TypeDeclaration {
+            name,
+            definition: TypeDefinition::Struct { methods, .. },
+            ..
+        } => Some((name, Type::Struct(name.clone()), methods)),
+        Statement::TypeDeclaration {
+            name,
+            definition: TypeDefinition::Enum { methods, .. },
+            ..
+        } => Some((name, Type::Enum(name.clone()), methods)),
+        _ => None,
+    }
+}
+
+/// Turns a block back into an expression, collapsing it if it declares nothing but constants
+/// it has already propagated.
+fn block_expression(block: Block) -> Expression {
+    let only_constants = block.statements.iter().all(|statement| {
+        matches!(
+            statement,
+            Statement::Declaration { mutable: false, value, .. } if value.is_constant()
+        )
+    });
+
+    if block.statements.is_empty() || (only_constants && block.return_value.is_constant()) {
+        *block.return_value
+    } else {
+        Expression::Block {
+            statements: block.statements,
+            return_value: block.return_value,
+        }
+    }
+}
+
+impl Functions for Validator {
+    /// Validates functions of the top level declared further down when they are first called.
+    fn function(&mut self, name: &str) -> Result<Option<ComptimeFunction>, SemanticError> {
+        if let Some((declaration, self_type)) = self.unchecked.remove(name) {
+            self.check_ahead(declaration, self_type)?;
+        }
+        Ok(self.comptime_functions.get(name).cloned())
     }
 }
 
 pub trait Visitor {
-    fn visit_program(&mut self, program: &Program) -> Program;
-    fn visit_statement(&mut self, statement: &Statement) -> Statement;
-    fn visit_expression(&mut self, expression: &Expression) -> Expression;
-    // fn visit_number(&mut self, number: &Number) -> Number;
-    fn visit_conditional_block(&mut self, conditional_block: &ConditionalBlock);
-    fn visit_if(&mut self, if_statement: &If);
-    fn visit_block(&mut self, block: &Block);
-    fn visit_param(&mut self, param: &Param);
-    fn visit_type(&mut self, datatype: &Type);
+    fn visit_program(&mut self, program: &Program) -> Result<Program, SemanticError>;
+    fn visit_statement(&mut self, statement: &Statement) -> Result<Statement, SemanticError>;
+    fn visit_expression(&mut self, expression: &Expression) -> Result<Expression, SemanticError>;
+    fn visit_conditional_block(
+        &mut self,
+        conditional_block: &ConditionalBlock,
+    ) -> Result<ConditionalBlock, SemanticError>;
+    fn visit_if(&mut self, if_expression: &If) -> Result<Expression, SemanticError>;
+    fn visit_block(&mut self, block: &Block) -> Result<Block, SemanticError>;
+    fn visit_param(&mut self, param: &Param) -> Result<Param, SemanticError>;
+    fn visit_type(&mut self, datatype: &Type) -> Result<Type, SemanticError>;
 }
 
 impl Visitor for Validator {
-    fn visit_program(&mut self, program: &Program) -> Program {
-        let mut new_program = Program { statements: vec![] };
+    fn visit_program(&mut self, program: &Program) -> Result<Program, SemanticError> {
+        self.declare_items(&program.statements)?;
+
+        let mut statements = vec![];
         for stmt in &program.statements {
-            new_program.statements.push(self.visit_statement(stmt));
+            if !is_type_factory(stmt) {
+                statements.push(self.visit_statement(stmt)?);
+            }
         }
-        new_program
+
+        // instances may be used from anywhere, so they are declared before everything else
+        let mut new_program = Program {
+            statements: self.check_instances()?,
+        };
+        new_program.statements.append(&mut statements);
+        Ok(new_program)
     }
 
-    fn visit_statement(&mut self, statement: &Statement) -> Statement {
+    fn visit_statement(&mut self, statement: &Statement) -> Result<Statement, SemanticError> {
         match statement {
             Statement::Declaration {
+                public,
                 mutable,
                 name,
                 datatype,
                 value,
-            } => Statement::Declaration {
-                mutable: *mutable,
-                name: name.clone(),
-                datatype: datatype.clone(),
-                value: self.visit_expression(&value),
-            },
-            _ => unimplemented!(),
-        }
-    }
+            } => {
+                let datatype = self.visit_type(datatype)?;
+                let (value, value_type) = match self.function_valued(value, &datatype) {
+                    Some(function) => self.check_expression(function)?,
+                    None if matches!(value, Expression::Function { .. }) => {
+                        self.check_closure(value)?
+                    }
+                    None => self.check_expression(value)?,
+                };
+                let value = self.coerce(value, &value_type, &datatype)?;
 
-    fn visit_expression(&mut self, expression: &Expression) -> Expression {
-        match expression {
-            Expression::Nil => expression.clone(),
-            Expression::NumberLiteral(literal) => {
-                if let Ok(integer) = literal.parse::<i64>() {
-                    Expression::Number(Number::Int(Integer::Signed(Signed::Int64(integer))))
-                } else if let Ok(double) = literal.parse::<f64>() {
-                    Expression::Number(Number::Float(Floating::Double(double)))
-                } else {
-                    panic!("Failed to parse number literal");
+                // only constants are propagated, mutable bindings may change later on
+                let known_value = (!mutable && value.is_constant()).then(|| value.clone());
+                let borrowing = self.borrows_variables(&value);
+                self.scopes.declare(
+                    name,
+                    Symbol {
+                        datatype: datatype.clone(),
+                        mutable: *mutable,
+                        function: false,
+                        value: known_value,
+                        borrowing,
+                    },
+                )?;
+
+                Ok(Statement::Declaration {
+                    public: *public,
+                    mutable: *mutable,
+                    name: name.clone(),
+                    datatype,
+                    value,
+                })
+            }
+            Statement::Destructuring {
+                mutable,
+                names,
+                datatype,
+                value,
+            } => {
+                let datatype = self.visit_type(datatype)?;
+                let fields = match &datatype {
+                    Type::Tuple(fields) if fields.len() == names.len() => fields.clone(),
+                    _ => {
+                        return Err(SemanticError::DestructuringMismatch {
+                            names: names.len(),
+                            datatype,
+                        })
+                    }
+                };
+
+                let (value, value_type) = self.check_expression(value)?;
+                let value = self.coerce(value, &value_type, &datatype)?;
+
+                for (index, (name, field)) in names.iter().zip(fields).enumerate() {
+                    let known_value = match &value {
+                        Expression::Tuple(elements) if !mutable && value.is_constant() => {
+                            Some(elements[index].value.clone())
+                        }
+                        _ => None,
+                    };
+                    self.scopes.declare(
+                        name,
+                        Symbol {
+                            datatype: field.datatype,
+                            mutable: *mutable,
+                            function: false,
+                            value: known_value,
+                            borrowing: false,
+                        },
+                    )?;
                 }
+
+                Ok(Statement::Destructuring {
+                    mutable: *mutable,
+                    names: names.clone(),
+                    datatype,
+                    value,
+                })
             }
-            Expression::Addition { augend, addend } => {
-                let reduced_augend = self.visit_expression(augend);
-                let reduced_addend = self.visit_expression(addend);
+            Statement::PlaceAssignment { target, value } => {
+                let (target, datatype, immutable) = self.check_place(target, true)?;
+                if let Some(error) = immutable {
+                    return Err(error);
+                }
 
-                // TODO: improve on this infinite nesting somehow...
-                match (reduced_augend, reduced_addend) {
-                    (
-                        Expression::Number(Number::Int(Integer::Signed(Signed::Int64(augend)))),
-                        Expression::Number(Number::Int(Integer::Signed(Signed::Int64(addend)))),
-                    ) => Expression::Number(Number::Int(Integer::Signed(Signed::Int64(
-                        augend + addend,
-                    )))),
-                    (
-                        Expression::Number(Number::Float(Floating::Double(augend))),
-                        Expression::Number(Number::Float(Floating::Double(addend))),
-                    ) => Expression::Number(Number::Float(Floating::Double(augend + addend))),
-                    _ => panic!("Trying to sum two different types!"),
+                let (value, value_type) = self.check_expression(value)?;
+                Ok(Statement::PlaceAssignment {
+                    target,
+                    value: self.coerce(value, &value_type, &datatype)?,
+                })
+            }
+            Statement::ReAssignment { name, value, span } => {
+                if !self.lookup(name)?.mutable {
+                    return Err(SemanticError::AssignmentToConstant { name: name.clone() });
                 }
+                let datatype = self.resolve(name, true, *span)?.datatype;
+
+                let (value, value_type) = self.check_expression(value)?;
+                Ok(Statement::ReAssignment {
+                    name: name.clone(),
+                    value: self.coerce(value, &value_type, &datatype)?,
+                    span: *span,
+                })
+            }
+            Statement::FuncDeclaration {
+                public,
+                inline,
+                name,
+                parameters,
+                return_type,
+                body,
+            } => {
+                if self.at_top_level() {
+                    if let Some(checked) = self.checked_ahead.remove(name) {
+                        return Ok(checked);
+                    }
+                    self.unchecked.remove(name);
+                }
+
+                // the signature was declared along with the other items of the scope
+                let return_type = self.visit_type(return_type)?;
+                let frame = FunctionFrame {
+                    name: name.clone(),
+                    boundary: self.scopes.depth(),
+                    closure: false,
+                    by_reference: false,
+                    captures: vec![],
+                    borrows: false,
+                };
+                let (parameters, body, _) =
+                    self.check_function_body(frame, parameters, &return_type, body)?;
+                self.comptime_functions.insert(
+                    name.clone(),
+                    ComptimeFunction {
+                        parameters: parameters
+                            .iter()
+                            .map(|Param::Parameter { name, .. }| name.clone())
+                            .collect(),
+                        body: body.clone(),
+                    },
+                );
+
+                Ok(Statement::FuncDeclaration {
+                    public: *public,
+                    inline: *inline,
+                    name: name.clone(),
+                    parameters,
+                    return_type,
+                    body,
+                })
             }
-            _ => unimplemented!(),
+            Statement::TypeDeclaration {
+                public,
+                name,
+                definition,
+            } => {
+                let definition = match definition {
+                    TypeDefinition::Struct { methods, .. } => TypeDefinition::Struct {
+                        fields: self.structs[name].clone(),
+                        methods: self.check_methods(name, Type::Struct(name.clone()), methods)?,
+                    },
+                    TypeDefinition::Enum { methods, .. } => TypeDefinition::Enum {
+                        variants: self.enums[name].clone(),
+                        methods: self.check_methods(name, Type::Enum(name.clone()), methods)?,
+                    },
+                    TypeDefinition::Alias(_) => TypeDefinition::Alias(self.named_type(name)?),
+                };
+                Ok(Statement::TypeDeclaration {
+                    public: *public,
+                    name: name.clone(),
+                    definition,
+                })
+            }
+            Statement::ExpressionStatement(expression) => Ok(Statement::ExpressionStatement(
+                self.visit_expression(expression)?,
+            )),
+            Statement::IfStatement(if_statement) => match self.visit_if(if_statement)? {
+                Expression::If(if_statement) => Ok(Statement::IfStatement(if_statement)),
+                folded => Ok(Statement::ExpressionStatement(folded)),
+            },
+            Statement::Import { .. } => unreachable!("imports are resolved by the module loader"),
         }
     }
 
-    // fn visit_number(&mut self, number_literal: &Expression) -> Number {
-    //     todo!();
-    // }
+    fn visit_expression(&mut self, expression: &Expression) -> Result<Expression, SemanticError> {
+        self.check_expression(expression)
+            .map(|(expression, _)| expression)
+    }
 
-    fn visit_conditional_block(&mut self, conditional_block: &ConditionalBlock) {
-        todo!()
+    fn visit_conditional_block(
+        &mut self,
+        conditional_block: &ConditionalBlock,
+    ) -> Result<ConditionalBlock, SemanticError> {
+        self.check_conditional_block(conditional_block)
+            .map(|(conditional_block, _)| conditional_block)
     }
 
-    fn visit_if(&mut self, if_statement: &If) {
-        todo!()
+    fn visit_if(&mut self, if_expression: &If) -> Result<Expression, SemanticError> {
+        self.check_if(if_expression)
+            .map(|(expression, _)| expression)
     }
 
-    fn visit_block(&mut self, block: &Block) {
-        todo!();
+    fn visit_block(&mut self, block: &Block) -> Result<Block, SemanticError> {
+        self.check_block(block).map(|(block, _)| block)
     }
 
-    fn visit_param(&mut self, param: &Param) {
-        todo!();
+    fn visit_param(&mut self, param: &Param) -> Result<Param, SemanticError> {
+        let Param::Parameter { name, datatype } = param;
+        let datatype = self.visit_type(datatype)?;
+
+        self.scopes.declare(
+            name,
+            Symbol {
+                datatype: datatype.clone(),
+                mutable: false,
+                function: false,
+                value: None,
+                borrowing: false,
+            },
+        )?;
+
+        Ok(Param::Parameter {
+            name: name.clone(),
+            datatype,
+        })
     }
 
-    fn visit_type(&mut self, datatype: &Type) {
-        todo!();
+    fn visit_type(&mut self, datatype: &Type) -> Result<Type, SemanticError> {
+        match datatype {
+            Type::Named(name) => self.named_type(name),
+            Type::Instance(call) => self.instantiate(&call.factory, &call.arguments),
+            Type::Type | Type::Comptime(_) => Err(SemanticError::ComptimeOnly {
+                datatype: datatype.clone(),
+            }),
+            Type::Array { element, length } => Ok(Type::Array {
+                element: Box::new(self.visit_type(element)?),
+                length: *length,
+            }),
+            Type::Pointer { pointee, mutable } => Ok(Type::Pointer {
+                pointee: Box::new(self.visit_type(pointee)?),
+                mutable: *mutable,
+            }),
+            Type::Optional(inner) => match self.visit_type(inner)? {
+                inner @ (Type::Nil | Type::Optional(_)) => {
+                    Err(SemanticError::InvalidOptional { datatype: inner })
+                }
+                inner => Ok(Type::Optional(Box::new(inner))),
+            },
+            Type::Tuple(fields) => {
+                let mut resolved = vec![];
+                for field in fields {
+                    resolved.push(TupleField {
+                        name: field.name.clone(),
+                        datatype: self.visit_type(&field.datatype)?,
+                    });
+                }
+                Ok(Type::Tuple(resolved))
+            }
+            Type::FuncType {
+                parameters,
+                return_type,
+            } => self.function_type(parameters, return_type),
+            _ => Ok(datatype.clone()),
+        }
     }
 }
@@ -0,0 +1,286 @@
+// PATTERN COVERAGE //
+
+use std::collections::HashMap;
+
+use crate::structures::parse_tree::{Expression, Pattern, Type, Variant};
+
+/// Stands for a pattern left out, like the payload of `Shape.Circle` or the components of a
+/// tuple matched by a binding.
+const WILDCARD: &Pattern = &Pattern::Wildcard;
+
+/// Finds a pattern for values of `datatype` that none of `patterns` match, if there is one.
+pub fn uncovered(
+    patterns: &[&Pattern],
+    datatype: &Type,
+    enums: &HashMap<String, Vec<Variant>>,
+) -> Option<Pattern> {
+    let rows: Vec<Vec<&Pattern>> = patterns.iter().map(|pattern| vec![*pattern]).collect();
+    uncovered_rows(&rows, &[datatype], enums)?.pop()
+}
+
+/// Whether every value matched by `pattern` is already matched by one of `patterns`.
+pub fn covered(
+    patterns: &[&Pattern],
+    pattern: &Pattern,
+    datatype: &Type,
+    enums: &HashMap<String, Vec<Variant>>,
+) -> bool {
+    let rows: Vec<Vec<&Pattern>> = patterns.iter().map(|pattern| vec![*pattern]).collect();
+    !useful(&rows, &[pattern], &[datatype], enums)
+}
+
+/// Finds values of `types`, given as a pattern for each, that no row of patterns matches.
+///
+/// Rows are matched column by column: the values of a type with a known set of them, like
+/// the variants of an enum, are tried one by one with the rows matching each of them, whose
+/// payloads become columns of their own, as do the components of tuples.
+fn uncovered_rows(
+    rows: &[Vec<&Pattern>],
+    types: &[&Type],
+    enums: &HashMap<String, Vec<Variant>>,
+) -> Option<Vec<Pattern>> {
+    if rows.is_empty() {
+        return Some(types.iter().map(|_| Pattern::Wildcard).collect());
+    }
+    // with no columns left, the rows match what is left of the values
+    let (datatype, rest) = types.split_first()?;
+
+    if let Type::Tuple(fields) = datatype {
+        let rows = expand(rows, fields.len());
+        let types: Vec<&Type> = fields
+            .iter()
+            .map(|field| &field.datatype)
+            .chain(rest.iter().copied())
+            .collect();
+        let mut components = uncovered_rows(&rows, &types, enums)?;
+        let rest = components.split_off(fields.len());
+        return Some(
+            std::iter::once(Pattern::Tuple(components))
+                .chain(rest)
+                .collect(),
+        );
+    }
+
+    let Some(constructors) = constructors(datatype, enums) else {
+        // values which can't be listed are only all matched by patterns matching anything
+        let rows: Vec<Vec<&Pattern>> = rows
+            .iter()
+            .filter(|row| row[0].is_irrefutable())
+            .map(|row| row[1..].to_vec())
+            .collect();
+        let rest = uncovered_rows(&rows, rest, enums)?;
+        return Some(std::iter::once(Pattern::Wildcard).chain(rest).collect());
+    };
+
+    constructors
+        .into_iter()
+        .find_map(|(constructor, payload_type)| {
+            let rows = specialize(rows, &constructor, payload_type.is_some());
+            let types: Vec<&Type> = payload_type
+                .into_iter()
+                .chain(rest.iter().copied())
+                .collect();
+            let mut missing = uncovered_rows(&rows, &types, enums)?;
+
+            let constructor = match constructor {
+                Pattern::Variant {
+                    enum_name, variant, ..
+                } if payload_type.is_some() => Pattern::Variant {
+                    enum_name,
+                    variant,
+                    payload: Some(Box::new(missing.remove(0))),
+                },
+                constructor => constructor,
+            };
+            Some(std::iter::once(constructor).chain(missing).collect())
+        })
+}
+
+/// Whether a row of patterns matches values of `types` that none of `rows` match.
+fn useful(
+    rows: &[Vec<&Pattern>],
+    row: &[&Pattern],
+    types: &[&Type],
+    enums: &HashMap<String, Vec<Variant>>,
+) -> bool {
+    let Some((datatype, rest)) = types.split_first() else {
+        return rows.is_empty();
+    };
+
+    if let Type::Tuple(fields) = datatype {
+        let rows = expand(rows, fields.len());
+        let row = expand(&[row.to_vec()], fields.len()).remove(0);
+        let types: Vec<&Type> = fields
+            .iter()
+            .map(|field| &field.datatype)
+            .chain(rest.iter().copied())
+            .collect();
+        return useful(&rows, &row, &types, enums);
+    }
+
+    let Some(constructors) = constructors(datatype, enums) else {
+        // other values are only told apart by the literals which match them
+        let rows: Vec<Vec<&Pattern>> = rows
+            .iter()
+            .filter(|other| {
+                other[0].is_irrefutable()
+                    || matches!((other[0], row[0]), (Pattern::Literal(other), Pattern::Literal(value)) if same_constant(other, value))
+            })
+            .map(|other| other[1..].to_vec())
+            .collect();
+        return useful(&rows, &row[1..], rest, enums);
+    };
+
+    constructors
+        .into_iter()
+        .filter(|(constructor, _)| row[0].is_irrefutable() || same_constructor(row[0], constructor))
+        .any(|(constructor, payload_type)| {
+            let payload = payload_type.is_some();
+            let rows = specialize(rows, &constructor, payload);
+            let row = specialize(&[row.to_vec()], &constructor, payload).remove(0);
+            let types: Vec<&Type> = payload_type
+                .into_iter()
+                .chain(rest.iter().copied())
+                .collect();
+            useful(&rows, &row, &types, enums)
+        })
+}
+
+/// Every value of a type with a known set of them, as a pattern along with the type of its
+/// payload, or nothing if the values can't be listed.
+fn constructors<'a>(
+    datatype: &Type,
+    enums: &'a HashMap<String, Vec<Variant>>,
+) -> Option<Vec<(Pattern, Option<&'a Type>)>> {
+    match datatype {
+        Type::Bool => Some(
+            [true, false]
+                .into_iter()
+                .map(|boolean| (Pattern::Literal(Expression::Boolean(boolean)), None))
+                .collect(),
+        ),
+        Type::Nil => Some(vec![(Pattern::Literal(Expression::Nil), None)]),
+        Type::Enum(name) => Some(
+            enums[name]
+                .iter()
+                .map(|variant| {
+                    let pattern = Pattern::Variant {
+                        enum_name: Some(name.clone()),
+                        variant: variant.name.clone(),
+                        payload: None,
+                    };
+                    (pattern, variant.payload.as_ref())
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Whether a refutable pattern matches the values of a constructor.
+fn same_constructor(pattern: &Pattern, constructor: &Pattern) -> bool {
+    match (pattern, constructor) {
+        (Pattern::Literal(value), Pattern::Literal(constructor)) => {
+            same_constant(value, constructor)
+        }
+        (Pattern::Variant { variant, .. }, Pattern::Variant { variant: name, .. }) => {
+            variant == name
+        }
+        _ => false,
+    }
+}
+
+/// The rows whose first pattern matches the values of a constructor, the pattern being
+/// replaced with the one for its payload if it has one.
+fn specialize<'a>(
+    rows: &[Vec<&'a Pattern>],
+    constructor: &Pattern,
+    payload: bool,
+) -> Vec<Vec<&'a Pattern>> {
+    rows.iter()
+        .filter(|row| row[0].is_irrefutable() || same_constructor(row[0], constructor))
+        .map(|row| {
+            let payload_pattern = match row[0] {
+                Pattern::Variant {
+                    payload: Some(payload),
+                    ..
+                } => payload,
+                _ => WILDCARD,
+            };
+            payload
+                .then_some(payload_pattern)
+                .into_iter()
+                .chain(row[1..].iter().copied())
+                .collect()
+        })
+        .collect()
+}
+
+/// The rows with their first pattern, which matches tuples of `length` components, replaced
+/// with a pattern for each component.
+fn expand<'a>(rows: &[Vec<&'a Pattern>], length: usize) -> Vec<Vec<&'a Pattern>> {
+    rows.iter()
+        .map(|row| {
+            let components: Vec<&Pattern> = match row[0] {
+                Pattern::Tuple(elements) => elements.iter().collect(),
+                _ => vec![WILDCARD; length],
+            };
+            components
+                .into_iter()
+                .chain(row[1..].iter().copied())
+                .collect()
+        })
+        .collect()
+}
+
+/// Matches a constant against a pattern, yielding the values of its bindings in order.
+pub fn bind_constant(pattern: &Pattern, value: &Expression) -> Option<Vec<Expression>> {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) => Some(vec![]),
+        (Pattern::Binding(_), _) => Some(vec![value.clone()]),
+        (Pattern::Literal(literal), _) => same_constant(literal, value).then(Vec::new),
+        (
+            Pattern::Variant {
+                variant, payload, ..
+            },
+            Expression::Variant {
+                variant: value_variant,
+                payload: value_payload,
+                ..
+            },
+        ) if variant == value_variant => match (payload, value_payload) {
+            (Some(payload), Some(value_payload)) => bind_constant(payload, value_payload),
+            _ => Some(vec![]),
+        },
+        (Pattern::Tuple(elements), Expression::Tuple(values)) => {
+            let mut bound = vec![];
+            for (element, value) in elements.iter().zip(values) {
+                bound.extend(bind_constant(element, &value.value)?);
+            }
+            Some(bound)
+        }
+        _ => None,
+    }
+}
+
+/// The names bound by a pattern, in the order `bind_constant` yields their values.
+pub fn bindings(pattern: &Pattern) -> Vec<&String> {
+    match pattern {
+        Pattern::Binding(name) => vec![name],
+        Pattern::Variant {
+            payload: Some(payload),
+            ..
+        } => bindings(payload),
+        Pattern::Tuple(elements) => elements.iter().flat_map(bindings).collect(),
+        _ => vec![],
+    }
+}
+
+fn same_constant(left: &Expression, right: &Expression) -> bool {
+    match (left, right) {
+        (Expression::Number(left), Expression::Number(right)) => left == right,
+        (Expression::Boolean(left), Expression::Boolean(right)) => left == right,
+        (Expression::Nil, Expression::Nil) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::structures::parse_tree::{Capture, Expression, Type};
+
+use super::error::SemanticError;
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub datatype: Type,
+    pub mutable: bool,
+    /// Whether the symbol names a `fn` declaration, which never needs capturing.
+    pub function: bool,
+    /// The folded value of the symbol, if it is a constant known at compile time.
+    pub value: Option<Expression>,
+    /// Whether the symbol holds a closure using variables of the function declaring it by
+    /// reference, which can only be called as they are gone once the function returns.
+    pub borrowing: bool,
+}
+
+/// A stack of lexical scopes, the first of which holds the globals.
+#[derive(Debug, Clone)]
+pub struct Scopes {
+    frames: Vec<HashMap<String, Symbol>>,
+}
+
+impl Scopes {
+    pub fn new() -> Self {
+        Self {
+            frames: vec![HashMap::new()],
+        }
+    }
+
+    pub fn enter(&mut self) {
+        self.frames.push(HashMap::new());
+    }
+
+    pub fn exit(&mut self) {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+
+    /// Declares a symbol in the innermost scope. Names may hide those of outer scopes, but not
+    /// be declared twice in the same one.
+    pub fn declare(&mut self, name: &str, symbol: Symbol) -> Result<(), SemanticError> {
+        let frame = self
+            .frames
+            .last_mut()
+            .expect("there is always a global scope");
+        if frame.contains_key(name) {
+            return Err(SemanticError::DuplicateName {
+                name: name.to_string(),
+            });
+        }
+        frame.insert(name.to_string(), symbol);
+        Ok(())
+    }
+
+    pub fn declare_global(&mut self, name: &str, symbol: Symbol) {
+        self.frames[0].insert(name.to_string(), symbol);
+    }
+
+    /// Leaves every scope but the global one, returning them to be entered again by `restore`.
+    pub fn leave_to_global(&mut self) -> Vec<HashMap<String, Symbol>> {
+        self.frames.split_off(1)
+    }
+
+    pub fn restore(&mut self, frames: Vec<HashMap<String, Symbol>>) {
+        self.frames.extend(frames);
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.frames.iter().rev().find_map(|frame| frame.get(name))
+    }
+
+    /// Looks up a symbol along with the index of the scope it was declared in.
+    pub fn lookup_with_depth(&self, name: &str) -> Option<(usize, &Symbol)> {
+        self.frames
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, frame)| frame.get(name).map(|symbol| (depth, symbol)))
+    }
+
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+/// A function body being validated, collecting the variables it captures.
+#[derive(Debug, Clone)]
+pub struct FunctionFrame {
+    /// Name of the function, for diagnostics.
+    pub name: String,
+    /// Scopes below this depth lie outside of the function.
+    pub boundary: usize,
+    /// Named `fn` declarations can't capture, only closures can.
+    pub closure: bool,
+    pub by_reference: bool,
+    pub captures: Vec<Capture>,
+    /// Whether the function takes the address of a variable, or has a closure capture some by
+    /// reference, so that its variables may be pointed to when it calls itself.
+    pub borrows: bool,
+}
+
+impl Default for Scopes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
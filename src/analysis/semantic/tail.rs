@@ -0,0 +1,92 @@
+// TAIL CALLS //
+
+use crate::structures::parse_tree::{Block, Expression, Param, Statement};
+
+use super::patterns::bindings;
+
+/// Marks the calls a checked function makes to itself for the value of its body, going into
+/// blocks, `if` branches and `match` arms, as tail calls. Nothing is left to do after them but
+/// return their result, so they are run as jumps back to the start of the function, which
+/// keeps deep recursion from using more stack.
+///
+/// Calls to a variable or function of the same name hiding the function are left alone.
+pub fn mark_tail_calls(name: &str, parameters: &[Param], body: &mut Block) {
+    let hidden = parameters.iter().any(
+        |Param::Parameter {
+             name: parameter, ..
+         }| parameter == name,
+    );
+    if !hidden {
+        mark_block(name, &body.statements, &mut body.return_value);
+    }
+}
+
+/// Whether a checked function body has tail calls.
+pub fn has_tail_calls(body: &Block) -> bool {
+    ends_in_tail_call(&body.return_value)
+}
+
+fn mark_block(name: &str, statements: &[Statement], return_value: &mut Expression) {
+    if !statements.iter().any(|statement| declares(statement, name)) {
+        mark(name, return_value);
+    }
+}
+
+fn mark(name: &str, expression: &mut Expression) {
+    match expression {
+        Expression::FunctionCall {
+            name: callee, tail, ..
+        } => *tail = callee == name,
+        Expression::Block {
+            statements,
+            return_value,
+        } => mark_block(name, statements, return_value),
+        Expression::If(if_expression) => {
+            let branches = std::iter::once(&mut if_expression.if_block)
+                .chain(&mut if_expression.else_if_blocks)
+                .map(|branch| &mut branch.block)
+                .chain(&mut if_expression.else_block);
+            for block in branches {
+                mark_block(name, &block.statements, &mut block.return_value);
+            }
+        }
+        Expression::Match(match_expression) => {
+            for arm in &mut match_expression.arms {
+                if !bindings(&arm.pattern)
+                    .iter()
+                    .any(|binding| *binding == name)
+                {
+                    mark(name, &mut arm.value);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn ends_in_tail_call(expression: &Expression) -> bool {
+    match expression {
+        Expression::FunctionCall { tail, .. } => *tail,
+        Expression::Block { return_value, .. } => ends_in_tail_call(return_value),
+        Expression::If(if_expression) => std::iter::once(&if_expression.if_block)
+            .chain(&if_expression.else_if_blocks)
+            .map(|branch| &branch.block)
+            .chain(&if_expression.else_block)
+            .any(|block| ends_in_tail_call(&block.return_value)),
+        Expression::Match(match_expression) => match_expression
+            .arms
+            .iter()
+            .any(|arm| ends_in_tail_call(&arm.value)),
+        _ => false,
+    }
+}
+
+/// Whether a statement declares a variable or function named `name`.
+fn declares(statement: &Statement, name: &str) -> bool {
+    match statement {
+        Statement::Declaration { name: declared, .. }
+        | Statement::FuncDeclaration { name: declared, .. } => declared == name,
+        Statement::Destructuring { names, .. } => names.iter().any(|declared| declared == name),
+        _ => false,
+    }
+}
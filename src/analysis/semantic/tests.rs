@@ -0,0 +1,1329 @@
+use crate::{
+    analysis::{lexical::lex, syntactic::parser::Parser},
+    structures::parse_tree::{
+        BinaryOperator, Expression, Floating, Integer, Number, NumericType, Program, Signed, Span,
+        Statement, TupleField, Type, Unsigned,
+    },
+};
+
+use super::{
+    comptime::{DEPTH_LIMIT, STEP_LIMIT},
+    error::SemanticError,
+    types::{join, widens},
+    Validator,
+};
+
+/// Validates a program, which must parse.
+fn validate(source: &str) -> Result<Program, SemanticError> {
+    let program = Parser::new(lex(source))
+        .parse_program()
+        .expect("the program parses");
+    Validator::new().validate(&program)
+}
+
+/// Validates a program which must be rejected, returning why.
+fn rejected(source: &str) -> SemanticError {
+    match validate(source) {
+        Ok(program) => panic!("the program is valid: {:?}", program),
+        Err(e) => e,
+    }
+}
+
+fn accepted(source: &str) {
+    if let Err(e) = validate(source) {
+        panic!("the program is invalid: {}", e);
+    }
+}
+
+#[test]
+fn names_are_declared_once_per_scope() {
+    let twice = |name: &str| SemanticError::DuplicateName {
+        name: name.to_string(),
+    };
+    assert_eq!(rejected("let a: int = 1; let a: bool = true;"), twice("a"));
+    assert_eq!(
+        rejected("fn f(): int { let x: int = 1; const x: int = 2; x }"),
+        twice("x")
+    );
+    assert_eq!(
+        rejected("let t: (int, int) = (1, 2); let (t, u): (int, int) = t;"),
+        twice("t")
+    );
+    // globals and functions share the top-level scope
+    assert_eq!(rejected("fn a(): int { 1 } let a: int = 2;"), twice("a"));
+    assert_eq!(rejected("let a: int = 2; fn a(): int { 1 }"), twice("a"));
+
+    // inner scopes may hide the names of outer ones
+    accepted("let a: int = 1; fn f(a: bool): bool { a }");
+    accepted("fn f(): int { let x: int = 1; const y: int = { let x: int = 2; x }; x + y }");
+    accepted("fn a(): int { 1 } fn f(): int { let a: int = 2; a }");
+}
+
+#[test]
+fn parameters_are_named_once() {
+    let twice = SemanticError::DuplicateParameter {
+        parameter: "a".to_string(),
+    };
+    assert_eq!(rejected("fn f(a: int, a: int): int { a }"), twice);
+    assert_eq!(
+        rejected("const f: (a: int, b: bool) -> int = fn(a: int, a: bool): int { a };"),
+        twice
+    );
+    accepted("fn f(a: int, b: int): int { a + b }");
+}
+
+/// The number the first declaration of a program, which must be valid, is folded to.
+fn first_number(source: &str) -> Number {
+    match validate(source) {
+        Ok(program) => match &program.statements[0] {
+            Statement::Declaration {
+                value: Expression::Number(number),
+                ..
+            } => number.clone(),
+            statement => panic!("not a constant declaration: {:?}", statement),
+        },
+        Err(e) => panic!("the program is invalid: {}", e),
+    }
+}
+
+fn u64(value: u64) -> Number {
+    Number::Int(Integer::Unsigned(Unsigned::UInt64(value)))
+}
+
+const RECURSIVE: &str = "
+fn fib(n: u64): u64 { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+fn depth(n: u64): u64 { if n == 0 { 0 } else { 1 + depth(n - 1) } }
+fn divide(a: u64, b: u64): u64 { a / b }
+";
+
+#[test]
+fn comptime_blocks_call_functions_declared_later() {
+    let source = format!("const a: u64 = comptime {{ fib(20) }};{}", RECURSIVE);
+    assert_eq!(first_number(&source), u64(6765));
+
+    // functions called ahead of their declaration see the top level as it is at the call
+    let source = "
+const a: u64 = comptime { f() };
+const k: u64 = 1;
+fn f(): u64 { k }
+";
+    assert_eq!(
+        rejected(source),
+        SemanticError::ComptimeFailure {
+            error: Box::new(SemanticError::UndefinedIdentifier {
+                name: "k".to_string()
+            }),
+            stack: vec!["comptime block".to_string()],
+        }
+    );
+    accepted("const k: u64 = 1; const a: u64 = comptime { f() }; fn f(): u64 { k }");
+}
+
+#[test]
+fn comptime_evaluation_is_limited() {
+    let source = format!("const a: u64 = comptime {{ fib(30) }};{}", RECURSIVE);
+    let SemanticError::ComptimeFailure { error, .. } = rejected(&source) else {
+        panic!("fib(30) is evaluated");
+    };
+    assert_eq!(
+        *error,
+        SemanticError::ComptimeStepLimit { limit: STEP_LIMIT }
+    );
+
+    let source = format!("const a: u64 = comptime {{ depth(5000) }};{}", RECURSIVE);
+    let SemanticError::ComptimeFailure { error, stack } = rejected(&source) else {
+        panic!("depth(5000) is evaluated");
+    };
+    assert_eq!(
+        *error,
+        SemanticError::ComptimeDepthLimit { limit: DEPTH_LIMIT }
+    );
+    assert_eq!(stack.len(), DEPTH_LIMIT + 1);
+}
+
+#[test]
+fn failing_comptime_calls_carry_their_stack() {
+    let source = format!(
+        "const a: u64 = comptime {{ const b: u64 = 0; divide(fib(3), b) }};{}",
+        RECURSIVE
+    );
+    assert_eq!(
+        rejected(&source),
+        SemanticError::ComptimeFailure {
+            error: Box::new(SemanticError::DivisionByZero {
+                span: Span {
+                    line: 4,
+                    column: 36
+                }
+            }),
+            stack: vec!["divide(2, 0)".to_string(), "comptime block".to_string()],
+        }
+    );
+}
+
+#[test]
+fn deep_stacks_are_shown_by_their_ends() {
+    let source = format!("const a: u64 = comptime {{ depth(5000) }};{}", RECURSIVE);
+    let message = rejected(&source).to_string();
+    let calls: Vec<&str> = message
+        .lines()
+        .filter(|line| line.starts_with("    in "))
+        .collect();
+    assert_eq!(calls.len(), 10, "{}", message);
+    assert!(
+        message.contains("\n    ... 991 more calls\n"),
+        "{}",
+        message
+    );
+    assert!(calls[9].contains("comptime block"), "{}", message);
+}
+
+#[test]
+fn errors_are_located() {
+    let at = |line, column| Span { line, column };
+    assert_eq!(
+        rejected("fn f(a: int): int { a }\nlet b: int = f(1, 2);"),
+        SemanticError::ArityMismatch {
+            name: "f".to_string(),
+            expected: 1,
+            found: 2,
+            span: at(2, 14),
+        }
+    );
+    assert_eq!(
+        rejected("fn f(): int {\n  let x: int = 1;\n  fn g(): int { x }\n  g()\n}"),
+        SemanticError::CaptureInFunction {
+            function: "g".to_string(),
+            name: "x".to_string(),
+            span: at(3, 17),
+        }
+    );
+    let assignment = rejected(
+        "fn f(): int {\n  let x: int = 1;\n  const g: () -> int = fn(): int { x = 2; x };\n  g()\n}",
+    );
+    assert_eq!(
+        assignment,
+        SemanticError::AssignmentToCapture {
+            name: "x".to_string(),
+            span: at(3, 36),
+        }
+    );
+    // the location is shown along with the message
+    assert!(assignment.to_string().contains("at line 3, column 36"));
+
+    let missing =
+        rejected("const E = enum { A, B };\nfn f(e: E): int {\n  match e { E.A => 1 }\n}");
+    assert!(matches!(
+        missing,
+        SemanticError::NonExhaustiveMatch { span, .. } if span == at(3, 3)
+    ));
+    let lossy = rejected("fn f(a: i64): i32 {\n  a\n}");
+    assert!(matches!(
+        lossy,
+        SemanticError::LossyConversion { span, .. } if span == at(2, 3)
+    ));
+}
+
+/// The value a declaration of a program, which must be valid, is left with.
+fn value_of(source: &str, name: &str) -> Expression {
+    let program = validate(source).unwrap_or_else(|e| panic!("the program is invalid: {}", e));
+    program
+        .statements
+        .into_iter()
+        .find_map(|statement| match statement {
+            Statement::Declaration {
+                name: declared,
+                value,
+                ..
+            } if declared == name => Some(value),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("'{}' is not declared", name))
+}
+
+fn i64(value: i64) -> Number {
+    Number::Int(Integer::Signed(Signed::Int64(value)))
+}
+
+#[test]
+fn constants_are_folded_and_propagated() {
+    let folded = |source: &str| match value_of(source, "a") {
+        Expression::Number(number) => number,
+        value => panic!("not folded: {:?}", value),
+    };
+    assert_eq!(
+        folded("const k: int = 6; const a: int = k * 7 - 2;"),
+        i64(40)
+    );
+    assert_eq!(folded("const a: int = if 1 < 2 { 3 } else { 4 };"), i64(3));
+    assert!(matches!(
+        value_of("const a: bool = 3 >= 4;", "a"),
+        Expression::Boolean(false)
+    ));
+
+    // constants are propagated into functions, whose parameters are only known at runtime
+    let program = validate("const k: int = 2; fn f(n: int): int { n / k }").unwrap();
+    let Statement::FuncDeclaration { body, .. } = &program.statements[1] else {
+        panic!("not a function: {:?}", program.statements[1]);
+    };
+    let Expression::Division { divisor, .. } = &*body.return_value else {
+        panic!("not a division: {:?}", body.return_value);
+    };
+    assert!(matches!(&**divisor, Expression::Number(two) if *two == i64(2)));
+}
+
+#[test]
+fn folding_reports_failing_operations() {
+    let at = |line, column| Span { line, column };
+    assert_eq!(
+        rejected("const a: int = 1 / 0;"),
+        SemanticError::DivisionByZero { span: at(1, 18) }
+    );
+    assert_eq!(
+        rejected("fn f(): int {\n  1 / 0\n}"),
+        SemanticError::DivisionByZero { span: at(2, 5) }
+    );
+    // a divisor known to be zero is enough
+    assert_eq!(
+        rejected("const k: int = 0;\nfn f(n: int): int { n / k }"),
+        SemanticError::DivisionByZero { span: at(2, 23) }
+    );
+    assert_eq!(
+        rejected("fn f(x: f64): f64 { x / 0.0 }"),
+        SemanticError::DivisionByZero { span: at(1, 23) }
+    );
+    assert_eq!(
+        rejected("const a: i64 = 9223372036854775807 + 1;"),
+        SemanticError::IntegerOverflow {
+            operator: BinaryOperator::Add,
+            span: at(1, 36),
+        }
+    );
+    assert_eq!(
+        rejected("const k: u8 = 200; const a: u8 = k + k;"),
+        SemanticError::IntegerOverflow {
+            operator: BinaryOperator::Add,
+            span: at(1, 36),
+        }
+    );
+    assert!(rejected("const a: int = 1 / 0;")
+        .to_string()
+        .contains("at line 1, column 18"));
+    // literals are folded before they are given a type
+    assert_eq!(
+        rejected("const a: u64 = 3 - 4;"),
+        SemanticError::ConstantOutOfRange {
+            value: "-1".to_string(),
+            datatype: Type::Numeric(NumericType::U64),
+        }
+    );
+}
+
+#[test]
+fn numbers_widen_only_when_nothing_is_lost() {
+    use NumericType::*;
+    assert!(widens(I32, I64) && widens(U8, I16) && widens(I32, F64) && widens(F32, F64));
+    // negative values, bits of precision and fractions would be lost
+    assert!(!widens(I8, U64) && !widens(U32, I32) && !widens(I64, F64) && !widens(F32, I128));
+
+    assert_eq!(join(I8, I64), Some(I64));
+    assert_eq!(join(U8, I8), Some(I16));
+    assert_eq!(join(U64, I64), Some(I128));
+    assert_eq!(join(I32, F32), Some(F64));
+    assert_eq!(join(U128, I8), None);
+}
+
+#[test]
+fn lossy_conversions_need_a_cast() {
+    let lossy = |source: &str| match rejected(source) {
+        SemanticError::LossyConversion {
+            from: Type::Numeric(from),
+            to: Type::Numeric(to),
+            ..
+        } => (from, to),
+        e => panic!("not a lossy conversion: {}", e),
+    };
+    accepted("fn f(a: i32): i64 { a }");
+    accepted("fn f(a: i32): f64 { a }");
+    accepted("fn f(a: u8, b: i8): i16 { a + b }");
+    assert_eq!(
+        lossy("fn f(a: u32): i32 { a }"),
+        (NumericType::U32, NumericType::I32)
+    );
+    assert_eq!(
+        lossy("fn f(a: i64): f64 { a }"),
+        (NumericType::I64, NumericType::F64)
+    );
+    // operands are joined before the result is converted
+    assert_eq!(
+        lossy("fn f(a: u8, b: i8): i8 { a + b }"),
+        (NumericType::I16, NumericType::I8)
+    );
+    assert_eq!(
+        rejected("fn f(a: u128, b: i8): i128 { a + b }"),
+        SemanticError::MismatchedOperands {
+            operator: BinaryOperator::Add,
+            left: Type::Numeric(NumericType::U128),
+            right: Type::Numeric(NumericType::I8),
+        }
+    );
+
+    // constants convert when their value fits
+    accepted("const a: u8 = 255; const b: f32 = 1;");
+    assert_eq!(
+        rejected("const a: u8 = 256;"),
+        SemanticError::ConstantOutOfRange {
+            value: "256".to_string(),
+            datatype: Type::Numeric(NumericType::U8),
+        }
+    );
+
+    accepted("fn f(a: i64): i32 { a as i32 } fn g(a: f64): u8 { a as u8 }");
+    accepted("fn f(a: bool): i32 { a as i32 }");
+    assert_eq!(
+        rejected("fn f(a: bool): f32 { a as f32 }"),
+        SemanticError::InvalidCast {
+            from: Type::Bool,
+            to: Type::Numeric(NumericType::F32),
+        }
+    );
+}
+
+#[test]
+fn calls_are_resolved_and_checked() {
+    // top-level functions may call each other in any order
+    accepted(
+        "
+fn main(): nil { const a: int = even(4); }
+fn even(n: int): int { if n == 0 { 1 } else { odd(n - 1) } }
+fn odd(n: int): int { if n == 0 { 0 } else { even(n - 1) } }
+",
+    );
+    accepted("fn main(): nil {} const x: nil = main();");
+
+    assert_eq!(
+        rejected("const x: int = g(1);"),
+        SemanticError::UndefinedFunction {
+            name: "g".to_string(),
+            span: Span {
+                line: 1,
+                column: 16
+            },
+        }
+    );
+    assert_eq!(
+        rejected("const y: int = 1; const x: int = y(1);"),
+        SemanticError::NotCallable {
+            name: "y".to_string(),
+            datatype: Type::Numeric(NumericType::I64),
+            span: Span {
+                line: 1,
+                column: 34
+            },
+        }
+    );
+    assert!(matches!(
+        rejected("fn f(a: int, b: bool): int { a } const x: int = f(1);"),
+        SemanticError::ArityMismatch { name, expected: 2, found: 1, .. } if name == "f"
+    ));
+    assert_eq!(
+        rejected("fn f(a: int, b: bool): int { a } const x: int = f(1, 2);"),
+        SemanticError::MismatchedArgument {
+            function: "f".to_string(),
+            parameter: "b".to_string(),
+            expected: Box::new(Type::Bool),
+            found: Box::new(Type::Numeric(NumericType::I64)),
+            span: Span {
+                line: 1,
+                column: 49
+            },
+        }
+    );
+    assert_eq!(
+        rejected("fn f(): int { 1 } fn g(): nil { const x: bool = f(); }"),
+        SemanticError::MismatchedTypes {
+            expected: Type::Bool,
+            found: Type::Numeric(NumericType::I64),
+            span: Span {
+                line: 1,
+                column: 49
+            },
+        }
+    );
+    // arguments failing to convert for another reason than their type keep that reason
+    assert!(matches!(
+        rejected("fn f(a: u8): u8 { a } const x: u8 = f(300);"),
+        SemanticError::ConstantOutOfRange { value, .. } if value == "300"
+    ));
+    assert_eq!(
+        rejected("fn f(a: i32): i32 { a } fn g(b: i64): i32 { f(b) }"),
+        SemanticError::LossyConversion {
+            from: Type::Numeric(NumericType::I64),
+            to: Type::Numeric(NumericType::I32),
+            span: Span {
+                line: 1,
+                column: 47
+            },
+        }
+    );
+
+    // the receiver of a method call is not counted among its arguments
+    let method = "
+const Pt = struct { x: u64, fn twice(self: Pt, n: u64): u64 { n * 2 } };
+fn g(p: Pt): u64 { p.twice(1, 2) }
+";
+    assert!(matches!(
+        rejected(method),
+        SemanticError::ArityMismatch { name, expected: 1, found: 2, .. } if name == "Pt.twice"
+    ));
+}
+
+/// What the closure declared last in the body of the first function of a valid program
+/// captures, by name and whether by reference.
+fn captures(source: &str) -> Vec<(String, bool)> {
+    let program = validate(source).unwrap_or_else(|e| panic!("the program is invalid: {}", e));
+    let Some(Statement::FuncDeclaration { body, .. }) = program.statements.first() else {
+        panic!("not a function: {:?}", program.statements.first());
+    };
+    body.statements
+        .iter()
+        .rev()
+        .find_map(|statement| match statement {
+            Statement::Declaration {
+                value: Expression::Function { captures, .. },
+                ..
+            } => Some(
+                captures
+                    .iter()
+                    .map(|capture| (capture.name.clone(), capture.by_reference))
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .expect("a closure is declared")
+}
+
+#[test]
+fn closures_capture_what_they_use() {
+    let by_value = |name: &str| (name.to_string(), false);
+    let by_reference = |name: &str| (name.to_string(), true);
+    assert_eq!(
+        captures(
+            "fn f(): int { let x: int = 1; let y: int = 2; const g: () -> int = fn(): int { x + y }; g() }"
+        ),
+        [by_value("x"), by_value("y")]
+    );
+    // constants are folded into the closure instead of captured
+    assert_eq!(
+        captures(
+            "fn f(): int { let x: int = 1; const k: int = 3; capture g: () -> int = { x = x + k; x }; g() }"
+        ),
+        [by_reference("x")]
+    );
+    // a closure in a closure makes the outer one capture what the inner one uses
+    assert_eq!(
+        captures(
+            "fn f(): int { let x: int = 1; const g: () -> int = fn(): int { const h: () -> int = fn(): int { x }; h() }; g() }"
+        ),
+        [by_value("x")]
+    );
+    assert_eq!(
+        captures("fn f(): int { const g: (a: int) -> int = fn(a: int): int { a * 2 }; g(1) }"),
+        []
+    );
+    accepted("fn f(g: (a: int) -> int): int { g(2) } const x: int = f(fn(a: int): int { a * 2 });");
+
+    // the value of a function-typed declaration is its body, unless it is a function already
+    let functions = "fn dbl(n: int): int { n * 2 } fn mk(): (x: int) -> int { dbl }";
+    assert!(matches!(
+        value_of(&format!("{} const g: (x: int) -> int = dbl;", functions), "g"),
+        Expression::Identifier { name, .. } if name == "dbl"
+    ));
+    assert!(matches!(
+        value_of(&format!("{} const f: (x: int) -> int = mk();", functions), "f"),
+        Expression::FunctionCall { name, .. } if name == "mk"
+    ));
+    assert!(matches!(
+        value_of(
+            &format!("{} const h: (n: int) -> int = dbl(n);", functions),
+            "h"
+        ),
+        Expression::Function { .. }
+    ));
+
+    // functions declared with `fn` capture nothing
+    assert!(matches!(
+        rejected("fn f(): int { let x: int = 1; fn g(): int { x } g() }"),
+        SemanticError::CaptureInFunction { function, name, .. } if function == "g" && name == "x"
+    ));
+    assert!(matches!(
+        rejected("fn f(): int { let x: int = 1; const g: () -> int = fn(): int { x = 2; x }; g() }"),
+        SemanticError::AssignmentToCapture { name, .. } if name == "x"
+    ));
+
+    // closures capturing by reference are gone along with the variables they use, so they
+    // are only ever called
+    let counter = "let c: int = n; capture inc: () -> int = { c = c + 1; c };";
+    assert!(matches!(
+        rejected(&format!("fn mk(n: int): () -> int {{ {} inc }}", counter)),
+        SemanticError::EscapingClosure { name, .. } if name == "inc"
+    ));
+    assert!(matches!(
+        rejected(&format!(
+            "fn ap(f: () -> int): int {{ f() }} fn g(n: int): int {{ {} ap(inc) }}",
+            counter
+        )),
+        SemanticError::EscapingClosure { name, .. } if name == "inc"
+    ));
+    assert!(matches!(
+        rejected(&format!(
+            "fn mk(n: int): () -> int {{ {} fn(): int {{ inc() }} }}",
+            counter
+        )),
+        SemanticError::EscapingClosure { .. }
+    ));
+    assert!(matches!(
+        rejected(&format!(
+            "fn mk(n: int): () -> int {{ {} const twice: () -> int = fn(): int {{ inc() + inc() }}; twice }}",
+            counter
+        )),
+        SemanticError::EscapingClosure { name, .. } if name == "twice"
+    ));
+    accepted(&format!(
+        "fn g(n: int): int {{ {} inc() + inc() }}",
+        counter
+    ));
+    accepted(
+        "fn mk(n: int): () -> int { let c: int = n; const get: () -> int = fn(): int { c }; get }",
+    );
+}
+
+#[test]
+fn where_clauses_bind_names_for_their_value_only() {
+    // the bindings become constants declared in a block around the value, in their order
+    let program = Parser::new(lex(
+        "const a: int = x + y, where x: int = 1, y: int = x * 2;",
+    ))
+    .parse_program()
+    .expect("the program parses");
+    let Statement::Declaration {
+        value: Expression::Block { statements, .. },
+        ..
+    } = &program.statements[0]
+    else {
+        panic!(
+            "the where clause is not a block: {:?}",
+            program.statements[0]
+        );
+    };
+    let names: Vec<_> = statements
+        .iter()
+        .map(|statement| match statement {
+            Statement::Declaration { name, mutable, .. } => (name.as_str(), *mutable),
+            statement => panic!("not a binding: {:?}", statement),
+        })
+        .collect();
+    assert_eq!(names, [("x", false), ("y", false)]);
+    assert!(Parser::new(lex("const a: int = x, where 1;"))
+        .parse_program()
+        .is_err());
+
+    // later bindings may use the earlier ones, and the value all of them
+    assert_eq!(
+        first_number("const a: int = x + y, where x: int = 1, y: int = x * 2;"),
+        i64(3)
+    );
+    assert_eq!(
+        rejected("const a: int = x, where x: int = y, y: int = 1;"),
+        SemanticError::UndefinedIdentifier {
+            name: "y".to_string()
+        }
+    );
+    // the bindings may hide outer names, and are gone after the declaration
+    assert_eq!(
+        first_number("const a: int = x, where x: int = 2; const x: int = 1;"),
+        i64(2)
+    );
+    assert_eq!(
+        rejected("const a: int = x, where x: int = 1; const b: int = x;"),
+        SemanticError::UndefinedIdentifier {
+            name: "x".to_string()
+        }
+    );
+
+    // the bindings of a function are scoped to its body, where they can use the parameters
+    accepted("fn f(): int { const g: (n: int) -> int = n * k, where k: int = 3; g(2) }");
+    accepted("fn f(): int { const g: (n: int) -> int = k, where k: int = n * 2; g(2) }");
+    assert_eq!(
+        rejected("fn f(): int { const g: (n: int) -> int = n * k, where k: int = 3; k }"),
+        SemanticError::UndefinedIdentifier {
+            name: "k".to_string()
+        }
+    );
+}
+
+#[test]
+fn tuples_are_built_accessed_and_destructured() {
+    let int = Type::Numeric(NumericType::I64);
+    let field = |name: Option<&str>| TupleField {
+        name: name.map(str::to_string),
+        datatype: int.clone(),
+    };
+
+    // parentheses only make a tuple with a comma or a name
+    assert_eq!(first_number("const a: int = (1);"), i64(1));
+    accepted("const t: (int) = (1,);");
+    accepted("const t: (x: int) = (x: 1);");
+
+    // components are accessed by position or by name, and folded if the tuple is constant
+    let tagged = "const p: (x: int, y: int) = (x: 3, y: 4);";
+    assert!(matches!(
+        value_of(&format!("{} const a: int = p.x * p.1;", tagged), "a"),
+        Expression::Number(n) if n == i64(12)
+    ));
+    assert_eq!(
+        rejected(&format!("{} const a: int = p.z;", tagged)),
+        SemanticError::UnknownField {
+            datatype: Type::Tuple(vec![field(Some("x")), field(Some("y"))]),
+            field: "z".to_string(),
+        }
+    );
+    assert!(matches!(
+        rejected(&format!("{} const a: int = p.2;", tagged)),
+        SemanticError::UnknownField { field, .. } if field == "2"
+    ));
+    assert_eq!(
+        rejected("const p: (x: int, y: int) = (x: 3, x: 4);"),
+        SemanticError::DuplicateField {
+            field: "x".to_string()
+        }
+    );
+
+    // components convert one by one, names only having to agree where both sides have one
+    let Expression::Tuple(elements) = value_of("const t: (u8, f64) = (1, 2);", "t") else {
+        panic!("not a tuple");
+    };
+    let numbers: Vec<_> = elements
+        .iter()
+        .map(|element| match &element.value {
+            Expression::Number(number) => number.clone(),
+            value => panic!("not a number: {:?}", value),
+        })
+        .collect();
+    assert_eq!(
+        numbers,
+        [
+            Number::Int(Integer::Unsigned(Unsigned::UInt8(1))),
+            Number::Float(Floating::Double(2.0)),
+        ]
+    );
+    accepted("const p: (x: int, y: int) = (1, 2);");
+    accepted("const p: (int, int) = (x: 1, y: 2);");
+    accepted("const p: (x: int, int) = (1, 2); const q: (x: int, y: int) = (x: 1, 2);");
+    let mismatch = |source: &str| match rejected(source) {
+        SemanticError::MismatchedTypes {
+            expected, found, ..
+        } => (expected, found),
+        e => panic!("not a type mismatch: {}", e),
+    };
+    assert_eq!(
+        mismatch("const p: (x: int, y: int) = (y: 1, x: 2);"),
+        (
+            Type::Tuple(vec![field(Some("x")), field(Some("y"))]),
+            Type::Tuple(vec![field(Some("y")), field(Some("x"))]),
+        )
+    );
+    assert_eq!(
+        mismatch("const p: (x: int, y: int) = (x: 1, 2, 3);"),
+        (
+            Type::Tuple(vec![field(Some("x")), field(Some("y"))]),
+            Type::Tuple(vec![field(Some("x")), field(None), field(None)]),
+        )
+    );
+
+    // destructuring names every component of a tuple of the given type
+    assert!(matches!(
+        value_of(
+            "const (a, b): (int, int) = (1, 2); const c: int = a + b;",
+            "c"
+        ),
+        Expression::Number(n) if n == i64(3)
+    ));
+    accepted("fn f(t: (x: int, y: int)): int { let (a, b): (int, int) = t; a = b; a }");
+    assert_eq!(
+        rejected("const (a, b): (int, int, int) = (1, 2, 3);"),
+        SemanticError::DestructuringMismatch {
+            names: 2,
+            datatype: Type::Tuple(vec![field(None), field(None), field(None)]),
+        }
+    );
+    assert_eq!(
+        rejected("const (a, b): int = 1;"),
+        SemanticError::DestructuringMismatch {
+            names: 2,
+            datatype: int.clone(),
+        }
+    );
+    assert_eq!(
+        Parser::new(lex("fn f(t: (int, int)): int { let (a, b) = t; a }"))
+            .parse_program()
+            .expect_err("the type is missing"),
+        "expected the tuple type of the destructured names"
+    );
+}
+
+#[test]
+fn structs_are_built_from_every_field_by_name() {
+    let pet = |source: &str| {
+        format!(
+            "const Pet = struct {{
+    legs: u8,
+    name: u64?,
+    fn new(legs: u8): Pet {{ .{{ legs }} }}
+    fn walks(self: Pet): bool {{ self.legs > 0 }}
+}};
+{}",
+            source
+        )
+    };
+    let pet_type = Type::Struct("Pet".to_string());
+
+    // fields are put in the order they are declared, optional ones being nil if left out
+    let Expression::StructLiteral { name, fields } =
+        value_of(&pet("const p: Pet = .{ name = 3, legs = 4 };"), "p")
+    else {
+        panic!("not a struct literal");
+    };
+    assert_eq!(name.as_deref(), Some("Pet"));
+    let names: Vec<_> = fields
+        .iter()
+        .map(|field| field.name.as_deref().unwrap_or_default())
+        .collect();
+    assert_eq!(names, ["legs", "name"]);
+    let Expression::StructLiteral { fields, .. } =
+        value_of(&pet("const p: Pet = .{ legs = 4 };"), "p")
+    else {
+        panic!("not a struct literal");
+    };
+    assert!(matches!(fields[1].value, Expression::Nil));
+
+    // a field named like the variable giving its value needs no `=`
+    accepted(&pet("fn f(legs: u8): Pet { .{ legs } }"));
+    accepted(&pet("const a: bool = Pet.new(4).walks();"));
+    accepted(&pet("fn f(p: Pet): u64? { p.name }"));
+
+    assert_eq!(
+        rejected(&pet("const p: Pet = .{ name = 3 };")),
+        SemanticError::MissingField {
+            datatype: pet_type.clone(),
+            field: "legs".to_string(),
+        }
+    );
+    assert_eq!(
+        rejected(&pet("const p: Pet = .{ legs = 4, tail = 1 };")),
+        SemanticError::UnknownField {
+            datatype: pet_type.clone(),
+            field: "tail".to_string(),
+        }
+    );
+    assert_eq!(
+        rejected(&pet("const p: Pet = .{ legs = 4, legs = 2 };")),
+        SemanticError::DuplicateField {
+            field: "legs".to_string()
+        }
+    );
+    assert_eq!(
+        rejected("const Pet = struct { legs: u8, legs: u8 };"),
+        SemanticError::DuplicateField {
+            field: "legs".to_string()
+        }
+    );
+    assert!(matches!(
+        rejected(&pet("const p: Pet = .{ legs = 300 };")),
+        SemanticError::ConstantOutOfRange { value, .. } if value == "300"
+    ));
+    assert!(matches!(
+        rejected(&pet("const p: Pet = (4, 3);")),
+        SemanticError::MismatchedTypes { expected, .. } if expected == pet_type
+    ));
+    assert_eq!(
+        rejected(&pet("fn f(p: Pet): u8 { p.tail }")),
+        SemanticError::UnknownField {
+            datatype: pet_type.clone(),
+            field: "tail".to_string(),
+        }
+    );
+    assert!(matches!(
+        rejected(&pet("fn f(p: Pet): bool { p.runs() }")),
+        SemanticError::UnknownMethod { method, .. } if method == "runs"
+    ));
+}
+
+const SHAPE: &str = "const Shape = enum { Dot, Circle(f64), Rect(w: f64, h: f64) };";
+
+#[test]
+fn matches_cover_every_value_once() {
+    let shape = |function: &str| format!("{}\n{}", SHAPE, function);
+    accepted(&shape(
+        "fn f(s: Shape): f64 { match s { Shape.Dot => 0.0, Shape.Circle(r) => r, Shape.Rect(_) => 1.0 } }",
+    ));
+    accepted(&shape(
+        "fn f(s: Shape): f64 { match s { .Dot => 0.0, .Circle(r) => r, _ => 1.0 } }",
+    ));
+    accepted(&shape(
+        "fn f(s: Shape): f64 { match s { Shape.Circle(2.0) => 0.0, Shape.Circle(r) => r, _ => 1.0 } }",
+    ));
+    accepted("fn f(n: int): int { match n { 0 => 1, x => x } }");
+    accepted("fn f(b: bool): int { match b { true => 1, false => 0 } }");
+
+    let missing = |source: &str| match rejected(source) {
+        SemanticError::NonExhaustiveMatch { missing, .. } => missing,
+        e => panic!("not a non-exhaustive match: {}", e),
+    };
+    assert_eq!(
+        missing(&shape(
+            "fn f(s: Shape): f64 { match s { Shape.Dot => 0.0, Shape.Circle(r) => r } }"
+        )),
+        "Shape.Rect(_)"
+    );
+    assert_eq!(
+        missing("fn f(n: int): int { match n { 0 => 1, 1 => 2 } }"),
+        "_"
+    );
+    assert_eq!(
+        missing("fn f(b: bool): int { match b { true => 1 } }"),
+        "false"
+    );
+
+    let unreachable = |source: &str| match rejected(source) {
+        SemanticError::UnreachablePattern { pattern, .. } => pattern,
+        e => panic!("not an unreachable pattern: {}", e),
+    };
+    assert_eq!(
+        unreachable(&shape(
+            "fn f(s: Shape): f64 { match s { _ => 0.0, Shape.Dot => 1.0 } }"
+        )),
+        "Shape.Dot"
+    );
+    assert_eq!(
+        unreachable(&shape(
+            "fn f(s: Shape): f64 { match s { Shape.Dot => 0.0, Shape.Dot => 1.0, _ => 2.0 } }"
+        )),
+        "Shape.Dot"
+    );
+    assert_eq!(
+        unreachable("fn f(b: bool): int { match b { true => 1, false => 0, _ => 2 } }"),
+        "_"
+    );
+
+    assert!(matches!(
+        rejected(&shape("fn f(s: Shape): f64 { match s { Shape.Dot(x) => 0.0, _ => 2.0 } }")),
+        SemanticError::ArityMismatch { name, expected: 0, found: 1, .. } if name == "Shape.Dot"
+    ));
+    assert_eq!(
+        rejected(&shape(
+            "fn f(s: Shape): f64 { match s { Shape.Square => 0.0, _ => 2.0 } }"
+        )),
+        SemanticError::UnknownVariant {
+            datatype: Type::Enum("Shape".to_string()),
+            variant: "Square".to_string(),
+        }
+    );
+
+    // the fields of a payload are matched one by one with a tuple of patterns
+    accepted(&shape(
+        "fn f(s: Shape): f64 { match s { .Dot => 0.0, .Circle(r) => r, .Rect(w, h) => w * h } }",
+    ));
+    accepted(&shape(
+        "fn f(s: Shape): f64 { match s { .Rect(1.0, h) => h, .Rect(w, _) => w, _ => 0.0 } }",
+    ));
+    accepted("fn f(t: (bool, bool)): int { match t { (true, _) => 1, (_, true) => 2, (false, false) => 3 } }");
+    // on a constant, the arm taken binds each field
+    let Expression::Block { statements, .. } = value_of(
+        &shape("const a: f64 = match Shape.Rect(2.0, 3.0) { .Rect(w, h) => w * h, _ => 0.0 };"),
+        "a",
+    ) else {
+        panic!("the match on a constant is not folded");
+    };
+    let bound: Vec<_> = statements
+        .iter()
+        .map(|statement| match statement {
+            Statement::Declaration {
+                name,
+                value: Expression::Number(number),
+                ..
+            } => (name.as_str(), number.clone()),
+            statement => panic!("not a constant binding: {:?}", statement),
+        })
+        .collect();
+    assert_eq!(
+        bound,
+        [
+            ("w", Number::Float(Floating::Double(2.0))),
+            ("h", Number::Float(Floating::Double(3.0))),
+        ]
+    );
+    assert_eq!(
+        missing(&shape(
+            "fn f(s: Shape): f64 { match s { .Dot => 0.0, .Circle(r) => r, .Rect(1.0, h) => h } }"
+        )),
+        "Shape.Rect(_, _)"
+    );
+    assert_eq!(
+        missing("fn f(t: (bool, bool)): int { match t { (true, _) => 1, (false, true) => 2 } }"),
+        "(false, false)"
+    );
+    assert_eq!(
+        unreachable("fn f(t: (bool, bool)): int { match t { (true, _) => 1, (false, _) => 2, (_, true) => 3 } }"),
+        "(_, true)"
+    );
+    assert!(matches!(
+        rejected(&shape("fn f(s: Shape): f64 { match s { .Rect(w, h, d) => w, _ => 2.0 } }")),
+        SemanticError::ArityMismatch { name, expected: 2, found: 3, .. } if name == "Shape.Rect"
+    ));
+    assert!(matches!(
+        rejected(&shape("fn f(s: Shape): f64 { match s { .Circle(a, b) => a, _ => 2.0 } }")),
+        SemanticError::ArityMismatch { name, expected: 1, found: 2, .. } if name == "Shape.Circle"
+    ));
+    assert_eq!(
+        rejected("fn f(n: int): int { match n { (a, b) => a } }"),
+        SemanticError::DestructuringMismatch {
+            names: 2,
+            datatype: Type::Numeric(NumericType::I64),
+        }
+    );
+}
+
+#[test]
+fn nil_is_only_given_to_optionals() {
+    let int = Type::Numeric(NumericType::I64);
+    let optional = Type::Optional(Box::new(int.clone()));
+    accepted("const a: int? = nil; const b: int = a ! 3;");
+    accepted("fn f(a: int): int? { a }");
+    accepted("fn f(a: int?, b: int?): int? { a ! b }");
+    accepted("fn f(a: int?): bool { a == nil }");
+    // the fallback gives the value the type it wraps, which may then be widened
+    accepted("fn f(a: u8?): i64 { a ! 3 }");
+
+    assert_eq!(
+        rejected("const a: int = nil;"),
+        SemanticError::NilToNonOptional {
+            datatype: int.clone()
+        }
+    );
+    assert_eq!(
+        rejected("fn f(a: int?): int { a }"),
+        SemanticError::UnwrappedOptional {
+            found: optional.clone(),
+            expected: int.clone(),
+        }
+    );
+    assert_eq!(
+        rejected("fn f(a: int): int { a ! 3 }"),
+        SemanticError::NotOptional {
+            datatype: int.clone()
+        }
+    );
+    assert_eq!(
+        rejected("const T = int?; fn f(a: T?): int { 1 }"),
+        SemanticError::InvalidOptional {
+            datatype: optional.clone()
+        }
+    );
+    // only constants are converted inside an optional
+    accepted("const a: i32? = 3; const b: i64? = a;");
+    assert!(matches!(
+        rejected("fn f(a: i32?): i64? { a }"),
+        SemanticError::MismatchedTypes { .. }
+    ));
+
+    // the values of optionals are only compared if they are numbers or booleans, though any
+    // optional is compared with nil
+    accepted("fn f(a: int?, b: int?): bool { a == b }");
+    accepted("fn f(a: bool?): bool { a != true }");
+    accepted("fn f(a: (int, int)?): bool { nil != a }");
+    let pair = Type::Optional(Box::new(Type::Tuple(vec![
+        TupleField {
+            name: None,
+            datatype: int.clone(),
+        },
+        TupleField {
+            name: None,
+            datatype: int.clone(),
+        },
+    ])));
+    assert_eq!(
+        rejected("fn f(a: (int, int)?, b: (int, int)?): bool { a == b }"),
+        SemanticError::InvalidOperands {
+            operator: BinaryOperator::Equal,
+            operand: pair,
+        }
+    );
+    assert_eq!(
+        rejected("fn f(a: int?): bool { a < 3 }"),
+        SemanticError::UnwrappedOptional {
+            found: optional,
+            expected: int.clone(),
+        }
+    );
+}
+
+/// Whether the index a function, the first statement of a valid program, returns is checked
+/// at runtime.
+fn bounds_checked(source: &str) -> bool {
+    let program = validate(source).unwrap_or_else(|e| panic!("the program is invalid: {}", e));
+    match &program.statements[0] {
+        Statement::FuncDeclaration { body, .. } => match &*body.return_value {
+            Expression::Index { bounds_check, .. } => *bounds_check,
+            value => panic!("not an index: {:?}", value),
+        },
+        statement => panic!("not a function: {:?}", statement),
+    }
+}
+
+#[test]
+fn constant_indices_are_checked_statically() {
+    let out_of_bounds = |index: &str| SemanticError::IndexOutOfBounds {
+        index: index.to_string(),
+        length: Some(3),
+    };
+    assert_eq!(
+        first_number("const a: u64 = comptime { const xs: [3]u64 = [1, 2, 3]; xs[2] };"),
+        u64(3)
+    );
+    assert_eq!(
+        rejected("const xs: [3]u64 = [1, 2, 3]; const a: u64 = xs[3];"),
+        out_of_bounds("3")
+    );
+    assert_eq!(
+        rejected("fn f(xs: [3]u64): u64 { xs[3] }"),
+        out_of_bounds("3")
+    );
+    assert_eq!(
+        rejected("fn f(): nil { let xs: [3]u64 = [1, 2, 3]; xs[4] = 5; }"),
+        out_of_bounds("4")
+    );
+    assert_eq!(
+        rejected("fn f(xs: u64): u64 { xs[0] }"),
+        SemanticError::NotIndexable {
+            datatype: Type::Numeric(NumericType::U64)
+        }
+    );
+
+    // only indices not known to be in bounds are checked at runtime
+    assert!(!bounds_checked("fn f(xs: [3]u64): u64 { xs[1] }"));
+    assert!(bounds_checked("fn f(xs: [3]u64, i: usize): u64 { xs[i] }"));
+    assert!(bounds_checked("fn f(xs: []u64): u64 { xs[3] }"));
+}
+
+#[test]
+fn aliases_resolve_transitively_without_cycles() {
+    let cycle = |names: &[&str]| SemanticError::CyclicType {
+        cycle: names.iter().map(|name| name.to_string()).collect(),
+    };
+    // aliases may be used before they are declared, and are the type they stand for
+    accepted("const A = B; const B = []u64; fn f(a: A): B { a }");
+    accepted("const A = int; fn f(a: A): i64 { a }");
+    // a struct may refer to itself through a pointer
+    accepted("const L = struct { next: *L, v: u64 };");
+
+    assert_eq!(
+        rejected("const A = B; const B = A;"),
+        cycle(&["A", "B", "A"])
+    );
+    assert_eq!(rejected("const A = []A;"), cycle(&["A", "A"]));
+    assert_eq!(
+        rejected("const A = (int, B); const B = C?; const C = A;"),
+        cycle(&["A", "B", "C", "A"])
+    );
+    assert_eq!(
+        rejected("const A = int; const A = bool;"),
+        SemanticError::DuplicateType {
+            name: "A".to_string()
+        }
+    );
+    assert_eq!(
+        rejected("fn f(a: Nope): int { 1 }"),
+        SemanticError::UndefinedType {
+            name: "Nope".to_string()
+        }
+    );
+}
+
+const MAYBE: &str = "
+fn Maybe(T: comptime type): type {
+    enum {
+        Some(T),
+        None,
+        fn or(self: Maybe(T), other: T): T { match self { .Some(v) => v, .None => other } }
+    }
+}
+";
+
+#[test]
+fn factories_create_a_type_once_per_argument_list() {
+    let source = format!(
+        "{}const A = Maybe(u64); const I = u64;
+fn f(a: A, b: Maybe(I)): u64 {{ a.or(1) + b.or(2) }}
+fn g(a: Maybe(u8)): u8 {{ a.or(3) }}
+fn h(a: Maybe(u64)): A {{ a }}",
+        MAYBE
+    );
+    let program = validate(&source).unwrap_or_else(|e| panic!("the program is invalid: {}", e));
+    let instances: Vec<&str> = program
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::TypeDeclaration { name, .. } if name.starts_with("Maybe(") => {
+                Some(name.as_str())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(instances, ["Maybe(u64)", "Maybe(u8)"]);
+
+    assert_eq!(
+        rejected(&format!("{}fn f(a: Maybe(u64)): Maybe(u32) {{ a }}", MAYBE)),
+        SemanticError::MismatchedTypes {
+            expected: Type::Enum("Maybe(u32)".to_string()),
+            found: Type::Enum("Maybe(u64)".to_string()),
+            span: Span {
+                line: 9,
+                column: 35
+            },
+        }
+    );
+    let arity = |source: &str| match rejected(&format!("{}{}", MAYBE, source)) {
+        SemanticError::ArityMismatch {
+            expected, found, ..
+        } => (expected, found),
+        e => panic!("not an arity mismatch: {}", e),
+    };
+    assert_eq!(arity("fn f(a: Maybe(u64, u8)): int { 1 }"), (1, 2));
+    assert_eq!(arity("fn f(a: Maybe): int { 1 }"), (1, 0));
+    assert_eq!(
+        rejected("fn Maybe(T: type): type { enum { Some(T), None } }"),
+        SemanticError::ComptimeParameter {
+            function: "Maybe".to_string(),
+            parameter: "T".to_string(),
+        }
+    );
+}
+
+const POINT: &str = "
+const Pt = struct {
+    x: u64,
+    fn set(self: *Pt, x: u64): nil { self.x = x; }
+    fn get(self: *const Pt): u64 { self.x }
+};
+";
+
+#[test]
+fn nothing_is_changed_through_const_pointers() {
+    let point = |source: &str| format!("{}{}", POINT, source);
+    let pointer = |pointee: Type, mutable| Type::Pointer {
+        pointee: Box::new(pointee),
+        mutable,
+    };
+    let pt = Type::Struct("Pt".to_string());
+    let u64_type = Type::Numeric(NumericType::U64);
+
+    accepted(&point("fn f(p: *Pt): nil { p.x = 1; }"));
+    accepted(&point("fn f(p: *const Pt): u64 { p.x }"));
+    accepted(&point("fn f(p: *u64): *const u64 { p }"));
+    accepted(&point(
+        "fn f(): nil { let x: u64 = 1; const p: *u64 = &x; p.* = 2; }",
+    ));
+    // methods taking a pointer are given the address of the receiver
+    accepted(&point("fn f(p: *Pt): u64 { p.get() }"));
+    accepted(&point("fn f(): nil { let p: Pt = .{ x = 1 }; p.set(2); }"));
+
+    assert_eq!(
+        rejected(&point("fn f(p: *const Pt): nil { p.x = 1; }")),
+        SemanticError::AssignmentThroughConst {
+            datatype: pointer(pt.clone(), false)
+        }
+    );
+    assert_eq!(
+        rejected(&point("fn f(p: *const u64): nil { p.* = 1; }")),
+        SemanticError::AssignmentThroughConst {
+            datatype: pointer(u64_type.clone(), false)
+        }
+    );
+    assert_eq!(
+        rejected(&point("fn f(p: *const Pt): nil { p.set(1); }")),
+        SemanticError::MismatchedArgument {
+            function: "Pt.set".to_string(),
+            parameter: "self".to_string(),
+            expected: Box::new(pointer(pt.clone(), true)),
+            found: Box::new(pointer(pt, false)),
+            span: Span {
+                line: 7,
+                column: 29
+            },
+        }
+    );
+    // constants only give const pointers
+    assert_eq!(
+        rejected(&point(
+            "fn f(): nil { const x: u64 = 1; const p: *u64 = &x; }"
+        )),
+        SemanticError::MismatchedTypes {
+            expected: pointer(u64_type.clone(), true),
+            found: pointer(u64_type.clone(), false),
+            span: Span {
+                line: 7,
+                column: 50
+            },
+        }
+    );
+    accepted(&point(
+        "fn f(): nil { const x: u64 = 1; const p: *const u64 = &x; }",
+    ));
+    assert_eq!(
+        rejected(&point(
+            "fn f(): nil { const p: Pt = .{ x = 1 }; p.set(2); }"
+        )),
+        SemanticError::AssignmentToConstant {
+            name: "p".to_string()
+        }
+    );
+
+    assert_eq!(
+        rejected("fn f(x: u64): u64 { x.* }"),
+        SemanticError::NotAPointer { datatype: u64_type }
+    );
+    assert_eq!(
+        rejected("fn f(): nil { const p: *u64 = &1; }"),
+        SemanticError::AddressOfTemporary
+    );
+}
+
+#[test]
+fn addresses_of_variables_are_not_returned() {
+    let escaping = |name: &str| SemanticError::EscapingAddress {
+        name: name.to_string(),
+        function: "f".to_string(),
+    };
+
+    assert_eq!(
+        rejected("fn f(): *u64 { let x: u64 = 1; &x }"),
+        escaping("x")
+    );
+    assert_eq!(rejected("fn f(x: u64): *const u64 { &x }"), escaping("x"));
+    assert_eq!(
+        rejected(&format!("{}{}", POINT, 
+            "fn f(c: bool, n: u64): (u64, *Pt) { let p: Pt = .{ x = n }; if c { (n, &p) } else { (n, &p) } }"
+        )),
+        escaping("p")
+    );
+    assert_eq!(
+        rejected("fn f(): *u64 { let xs: [2]u64 = [1, 2]; &xs[0] }"),
+        escaping("xs")
+    );
+
+    // what pointers passed in point to outlives the call
+    accepted(&format!("{}{}", POINT, "fn f(p: *Pt): *u64 { &p.x }"));
+    accepted("fn f(p: *u64): *u64 { p }");
+    accepted("let g: u64 = 1;\nfn f(): *const u64 { &g }");
+}
@@ -0,0 +1,137 @@
+// NUMERIC PROMOTION LATTICE //
+//
+// A numeric type implicitly widens to another if every value of the former is
+// exactly representable by the latter. Anything else requires an explicit `as`.
+
+use crate::structures::parse_tree::{NumericType, Param, TupleField, Type};
+
+/// Candidates for the join of two numeric types, from narrowest to widest.
+const PROMOTION_ORDER: [NumericType; 14] = [
+    NumericType::I8,
+    NumericType::U8,
+    NumericType::I16,
+    NumericType::U16,
+    NumericType::I32,
+    NumericType::U32,
+    NumericType::F32,
+    NumericType::I64,
+    NumericType::ISize,
+    NumericType::U64,
+    NumericType::USize,
+    NumericType::F64,
+    NumericType::I128,
+    NumericType::U128,
+];
+
+/// Number of bits of precision in the significand of a floating point type.
+fn mantissa_bits(float: NumericType) -> u32 {
+    match float {
+        NumericType::F32 => f32::MANTISSA_DIGITS,
+        _ => f64::MANTISSA_DIGITS,
+    }
+}
+
+/// Whether `from` converts losslessly into `to`.
+pub fn widens(from: NumericType, to: NumericType) -> bool {
+    if from == to {
+        return true;
+    }
+
+    match (from.is_float(), to.is_float()) {
+        (true, true) => from.bits() < to.bits(),
+        (true, false) => false,
+        (false, true) => from.bits() < mantissa_bits(to),
+        (false, false) => match (from.is_signed(), to.is_signed()) {
+            (true, false) => false,
+            _ => from.bits() < to.bits(),
+        },
+    }
+}
+
+/// The narrowest type both `left` and `right` widen to, if there is one.
+pub fn join(left: NumericType, right: NumericType) -> Option<NumericType> {
+    if widens(left, right) {
+        return Some(right);
+    }
+    if widens(right, left) {
+        return Some(left);
+    }
+
+    PROMOTION_ORDER
+        .into_iter()
+        .find(|&candidate| widens(left, candidate) && widens(right, candidate))
+}
+
+/// Whether two types are the same, disregarding the names of function parameters.
+pub fn same_type(left: &Type, right: &Type) -> bool {
+    match (left, right) {
+        (
+            Type::FuncType {
+                parameters: left_parameters,
+                return_type: left_return,
+            },
+            Type::FuncType {
+                parameters: right_parameters,
+                return_type: right_return,
+            },
+        ) => {
+            left_parameters.len() == right_parameters.len()
+                && left_parameters.iter().zip(right_parameters).all(
+                    |(
+                        Param::Parameter { datatype: left, .. },
+                        Param::Parameter {
+                            datatype: right, ..
+                        },
+                    )| same_type(left, right),
+                )
+                && same_type(left_return, right_return)
+        }
+        (Type::Optional(left), Type::Optional(right)) => same_type(left, right),
+        (
+            Type::Pointer {
+                pointee: left,
+                mutable: left_mutable,
+            },
+            Type::Pointer {
+                pointee: right,
+                mutable: right_mutable,
+            },
+        ) => left_mutable == right_mutable && same_type(left, right),
+        (
+            Type::Array {
+                element: left_element,
+                length: left_length,
+            },
+            Type::Array {
+                element: right_element,
+                length: right_length,
+            },
+        ) => left_length == right_length && same_type(left_element, right_element),
+        (Type::Tuple(left_fields), Type::Tuple(right_fields)) => {
+            left_fields.len() == right_fields.len()
+                && left_fields.iter().zip(right_fields).all(|(left, right)| {
+                    compatible_names(&left.name, &right.name)
+                        && same_type(&left.datatype, &right.datatype)
+                })
+        }
+        _ => left == right,
+    }
+}
+
+/// Untagged tuple components are compatible with tagged ones of any name.
+pub fn compatible_names(left: &Option<String>, right: &Option<String>) -> bool {
+    match (left, right) {
+        (Some(left), Some(right)) => left == right,
+        _ => true,
+    }
+}
+
+/// Position of a tuple component, given either by index or by name.
+pub fn tuple_field_index(fields: &[TupleField], field: &str) -> Option<usize> {
+    match field.parse::<usize>() {
+        Ok(index) => (index < fields.len()).then_some(index),
+        Err(_) => fields
+            .iter()
+            .position(|candidate| candidate.name.as_deref() == Some(field)),
+    }
+}
@@ -4,12 +4,14 @@ use colored::Colorize;
 
 use crate::structures::{
     parse_tree::{
-        Arguments, Block, ConditionalBlock, Expression, If, Param, Program, Statement, Type,
+        Arguments, Block, ConditionalBlock, Expression, FactoryCall, If, Match, MatchArm,
+        NumericType, Param, Pattern, Program, Span, Statement, StructField, TupleElement,
+        TupleField, Type, TypeDefinition, Variant,
     },
     tokens::Token,
 };
 
-pub fn parse(tokens: &[Token]) -> Program {
+pub fn parse(tokens: &[(Token, Span)]) -> Program {
     match Parser::new(tokens.to_owned()).parse_program() {
         Ok(program) => program,
         Err(e) => {
@@ -21,30 +23,38 @@ pub fn parse(tokens: &[Token]) -> Program {
 
 pub struct Parser {
     tokens: Vec<Token>,
+    spans: Vec<Span>,
     position: usize,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+    pub fn new(tokens: Vec<(Token, Span)>) -> Self {
+        let (tokens, spans) = tokens.into_iter().unzip();
         Self {
             tokens,
+            spans,
             position: 0,
         }
     }
 
+    /// Where the next token starts, or the last one if there are no more.
+    fn span(&self) -> Span {
+        self.spans
+            .get(self.position)
+            .or(self.spans.last())
+            .copied()
+            .unwrap_or_default()
+    }
+
     fn peek(&self) -> Option<&Token> {
         self.tokens.get(self.position)
     }
 
     fn peek_two(&self) -> Option<(&Token, &Token)> {
-        let first = self.tokens.get(self.position);
-        let second = self.tokens.get(self.position + 1);
+        let first = self.tokens.get(self.position)?;
+        let second = self.tokens.get(self.position + 1)?;
 
-        if first.is_some() && second.is_some() {
-            Some((first.unwrap(), second.unwrap()))
-        } else {
-            None
-        }
+        Some((first, second))
     }
 
     fn consume(&mut self) -> Option<Token> {
@@ -59,10 +69,10 @@ impl Parser {
         let next = if let Some(token) = actual {
             format!("{}", token)
         } else {
-            format!("none")
+            "none".to_string()
         };
         let expected_part = format!("expected {}", expected.to_string().bold());
-        let found_part = format!("found {}", next.to_string().bold());
+        let found_part = format!("found {}", next.bold());
         format!("{}, {}", expected_part, found_part)
             .red()
             .to_string()
@@ -82,34 +92,147 @@ impl Parser {
     }
 
     fn parse_type(&mut self) -> Result<Type, String> {
-        match self.consume() {
-            Some(Token::NumberKeyword) => Ok(Type::Number),
-            Some(Token::IntKeyword) => Ok(Type::Int),
-            Some(Token::Nil) => Ok(Type::Nil),
-            // closure
-            // Some(Token::OpenParen) => {
-            //     let mut param_types = Vec::new();
-            //     while self.peek() != Some(&Token::CloseParen) {
-            //         let param = self.parse_param()?;
-            //         param_types.push(param);
-            //         if self.peek() == Some(&Token::Comma) {
-            //             self.consume();
-            //         }
-            //     }
-            //     self.expect(Token::CloseParen)?;
-            //     self.expect(Token::Arrow)?;
-            //     let return_type = self.parse_type()?;
-            //     Ok(Type::FuncType {
-            //         parameters: param_types,
-            //         return_type: Box::new(return_type),
-            //     })
-            // }
-            _ => Err("expected a type".to_string()),
+        // tuple or function type
+        let datatype = if let Some(Token::OpenParen) = self.peek() {
+            self.parse_parenthesized_type()?
+        } else {
+            match self.consume() {
+                Some(Token::NumberKeyword) => Type::Numeric(NumericType::F64),
+                Some(Token::IntKeyword) => Type::Numeric(NumericType::I64),
+                Some(Token::NumericKeyword(numeric)) => Type::Numeric(numeric),
+                Some(Token::BoolKeyword) => Type::Bool,
+                Some(Token::Nil) => Type::Nil,
+                Some(Token::Identifier(name)) => {
+                    // a type of another module, like `shapes.Circle`
+                    let mut name = name;
+                    while let Some((Token::Dot, Token::Identifier(member))) = self.peek_two() {
+                        name = format!("{name}.{member}");
+                        self.consume(); // consume '.'
+                        self.consume(); // consume the member
+                    }
+
+                    if self.peek() != Some(&Token::OpenParen) {
+                        return self.optional_type(Type::Named(name));
+                    }
+
+                    self.consume(); // consume '('
+                    let mut arguments = vec![];
+                    while self.peek() != Some(&Token::CloseParen) {
+                        arguments.push(self.parse_type()?);
+                        if self.peek() != Some(&Token::CloseParen) {
+                            self.expect(Token::Comma)?;
+                        }
+                    }
+                    self.expect(Token::CloseParen)?;
+                    Type::Instance(Box::new(FactoryCall {
+                        factory: name,
+                        arguments,
+                    }))
+                }
+                Some(Token::Asterisk) => {
+                    let mutable = self.peek() != Some(&Token::Const);
+                    if !mutable {
+                        self.consume(); // consume `const`
+                    }
+                    Type::Pointer {
+                        pointee: Box::new(self.parse_type()?),
+                        mutable,
+                    }
+                }
+                Some(Token::TypeKeyword) => Type::Type,
+                Some(Token::Comptime) => Type::Comptime(Box::new(self.parse_type()?)),
+                Some(Token::OpenBracket) => {
+                    let length = match self.peek() {
+                        Some(Token::NumberLiteral(length)) => {
+                            let length = length
+                                .parse::<usize>()
+                                .map_err(|_| "expected the length of an array".to_string())?;
+                            self.consume();
+                            Some(length)
+                        }
+                        _ => None,
+                    };
+                    self.expect(Token::CloseBracket)?;
+                    Type::Array {
+                        element: Box::new(self.parse_type()?),
+                        length,
+                    }
+                }
+                _ => return Err("expected a type".to_string()),
+            }
+        };
+
+        self.optional_type(datatype)
+    }
+
+    /// Makes `datatype` optional if it is followed by a question mark.
+    fn optional_type(&mut self, datatype: Type) -> Result<Type, String> {
+        if let Some(Token::QuestionMark) = self.peek() {
+            self.consume();
+            return Ok(Type::Optional(Box::new(datatype)));
+        }
+
+        Ok(datatype)
+    }
+
+    fn parse_parenthesized_type(&mut self) -> Result<Type, String> {
+        self.expect(Token::OpenParen)?;
+
+        let mut fields = vec![];
+        while self.peek() != Some(&Token::CloseParen) {
+            let name = if let Some((Token::Identifier(name), Token::Colon)) = self.peek_two() {
+                let name = name.clone();
+                self.consume(); // consume the name
+                self.consume(); // consume ':'
+                Some(name)
+            } else {
+                None
+            };
+            let datatype = self.parse_type()?;
+            fields.push(TupleField { name, datatype });
+
+            if self.peek() == Some(&Token::Comma) {
+                self.consume();
+            } else {
+                break;
+            }
         }
+        self.expect(Token::CloseParen)?;
+
+        if self.peek() != Some(&Token::Arrow) {
+            return if fields.is_empty() {
+                Err("expected a type".to_string())
+            } else {
+                Ok(Type::Tuple(fields))
+            };
+        }
+        self.consume(); // consume '->'
+
+        let mut parameters = vec![];
+        for TupleField { name, datatype } in fields {
+            match name {
+                Some(name) => parameters.push(Param::Parameter { name, datatype }),
+                None => return Err("expected a named parameter".to_string()),
+            }
+        }
+        let return_type = self.parse_type()?;
+
+        Ok(Type::FuncType {
+            parameters,
+            return_type: Box::new(return_type),
+        })
     }
 
     fn parse_single_parameter(&mut self) -> Result<Param, String> {
         if let Some(Token::Identifier(name)) = self.consume() {
+            // a bare `self` receives the struct the method belongs to
+            if name == "self" && self.peek() != Some(&Token::Colon) {
+                return Ok(Param::Parameter {
+                    name,
+                    datatype: Type::Named("Self".to_string()),
+                });
+            }
+
             self.expect(Token::Colon)?;
             let datatype = self.parse_type()?;
             Ok(Param::Parameter { name, datatype })
@@ -141,6 +264,11 @@ impl Parser {
 
         let mut arguments = vec![];
 
+        if let Some(Token::CloseParen) = self.peek() {
+            self.consume();
+            return Ok(arguments);
+        }
+
         let argument = self.parse_expression()?;
         arguments.push(argument);
 
@@ -156,23 +284,60 @@ impl Parser {
     }
 
     fn parse_factor(&mut self) -> Result<Expression, String> {
+        let span = self.span();
         let next = self.consume();
         if let Some(Token::OpenParen) = next {
-            let expr = self.parse_expression()?;
-            self.expect(Token::CloseParen)?;
-            Ok(expr)
+            self.parse_parenthesized()
         } else {
             match next {
                 Some(Token::NumberLiteral(n)) => Ok(Expression::NumberLiteral(n)),
+                Some(Token::True) => Ok(Expression::Boolean(true)),
+                Some(Token::False) => Ok(Expression::Boolean(false)),
+                Some(Token::Nil) => Ok(Expression::Nil),
+                Some(Token::Dot) => self.parse_struct_literal(),
+                Some(Token::Match) => self.parse_match(span),
+                Some(Token::Ampersand) => {
+                    Ok(Expression::AddressOf(Box::new(self.parse_postfix()?)))
+                }
+                Some(Token::Comptime) => Ok(Expression::Comptime(self.parse_block()?)),
+                Some(Token::OpenBracket) => {
+                    let mut elements = vec![];
+                    while self.peek() != Some(&Token::CloseBracket) {
+                        elements.push(self.parse_expression()?);
+                        if self.peek() == Some(&Token::Comma) {
+                            self.consume();
+                        } else {
+                            break;
+                        }
+                    }
+                    self.expect(Token::CloseBracket)?;
+                    Ok(Expression::Array(elements))
+                }
+                // anonymous function
+                Some(Token::FnKeyword) => {
+                    let parameters = self.parse_parameter_list()?;
+                    self.expect(Token::Colon)?;
+                    let return_type = self.parse_type()?;
+                    let body = self.parse_block()?;
+                    Ok(Expression::Function {
+                        parameters,
+                        return_type: Box::new(return_type),
+                        body,
+                        by_reference: false,
+                        captures: vec![],
+                    })
+                }
                 Some(Token::Identifier(id)) => {
                     if let Some(Token::OpenParen) = self.peek() {
                         let arguments = self.parse_arguments()?;
                         Ok(Expression::FunctionCall {
                             name: id,
                             arguments,
+                            span,
+                            tail: false,
                         })
                     } else {
-                        Ok(Expression::Identifier(id))
+                        Ok(Expression::Identifier { name: id, span })
                     }
                 }
                 _ => Err("expected a factor".to_string()),
@@ -180,25 +345,273 @@ impl Parser {
         }
     }
 
+    /// Parses either a grouped expression or a tuple literal, after the opening parenthesis.
+    fn parse_parenthesized(&mut self) -> Result<Expression, String> {
+        let mut elements = vec![];
+        let mut tuple = false;
+
+        loop {
+            let name = if let Some((Token::Identifier(name), Token::Colon)) = self.peek_two() {
+                let name = name.clone();
+                self.consume(); // consume the name
+                self.consume(); // consume ':'
+                tuple = true;
+                Some(name)
+            } else {
+                None
+            };
+            let value = self.parse_expression()?;
+            elements.push(TupleElement { name, value });
+
+            if self.peek() == Some(&Token::Comma) {
+                self.consume();
+                tuple = true;
+                if self.peek() == Some(&Token::CloseParen) {
+                    break;
+                }
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::CloseParen)?;
+
+        if tuple {
+            Ok(Expression::Tuple(elements))
+        } else {
+            Ok(elements.remove(0).value)
+        }
+    }
+
+    /// Parses `{ field = value, shorthand, ... }`, after the dot of a struct literal.
+    fn parse_struct_literal(&mut self) -> Result<Expression, String> {
+        self.expect(Token::OpenBrace)?;
+
+        let mut fields = vec![];
+        while let Some(Token::Identifier(name)) = self.peek().cloned() {
+            let span = self.span();
+            self.consume();
+
+            let value = if let Some(Token::Assignment | Token::Colon) = self.peek() {
+                self.consume();
+                self.parse_expression()?
+            } else {
+                Expression::Identifier {
+                    name: name.clone(),
+                    span,
+                }
+            };
+            fields.push(TupleElement {
+                name: Some(name),
+                value,
+            });
+
+            if self.peek() == Some(&Token::Comma) {
+                self.consume();
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::CloseBrace)?;
+
+        Ok(Expression::StructLiteral { name: None, fields })
+    }
+
+    /// Parses `value { pattern => value, ... }`, after `match` at `span`.
+    fn parse_match(&mut self, span: Span) -> Result<Expression, String> {
+        let value = self.parse_expression()?;
+        self.expect(Token::OpenBrace)?;
+
+        let mut arms = vec![];
+        while self.peek() != Some(&Token::CloseBrace) {
+            let pattern = self.parse_pattern()?;
+            self.expect(Token::FatArrow)?;
+            let value = self.parse_expression()?;
+            arms.push(MatchArm { pattern, value });
+
+            if self.peek() == Some(&Token::Comma) {
+                self.consume();
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::CloseBrace)?;
+
+        Ok(Expression::Match(Match {
+            value: Box::new(value),
+            arms,
+            span,
+        }))
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, String> {
+        match self.consume() {
+            Some(Token::Identifier(name)) if name == "_" => Ok(Pattern::Wildcard),
+            Some(Token::Identifier(name)) if self.peek() == Some(&Token::Dot) => {
+                self.consume(); // consume '.'
+
+                // the enum may belong to another module, as in `shapes.Shape.Circle`
+                let mut enum_name = name;
+                while let Some((Token::Identifier(member), Token::Dot)) = self.peek_two() {
+                    enum_name = format!("{enum_name}.{member}");
+                    self.consume(); // consume the member
+                    self.consume(); // consume '.'
+                }
+
+                self.parse_variant_pattern(Some(enum_name))
+            }
+            Some(Token::Identifier(name)) => Ok(Pattern::Binding(name)),
+            Some(Token::Dot) => self.parse_variant_pattern(None),
+            Some(Token::OpenParen) => self.parse_pattern_list(),
+            Some(Token::NumberLiteral(n)) => Ok(Pattern::Literal(Expression::NumberLiteral(n))),
+            Some(Token::True) => Ok(Pattern::Literal(Expression::Boolean(true))),
+            Some(Token::False) => Ok(Pattern::Literal(Expression::Boolean(false))),
+            Some(Token::Nil) => Ok(Pattern::Literal(Expression::Nil)),
+            _ => Err("expected a pattern".to_string()),
+        }
+    }
+
+    /// Parses `Variant`, `Variant(pattern)` or `Variant(pattern, ...)`, after the dot.
+    fn parse_variant_pattern(&mut self, enum_name: Option<String>) -> Result<Pattern, String> {
+        let Some(Token::Identifier(variant)) = self.consume() else {
+            return Err("expected a variant".to_string());
+        };
+
+        let payload = if self.peek() == Some(&Token::OpenParen) {
+            self.consume();
+            Some(Box::new(self.parse_pattern_list()?))
+        } else {
+            None
+        };
+
+        Ok(Pattern::Variant {
+            enum_name,
+            variant,
+            payload,
+        })
+    }
+
+    /// Parses patterns separated by commas up to the closing parenthesis, which make a tuple
+    /// pattern unless there is a single one without a trailing comma, like `(pattern)`.
+    fn parse_pattern_list(&mut self) -> Result<Pattern, String> {
+        let mut elements = vec![];
+        let mut tuple = false;
+        loop {
+            elements.push(self.parse_pattern()?);
+            if self.peek() != Some(&Token::Comma) {
+                break;
+            }
+            self.consume();
+            tuple = true;
+            if self.peek() == Some(&Token::CloseParen) {
+                break;
+            }
+        }
+        self.expect(Token::CloseParen)?;
+
+        match tuple {
+            true => Ok(Pattern::Tuple(elements)),
+            false => Ok(elements.remove(0)),
+        }
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expression, String> {
+        let mut value = self.parse_factor()?;
+
+        while let Some(Token::Dot | Token::OpenBracket) = self.peek() {
+            let span = self.span();
+            if let Some(Token::OpenBracket) = self.consume() {
+                let index = self.parse_expression()?;
+                self.expect(Token::CloseBracket)?;
+                value = Expression::Index {
+                    value: Box::new(value),
+                    index: Box::new(index),
+                    bounds_check: true,
+                    span,
+                };
+                continue;
+            }
+
+            let span = self.span();
+            let field = match self.consume() {
+                Some(Token::Asterisk) => {
+                    value = Expression::Deref(Box::new(value));
+                    continue;
+                }
+                Some(Token::Identifier(field) | Token::NumberLiteral(field)) => field,
+                _ => return Err("expected a field".to_string()),
+            };
+
+            value = if let Some(Token::OpenParen) = self.peek() {
+                Expression::MethodCall {
+                    receiver: Box::new(value),
+                    method: field,
+                    arguments: self.parse_arguments()?,
+                    span,
+                }
+            } else {
+                Expression::FieldAccess {
+                    value: Box::new(value),
+                    field,
+                }
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn parse_cast(&mut self) -> Result<Expression, String> {
+        let mut value = self.parse_postfix()?;
+
+        while let Some(Token::As) = self.peek() {
+            self.consume();
+            let datatype = self.parse_type()?;
+            value = Expression::Cast {
+                value: Box::new(value),
+                datatype,
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn parse_default(&mut self) -> Result<Expression, String> {
+        let mut value = self.parse_cast()?;
+
+        while let Some(Token::Negate) = self.peek() {
+            self.consume();
+            let fallback = self.parse_cast()?;
+            value = Expression::Default {
+                value: Box::new(value),
+                fallback: Box::new(fallback),
+            };
+        }
+
+        Ok(value)
+    }
+
     fn parse_term(&mut self) -> Result<Expression, String> {
-        let mut left = self.parse_factor()?;
+        let mut left = self.parse_default()?;
 
         while let Some(token) = self.peek().cloned() {
             match token {
                 Token::Asterisk => {
+                    let span = self.span();
                     self.consume();
-                    let right = self.parse_factor()?;
+                    let right = self.parse_default()?;
                     left = Expression::Multiplication {
                         multiplicant: Box::new(left),
                         multiplier: Box::new(right),
+                        span,
                     };
                 }
                 Token::Slash => {
+                    let span = self.span();
                     self.consume();
-                    let right = self.parse_factor()?;
+                    let right = self.parse_default()?;
                     left = Expression::Division {
                         dividend: Box::new(left),
                         divisor: Box::new(right),
+                        span,
                     };
                 }
                 _ => break,
@@ -282,19 +695,23 @@ impl Parser {
             while let Some(token) = self.peek().cloned() {
                 match token {
                     Token::Plus => {
+                        let span = self.span();
                         self.consume(); // Consume the '+' token
                         let right = self.parse_term()?;
                         left = Expression::Addition {
                             augend: Box::new(left),
                             addend: Box::new(right),
+                            span,
                         };
                     }
                     Token::Minus => {
+                        let span = self.span();
                         self.consume(); // Consume the '-' token
                         let right = self.parse_term()?;
                         left = Expression::Subtraction {
                             minuend: Box::new(left),
                             subtrahend: Box::new(right),
+                            span,
                         };
                     }
                     _ => break,
@@ -329,15 +746,18 @@ impl Parser {
                 Token::CloseBrace => break,
                 _ => {
                     let save_point = self.position;
-                    if let Some(stmt) = self.parse_statement().ok() {
-                        statements.push(stmt);
-                    } else {
-                        self.rewind(save_point);
-                        if let Some(expr) = self.parse_expression().ok() {
-                            return_value = expr;
+                    match self.parse_statement() {
+                        Ok(stmt) => statements.push(stmt),
+                        // a declaration is never the value of a block, so the error is in it
+                        Err(e) if matches!(token, Token::Let | Token::Const | Token::Capture) => {
+                            return Err(e)
+                        }
+                        Err(_) => {
+                            self.rewind(save_point);
+                            return_value = self.parse_expression()?;
                             break;
                         }
-                    };
+                    }
                 }
             }
         }
@@ -378,16 +798,263 @@ impl Parser {
 
         Ok(If {
             if_block,
-            else_if_blocks: vec![],
+            else_if_blocks,
             else_block,
         })
     }
 
+    /// Parses the trailing `, where name: type = value, ...` bindings of a declaration,
+    /// lowering them to constants declared in a block around the value.
+    fn parse_where_clause(&mut self, value: Expression) -> Result<Expression, String> {
+        if !matches!(self.peek_two(), Some((Token::Comma, Token::Where))) {
+            return Ok(value);
+        }
+        self.consume(); // consume ','
+        self.consume(); // consume 'where'
+
+        let mut bindings = vec![];
+        loop {
+            if let Some(Token::Identifier(name)) = self.consume() {
+                self.expect(Token::Colon)?;
+                let datatype = self.parse_type()?;
+                self.expect(Token::Assignment)?;
+                let value = self.parse_expression()?;
+
+                bindings.push(Statement::Declaration {
+                    public: false,
+                    mutable: false,
+                    name,
+                    datatype,
+                    value,
+                });
+            } else {
+                return Err("expected an identifier".to_string());
+            }
+
+            if let Some((Token::Comma, Token::Identifier(_))) = self.peek_two() {
+                self.consume(); // consume ','
+            } else {
+                break;
+            }
+        }
+
+        // the bindings of an anonymous function are scoped to its body
+        match value {
+            Expression::Function {
+                parameters,
+                return_type,
+                body,
+                by_reference,
+                captures,
+            } => Ok(Expression::Function {
+                parameters,
+                return_type,
+                body: Block {
+                    statements: bindings,
+                    return_value: Box::new(Expression::Block {
+                        statements: body.statements,
+                        return_value: body.return_value,
+                    }),
+                },
+                by_reference,
+                captures,
+            }),
+            value => Ok(Expression::Block {
+                statements: bindings,
+                return_value: Box::new(value),
+            }),
+        }
+    }
+
+    fn parse_function_declaration(&mut self) -> Result<Statement, String> {
+        let public = self.peek() == Some(&Token::PubKeyword);
+
+        if public {
+            // consume the `pub` keyword
+            self.consume();
+        }
+
+        let inline = self.peek() == Some(&Token::Inline);
+
+        if inline {
+            // consume the `inline` keyword
+            self.consume();
+        }
+
+        self.expect(Token::FnKeyword)?;
+
+        if let Some(Token::Identifier(name)) = self.consume() {
+            let parameters = self.parse_parameter_list()?;
+            self.expect(Token::Colon)?;
+            let return_type = self.parse_type()?; // return type of the function
+            let body = if return_type == Type::Type {
+                self.parse_factory_body()?
+            } else {
+                self.parse_block()?
+            };
+            Ok(Statement::FuncDeclaration {
+                public,
+                inline,
+                name,
+                parameters,
+                return_type,
+                body,
+            })
+        } else {
+            Err("expected an identifier".to_string())
+        }
+    }
+
+    /// Parses the body of a function returning a `type`, which is the definition of that type.
+    fn parse_factory_body(&mut self) -> Result<Block, String> {
+        self.expect(Token::OpenBrace)?;
+        let definition = self.parse_type_definition()?;
+        self.expect(Token::CloseBrace)?;
+
+        Ok(Block {
+            statements: vec![],
+            return_value: Box::new(Expression::Type(Box::new(definition))),
+        })
+    }
+
+    /// Parses `const Name = <definition>;`, after `const Name =`.
+    fn parse_type_declaration(&mut self, name: String) -> Result<Statement, String> {
+        let definition = self.parse_type_definition()?;
+        self.expect(Token::SemiColon)?;
+        Ok(Statement::TypeDeclaration {
+            public: false,
+            name,
+            definition,
+        })
+    }
+
+    /// Parses `struct { field: type, ... fn method(...) ... }`,
+    /// `enum { Variant, Variant(type), ... fn method(...) ... }` or any other type to alias.
+    fn parse_type_definition(&mut self) -> Result<TypeDefinition, String> {
+        if !matches!(self.peek(), Some(Token::Struct | Token::Enum)) {
+            return Ok(TypeDefinition::Alias(self.parse_type()?));
+        }
+
+        let keyword = self.consume();
+        self.expect(Token::OpenBrace)?;
+
+        let mut fields = vec![];
+        let mut variants = vec![];
+        let mut methods = vec![];
+        loop {
+            match self.peek() {
+                Some(Token::PubKeyword | Token::Inline | Token::FnKeyword) => {
+                    methods.push(self.parse_function_declaration()?);
+                }
+                Some(Token::Identifier(member)) => {
+                    let member = member.clone();
+                    self.consume();
+
+                    if keyword == Some(Token::Struct) {
+                        self.expect(Token::Colon)?;
+                        let datatype = self.parse_type()?;
+                        fields.push(StructField {
+                            name: member,
+                            datatype,
+                        });
+                    } else {
+                        let payload = if self.peek() == Some(&Token::OpenParen) {
+                            Some(self.parse_payload_type()?)
+                        } else {
+                            None
+                        };
+                        variants.push(Variant {
+                            name: member,
+                            payload,
+                        });
+                    }
+
+                    if self.peek() != Some(&Token::CloseBrace) {
+                        self.expect(Token::Comma)?;
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        self.expect(Token::CloseBrace)?;
+
+        if keyword == Some(Token::Struct) {
+            Ok(TypeDefinition::Struct { fields, methods })
+        } else {
+            Ok(TypeDefinition::Enum { variants, methods })
+        }
+    }
+
+    /// Parses the payload of a variant, where `(type)` is the type itself rather than a tuple.
+    fn parse_payload_type(&mut self) -> Result<Type, String> {
+        match self.parse_parenthesized_type()? {
+            Type::Tuple(mut fields) if fields.len() == 1 && fields[0].name.is_none() => {
+                Ok(fields.remove(0).datatype)
+            }
+            datatype => Ok(datatype),
+        }
+    }
+
+    /// Parses `(a, b): type = value;`, after the declaration keyword.
+    fn parse_destructuring(&mut self, mutable: bool) -> Result<Statement, String> {
+        self.expect(Token::OpenParen)?;
+
+        let mut names = vec![];
+        loop {
+            match self.consume() {
+                Some(Token::Identifier(name)) => names.push(name),
+                _ => return Err("expected an identifier".to_string()),
+            }
+
+            if self.peek() == Some(&Token::Comma) {
+                self.consume();
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::CloseParen)?;
+
+        if self.peek() != Some(&Token::Colon) {
+            return Err("expected the tuple type of the destructured names".to_string());
+        }
+        self.consume();
+        let datatype = self.parse_type()?;
+
+        self.expect(Token::Assignment)?;
+        let value = self.parse_expression()?;
+        let value = self.parse_where_clause(value)?;
+
+        self.expect(Token::SemiColon)?;
+
+        Ok(Statement::Destructuring {
+            mutable,
+            names,
+            datatype,
+            value,
+        })
+    }
+
     fn parse_statement(&mut self) -> Result<Statement, String> {
         match self.peek() {
             // variable/constant declaration
-            Some(Token::Let | Token::Const) => {
-                let mutable = self.consume() == Some(Token::Const);
+            Some(Token::Let | Token::Const | Token::Capture) => {
+                let keyword = self.consume();
+                let mutable = keyword == Some(Token::Let);
+                let by_reference = keyword == Some(Token::Capture);
+
+                if !by_reference && self.peek() == Some(&Token::OpenParen) {
+                    return self.parse_destructuring(mutable);
+                }
+
+                if keyword == Some(Token::Const) {
+                    if let Some((Token::Identifier(name), Token::Assignment)) = self.peek_two() {
+                        let name = name.clone();
+                        self.consume(); // consume the name
+                        self.consume(); // consume '='
+                        return self.parse_type_declaration(name);
+                    }
+                }
 
                 if let Some(Token::Identifier(name)) = self.consume() {
                     self.expect(Token::Colon)?;
@@ -395,10 +1062,44 @@ impl Parser {
 
                     self.expect(Token::Assignment)?;
                     let value = self.parse_expression()?;
+                    let value = self.parse_where_clause(value)?;
+
+                    // the value of a function-typed declaration is the body of the function, the
+                    // validator unwraps it again if it turns out to be a function of that type
+                    let value = match (&datatype, value) {
+                        (
+                            _,
+                            Expression::Function {
+                                parameters,
+                                return_type,
+                                body,
+                                captures,
+                                ..
+                            },
+                        ) => Expression::Function {
+                            parameters,
+                            return_type,
+                            body,
+                            by_reference,
+                            captures,
+                        },
+                        (
+                            Type::FuncType {
+                                parameters,
+                                return_type,
+                            },
+                            value,
+                        ) => function_value(parameters, return_type, value, by_reference),
+                        _ if by_reference => {
+                            return Err("expected a function type for a capture".to_string())
+                        }
+                        (_, value) => value,
+                    };
 
                     self.expect(Token::SemiColon)?;
 
                     Ok(Statement::Declaration {
+                        public: false,
                         mutable,
                         name,
                         datatype,
@@ -408,33 +1109,39 @@ impl Parser {
                     Err("expected an identifier".to_string())
                 }
             }
-            // function declaration
-            Some(Token::PubKeyword | Token::FnKeyword) => {
-                let public = self.peek() == Some(&Token::PubKeyword);
-
-                if public {
-                    // consume the `pub` keyword
-                    self.consume();
-                }
-
-                self.expect(Token::FnKeyword)?;
-
-                if let Some(Token::Identifier(name)) = self.consume() {
-                    let parameters = self.parse_parameter_list()?;
-                    self.expect(Token::Colon)?;
-                    let return_type = self.parse_type()?; // return type of the function
-                    let body = self.parse_block()?;
-                    Ok(Statement::FuncDeclaration {
-                        public,
+            // public variable, constant or type declaration
+            Some(Token::PubKeyword)
+                if matches!(self.peek_two(), Some((_, Token::Let | Token::Const))) =>
+            {
+                self.consume(); // consume `pub`
+                match self.parse_statement()? {
+                    Statement::Declaration {
+                        mutable,
                         name,
-                        parameters,
-                        return_type,
-                        body,
-                    })
-                } else {
-                    Err("expected an identifier".to_string())
+                        datatype,
+                        value,
+                        ..
+                    } => Ok(Statement::Declaration {
+                        public: true,
+                        mutable,
+                        name,
+                        datatype,
+                        value,
+                    }),
+                    Statement::TypeDeclaration {
+                        name, definition, ..
+                    } => Ok(Statement::TypeDeclaration {
+                        public: true,
+                        name,
+                        definition,
+                    }),
+                    _ => Err("expected a single name to make public".to_string()),
                 }
             }
+            // function declaration
+            Some(Token::PubKeyword | Token::Inline | Token::FnKeyword) => {
+                self.parse_function_declaration()
+            }
             // block
             Some(Token::OpenBrace) => {
                 let block = self.parse_block()?;
@@ -445,8 +1152,46 @@ impl Parser {
                 }))
             }
             // re-assignment
-            Some(Token::Identifier(_) | Token::NumberLiteral(_)) => {
+            Some(Token::Identifier(name))
+                if matches!(self.peek_two(), Some((_, Token::Assignment))) =>
+            {
+                let name = name.clone();
+                let span = self.span();
+                self.consume(); // consume the identifier
+                self.expect(Token::Assignment)?;
+                let value = self.parse_expression()?;
+                self.expect(Token::SemiColon)?;
+                Ok(Statement::ReAssignment { name, value, span })
+            }
+            // expression statement
+            Some(
+                Token::Identifier(_)
+                | Token::NumberLiteral(_)
+                | Token::True
+                | Token::False
+                | Token::Nil
+                | Token::Match
+                | Token::Comptime,
+            ) => {
                 let expression = self.parse_expression()?;
+
+                // assignment to an element, a field or through a pointer
+                if let (
+                    Expression::Index { .. }
+                    | Expression::FieldAccess { .. }
+                    | Expression::Deref(_),
+                    Some(Token::Assignment),
+                ) = (&expression, self.peek())
+                {
+                    self.consume();
+                    let value = self.parse_expression()?;
+                    self.expect(Token::SemiColon)?;
+                    return Ok(Statement::PlaceAssignment {
+                        target: expression,
+                        value,
+                    });
+                }
+
                 self.expect(Token::SemiColon)?;
                 Ok(Statement::ExpressionStatement(expression))
             }
@@ -454,15 +1199,93 @@ impl Parser {
         }
     }
 
+    /// Parses `import "path.hon";` or `import "path.hon" as name;`.
+    fn parse_import(&mut self) -> Result<Statement, String> {
+        self.consume(); // consume `import`
+
+        let Some(Token::StringLiteral(path)) = self.consume() else {
+            return Err("expected the path of a module".to_string());
+        };
+
+        let name = if self.peek() == Some(&Token::As) {
+            self.consume(); // consume `as`
+            match self.consume() {
+                Some(Token::Identifier(name)) => name,
+                _ => return Err("expected an identifier".to_string()),
+            }
+        } else {
+            // the module is named after its file, if that makes an identifier
+            let stem = std::path::Path::new(&path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let is_identifier = stem.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                && stem.chars().all(|c| c.is_alphanumeric() || c == '_');
+            if !is_identifier {
+                return Err(format!(
+                    "expected `as` and a name for the module {}",
+                    path.bold()
+                ));
+            }
+            stem
+        };
+
+        self.expect(Token::SemiColon)?;
+        Ok(Statement::Import { path, name })
+    }
+
     pub fn parse_program(&mut self) -> Result<Program, String> {
         let mut program = Vec::new();
 
         while self.peek().is_some() {
-            program.push(self.parse_statement()?);
+            if self.peek() == Some(&Token::Import) {
+                program.push(self.parse_import()?);
+            } else {
+                program.push(self.parse_statement()?);
+            }
         }
 
         Ok(Program {
             statements: program,
         })
     }
+
+    /// Parses input which consists of a single expression.
+    pub fn parse_lone_expression(&mut self) -> Result<Expression, String> {
+        let expression = self.parse_expression()?;
+        match self.peek() {
+            None => Ok(expression),
+            Some(_) => Err("expected the end of the expression".to_string()),
+        }
+    }
+}
+
+/// Turns the value of a function-typed declaration into the body of an anonymous function.
+fn function_value(
+    parameters: &[Param],
+    return_type: &Type,
+    value: Expression,
+    by_reference: bool,
+) -> Expression {
+    let body = match value {
+        Expression::Block {
+            statements,
+            return_value,
+        } => Block {
+            statements,
+            return_value,
+        },
+        value => Block {
+            statements: vec![],
+            return_value: Box::new(value),
+        },
+    };
+
+    Expression::Function {
+        parameters: parameters.to_vec(),
+        return_type: Box::new(return_type.clone()),
+        body,
+        by_reference,
+        captures: vec![],
+    }
 }
@@ -0,0 +1,321 @@
+use std::collections::HashSet;
+
+use colored::Colorize;
+
+use crate::structures::ir::{
+    BasicBlock, BlockId, Callee, Constant, Function, Instruction, Module, Operand, Register,
+    Terminator,
+};
+
+/// What dead code elimination removed from a module.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Removals {
+    /// The functions instructions were removed from, with how many.
+    pub instructions: Vec<(String, usize)>,
+    /// The functions blocks were removed from, with how many.
+    pub blocks: Vec<(String, usize)>,
+    /// The functions blocks were merged into the only block jumping to them in, with how many.
+    pub merged: Vec<(String, usize)>,
+    pub functions: Vec<String>,
+}
+
+impl Removals {
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+            && self.blocks.is_empty()
+            && self.merged.is_empty()
+            && self.functions.is_empty()
+    }
+
+    /// Adds what a later elimination removed, adding up the counts of every function.
+    pub fn merge(&mut self, other: Removals) {
+        for (counts, more) in [
+            (&mut self.instructions, other.instructions),
+            (&mut self.blocks, other.blocks),
+            (&mut self.merged, other.merged),
+        ] {
+            for (function, count) in more {
+                match counts.iter_mut().find(|(name, _)| *name == function) {
+                    Some((_, total)) => *total += count,
+                    None => counts.push((function, count)),
+                }
+            }
+        }
+        self.functions.extend(other.functions);
+    }
+}
+
+impl std::fmt::Display for Removals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "{}", "nothing removed".bold());
+        }
+        for (function, count) in &self.instructions {
+            writeln!(
+                f,
+                "{} {} {} '{}'",
+                "removed".bold(),
+                count.to_string().bold().yellow(),
+                "unused instruction(s) from".bold(),
+                function.bold().yellow()
+            )?;
+        }
+        for (function, count) in &self.blocks {
+            writeln!(
+                f,
+                "{} {} {} '{}'",
+                "removed".bold(),
+                count.to_string().bold().yellow(),
+                "unreachable block(s) from".bold(),
+                function.bold().yellow()
+            )?;
+        }
+        for (function, count) in &self.merged {
+            writeln!(
+                f,
+                "{} {} {} '{}'",
+                "merged".bold(),
+                count.to_string().bold().yellow(),
+                "block(s) into the block jumping to them in".bold(),
+                function.bold().yellow()
+            )?;
+        }
+        for function in &self.functions {
+            writeln!(
+                f,
+                "{} '{}'",
+                "removed unused function".bold(),
+                function.bold().yellow()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes the code of a module which can't change what the program does: instructions
+/// computing values nothing uses, blocks control can't reach once branches on constants are
+/// turned into jumps, and private functions nothing refers to. Blocks only ever jumped to from
+/// one block are merged into it along the way.
+pub fn eliminate_dead_code(module: &mut Module) -> Removals {
+    let mut removals = Removals::default();
+    for function in &mut module.functions {
+        fold_branches(function);
+        let blocks = function.remove_unreachable_blocks();
+        if blocks > 0 {
+            removals.blocks.push((function.name.clone(), blocks));
+        }
+        let merged = merge_blocks(function);
+        if merged > 0 {
+            removals.merged.push((function.name.clone(), merged));
+        }
+        let instructions = remove_unused_instructions(function);
+        if instructions > 0 {
+            removals
+                .instructions
+                .push((function.name.clone(), instructions));
+        }
+    }
+    removals.functions = remove_unused_functions(module);
+    removals
+}
+
+/// Turns branches whose condition is known, or whose targets are the same, into jumps.
+fn fold_branches(function: &mut Function) {
+    for block in 0..function.blocks.len() {
+        let Terminator::Branch {
+            condition,
+            then,
+            otherwise,
+        } = &function.blocks[block].terminator
+        else {
+            continue;
+        };
+        let (taken, dropped) = match condition {
+            _ if then == otherwise => (*then, None),
+            Operand::Constant(Constant::Boolean(true)) => (*then, Some(*otherwise)),
+            Operand::Constant(Constant::Boolean(false)) => (*otherwise, Some(*then)),
+            _ => continue,
+        };
+        function.blocks[block].terminator = Terminator::Jump(taken);
+
+        // the block no longer comes into the target it stopped jumping to
+        if let Some(dropped) = dropped {
+            for instruction in &mut function.blocks[dropped.0].instructions {
+                if let Instruction::Phi { incoming, .. } = instruction {
+                    incoming.retain(|(from, _)| *from != BlockId(block));
+                }
+            }
+        }
+    }
+}
+
+/// Merges the blocks only ever jumped to from one block into that block, returning how many
+/// were merged.
+fn merge_blocks(function: &mut Function) -> usize {
+    let mut merged = 0;
+    loop {
+        let predecessors = function.predecessors();
+        let mergeable =
+            (0..function.blocks.len()).find_map(|block| match function.blocks[block].terminator {
+                Terminator::Jump(next)
+                    if next.0 != 0
+                        && next.0 != block
+                        && predecessors[next.0] == [BlockId(block)] =>
+                {
+                    Some((BlockId(block), next))
+                }
+                _ => None,
+            });
+        let Some((block, next)) = mergeable else {
+            break;
+        };
+
+        let absorbed = std::mem::replace(
+            &mut function.blocks[next.0],
+            BasicBlock {
+                instructions: vec![],
+                terminator: Terminator::Unreachable,
+            },
+        );
+        // with a single way in, phis have a single value
+        let instructions = absorbed
+            .instructions
+            .into_iter()
+            .map(|instruction| match instruction {
+                Instruction::Phi { dest, mut incoming } => Instruction::Copy {
+                    dest,
+                    value: incoming.remove(0).1,
+                },
+                instruction => instruction,
+            });
+        function.blocks[block.0].instructions.extend(instructions);
+        for successor in absorbed.terminator.successors() {
+            for instruction in &mut function.blocks[successor.0].instructions {
+                if let Instruction::Phi { incoming, .. } = instruction {
+                    for (from, _) in incoming.iter_mut() {
+                        if *from == next {
+                            *from = block;
+                        }
+                    }
+                }
+            }
+        }
+        function.blocks[block.0].terminator = absorbed.terminator;
+        merged += 1;
+    }
+    function.remove_unreachable_blocks();
+    merged
+}
+
+/// Removes the instructions without side effects whose registers nothing ends up reading,
+/// returning how many were removed.
+fn remove_unused_instructions(function: &mut Function) -> usize {
+    let mut used = HashSet::new();
+    let mut pending: Vec<Register> = vec![];
+    let mut read = |operands: Vec<&Operand>, pending: &mut Vec<Register>| {
+        for operand in operands {
+            if let Operand::Register(register) = operand {
+                if used.insert(*register) {
+                    pending.push(*register);
+                }
+            }
+        }
+    };
+
+    for block in &function.blocks {
+        for instruction in &block.instructions {
+            if instruction.has_side_effects() {
+                read(instruction.operands(), &mut pending);
+            }
+        }
+        read(block.terminator.operands(), &mut pending);
+    }
+
+    // registers may be assigned in several places outside of SSA form
+    let mut assignments: Vec<Vec<&Instruction>> = vec![vec![]; function.registers];
+    for instruction in function.blocks.iter().flat_map(|block| &block.instructions) {
+        if let Some(dest) = instruction.dest() {
+            assignments[dest.0].push(instruction);
+        }
+    }
+    while let Some(register) = pending.pop() {
+        for instruction in &assignments[register.0] {
+            read(instruction.operands(), &mut pending);
+        }
+    }
+
+    let mut removed = 0;
+    for block in &mut function.blocks {
+        block.instructions.retain(|instruction| {
+            let unused = !instruction.has_side_effects()
+                && instruction.dest().is_some_and(|dest| !used.contains(&dest));
+            removed += unused as usize;
+            !unused
+        });
+    }
+    removed
+}
+
+/// Removes the private functions which neither public functions, `main` nor the
+/// initialisation of globals end up calling or referring to, returning their names.
+fn remove_unused_functions(module: &mut Module) -> Vec<String> {
+    let mut used: HashSet<String> = HashSet::new();
+    let mut pending: Vec<String> = module
+        .functions
+        .iter()
+        .filter(|function| {
+            function.public || function.name == "main" || function.name == Module::INIT
+        })
+        .map(|function| function.name.clone())
+        .collect();
+    used.extend(pending.iter().cloned());
+
+    while let Some(name) = pending.pop() {
+        let Some(function) = module.function(&name) else {
+            continue;
+        };
+        for referred in referred_functions(function) {
+            if used.insert(referred.clone()) {
+                pending.push(referred);
+            }
+        }
+    }
+
+    let mut removed = vec![];
+    module.functions.retain(|function| {
+        let unused = !used.contains(&function.name);
+        if unused {
+            removed.push(function.name.clone());
+        }
+        !unused
+    });
+    removed
+}
+
+/// The functions a function calls, makes closures of or uses as values.
+fn referred_functions(function: &Function) -> Vec<String> {
+    let mut referred = vec![];
+    for block in &function.blocks {
+        for instruction in &block.instructions {
+            match instruction {
+                Instruction::Call {
+                    callee: Callee::Function(name),
+                    ..
+                }
+                | Instruction::Closure { function: name, .. } => referred.push(name.clone()),
+                _ => {}
+            }
+        }
+        let operands = block
+            .instructions
+            .iter()
+            .flat_map(Instruction::operands)
+            .chain(block.terminator.operands());
+        for operand in operands {
+            if let Operand::Constant(Constant::Function(name)) = operand {
+                referred.push(name.clone());
+            }
+        }
+    }
+    referred
+}
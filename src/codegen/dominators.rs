@@ -0,0 +1,170 @@
+use crate::structures::ir::{BlockId, Function};
+
+/// The dominator tree of a function along with the dominance frontier of every block, where
+/// a block dominates another if every path from the start of the function to the other block
+/// goes through it. Blocks control never reaches have no dominators.
+pub struct Dominators {
+    /// The immediate dominator of every reachable block, the start being its own.
+    immediate: Vec<Option<BlockId>>,
+    /// The blocks each block immediately dominates.
+    children: Vec<Vec<BlockId>>,
+    /// The blocks where the dominance of each block ends.
+    frontiers: Vec<Vec<BlockId>>,
+    /// The reachable blocks in reverse postorder, so that blocks come before those they
+    /// dominate.
+    order: Vec<BlockId>,
+}
+
+impl Dominators {
+    /// Finds the dominators with the iterative algorithm of Cooper, Harvey and Kennedy.
+    pub fn new(function: &Function) -> Self {
+        let order = reverse_postorder(function);
+        let mut position = vec![usize::MAX; function.blocks.len()];
+        for (index, block) in order.iter().enumerate() {
+            position[block.0] = index;
+        }
+        let predecessors = function.predecessors();
+
+        let mut immediate: Vec<Option<BlockId>> = vec![None; function.blocks.len()];
+        immediate[0] = Some(BlockId(0));
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in order.iter().skip(1) {
+                let mut processed = predecessors[block.0]
+                    .iter()
+                    .copied()
+                    .filter(|predecessor| immediate[predecessor.0].is_some());
+                let Some(first) = processed.next() else {
+                    continue;
+                };
+                let dominator = processed.fold(first, |dominator, predecessor| {
+                    intersect(&immediate, &position, dominator, predecessor)
+                });
+                if immediate[block.0] != Some(dominator) {
+                    immediate[block.0] = Some(dominator);
+                    changed = true;
+                }
+            }
+        }
+
+        let mut children = vec![vec![]; function.blocks.len()];
+        for &block in order.iter().skip(1) {
+            let dominator = immediate[block.0].expect("reachable blocks have dominators");
+            children[dominator.0].push(block);
+        }
+
+        // a block is in the frontier of every block dominating one of its predecessors, up to
+        // its own immediate dominator
+        let mut frontiers: Vec<Vec<BlockId>> = vec![vec![]; function.blocks.len()];
+        for &block in &order {
+            let reachable = predecessors[block.0]
+                .iter()
+                .filter(|predecessor| immediate[predecessor.0].is_some())
+                .collect::<Vec<_>>();
+            if reachable.len() < 2 {
+                continue;
+            }
+            let dominator = immediate[block.0].expect("reachable blocks have dominators");
+            for &predecessor in reachable {
+                let mut runner = predecessor;
+                while runner != dominator {
+                    if !frontiers[runner.0].contains(&block) {
+                        frontiers[runner.0].push(block);
+                    }
+                    runner = immediate[runner.0].expect("reachable blocks have dominators");
+                }
+            }
+        }
+
+        Dominators {
+            immediate,
+            children,
+            frontiers,
+            order,
+        }
+    }
+
+    /// The immediate dominator of a block, which is `None` for the start of the function and
+    /// blocks control never reaches.
+    pub fn immediate(&self, block: BlockId) -> Option<BlockId> {
+        self.immediate[block.0].filter(|dominator| *dominator != block)
+    }
+
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.immediate[block.0].is_some()
+    }
+
+    /// Whether every path to `block` goes through `dominator`, which holds for the block itself.
+    pub fn dominates(&self, dominator: BlockId, block: BlockId) -> bool {
+        if !self.is_reachable(block) {
+            return false;
+        }
+        let mut runner = block;
+        loop {
+            if runner == dominator {
+                return true;
+            }
+            match self.immediate(runner) {
+                Some(next) => runner = next,
+                None => return false,
+            }
+        }
+    }
+
+    /// The blocks a block immediately dominates.
+    pub fn children(&self, block: BlockId) -> &[BlockId] {
+        &self.children[block.0]
+    }
+
+    pub fn frontier(&self, block: BlockId) -> &[BlockId] {
+        &self.frontiers[block.0]
+    }
+
+    /// The reachable blocks, each after the blocks dominating it.
+    pub fn order(&self) -> &[BlockId] {
+        &self.order
+    }
+}
+
+/// The closest block dominating both `left` and `right`.
+fn intersect(
+    immediate: &[Option<BlockId>],
+    position: &[usize],
+    mut left: BlockId,
+    mut right: BlockId,
+) -> BlockId {
+    while left != right {
+        while position[left.0] > position[right.0] {
+            left = immediate[left.0].expect("processed blocks have dominators");
+        }
+        while position[right.0] > position[left.0] {
+            right = immediate[right.0].expect("processed blocks have dominators");
+        }
+    }
+    left
+}
+
+/// The reachable blocks of a function in reverse postorder.
+pub fn reverse_postorder(function: &Function) -> Vec<BlockId> {
+    let mut visited = vec![false; function.blocks.len()];
+    let mut postorder = vec![];
+    // blocks along with how many of their successors were visited so far
+    let mut stack = vec![(BlockId(0), 0)];
+    visited[0] = true;
+    while let Some((block, next)) = stack.pop() {
+        let successors = function.blocks[block.0].terminator.successors();
+        match successors.get(next) {
+            Some(&successor) => {
+                stack.push((block, next + 1));
+                if !visited[successor.0] {
+                    visited[successor.0] = true;
+                    stack.push((successor, 0));
+                }
+            }
+            None => postorder.push(block),
+        }
+    }
+    postorder.reverse();
+    postorder
+}
@@ -0,0 +1,237 @@
+use colored::Colorize;
+
+use crate::structures::ir::{BlockId, Register};
+
+#[derive(Debug)]
+pub enum IrError {
+    EmptyFunction {
+        function: String,
+    },
+    DuplicateFunction {
+        function: String,
+    },
+    DuplicateGlobal {
+        global: String,
+    },
+    /// A global with the same name as a function.
+    NameClash {
+        name: String,
+    },
+    UndefinedBlock {
+        function: String,
+        block: BlockId,
+    },
+    UndefinedRegister {
+        function: String,
+        register: Register,
+    },
+    /// A register read on a path where nothing was assigned to it.
+    UseBeforeDefinition {
+        function: String,
+        block: BlockId,
+        register: Register,
+    },
+    UndefinedFunction {
+        function: String,
+        callee: String,
+    },
+    UndefinedGlobal {
+        function: String,
+        global: String,
+    },
+    ArgumentCount {
+        function: String,
+        callee: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A register assigned more than once in code meant to be in SSA form.
+    MultipleDefinitions {
+        function: String,
+        register: Register,
+    },
+    /// A phi after other instructions of its block.
+    MisplacedPhi {
+        function: String,
+        block: BlockId,
+    },
+    /// A phi whose incoming blocks aren't the predecessors of its block.
+    PhiPredecessors {
+        function: String,
+        block: BlockId,
+    },
+    /// A register read where its assignment doesn't dominate the read.
+    UndominatedUse {
+        function: String,
+        block: BlockId,
+        register: Register,
+    },
+}
+
+impl std::fmt::Display for IrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IrError::EmptyFunction { function } => {
+                write!(
+                    f,
+                    "{} '{}' {}",
+                    "function".bold().red(),
+                    function.bold(),
+                    "has no blocks".bold().red()
+                )
+            }
+            IrError::DuplicateFunction { function } => {
+                write!(
+                    f,
+                    "{} '{}'",
+                    "function defined twice".bold().red(),
+                    function.bold()
+                )
+            }
+            IrError::DuplicateGlobal { global } => {
+                write!(
+                    f,
+                    "{} '{}'",
+                    "global defined twice".bold().red(),
+                    global.bold()
+                )
+            }
+            IrError::NameClash { name } => {
+                write!(
+                    f,
+                    "{} '{}'",
+                    "global and function of the same name".bold().red(),
+                    name.bold()
+                )
+            }
+            IrError::UndefinedBlock { function, block } => {
+                write!(
+                    f,
+                    "{} {} {} '{}'",
+                    "jump to undefined block".bold().red(),
+                    block.to_string().bold(),
+                    "in".bold().red(),
+                    function.bold()
+                )
+            }
+            IrError::UndefinedRegister { function, register } => {
+                write!(
+                    f,
+                    "{} {} {} '{}'",
+                    "register".bold().red(),
+                    register.to_string().bold(),
+                    "is out of the range of registers of".bold().red(),
+                    function.bold()
+                )
+            }
+            IrError::UseBeforeDefinition {
+                function,
+                block,
+                register,
+            } => {
+                write!(
+                    f,
+                    "{} {} {} {} {} '{}'",
+                    "register".bold().red(),
+                    register.to_string().bold(),
+                    "may be used before it is assigned in".bold().red(),
+                    block.to_string().bold(),
+                    "of".bold().red(),
+                    function.bold()
+                )
+            }
+            IrError::UndefinedFunction { function, callee } => {
+                write!(
+                    f,
+                    "{} '{}' {} '{}'",
+                    "call to undefined function".bold().red(),
+                    callee.bold(),
+                    "in".bold().red(),
+                    function.bold()
+                )
+            }
+            IrError::UndefinedGlobal { function, global } => {
+                write!(
+                    f,
+                    "{} '{}' {} '{}'",
+                    "use of undefined global".bold().red(),
+                    global.bold(),
+                    "in".bold().red(),
+                    function.bold()
+                )
+            }
+            IrError::ArgumentCount {
+                function,
+                callee,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "{} '{}' {} {} {} {} {} '{}'",
+                    "call to".bold().red(),
+                    callee.bold(),
+                    "with".bold().red(),
+                    found.to_string().bold(),
+                    "argument(s) instead of".bold().red(),
+                    expected.to_string().bold(),
+                    "in".bold().red(),
+                    function.bold()
+                )
+            }
+            IrError::MultipleDefinitions { function, register } => {
+                write!(
+                    f,
+                    "{} {} {} '{}'",
+                    "register".bold().red(),
+                    register.to_string().bold(),
+                    "is assigned more than once in".bold().red(),
+                    function.bold()
+                )
+            }
+            IrError::MisplacedPhi { function, block } => {
+                write!(
+                    f,
+                    "{} {} {} '{}'",
+                    "phi after other instructions in".bold().red(),
+                    block.to_string().bold(),
+                    "of".bold().red(),
+                    function.bold()
+                )
+            }
+            IrError::PhiPredecessors { function, block } => {
+                write!(
+                    f,
+                    "{} {} {} '{}'",
+                    "phi not matching the predecessors of".bold().red(),
+                    block.to_string().bold(),
+                    "in".bold().red(),
+                    function.bold()
+                )
+            }
+            IrError::UndominatedUse {
+                function,
+                block,
+                register,
+            } => {
+                write!(
+                    f,
+                    "{} {} {} {} {} '{}'",
+                    "register".bold().red(),
+                    register.to_string().bold(),
+                    "is used where its assignment does not dominate in"
+                        .bold()
+                        .red(),
+                    block.to_string().bold(),
+                    "of".bold().red(),
+                    function.bold()
+                )
+            }
+        }
+    }
+}
+
+pub fn err_msg(error: IrError) -> String {
+    let prefix = "Failed to verify the generated code:";
+    format!("{} {}", prefix, error)
+}
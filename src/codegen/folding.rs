@@ -0,0 +1,76 @@
+use crate::{
+    analysis::semantic::folding::{cast_number, fold_binary, integer},
+    structures::{
+        ir::{Constant, Function, Instruction, Module, Operand},
+        parse_tree::Expression,
+    },
+};
+
+/// Computes the instructions of a module whose operands are all constants, replacing them with
+/// copies of their results. Operations which would fail, like dividing by zero, are left to
+/// fail when the program runs.
+pub fn fold_constants(module: &mut Module) {
+    for function in &mut module.functions {
+        fold_function(function);
+    }
+}
+
+fn fold_function(function: &mut Function) {
+    for instruction in function
+        .blocks
+        .iter_mut()
+        .flat_map(|block| &mut block.instructions)
+    {
+        let folded = match instruction {
+            Instruction::Binary {
+                operator,
+                left: Operand::Constant(left),
+                right: Operand::Constant(right),
+                span,
+                ..
+            } => match (expression(left), expression(right)) {
+                (Some(left), Some(right)) => match fold_binary(*operator, &left, &right, *span) {
+                    Ok(Some(folded)) => constant(folded),
+                    _ => None,
+                },
+                _ => None,
+            },
+            Instruction::Cast {
+                value: Operand::Constant(value),
+                to,
+                ..
+            } => match value {
+                Constant::Number(number) => Some(Constant::Number(cast_number(number, *to))),
+                Constant::Boolean(boolean) => integer(*to, *boolean as i128).map(Constant::Number),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let (Some(folded), Some(dest)) = (folded, instruction.dest()) {
+            *instruction = Instruction::Copy {
+                dest,
+                value: Operand::Constant(folded),
+            };
+        }
+    }
+}
+
+/// The constant expression a constant of the IR stands for, if it can be folded.
+fn expression(constant: &Constant) -> Option<Expression> {
+    match constant {
+        Constant::Number(number) => Some(Expression::Number(number.clone())),
+        Constant::Boolean(boolean) => Some(Expression::Boolean(*boolean)),
+        Constant::Nil => Some(Expression::Nil),
+        Constant::Function(_) => None,
+    }
+}
+
+fn constant(expression: Expression) -> Option<Constant> {
+    match expression {
+        Expression::Number(number) => Some(Constant::Number(number)),
+        Expression::Boolean(boolean) => Some(Constant::Boolean(boolean)),
+        Expression::Nil => Some(Constant::Nil),
+        _ => None,
+    }
+}
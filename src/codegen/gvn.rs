@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use crate::structures::{
+    ir::{BlockId, Function, Instruction, Module, Operand, Register, Terminator},
+    parse_tree::{BinaryOperator, Span},
+};
+
+use super::dominators::Dominators;
+
+/// Numbers the values computed by the functions of a module, so that a computation repeated
+/// where an equal one dominates it is replaced by a copy of the first result, and the same
+/// computation at the start of both branches of an `if` is done once before branching.
+///
+/// A register assigned more than once may hold different values at two computations reading
+/// it, so only computations reading and assigning registers assigned once are numbered. Run
+/// after SSA construction, that is every computation.
+pub fn number_values(module: &mut Module) {
+    for function in &mut module.functions {
+        function.remove_unreachable_blocks();
+        let dominators = Dominators::new(function);
+        let mut numbering = Numbering::new(function);
+        numbering.number(function, &dominators, BlockId(0));
+    }
+}
+
+struct Numbering {
+    /// Whether every register is assigned exactly once, parameters included.
+    stable: Vec<bool>,
+    /// The block every register is assigned in, parameters being assigned in the first.
+    blocks: Vec<BlockId>,
+    /// The registers found to hold the same value as an earlier one.
+    values: HashMap<Register, Operand>,
+    /// The registers holding the computations of the blocks dominating the current one.
+    available: HashMap<String, Register>,
+}
+
+impl Numbering {
+    fn new(function: &Function) -> Self {
+        let mut assignments = vec![0usize; function.registers];
+        let mut blocks = vec![BlockId(0); function.registers];
+        for &parameter in &function.parameters {
+            assignments[parameter.0] += 1;
+        }
+        for (id, block) in function.blocks.iter().enumerate() {
+            for dest in block.instructions.iter().filter_map(Instruction::dest) {
+                assignments[dest.0] += 1;
+                blocks[dest.0] = BlockId(id);
+            }
+        }
+
+        Numbering {
+            stable: assignments.into_iter().map(|count| count == 1).collect(),
+            blocks,
+            values: HashMap::new(),
+            available: HashMap::new(),
+        }
+    }
+
+    fn resolve(&self, operand: &mut Operand) {
+        if let Operand::Register(register) = operand {
+            if let Some(value) = self.values.get(register) {
+                *operand = value.clone();
+            }
+        }
+    }
+
+    /// The computation an instruction does, as a key equal for equal computations, if it
+    /// always gives the same result for the same operands.
+    fn key(&self, instruction: &Instruction) -> Option<String> {
+        let numbered = matches!(
+            instruction,
+            Instruction::Binary { .. }
+                | Instruction::Cast { .. }
+                | Instruction::Aggregate { .. }
+                | Instruction::Variant { .. }
+                | Instruction::Extract { .. }
+                | Instruction::Length { .. }
+                | Instruction::Index { .. }
+                | Instruction::IsVariant { .. }
+                | Instruction::Payload { .. }
+                | Instruction::Global { .. }
+                | Instruction::FieldAddress { .. }
+        );
+        let stable = instruction.dest().is_some_and(|dest| self.stable[dest.0])
+            && instruction.operands().iter().all(|operand| match operand {
+                Operand::Register(register) => self.stable[register.0],
+                Operand::Constant(_) => true,
+            });
+        if !numbered || !stable {
+            return None;
+        }
+
+        // where arithmetic would fail doesn't matter, as the first computation fails first
+        let mut normalized = instruction.clone();
+        if let Some(dest) = normalized.dest_mut() {
+            *dest = Register(0);
+        }
+        if let Instruction::Binary {
+            operator,
+            left,
+            right,
+            span,
+            ..
+        } = &mut normalized
+        {
+            *span = Span::default();
+            let commutative = matches!(
+                operator,
+                BinaryOperator::Add
+                    | BinaryOperator::Multiply
+                    | BinaryOperator::Equal
+                    | BinaryOperator::NotEqual
+            );
+            if commutative && left.to_string() > right.to_string() {
+                std::mem::swap(left, right);
+            }
+        }
+        if let Instruction::Index { span, .. } = &mut normalized {
+            *span = Span::default();
+        }
+        Some(normalized.to_string())
+    }
+
+    /// Numbers the instructions of a block and of the blocks it dominates, whose computations
+    /// are only available to the blocks it dominates.
+    fn number(&mut self, function: &mut Function, dominators: &Dominators, block: BlockId) {
+        let mut added = vec![];
+        let instructions = std::mem::take(&mut function.blocks[block.0].instructions);
+        let mut numbered = Vec::with_capacity(instructions.len());
+        for mut instruction in instructions {
+            for operand in instruction.operands_mut() {
+                self.resolve(operand);
+            }
+            match self.key(&instruction) {
+                Some(key) => {
+                    let dest = instruction.dest().expect("numbered instructions assign");
+                    match self.available.get(&key) {
+                        Some(&first) => {
+                            self.values.insert(dest, Operand::Register(first));
+                            instruction = Instruction::Copy {
+                                dest,
+                                value: Operand::Register(first),
+                            };
+                        }
+                        None => {
+                            self.available.insert(key.clone(), dest);
+                            added.push(key);
+                        }
+                    }
+                }
+                None => {
+                    // copies are numbered as what they copy
+                    if let Instruction::Copy { dest, value } = &instruction {
+                        let stable = match value {
+                            Operand::Register(register) => self.stable[register.0],
+                            Operand::Constant(_) => true,
+                        };
+                        if self.stable[dest.0] && stable {
+                            self.values.insert(*dest, value.clone());
+                        }
+                    }
+                }
+            }
+            numbered.push(instruction);
+        }
+        function.blocks[block.0].instructions = numbered;
+        for operand in function.blocks[block.0].terminator.operands_mut() {
+            self.resolve(operand);
+        }
+
+        added.extend(self.hoist(function, block));
+
+        for &child in dominators.children(block) {
+            self.number(function, dominators, child);
+        }
+
+        for key in added {
+            self.available.remove(&key);
+        }
+    }
+
+    /// Moves the computations both branches leaving a block start with into the block, so they
+    /// are done once, returning the keys of the computations moved.
+    fn hoist(&mut self, function: &mut Function, block: BlockId) -> Vec<String> {
+        let Terminator::Branch {
+            then, otherwise, ..
+        } = function.blocks[block.0].terminator
+        else {
+            return vec![];
+        };
+        let predecessors = function.predecessors();
+        if then == otherwise
+            || predecessors[then.0] != [block]
+            || predecessors[otherwise.0] != [block]
+        {
+            return vec![];
+        }
+
+        let mut hoisted = vec![];
+        while let Some((first, second)) = self.hoistable(function, then, otherwise) {
+            let mut instruction = function.blocks[then.0].instructions.remove(first);
+            for operand in instruction.operands_mut() {
+                self.resolve(operand);
+            }
+            let dest = instruction.dest().expect("numbered instructions assign");
+            let key = self
+                .key(&instruction)
+                .expect("hoisted instructions are numbered");
+            self.blocks[dest.0] = block;
+            function.blocks[block.0].instructions.push(instruction);
+
+            let duplicate = &mut function.blocks[otherwise.0].instructions[second];
+            let other = duplicate.dest().expect("numbered instructions assign");
+            *duplicate = Instruction::Copy {
+                dest: other,
+                value: Operand::Register(dest),
+            };
+            self.values.insert(other, Operand::Register(dest));
+
+            self.available.insert(key.clone(), dest);
+            hoisted.push(key);
+        }
+        hoisted
+    }
+
+    /// Finds an instruction of `then` and an equal one of `otherwise` which could be done
+    /// before branching instead: their operands are available before the branch, and if they
+    /// may fail, nothing else with side effects happens before them.
+    fn hoistable(
+        &self,
+        function: &Function,
+        then: BlockId,
+        otherwise: BlockId,
+    ) -> Option<(usize, usize)> {
+        let available = |instruction: &Instruction| {
+            instruction.operands().iter().all(|operand| match operand {
+                Operand::Register(register) => {
+                    let register = match self.values.get(register) {
+                        Some(Operand::Register(value)) => value,
+                        Some(Operand::Constant(_)) => return true,
+                        None => register,
+                    };
+                    self.blocks[register.0] != then && self.blocks[register.0] != otherwise
+                }
+                Operand::Constant(_) => true,
+            })
+        };
+        let candidates = |block: BlockId| {
+            let mut effects = false;
+            let mut candidates = vec![];
+            for (position, instruction) in function.blocks[block.0].instructions.iter().enumerate()
+            {
+                let key = self.resolved_key(instruction);
+                if let Some(key) = key {
+                    if available(instruction) && (!effects || !instruction.has_side_effects()) {
+                        candidates.push((position, key));
+                    }
+                }
+                effects |= instruction.has_side_effects();
+            }
+            candidates
+        };
+
+        let others = candidates(otherwise);
+        candidates(then).into_iter().find_map(|(first, key)| {
+            others
+                .iter()
+                .find(|(_, other)| *other == key)
+                .map(|(second, _)| (first, *second))
+        })
+    }
+
+    /// The key of an instruction once its operands are replaced with the values they hold.
+    fn resolved_key(&self, instruction: &Instruction) -> Option<String> {
+        let mut resolved = instruction.clone();
+        for operand in resolved.operands_mut() {
+            self.resolve(operand);
+        }
+        self.key(&resolved)
+    }
+}
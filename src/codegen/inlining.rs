@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::structures::ir::{
+    BasicBlock, BlockId, Callee, Function, Instruction, Module, Operand, Register, Terminator,
+};
+
+/// The most instructions a function may have to be inlined without being marked `inline`.
+const SIZE_LIMIT: usize = 16;
+
+/// The most instructions a function may grow to by inlining functions not marked `inline`.
+const GROWTH_LIMIT: usize = 1024;
+
+/// Replaces the direct calls to small functions, and to those marked `inline`, with the body
+/// of the function called.
+///
+/// Functions which may end up calling themselves are never inlined, so inlining always stops.
+/// The instructions inlined keep their spans, so failures point at the function inlined.
+pub fn inline_calls(module: &mut Module) {
+    let recursive = recursive_functions(module);
+    let inlinable: HashMap<String, Function> = module
+        .functions
+        .iter()
+        .filter(|function| {
+            !recursive.contains(&function.name)
+                && (function.inline || size(function) <= SIZE_LIMIT)
+                // a function jumping back to its start can't be entered by a jump
+                && function.predecessors()[0].is_empty()
+        })
+        .map(|function| (function.name.clone(), function.clone()))
+        .collect();
+
+    for caller in &mut module.functions {
+        while let Some((block, position, callee)) = next_call(caller, &inlinable) {
+            inline(caller, block, position, callee);
+        }
+    }
+}
+
+/// The number of instructions of a function, counting terminators.
+fn size(function: &Function) -> usize {
+    function
+        .blocks
+        .iter()
+        .map(|block| block.instructions.len() + 1)
+        .sum()
+}
+
+/// The functions which may call themselves, directly or through other functions.
+fn recursive_functions(module: &Module) -> HashSet<String> {
+    let calls: HashMap<&str, Vec<&str>> = module
+        .functions
+        .iter()
+        .map(|function| {
+            let callees = function
+                .blocks
+                .iter()
+                .flat_map(|block| &block.instructions)
+                .filter_map(|instruction| match instruction {
+                    Instruction::Call {
+                        callee: Callee::Function(callee),
+                        ..
+                    } => Some(callee.as_str()),
+                    _ => None,
+                })
+                .collect();
+            (function.name.as_str(), callees)
+        })
+        .collect();
+
+    let mut recursive = HashSet::new();
+    for &function in calls.keys() {
+        let mut visited = HashSet::new();
+        let mut pending = calls[function].clone();
+        while let Some(callee) = pending.pop() {
+            if callee == function {
+                recursive.insert(function.to_string());
+                break;
+            }
+            if visited.insert(callee) {
+                pending.extend(calls.get(callee).into_iter().flatten());
+            }
+        }
+    }
+    recursive
+}
+
+/// The first call in a function to a function to inline there, as its block, its position in
+/// the block and the function called.
+fn next_call<'m>(
+    caller: &Function,
+    inlinable: &'m HashMap<String, Function>,
+) -> Option<(BlockId, usize, &'m Function)> {
+    let grown = size(caller) > GROWTH_LIMIT;
+    for (id, block) in caller.blocks.iter().enumerate() {
+        for (position, instruction) in block.instructions.iter().enumerate() {
+            let Instruction::Call {
+                callee: Callee::Function(name),
+                ..
+            } = instruction
+            else {
+                continue;
+            };
+            match inlinable.get(name) {
+                Some(callee) if callee.name != caller.name && (callee.inline || !grown) => {
+                    return Some((BlockId(id), position, callee));
+                }
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// Replaces a call with a copy of the blocks of the function called, which the block of the
+/// call jumps to after assigning the arguments to the parameters. What comes after the call
+/// moves to a new block, which the returns of the copy jump to.
+fn inline(caller: &mut Function, block: BlockId, position: usize, callee: &Function) {
+    let registers = caller.registers;
+    let start = caller.blocks.len();
+    let after = BlockId(start + callee.blocks.len());
+    caller.registers += callee.registers;
+
+    let rest = caller.blocks[block.0].instructions.split_off(position + 1);
+    let Some(Instruction::Call {
+        dest, arguments, ..
+    }) = caller.blocks[block.0].instructions.pop()
+    else {
+        unreachable!("the call to inline is where it was found");
+    };
+    for (parameter, argument) in callee.parameters.iter().zip(arguments) {
+        caller.blocks[block.0].instructions.push(Instruction::Copy {
+            dest: Register(parameter.0 + registers),
+            value: argument,
+        });
+    }
+    let terminator = std::mem::replace(
+        &mut caller.blocks[block.0].terminator,
+        Terminator::Jump(BlockId(start)),
+    );
+
+    // the blocks the call's block went on to now come after the returns
+    for successor in terminator.successors() {
+        for instruction in &mut caller.blocks[successor.0].instructions {
+            if let Instruction::Phi { incoming, .. } = instruction {
+                for (from, _) in incoming.iter_mut() {
+                    if *from == block {
+                        *from = after;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut returns = vec![];
+    for (id, original) in callee.blocks.iter().enumerate() {
+        let mut copy = original.clone();
+        for instruction in &mut copy.instructions {
+            if let Some(dest) = instruction.dest_mut() {
+                dest.0 += registers;
+            }
+            for operand in instruction.operands_mut() {
+                offset(operand, registers);
+            }
+            if let Instruction::Phi { incoming, .. } = instruction {
+                for (from, _) in incoming.iter_mut() {
+                    from.0 += start;
+                }
+            }
+        }
+        for operand in copy.terminator.operands_mut() {
+            offset(operand, registers);
+        }
+        for successor in copy.terminator.successors_mut() {
+            successor.0 += start;
+        }
+        if let Terminator::Return(value) = copy.terminator {
+            returns.push((BlockId(start + id), value));
+            copy.terminator = Terminator::Jump(after);
+        }
+        caller.blocks.push(copy);
+    }
+
+    // a single return is copied, several are chosen between by where control comes from
+    let mut instructions = match returns.len() {
+        0 => vec![],
+        1 => vec![Instruction::Copy {
+            dest,
+            value: returns.remove(0).1,
+        }],
+        _ => vec![Instruction::Phi {
+            dest,
+            incoming: returns,
+        }],
+    };
+    instructions.extend(rest);
+    caller.blocks.push(BasicBlock {
+        instructions,
+        terminator,
+    });
+}
+
+fn offset(operand: &mut Operand, registers: usize) {
+    if let Operand::Register(register) = operand {
+        register.0 += registers;
+    }
+}
@@ -0,0 +1,119 @@
+use crate::structures::ir::{BlockId, Function, Instruction, Module, Operand};
+
+use super::{
+    dominators::Dominators,
+    loops::{natural_loops, preheader, Loop},
+};
+
+/// Moves the computations of loops which give the same result every time around the loop
+/// into a block before the loop, so they are done once, inner loops first.
+///
+/// Instructions without side effects are moved from anywhere in the loop, since doing them
+/// when the loop wouldn't have changes nothing. Arithmetic and indexing may fail, so they are
+/// only moved from the start of the header, before anything else with side effects: whenever
+/// control enters the loop they would have been done first thing anyway.
+///
+/// A value is unchanging when it is a constant, a register assigned once outside the loop or one
+/// assigned by an instruction already moved. A variable the loop reassigns, like its counter, is
+/// never moved, since code after the loop must see its last value; in three-address code not in
+/// SSA form, that keeps most of what loops compute where it is.
+pub fn hoist_invariants(module: &mut Module) {
+    for function in &mut module.functions {
+        function.remove_unreachable_blocks();
+        let dominators = Dominators::new(function);
+        let headers: Vec<BlockId> = natural_loops(function, &dominators)
+            .iter()
+            .map(|found| found.header)
+            .collect();
+
+        // preheaders of inner loops belong to the loops around them, so loops are found again
+        for header in headers {
+            let dominators = Dominators::new(function);
+            let loops = natural_loops(function, &dominators);
+            let found = loops
+                .iter()
+                .find(|found| found.header == header)
+                .expect("adding preheaders keeps the loops");
+            hoist_loop(function, &dominators, found);
+        }
+    }
+}
+
+fn hoist_loop(function: &mut Function, dominators: &Dominators, found: &Loop) {
+    let mut assignments = vec![0usize; function.registers];
+    let mut defined = vec![None; function.registers];
+    for &parameter in &function.parameters {
+        assignments[parameter.0] += 1;
+    }
+    for (id, block) in function.blocks.iter().enumerate() {
+        for dest in block.instructions.iter().filter_map(Instruction::dest) {
+            assignments[dest.0] += 1;
+            defined[dest.0] = Some(BlockId(id));
+        }
+    }
+
+    // the positions of the instructions to move, in the order they are done
+    let mut hoisted: Vec<(BlockId, usize)> = vec![];
+    let mut invariant = vec![false; function.registers];
+    for &block in dominators.order() {
+        if !found.contains(block) {
+            continue;
+        }
+        let mut effects = false;
+        for (position, instruction) in function.blocks[block.0].instructions.iter().enumerate() {
+            let movable = match instruction {
+                Instruction::Phi { .. }
+                | Instruction::Alloc { .. }
+                | Instruction::Closure { .. } => false,
+                Instruction::Binary { .. } | Instruction::Index { .. }
+                    if instruction.has_side_effects() =>
+                {
+                    block == found.header && !effects
+                }
+                _ => !instruction.has_side_effects(),
+            };
+            let operands = instruction.operands().iter().all(|operand| match operand {
+                Operand::Register(register) => {
+                    assignments[register.0] == 1
+                        && (invariant[register.0]
+                            || defined[register.0].is_none_or(|block| !found.contains(block)))
+                }
+                Operand::Constant(_) => true,
+            });
+            let dest = instruction
+                .dest()
+                .is_some_and(|dest| assignments[dest.0] == 1);
+
+            if movable && operands && dest {
+                invariant[instruction.dest().expect("moved instructions assign").0] = true;
+                hoisted.push((block, position));
+            } else {
+                effects |= instruction.has_side_effects();
+            }
+        }
+    }
+    if hoisted.is_empty() {
+        return;
+    }
+    let Some(preheader) = preheader(function, found) else {
+        return;
+    };
+
+    // the instructions of a block to move are next to each other, in the order they are in
+    let mut blocks: Vec<BlockId> = hoisted.iter().map(|(block, _)| *block).collect();
+    blocks.dedup();
+    for block in blocks {
+        let instructions = std::mem::take(&mut function.blocks[block.0].instructions);
+        let (moved, kept): (Vec<_>, Vec<_>) = instructions
+            .into_iter()
+            .enumerate()
+            .partition(|(position, _)| hoisted.contains(&(block, *position)));
+        function.blocks[block.0].instructions = kept
+            .into_iter()
+            .map(|(_, instruction)| instruction)
+            .collect();
+        function.blocks[preheader.0]
+            .instructions
+            .extend(moved.into_iter().map(|(_, instruction)| instruction));
+    }
+}
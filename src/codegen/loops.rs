@@ -0,0 +1,126 @@
+use std::collections::BTreeSet;
+
+use crate::structures::ir::{
+    BasicBlock, BlockId, Function, Instruction, Operand, Register, Terminator,
+};
+
+use super::dominators::Dominators;
+
+/// A natural loop: the blocks from which control can go back to the header without leaving
+/// the loop, where the header dominates all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loop {
+    pub header: BlockId,
+    /// The blocks of the loop, the header included, in order.
+    pub blocks: BTreeSet<BlockId>,
+    /// The blocks jumping back to the header.
+    pub latches: Vec<BlockId>,
+}
+
+impl Loop {
+    pub fn contains(&self, block: BlockId) -> bool {
+        self.blocks.contains(&block)
+    }
+}
+
+/// Finds the natural loops of a function from its back edges, the edges going to a block
+/// dominating the block they leave. Back edges to the same header make up a single loop.
+/// Inner loops come before the loops they are nested in.
+pub fn natural_loops(function: &Function, dominators: &Dominators) -> Vec<Loop> {
+    let predecessors = function.predecessors();
+    let mut loops: Vec<Loop> = vec![];
+    for &block in dominators.order() {
+        for successor in function.blocks[block.0].terminator.successors() {
+            if !dominators.dominates(successor, block) {
+                continue;
+            }
+
+            let index = match loops.iter().position(|found| found.header == successor) {
+                Some(index) => index,
+                None => {
+                    loops.push(Loop {
+                        header: successor,
+                        blocks: BTreeSet::from([successor]),
+                        latches: vec![],
+                    });
+                    loops.len() - 1
+                }
+            };
+            let found = &mut loops[index];
+            if !found.latches.contains(&block) {
+                found.latches.push(block);
+            }
+
+            // every block reaching the latch without going through the header is in the loop
+            let mut pending = vec![block];
+            while let Some(member) = pending.pop() {
+                if found.blocks.insert(member) {
+                    pending.extend(
+                        predecessors[member.0]
+                            .iter()
+                            .filter(|predecessor| dominators.is_reachable(**predecessor)),
+                    );
+                }
+            }
+        }
+    }
+
+    loops.sort_by_key(|found| found.blocks.len());
+    loops
+}
+
+/// The block control always comes from when it enters a loop, which only jumps to the header,
+/// made by putting a block in front of the header if there isn't one. The values phis of the
+/// header take from outside of the loop are then chosen in that block.
+///
+/// Loops starting the function have no way in, so they have no preheader.
+pub fn preheader(function: &mut Function, found: &Loop) -> Option<BlockId> {
+    let header = found.header;
+    if header.0 == 0 {
+        return None;
+    }
+    let outside: Vec<BlockId> = function.predecessors()[header.0]
+        .iter()
+        .copied()
+        .filter(|predecessor| !found.contains(*predecessor))
+        .collect();
+    if let [single] = outside[..] {
+        if function.blocks[single.0].terminator == Terminator::Jump(header) {
+            return Some(single);
+        }
+    }
+
+    let preheader = BlockId(function.blocks.len());
+    let mut instructions = vec![];
+    let mut registers = function.registers;
+    for instruction in &mut function.blocks[header.0].instructions {
+        let Instruction::Phi { incoming, .. } = instruction else {
+            continue;
+        };
+        let (entering, looping): (Vec<_>, Vec<_>) = incoming
+            .drain(..)
+            .partition(|(from, _)| outside.contains(from));
+        let dest = Register(registers);
+        registers += 1;
+        instructions.push(Instruction::Phi {
+            dest,
+            incoming: entering,
+        });
+        incoming.push((preheader, Operand::Register(dest)));
+        incoming.extend(looping);
+    }
+    function.registers = registers;
+    function.blocks.push(BasicBlock {
+        instructions,
+        terminator: Terminator::Jump(header),
+    });
+
+    for predecessor in outside {
+        for successor in function.blocks[predecessor.0].terminator.successors_mut() {
+            if *successor == header {
+                *successor = preheader;
+            }
+        }
+    }
+    Some(preheader)
+}
@@ -0,0 +1,983 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analysis::semantic::{folding::fold_literal, patterns::bindings, tail::has_tail_calls},
+    structures::{
+        ir::{
+            Aggregate, BasicBlock, BlockId, Callee, Constant, Function, Instruction, Module,
+            Operand, Register, Terminator,
+        },
+        parse_tree::{
+            BinaryOperator, Block, Capture, Expression, If, Param, Pattern, Program, Span,
+            Statement, TupleElement, Type, TypeDefinition,
+        },
+    },
+};
+
+/// Lowers a validated program to three-address code.
+pub fn lower(program: &Program) -> Module {
+    let mut lowering = Lowering::new();
+    lowering.program(program);
+    Module {
+        globals: lowering.globals,
+        functions: lowering.functions.into_iter().flatten().collect(),
+    }
+}
+
+/// Where the value of a variable is kept.
+#[derive(Clone)]
+enum Storage {
+    Register(Register),
+    /// A variable in memory, through the pointer held by the register.
+    Memory(Register),
+    Global(String),
+}
+
+/// A function being lowered, whose blocks are filled in one at a time.
+struct Builder {
+    name: String,
+    public: bool,
+    inline: bool,
+    /// Where the function goes in the module, to keep the functions in the order of the source.
+    slot: usize,
+    parameters: Vec<Register>,
+    blocks: Vec<BasicBlock>,
+    current: BlockId,
+    registers: usize,
+    /// The variables declared in each of the scopes, innermost last, which is empty outside of
+    /// functions, where variables are globals.
+    scopes: Vec<HashMap<String, Storage>>,
+    /// The variables which have to live in memory.
+    memory: HashSet<String>,
+    /// The block the tail calls of the function jump back to once they have assigned the
+    /// parameters, if it makes any.
+    start: Option<BlockId>,
+}
+
+pub struct Lowering {
+    functions: Vec<Option<Function>>,
+    globals: Vec<String>,
+    /// The names in the module of the functions declared in each scope, innermost last.
+    names: Vec<HashMap<String, String>>,
+    /// The names in the module given out so far.
+    taken: HashSet<String>,
+    /// The functions being lowered, innermost last.
+    builders: Vec<Builder>,
+}
+
+impl Lowering {
+    fn new() -> Self {
+        Lowering {
+            functions: vec![],
+            globals: vec![],
+            names: vec![],
+            taken: HashSet::new(),
+            builders: vec![],
+        }
+    }
+
+    fn program(&mut self, program: &Program) {
+        for statement in &program.statements {
+            match statement {
+                Statement::Declaration { name, .. } => self.globals.push(name.clone()),
+                Statement::Destructuring { names, .. } => self.globals.extend(names.clone()),
+                _ => {}
+            }
+        }
+
+        // functions in blocks of the top level may hide globals, but are named apart from them
+        self.taken.extend(self.globals.iter().cloned());
+        // the top level is run by a function of its own, before anything else
+        self.taken.insert(Module::INIT.to_string());
+        self.declare_functions(&program.statements);
+        self.begin(Module::INIT.to_string(), false, HashSet::new());
+        for statement in &program.statements {
+            self.statement(statement);
+        }
+        self.terminate(Terminator::Return(Operand::Constant(Constant::Nil)));
+        self.finish();
+        self.names.pop();
+    }
+
+    fn builder(&mut self) -> &mut Builder {
+        self.builders
+            .last_mut()
+            .expect("lowering happens inside of a function")
+    }
+
+    fn begin(&mut self, name: String, public: bool, memory: HashSet<String>) {
+        self.functions.push(None);
+        self.builders.push(Builder {
+            name,
+            public,
+            inline: false,
+            slot: self.functions.len() - 1,
+            parameters: vec![],
+            blocks: vec![BasicBlock {
+                instructions: vec![],
+                terminator: Terminator::Unreachable,
+            }],
+            current: BlockId(0),
+            registers: 0,
+            scopes: vec![],
+            memory,
+            start: None,
+        });
+    }
+
+    fn finish(&mut self) {
+        let builder = self.builders.pop().expect("a function is being lowered");
+        self.functions[builder.slot] = Some(Function {
+            name: builder.name,
+            public: builder.public,
+            inline: builder.inline,
+            parameters: builder.parameters,
+            blocks: builder.blocks,
+            registers: builder.registers,
+        });
+    }
+
+    fn register(&mut self) -> Register {
+        let builder = self.builder();
+        builder.registers += 1;
+        Register(builder.registers - 1)
+    }
+
+    fn emit(&mut self, instruction: Instruction) {
+        let builder = self.builder();
+        builder.blocks[builder.current.0]
+            .instructions
+            .push(instruction);
+    }
+
+    /// Emits an instruction assigning to a new register, which is returned as an operand.
+    fn emit_value(&mut self, instruction: impl FnOnce(Register) -> Instruction) -> Operand {
+        let dest = self.register();
+        self.emit(instruction(dest));
+        Operand::Register(dest)
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let builder = self.builder();
+        builder.blocks[builder.current.0].terminator = terminator;
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        let builder = self.builder();
+        builder.blocks.push(BasicBlock {
+            instructions: vec![],
+            terminator: Terminator::Unreachable,
+        });
+        BlockId(builder.blocks.len() - 1)
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.builder().current = block;
+    }
+
+    /// Ends the current block with a jump to a block created later, returning the block.
+    fn jump_later(&mut self) -> BlockId {
+        self.builder().current
+    }
+
+    /// Makes the blocks left by `jump_later` jump to a new block, which is switched to.
+    fn join(&mut self, ends: Vec<BlockId>) {
+        let join = self.new_block();
+        for end in ends {
+            self.builder().blocks[end.0].terminator = Terminator::Jump(join);
+        }
+        self.switch_to(join);
+    }
+
+    /// Gives a function a name unique in the module, based on `name`.
+    fn unique(&mut self, name: String) -> String {
+        if self.taken.insert(name.clone()) {
+            return name;
+        }
+        (1..)
+            .map(|suffix| format!("{name}.{suffix}"))
+            .find(|candidate| self.taken.insert(candidate.clone()))
+            .expect("there is always a free name")
+    }
+
+    /// Names the functions and methods declared in a list of statements, which may be called
+    /// before they are declared. Nested functions are named after the function around them.
+    fn declare_functions(&mut self, statements: &[Statement]) {
+        let mut names = HashMap::new();
+        for statement in statements {
+            for (name, ..) in functions(statement) {
+                let qualified = match self.builders.last() {
+                    Some(builder) if builder.name != Module::INIT => {
+                        format!("{}.{}", builder.name, name)
+                    }
+                    _ => name.clone(),
+                };
+                names.insert(name.clone(), self.unique(qualified));
+            }
+        }
+        self.names.push(names);
+    }
+
+    fn function_name(&self, name: &str) -> Option<String> {
+        self.names
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+    }
+
+    fn storage(&self, name: &str) -> Option<Storage> {
+        let builder = self.builders.last().expect("a function is being lowered");
+        builder
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .or_else(|| {
+                self.globals
+                    .iter()
+                    .any(|global| global == name)
+                    .then(|| Storage::Global(name.to_string()))
+            })
+    }
+
+    /// Declares a variable in the innermost scope, or a global outside of functions.
+    fn bind(&mut self, name: &str, value: Operand) {
+        if self.builder().scopes.is_empty() {
+            let pointer = self.emit_value(|dest| Instruction::Global {
+                dest,
+                name: name.to_string(),
+            });
+            self.emit(Instruction::Store { pointer, value });
+            return;
+        }
+
+        let storage = if self.builder().memory.contains(name) {
+            let pointer = self.register();
+            // functions jumping back to their start reuse their variables instead of allocating
+            // them over again, which nothing pointing to them allows
+            match self.builder().start {
+                Some(_) => self.builder().blocks[0]
+                    .instructions
+                    .push(Instruction::Alloc { dest: pointer }),
+                None => self.emit(Instruction::Alloc { dest: pointer }),
+            }
+            self.emit(Instruction::Store {
+                pointer: Operand::Register(pointer),
+                value,
+            });
+            Storage::Memory(pointer)
+        } else {
+            let dest = self.register();
+            self.emit(Instruction::Copy { dest, value });
+            Storage::Register(dest)
+        };
+        let scope = self.builder().scopes.last_mut();
+        scope
+            .expect("there is a scope inside of functions")
+            .insert(name.to_string(), storage);
+    }
+
+    /// Lowers a named function, a method or a closure, whose first parameters are its
+    /// captures.
+    fn function(
+        &mut self,
+        name: String,
+        public: bool,
+        inline: bool,
+        captures: &[Capture],
+        parameters: &[Param],
+        body: &Block,
+    ) {
+        let mut memory = HashSet::new();
+        memory_variables(&body.statements, &body.return_value, &mut memory);
+        self.begin(name, public, memory);
+        self.builder().inline = inline;
+        self.builder().scopes.push(HashMap::new());
+        if has_tail_calls(body) {
+            let start = self.new_block();
+            self.terminate(Terminator::Jump(start));
+            self.switch_to(start);
+            self.builder().start = Some(start);
+        }
+
+        for capture in captures {
+            let register = self.register();
+            self.builder().parameters.push(register);
+            let scope = self.builder().scopes.last_mut().expect("there is a scope");
+            if capture.by_reference {
+                scope.insert(capture.name.clone(), Storage::Memory(register));
+            } else {
+                scope.insert(capture.name.clone(), Storage::Register(register));
+            }
+        }
+        for Param::Parameter { name, .. } in parameters {
+            let register = self.register();
+            self.builder().parameters.push(register);
+            if self.builder().memory.contains(name) {
+                self.bind(name, Operand::Register(register));
+            } else {
+                let scope = self.builder().scopes.last_mut().expect("there is a scope");
+                scope.insert(name.clone(), Storage::Register(register));
+            }
+        }
+        // captured copies of variables which are assigned to are kept in memory as well
+        for capture in captures.iter().filter(|capture| !capture.by_reference) {
+            if self.builder().memory.contains(&capture.name) {
+                let Some(Storage::Register(register)) = self.storage(&capture.name) else {
+                    unreachable!("captured copies are parameters")
+                };
+                self.bind(&capture.name, Operand::Register(register));
+            }
+        }
+
+        let value = self.block(&body.statements, &body.return_value);
+        self.terminate(Terminator::Return(value));
+        self.finish();
+    }
+
+    fn block(&mut self, statements: &[Statement], return_value: &Expression) -> Operand {
+        self.builder().scopes.push(HashMap::new());
+        self.declare_functions(statements);
+
+        for statement in statements {
+            self.statement(statement);
+        }
+        let value = self.expression(return_value);
+
+        self.names.pop();
+        self.builder().scopes.pop();
+        value
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Declaration { name, value, .. } => {
+                let value = self.expression(value);
+                self.bind(name, value);
+            }
+            Statement::Destructuring { names, value, .. } => {
+                let value = self.expression(value);
+                for (index, name) in names.iter().enumerate() {
+                    let value = value.clone();
+                    let field = self.emit_value(|dest| Instruction::Extract { dest, value, index });
+                    self.bind(name, field);
+                }
+            }
+            Statement::ReAssignment { name, value, .. } => {
+                let value = self.expression(value);
+                match self.storage(name).expect("assigned variables are declared") {
+                    Storage::Register(dest) => self.emit(Instruction::Copy { dest, value }),
+                    Storage::Memory(pointer) => self.emit(Instruction::Store {
+                        pointer: Operand::Register(pointer),
+                        value,
+                    }),
+                    Storage::Global(name) => {
+                        let pointer = self.emit_value(|dest| Instruction::Global { dest, name });
+                        self.emit(Instruction::Store { pointer, value });
+                    }
+                }
+            }
+            Statement::PlaceAssignment { target, value } => {
+                let value = self.expression(value);
+                let pointer = self.place(target);
+                self.emit(Instruction::Store { pointer, value });
+            }
+            Statement::ExpressionStatement(expression) => {
+                self.expression(expression);
+            }
+            Statement::IfStatement(if_statement) => {
+                self.if_expression(if_statement);
+            }
+            Statement::FuncDeclaration { .. } | Statement::TypeDeclaration { .. } => {
+                for (name, public, inline, parameters, body) in functions(statement) {
+                    let name = self.function_name(name).expect("functions are declared");
+                    self.function(name, public, inline, &[], parameters, body);
+                }
+            }
+            Statement::Import { .. } => unreachable!("imports are resolved by the module loader"),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Operand {
+        if let Some((operator, left, right)) = expression.as_binary() {
+            let left = self.expression(left);
+            let right = self.expression(right);
+            let span = expression.span();
+            return self.emit_value(|dest| Instruction::Binary {
+                dest,
+                operator,
+                left,
+                right,
+                span,
+            });
+        }
+
+        match expression {
+            Expression::NumberLiteral(literal) => match fold_literal(literal) {
+                Ok(Expression::Number(number)) => Operand::Constant(Constant::Number(number)),
+                _ => unreachable!("the validator checks literals"),
+            },
+            Expression::Number(number) => Operand::Constant(Constant::Number(number.clone())),
+            Expression::Boolean(boolean) => Operand::Constant(Constant::Boolean(*boolean)),
+            Expression::Nil => Operand::Constant(Constant::Nil),
+            Expression::Identifier { name, .. } => match self.storage(name) {
+                Some(storage) => self.read(storage),
+                None => {
+                    let name = self
+                        .function_name(name)
+                        .expect("the validator checks names");
+                    Operand::Constant(Constant::Function(name))
+                }
+            },
+            Expression::Cast { value, datatype } => {
+                let value = self.expression(value);
+                match datatype {
+                    Type::Numeric(to) => {
+                        let to = *to;
+                        self.emit_value(|dest| Instruction::Cast { dest, value, to })
+                    }
+                    // wrapping a value in an optional doesn't change it
+                    _ => value,
+                }
+            }
+            Expression::Default { value, fallback } => {
+                let value = self.expression(value);
+                let result = self.register();
+                let is_nil = self.emit_value(|dest| Instruction::Binary {
+                    dest,
+                    operator: BinaryOperator::Equal,
+                    left: value.clone(),
+                    right: Operand::Constant(Constant::Nil),
+                    span: Span::default(),
+                });
+                let (nil, some) = (self.new_block(), self.new_block());
+                self.terminate(Terminator::Branch {
+                    condition: is_nil,
+                    then: nil,
+                    otherwise: some,
+                });
+
+                self.switch_to(nil);
+                let fallback = self.expression(fallback);
+                self.emit(Instruction::Copy {
+                    dest: result,
+                    value: fallback,
+                });
+                let nil_end = self.jump_later();
+
+                self.switch_to(some);
+                self.emit(Instruction::Copy {
+                    dest: result,
+                    value,
+                });
+                let some_end = self.jump_later();
+
+                self.join(vec![nil_end, some_end]);
+                Operand::Register(result)
+            }
+            Expression::Tuple(elements) => {
+                let elements = self.elements(elements);
+                self.emit_value(|dest| Instruction::Aggregate {
+                    dest,
+                    kind: Aggregate::Tuple,
+                    elements,
+                })
+            }
+            Expression::StructLiteral { name, fields } => {
+                let name = name.clone().expect("the validator names struct literals");
+                let elements = self.elements(fields);
+                self.emit_value(|dest| Instruction::Aggregate {
+                    dest,
+                    kind: Aggregate::Struct(name),
+                    elements,
+                })
+            }
+            Expression::Array(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| self.expression(element))
+                    .collect();
+                self.emit_value(|dest| Instruction::Aggregate {
+                    dest,
+                    kind: Aggregate::Array,
+                    elements,
+                })
+            }
+            Expression::Index {
+                value,
+                index,
+                bounds_check,
+                span,
+            } => {
+                let value = self.expression(value);
+                let index = self.expression(index);
+                let (bounds_check, span) = (*bounds_check, *span);
+                self.emit_value(|dest| Instruction::Index {
+                    dest,
+                    value,
+                    index,
+                    bounds_check,
+                    span,
+                })
+            }
+            Expression::FieldAccess { value, field } => {
+                let value = self.expression(value);
+                match field.parse() {
+                    Ok(index) => {
+                        self.emit_value(|dest| Instruction::Extract { dest, value, index })
+                    }
+                    // the validator only leaves the length of arrays named
+                    Err(_) => self.emit_value(|dest| Instruction::Length { dest, value }),
+                }
+            }
+            Expression::AddressOf(place) => self.place(place),
+            Expression::Deref(pointer) => {
+                let pointer = self.expression(pointer);
+                self.emit_value(|dest| Instruction::Load { dest, pointer })
+            }
+            Expression::FunctionCall {
+                name,
+                arguments,
+                span,
+                tail,
+            } => {
+                if *tail {
+                    let arguments = arguments
+                        .iter()
+                        .map(|argument| self.expression(argument))
+                        .collect();
+                    return self.tail_call(arguments);
+                }
+
+                let callee = match self.storage(name) {
+                    Some(storage) => Callee::Value(self.read(storage)),
+                    None => Callee::Function(
+                        self.function_name(name)
+                            .expect("the validator checks calls"),
+                    ),
+                };
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.expression(argument))
+                    .collect();
+                let span = *span;
+                self.emit_value(|dest| Instruction::Call {
+                    dest,
+                    callee,
+                    arguments,
+                    span,
+                })
+            }
+            Expression::Block {
+                statements,
+                return_value,
+            } => self.block(statements, return_value),
+            Expression::Function {
+                parameters,
+                body,
+                captures,
+                ..
+            } => {
+                let captured = captures
+                    .iter()
+                    .map(|capture| {
+                        match self.storage(&capture.name).expect("captures are declared") {
+                            Storage::Memory(pointer) if capture.by_reference => {
+                                Operand::Register(pointer)
+                            }
+                            storage => self.read(storage),
+                        }
+                    })
+                    .collect();
+
+                let name = format!("{}.closure", self.builder().name);
+                let function = self.unique(name);
+                self.function(function.clone(), false, false, captures, parameters, body);
+                self.emit_value(|dest| Instruction::Closure {
+                    dest,
+                    function,
+                    captures: captured,
+                })
+            }
+            Expression::If(if_expression) => self.if_expression(if_expression),
+            Expression::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                let payload = payload.as_ref().map(|payload| self.expression(payload));
+                self.emit_value(|dest| Instruction::Variant {
+                    dest,
+                    enum_name: enum_name.clone(),
+                    variant: variant.clone(),
+                    payload,
+                })
+            }
+            Expression::Match(match_expression) => {
+                let value = self.expression(&match_expression.value);
+                let result = self.register();
+                let mut ends = vec![];
+
+                for arm in &match_expression.arms {
+                    let next = self.new_block();
+                    let mut values = vec![];
+                    self.test(&arm.pattern, value.clone(), next, &mut values);
+
+                    self.builder().scopes.push(HashMap::new());
+                    for (name, value) in bindings(&arm.pattern).into_iter().zip(values) {
+                        self.bind(name, value);
+                    }
+                    let value = self.expression(&arm.value);
+                    self.builder().scopes.pop();
+                    self.emit(Instruction::Copy {
+                        dest: result,
+                        value,
+                    });
+                    ends.push(self.jump_later());
+                    self.switch_to(next);
+                }
+                // matches are exhaustive
+                self.terminate(Terminator::Unreachable);
+
+                self.join(ends);
+                Operand::Register(result)
+            }
+            Expression::Comptime(block) => self.block(&block.statements, &block.return_value),
+            Expression::Type(_) => unreachable!("types only exist at compile time"),
+            _ => unreachable!("method calls are lowered, binary operations are handled above"),
+        }
+    }
+
+    fn elements(&mut self, elements: &[TupleElement]) -> Vec<Operand> {
+        elements
+            .iter()
+            .map(|element| self.expression(&element.value))
+            .collect()
+    }
+
+    fn read(&mut self, storage: Storage) -> Operand {
+        match storage {
+            Storage::Register(register) => Operand::Register(register),
+            Storage::Memory(pointer) => self.emit_value(|dest| Instruction::Load {
+                dest,
+                pointer: Operand::Register(pointer),
+            }),
+            Storage::Global(name) => {
+                let pointer = self.emit_value(|dest| Instruction::Global { dest, name });
+                self.emit_value(|dest| Instruction::Load { dest, pointer })
+            }
+        }
+    }
+
+    /// A pointer to the variable, element or field a place refers to.
+    fn place(&mut self, place: &Expression) -> Operand {
+        match place {
+            Expression::Identifier { name, .. } => match self.storage(name) {
+                Some(Storage::Memory(pointer)) => Operand::Register(pointer),
+                Some(Storage::Global(name)) => {
+                    self.emit_value(|dest| Instruction::Global { dest, name })
+                }
+                _ => unreachable!("variables used as places live in memory"),
+            },
+            Expression::Index {
+                value,
+                index,
+                bounds_check,
+                span,
+            } => {
+                let pointer = self.place(value);
+                let index = self.expression(index);
+                let (bounds_check, span) = (*bounds_check, *span);
+                self.emit_value(|dest| Instruction::ElementAddress {
+                    dest,
+                    pointer,
+                    index,
+                    bounds_check,
+                    span,
+                })
+            }
+            Expression::FieldAccess { value, field } => {
+                let pointer = self.place(value);
+                let index = field
+                    .parse()
+                    .expect("the validator turns fields into positions");
+                self.emit_value(|dest| Instruction::FieldAddress {
+                    dest,
+                    pointer,
+                    index,
+                })
+            }
+            Expression::Deref(pointer) => self.expression(pointer),
+            _ => unreachable!("the validator checks places"),
+        }
+    }
+
+    /// Assigns the arguments of a call of the function to itself to its parameters, and jumps
+    /// back to its start. What comes after is never run, so it goes in a block nothing jumps to.
+    fn tail_call(&mut self, arguments: Vec<Operand>) -> Operand {
+        let start = self
+            .builder()
+            .start
+            .expect("functions making tail calls jump back to their start");
+        // arguments may read parameters, so they are all copied before any is assigned
+        let values: Vec<Operand> = arguments
+            .into_iter()
+            .map(|value| self.emit_value(|dest| Instruction::Copy { dest, value }))
+            .collect();
+        let parameters = self.builder().parameters.clone();
+        let captures = parameters.len() - values.len();
+        for (&dest, value) in parameters[captures..].iter().zip(values) {
+            self.emit(Instruction::Copy { dest, value });
+        }
+        self.terminate(Terminator::Jump(start));
+
+        let after = self.new_block();
+        self.switch_to(after);
+        Operand::Constant(Constant::Nil)
+    }
+
+    fn if_expression(&mut self, if_expression: &If) -> Operand {
+        let result = self.register();
+        let mut ends = vec![];
+
+        let branches =
+            std::iter::once(&if_expression.if_block).chain(&if_expression.else_if_blocks);
+        for branch in branches {
+            let condition = self.expression(&branch.condition);
+            let (then, otherwise) = (self.new_block(), self.new_block());
+            self.terminate(Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            });
+
+            self.switch_to(then);
+            let value = self.block(&branch.block.statements, &branch.block.return_value);
+            self.emit(Instruction::Copy {
+                dest: result,
+                value,
+            });
+            ends.push(self.jump_later());
+            self.switch_to(otherwise);
+        }
+
+        let value = match &if_expression.else_block {
+            Some(block) => self.block(&block.statements, &block.return_value),
+            None => Operand::Constant(Constant::Nil),
+        };
+        self.emit(Instruction::Copy {
+            dest: result,
+            value,
+        });
+        ends.push(self.jump_later());
+
+        self.join(ends);
+        Operand::Register(result)
+    }
+
+    /// Branches to `fail` unless a pattern matches a value, collecting the values it binds in
+    /// the order of `bindings`.
+    fn test(
+        &mut self,
+        pattern: &Pattern,
+        value: Operand,
+        fail: BlockId,
+        values: &mut Vec<Operand>,
+    ) {
+        let condition = match pattern {
+            Pattern::Wildcard => return,
+            Pattern::Binding(_) => return values.push(value),
+            Pattern::Tuple(elements) => {
+                for (index, element) in elements.iter().enumerate() {
+                    let value = value.clone();
+                    let component =
+                        self.emit_value(|dest| Instruction::Extract { dest, value, index });
+                    self.test(element, component, fail, values);
+                }
+                return;
+            }
+            Pattern::Literal(literal) => {
+                let literal = self.expression(literal);
+                self.emit_value(|dest| Instruction::Binary {
+                    dest,
+                    operator: BinaryOperator::Equal,
+                    left: value.clone(),
+                    right: literal,
+                    span: Span::default(),
+                })
+            }
+            Pattern::Variant { variant, .. } => {
+                let (value, variant) = (value.clone(), variant.clone());
+                self.emit_value(|dest| Instruction::IsVariant {
+                    dest,
+                    value,
+                    variant,
+                })
+            }
+        };
+
+        let matched = self.new_block();
+        self.terminate(Terminator::Branch {
+            condition,
+            then: matched,
+            otherwise: fail,
+        });
+        self.switch_to(matched);
+
+        if let Pattern::Variant {
+            payload: Some(payload),
+            ..
+        } = pattern
+        {
+            let payload_value = self.emit_value(|dest| Instruction::Payload { dest, value });
+            self.test(payload, payload_value, fail, values);
+        }
+    }
+}
+
+/// The functions a statement declares, which are the methods of a type declaration, along
+/// with whether they are public and to be inlined, their parameters and their body.
+fn functions(statement: &Statement) -> Vec<(&String, bool, bool, &[Param], &Block)> {
+    match statement {
+        Statement::FuncDeclaration {
+            public,
+            inline,
+            name,
+            parameters,
+            body,
+            ..
+        } => vec![(name, *public, *inline, parameters, body)],
+        Statement::TypeDeclaration {
+            public,
+            definition:
+                TypeDefinition::Struct { methods, .. } | TypeDefinition::Enum { methods, .. },
+            ..
+        } => methods
+            .iter()
+            .flat_map(functions)
+            .map(|(name, method_public, inline, parameters, body)| {
+                (name, *public || method_public, inline, parameters, body)
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// The root variable of a place, unless it is reached through a pointer.
+fn root(place: &Expression) -> Option<&String> {
+    match place {
+        Expression::Identifier { name, .. } => Some(name),
+        Expression::Index { value, .. } | Expression::FieldAccess { value, .. } => root(value),
+        _ => None,
+    }
+}
+
+/// Collects the variables of a function which have to live in memory, which are those whose
+/// address is used, which are assigned to in part, or which closures capture by reference.
+/// Variables are told apart by name only, so shadowed ones may end up in memory too.
+fn memory_variables(
+    statements: &[Statement],
+    return_value: &Expression,
+    memory: &mut HashSet<String>,
+) {
+    for statement in statements {
+        match statement {
+            Statement::Declaration { value, .. }
+            | Statement::Destructuring { value, .. }
+            | Statement::ReAssignment { value, .. }
+            | Statement::ExpressionStatement(value) => addressed(value, memory),
+            Statement::PlaceAssignment { target, value } => {
+                memory.extend(root(target).cloned());
+                addressed(target, memory);
+                addressed(value, memory);
+            }
+            Statement::IfStatement(if_statement) => addressed_in_if(if_statement, memory),
+            // nested functions are lowered on their own
+            Statement::FuncDeclaration { .. }
+            | Statement::TypeDeclaration { .. }
+            | Statement::Import { .. } => {}
+        }
+    }
+    addressed(return_value, memory);
+}
+
+fn addressed_in_if(if_expression: &If, memory: &mut HashSet<String>) {
+    let branches = std::iter::once(&if_expression.if_block).chain(&if_expression.else_if_blocks);
+    for branch in branches {
+        addressed(&branch.condition, memory);
+        memory_variables(&branch.block.statements, &branch.block.return_value, memory);
+    }
+    if let Some(block) = &if_expression.else_block {
+        memory_variables(&block.statements, &block.return_value, memory);
+    }
+}
+
+fn addressed(expression: &Expression, memory: &mut HashSet<String>) {
+    if let Some((_, left, right)) = expression.as_binary() {
+        addressed(left, memory);
+        addressed(right, memory);
+        return;
+    }
+
+    match expression {
+        Expression::AddressOf(place) => {
+            memory.extend(root(place).cloned());
+            addressed(place, memory);
+        }
+        Expression::Function { captures, .. } => memory.extend(
+            captures
+                .iter()
+                .filter(|capture| capture.by_reference)
+                .map(|capture| capture.name.clone()),
+        ),
+        Expression::Cast { value, .. }
+        | Expression::Deref(value)
+        | Expression::FieldAccess { value, .. } => addressed(value, memory),
+        Expression::Default { value, fallback } => {
+            addressed(value, memory);
+            addressed(fallback, memory);
+        }
+        Expression::Index { value, index, .. } => {
+            addressed(value, memory);
+            addressed(index, memory);
+        }
+        Expression::Tuple(elements)
+        | Expression::StructLiteral {
+            fields: elements, ..
+        } => {
+            for element in elements {
+                addressed(&element.value, memory);
+            }
+        }
+        Expression::Array(elements)
+        | Expression::FunctionCall {
+            arguments: elements,
+            ..
+        } => {
+            for element in elements {
+                addressed(element, memory);
+            }
+        }
+        Expression::Block {
+            statements,
+            return_value,
+        } => memory_variables(statements, return_value, memory),
+        Expression::Comptime(block) => {
+            memory_variables(&block.statements, &block.return_value, memory)
+        }
+        Expression::If(if_expression) => addressed_in_if(if_expression, memory),
+        Expression::Variant {
+            payload: Some(payload),
+            ..
+        } => addressed(payload, memory),
+        Expression::Match(match_expression) => {
+            addressed(&match_expression.value, memory);
+            for arm in &match_expression.arms {
+                addressed(&arm.value, memory);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,63 @@
+// CODE GENERATION //
+
+use crate::structures::{ir::Module, parse_tree::Program};
+
+use dce::Removals;
+use passes::PassManager;
+
+pub mod dce;
+pub mod dominators;
+pub mod error;
+pub mod folding;
+pub mod gvn;
+pub mod inlining;
+pub mod licm;
+pub mod loops;
+pub mod lowering;
+pub mod passes;
+pub mod propagation;
+pub mod ssa;
+pub mod strength;
+pub mod verifier;
+use error::{err_msg, IrError};
+
+#[cfg(test)]
+mod tests;
+
+/// Lowers a validated program to three-address code, which is verified before it is returned.
+pub fn generate(program: &Program) -> Module {
+    let module = lowering::lower(program);
+    check(verifier::verify(&module));
+    module
+}
+
+/// Lowers a validated program to three-address code in SSA form, which is verified before it
+/// is returned.
+pub fn generate_ssa(program: &Program) -> Module {
+    let mut module = generate(program);
+    ssa::construct_module(&mut module);
+    check(verifier::verify_ssa(&module));
+    module
+}
+
+/// Converts a module in SSA form back to three-address code, which is verified before it is
+/// returned.
+pub fn destruct_ssa(mut module: Module) -> Module {
+    ssa::destruct_module(&mut module);
+    check(verifier::verify(&module));
+    module
+}
+
+/// Runs the passes of a pass manager over a module, which is verified again afterwards.
+pub fn optimize(module: &mut Module, passes: &PassManager) -> Removals {
+    let removals = passes.run(module);
+    check(verifier::verify(module));
+    removals
+}
+
+fn check(result: Result<(), IrError>) {
+    if let Err(e) = result {
+        println!("{}", err_msg(e));
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,145 @@
+use colored::Colorize;
+
+use crate::structures::ir::Module;
+
+use super::{
+    dce::{eliminate_dead_code, Removals},
+    folding::fold_constants,
+    gvn::number_values,
+    inlining::inline_calls,
+    licm::hoist_invariants,
+    propagation::propagate_copies,
+    strength::reduce_strength,
+};
+
+/// How many times the passes may all be run before giving up on reaching a fixpoint.
+const ROUND_LIMIT: usize = 32;
+
+/// A transformation of a whole module which keeps what the program does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    Inline,
+    Fold,
+    Propagate,
+    Gvn,
+    Licm,
+    Strength,
+    Dce,
+}
+
+impl Pass {
+    /// The names passes are given on the command line.
+    pub const NAMES: &'static [&'static str] = &[
+        "inline",
+        "fold",
+        "propagate",
+        "gvn",
+        "licm",
+        "strength",
+        "dce",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::Inline => "inline",
+            Pass::Fold => "fold",
+            Pass::Propagate => "propagate",
+            Pass::Gvn => "gvn",
+            Pass::Licm => "licm",
+            Pass::Strength => "strength",
+            Pass::Dce => "dce",
+        }
+    }
+
+    /// The passes run at an optimization level: none at 0, the ones which only clean up
+    /// after lowering at 1, and every pass at 2.
+    pub fn level(level: u8) -> Vec<Pass> {
+        match level {
+            0 => vec![],
+            1 => vec![Pass::Fold, Pass::Dce],
+            _ => vec![
+                Pass::Inline,
+                Pass::Fold,
+                Pass::Propagate,
+                Pass::Gvn,
+                Pass::Licm,
+                Pass::Strength,
+                Pass::Dce,
+            ],
+        }
+    }
+
+    fn run(&self, module: &mut Module, removals: &mut Removals) {
+        match self {
+            Pass::Inline => inline_calls(module),
+            Pass::Fold => fold_constants(module),
+            Pass::Propagate => propagate_copies(module),
+            Pass::Gvn => number_values(module),
+            Pass::Licm => hoist_invariants(module),
+            Pass::Strength => reduce_strength(module),
+            Pass::Dce => removals.merge(eliminate_dead_code(module)),
+        }
+    }
+}
+
+impl std::str::FromStr for Pass {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "inline" => Ok(Pass::Inline),
+            "fold" => Ok(Pass::Fold),
+            "propagate" => Ok(Pass::Propagate),
+            "gvn" => Ok(Pass::Gvn),
+            "licm" => Ok(Pass::Licm),
+            "strength" => Ok(Pass::Strength),
+            "dce" => Ok(Pass::Dce),
+            _ => Err(format!("unknown pass '{}'", name)),
+        }
+    }
+}
+
+/// Runs passes over a module in order, over and over until none of them changes it.
+pub struct PassManager {
+    passes: Vec<Pass>,
+    /// Whether the module is printed after every pass which changed it.
+    dump: bool,
+}
+
+impl PassManager {
+    pub fn new(passes: Vec<Pass>, dump: bool) -> Self {
+        PassManager { passes, dump }
+    }
+
+    /// Runs the passes until the module stops changing, returning what dead code elimination
+    /// removed along the way. Dumps are printed to stderr, away from the code emitted.
+    pub fn run(&self, module: &mut Module) -> Removals {
+        let mut removals = Removals::default();
+        for round in 1..=ROUND_LIMIT {
+            let mut changed = false;
+            for pass in &self.passes {
+                let before = module.clone();
+                pass.run(module, &mut removals);
+                if *module == before {
+                    continue;
+                }
+
+                changed = true;
+                if self.dump {
+                    eprintln!(
+                        "{} {} {} {}\n{}",
+                        ";".bold().magenta(),
+                        "after".bold().magenta(),
+                        pass.name().bold(),
+                        format!("(round {})", round).magenta(),
+                        module
+                    );
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        removals
+    }
+}
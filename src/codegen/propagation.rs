@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use crate::structures::ir::{Function, Instruction, Module, Operand, Register};
+
+/// Replaces the uses of registers which only ever hold a copy of a constant or of another
+/// register with what they copy, leaving the copies for dead code elimination.
+pub fn propagate_copies(module: &mut Module) {
+    for function in &mut module.functions {
+        propagate_function(function);
+    }
+}
+
+fn propagate_function(function: &mut Function) {
+    // registers assigned once are assigned before every use, so their value never changes
+    // where they are read
+    let mut assignments = vec![0usize; function.registers];
+    for &parameter in &function.parameters {
+        assignments[parameter.0] += 1;
+    }
+    for dest in function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter_map(Instruction::dest)
+    {
+        assignments[dest.0] += 1;
+    }
+    let stable = |operand: &Operand| match operand {
+        Operand::Register(register) => assignments[register.0] == 1,
+        Operand::Constant(_) => true,
+    };
+
+    let mut copies: HashMap<Register, Operand> = HashMap::new();
+    for instruction in function.blocks.iter().flat_map(|block| &block.instructions) {
+        match instruction {
+            Instruction::Copy { dest, value } if assignments[dest.0] == 1 && stable(value) => {
+                copies.insert(*dest, value.clone());
+            }
+            // a phi choosing the same value whichever way control comes, or itself in loops
+            Instruction::Phi { dest, incoming } if assignments[dest.0] == 1 => {
+                let mut values = incoming
+                    .iter()
+                    .map(|(_, value)| value)
+                    .filter(|value| **value != Operand::Register(*dest));
+                if let Some(first) = values.next() {
+                    if stable(first) && values.all(|value| value == first) {
+                        copies.insert(*dest, first.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if copies.is_empty() {
+        return;
+    }
+
+    let resolve = |operand: &Operand| {
+        let mut resolved = operand.clone();
+        // copies of copies are followed to what they end up copying
+        for _ in 0..=copies.len() {
+            match &resolved {
+                Operand::Register(register) if copies.contains_key(register) => {
+                    resolved = copies[register].clone();
+                }
+                _ => break,
+            }
+        }
+        resolved
+    };
+
+    for block in &mut function.blocks {
+        for instruction in &mut block.instructions {
+            for operand in instruction.operands_mut() {
+                *operand = resolve(operand);
+            }
+        }
+        for operand in block.terminator.operands_mut() {
+            *operand = resolve(operand);
+        }
+    }
+}
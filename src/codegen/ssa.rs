@@ -0,0 +1,341 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::structures::ir::{
+    BasicBlock, BlockId, Constant, Function, Instruction, Module, Operand, Register, Terminator,
+};
+
+use super::dominators::Dominators;
+
+/// Converts every function of a module to SSA form.
+pub fn construct_module(module: &mut Module) {
+    for function in &mut module.functions {
+        construct(function);
+    }
+}
+
+/// Converts every function of a module back from SSA form.
+pub fn destruct_module(module: &mut Module) {
+    for function in &mut module.functions {
+        destruct(function);
+    }
+}
+
+/// Converts a function to SSA form, where every register is assigned exactly once and values
+/// merging where control flow does are chosen by phis.
+///
+/// Only registers assigned more than once are renamed, since the verifier makes sure a register
+/// assigned once is assigned before all of its uses. Phis are placed in the iterated dominance
+/// frontiers of the blocks assigning to them, and those whose value is never used are removed.
+pub fn construct(function: &mut Function) {
+    function.remove_unreachable_blocks();
+    let dominators = Dominators::new(function);
+    let predecessors = function.predecessors();
+
+    // the blocks assigning to every register, parameters being assigned at the start
+    let mut assignments: HashMap<Register, Vec<BlockId>> = HashMap::new();
+    let mut counts: HashMap<Register, usize> = HashMap::new();
+    for &parameter in &function.parameters {
+        assignments.entry(parameter).or_default().push(BlockId(0));
+        *counts.entry(parameter).or_default() += 1;
+    }
+    for (id, block) in function.blocks.iter().enumerate() {
+        for dest in block.instructions.iter().filter_map(Instruction::dest) {
+            assignments.entry(dest).or_default().push(BlockId(id));
+            *counts.entry(dest).or_default() += 1;
+        }
+    }
+    let mut variables: Vec<Register> = counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(register, _)| register)
+        .collect();
+    variables.sort();
+
+    for &variable in &variables {
+        let mut has_phi = HashSet::new();
+        let mut pending = assignments[&variable].clone();
+        let mut assigning: HashSet<BlockId> = pending.iter().copied().collect();
+        while let Some(block) = pending.pop() {
+            for &frontier in dominators.frontier(block) {
+                if !has_phi.insert(frontier) {
+                    continue;
+                }
+                // the operands are renamed to the values reaching the end of each predecessor
+                let incoming = predecessors[frontier.0]
+                    .iter()
+                    .map(|&predecessor| (predecessor, Operand::Register(variable)))
+                    .collect();
+                function.blocks[frontier.0].instructions.insert(
+                    0,
+                    Instruction::Phi {
+                        dest: variable,
+                        incoming,
+                    },
+                );
+                if assigning.insert(frontier) {
+                    pending.push(frontier);
+                }
+            }
+        }
+    }
+
+    let variables: HashSet<Register> = variables.into_iter().collect();
+    let mut renamer = Renamer {
+        function,
+        dominators: &dominators,
+        variables: &variables,
+        stacks: HashMap::new(),
+    };
+    for &parameter in &renamer.function.parameters.clone() {
+        if variables.contains(&parameter) {
+            renamer.stacks.entry(parameter).or_default().push(parameter);
+        }
+    }
+    renamer.rename(BlockId(0));
+
+    remove_dead_phis(function);
+}
+
+struct Renamer<'f> {
+    function: &'f mut Function,
+    dominators: &'f Dominators,
+    /// The registers assigned more than once, which are renamed.
+    variables: &'f HashSet<Register>,
+    /// The registers holding the current value of every variable, innermost last.
+    stacks: HashMap<Register, Vec<Register>>,
+}
+
+impl Renamer<'_> {
+    fn current(&self, variable: Register) -> Operand {
+        match self.stacks.get(&variable).and_then(|stack| stack.last()) {
+            Some(&register) => Operand::Register(register),
+            // only phis of variables which aren't used past them may see no assignment, and
+            // those phis are removed
+            None => Operand::Constant(Constant::Nil),
+        }
+    }
+
+    fn use_current(&self, operand: &mut Operand) {
+        if let Operand::Register(register) = *operand {
+            if self.variables.contains(&register) {
+                *operand = self.current(register);
+            }
+        }
+    }
+
+    fn define(&mut self, dest: &mut Register, pushed: &mut Vec<Register>) {
+        if self.variables.contains(dest) {
+            let register = Register(self.function.registers);
+            self.function.registers += 1;
+            self.stacks.entry(*dest).or_default().push(register);
+            pushed.push(*dest);
+            *dest = register;
+        }
+    }
+
+    /// Renames the assignments and uses of variables in a block and the blocks it dominates.
+    fn rename(&mut self, block: BlockId) {
+        let mut pushed = vec![];
+        let mut instructions = std::mem::take(&mut self.function.blocks[block.0].instructions);
+        for instruction in &mut instructions {
+            if !instruction.is_phi() {
+                for operand in instruction.operands_mut() {
+                    self.use_current(operand);
+                }
+            }
+            if let Some(dest) = instruction.dest_mut() {
+                self.define(dest, &mut pushed);
+            }
+        }
+        self.function.blocks[block.0].instructions = instructions;
+
+        let mut terminator = self.function.blocks[block.0].terminator.clone();
+        for operand in terminator.operands_mut() {
+            self.use_current(operand);
+        }
+        self.function.blocks[block.0].terminator = terminator.clone();
+
+        let mut successors = terminator.successors();
+        successors.dedup();
+        for successor in successors {
+            let mut instructions =
+                std::mem::take(&mut self.function.blocks[successor.0].instructions);
+            for instruction in &mut instructions {
+                if let Instruction::Phi { incoming, .. } = instruction {
+                    for (from, value) in incoming.iter_mut() {
+                        if *from == block {
+                            self.use_current(value);
+                        }
+                    }
+                }
+            }
+            self.function.blocks[successor.0].instructions = instructions;
+        }
+
+        for &child in self.dominators.children(block) {
+            self.rename(child);
+        }
+
+        for variable in pushed {
+            self.stacks
+                .get_mut(&variable)
+                .expect("pushed variables have stacks")
+                .pop();
+        }
+    }
+}
+
+/// Removes the phis whose value no other instruction ends up using.
+fn remove_dead_phis(function: &mut Function) {
+    loop {
+        let mut used = HashSet::new();
+        for block in &function.blocks {
+            let operands = block
+                .instructions
+                .iter()
+                .flat_map(Instruction::operands)
+                .chain(block.terminator.operands());
+            for operand in operands {
+                if let Operand::Register(register) = operand {
+                    used.insert(*register);
+                }
+            }
+        }
+
+        let mut removed = false;
+        for block in &mut function.blocks {
+            block.instructions.retain(|instruction| match instruction {
+                Instruction::Phi { dest, .. } if !used.contains(dest) => {
+                    removed = true;
+                    false
+                }
+                _ => true,
+            });
+        }
+        if !removed {
+            return;
+        }
+    }
+}
+
+/// Converts a function back from SSA form, replacing every phi with copies at the end of the
+/// blocks control comes from.
+///
+/// Edges from blocks with several successors into blocks with phis are split first, so the
+/// copies only run on their way into the block. The copies of a block happen at once, through
+/// temporary registers where one phi reads what another assigns.
+pub fn destruct(function: &mut Function) {
+    split_edges(function);
+
+    for block in 0..function.blocks.len() {
+        let phis: Vec<(Register, Vec<(BlockId, Operand)>)> = function.blocks[block]
+            .instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::Phi { dest, incoming } => Some((*dest, incoming.clone())),
+                _ => None,
+            })
+            .collect();
+        if phis.is_empty() {
+            continue;
+        }
+        function.blocks[block]
+            .instructions
+            .retain(|instruction| !instruction.is_phi());
+
+        let mut predecessors: Vec<BlockId> = phis[0].1.iter().map(|(from, _)| *from).collect();
+        predecessors.dedup();
+        for predecessor in predecessors {
+            let copies: Vec<(Register, Operand)> = phis
+                .iter()
+                .map(|(dest, incoming)| {
+                    let (_, value) = incoming
+                        .iter()
+                        .find(|(from, _)| *from == predecessor)
+                        .expect("phis have a value for every predecessor");
+                    (*dest, value.clone())
+                })
+                .collect();
+            let copies = sequentialize(function, copies);
+            function.blocks[predecessor.0].instructions.extend(copies);
+        }
+    }
+}
+
+/// Turns copies meant to happen at once into a sequence of copies with the same effect.
+fn sequentialize(function: &mut Function, copies: Vec<(Register, Operand)>) -> Vec<Instruction> {
+    let dests: HashSet<Register> = copies.iter().map(|(dest, _)| *dest).collect();
+    let overlapping = copies.iter().any(|(dest, value)| match value {
+        Operand::Register(register) => register != dest && dests.contains(register),
+        Operand::Constant(_) => false,
+    });
+
+    if !overlapping {
+        return copies
+            .into_iter()
+            .filter(|(dest, value)| *value != Operand::Register(*dest))
+            .map(|(dest, value)| Instruction::Copy { dest, value })
+            .collect();
+    }
+
+    let mut sequence = vec![];
+    let mut temporaries = vec![];
+    for (dest, value) in copies {
+        let temporary = Register(function.registers);
+        function.registers += 1;
+        sequence.push(Instruction::Copy {
+            dest: temporary,
+            value,
+        });
+        temporaries.push((dest, temporary));
+    }
+    for (dest, temporary) in temporaries {
+        sequence.push(Instruction::Copy {
+            dest,
+            value: Operand::Register(temporary),
+        });
+    }
+    sequence
+}
+
+/// Splits the edges from blocks with several successors into blocks with phis, by putting a
+/// block which only jumps on them.
+fn split_edges(function: &mut Function) {
+    for block in 0..function.blocks.len() {
+        let has_phis = function.blocks[block]
+            .instructions
+            .first()
+            .is_some_and(Instruction::is_phi);
+        if !has_phis {
+            continue;
+        }
+
+        let mut predecessors = function.predecessors()[block].clone();
+        predecessors.dedup();
+        for predecessor in predecessors {
+            if function.blocks[predecessor.0].terminator.successors().len() < 2 {
+                continue;
+            }
+
+            let split = BlockId(function.blocks.len());
+            function.blocks.push(BasicBlock {
+                instructions: vec![],
+                terminator: Terminator::Jump(BlockId(block)),
+            });
+            for successor in function.blocks[predecessor.0].terminator.successors_mut() {
+                if *successor == BlockId(block) {
+                    *successor = split;
+                }
+            }
+            for instruction in &mut function.blocks[block].instructions {
+                if let Instruction::Phi { incoming, .. } = instruction {
+                    for (from, _) in incoming.iter_mut() {
+                        if *from == predecessor {
+                            *from = split;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
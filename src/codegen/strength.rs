@@ -0,0 +1,262 @@
+use crate::{
+    analysis::semantic::folding::fold_binary,
+    structures::{
+        ir::{BlockId, Constant, Function, Instruction, Module, Operand, Register, Terminator},
+        parse_tree::{BinaryOperator, Expression, Number, Span},
+    },
+};
+
+use super::{
+    dominators::Dominators,
+    loops::{natural_loops, preheader, Loop},
+};
+
+/// Replaces the multiplications of induction variables by constants in loops with a variable
+/// of its own, which steps by the product every time around the loop.
+///
+/// An induction variable is a phi of the header of a loop with a single latch, taking a value
+/// from before the loop and the value plus or minus a constant from the latch. Only integers
+/// are reduced, which are exact, so the new variable holds the product whenever it fits. When
+/// it doesn't, both the step and the multiplication overflow: the multiplication has to be at
+/// the start of the header, before anything else with side effects, so the program fails with
+/// the same error before doing anything more either way.
+pub fn reduce_strength(module: &mut Module) {
+    for function in &mut module.functions {
+        function.remove_unreachable_blocks();
+        while let Some(candidate) = next_candidate(function) {
+            reduce(function, candidate);
+        }
+    }
+}
+
+/// A multiplication of an induction variable by a constant.
+struct Candidate {
+    found: Loop,
+    latch: BlockId,
+    /// The position of the multiplication in the header.
+    position: usize,
+    /// The operation stepping the induction variable.
+    operator: BinaryOperator,
+    /// The constant the variable is multiplied by.
+    factor: Constant,
+    /// The step times the factor.
+    step: Constant,
+}
+
+fn next_candidate(function: &Function) -> Option<Candidate> {
+    let mut assignments = vec![0usize; function.registers];
+    for &parameter in &function.parameters {
+        assignments[parameter.0] += 1;
+    }
+    for dest in function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter_map(Instruction::dest)
+    {
+        assignments[dest.0] += 1;
+    }
+
+    let dominators = Dominators::new(function);
+    for found in natural_loops(function, &dominators) {
+        let [latch] = found.latches[..] else {
+            continue;
+        };
+        if found.header.0 == 0
+            || function.blocks[latch.0].terminator != Terminator::Jump(found.header)
+        {
+            continue;
+        }
+
+        let instructions = &function.blocks[found.header.0].instructions;
+        for (position, instruction) in instructions.iter().enumerate() {
+            if let Instruction::Binary {
+                operator: BinaryOperator::Multiply,
+                left,
+                right,
+                ..
+            } = instruction
+            {
+                let operands = match (left, right) {
+                    (Operand::Register(variable), Operand::Constant(factor))
+                    | (Operand::Constant(factor), Operand::Register(variable)) => {
+                        Some((*variable, factor))
+                    }
+                    _ => None,
+                };
+                if let Some((variable, factor)) = operands {
+                    let stepping = stepping(function, &found, latch, &assignments, variable);
+                    if let Some((operator, step)) = stepping {
+                        if let Some(step) = multiply(step, factor) {
+                            return Some(Candidate {
+                                found,
+                                latch,
+                                position,
+                                operator,
+                                factor: factor.clone(),
+                                step,
+                            });
+                        }
+                    }
+                }
+            }
+            if instruction.has_side_effects() {
+                break;
+            }
+        }
+    }
+    None
+}
+
+/// How a register steps, if it is an induction variable of a loop: the phi of the header it
+/// is assigned by takes it plus or minus a constant integer from the latch.
+fn stepping<'f>(
+    function: &'f Function,
+    found: &Loop,
+    latch: BlockId,
+    assignments: &[usize],
+    variable: Register,
+) -> Option<(BinaryOperator, &'f Constant)> {
+    if assignments[variable.0] != 1 {
+        return None;
+    }
+    let incoming = function.blocks[found.header.0]
+        .instructions
+        .iter()
+        .find_map(|instruction| match instruction {
+            Instruction::Phi { dest, incoming } if *dest == variable => Some(incoming),
+            _ => None,
+        })?;
+    let looping: Vec<&Operand> = incoming
+        .iter()
+        .filter(|(from, _)| found.contains(*from))
+        .map(|(_, value)| value)
+        .collect();
+    let [Operand::Register(next)] = looping[..] else {
+        return None;
+    };
+    if assignments[next.0] != 1 || !incoming.iter().any(|(from, _)| *from == latch) {
+        return None;
+    }
+
+    found
+        .blocks
+        .iter()
+        .flat_map(|block| &function.blocks[block.0].instructions)
+        .find_map(|instruction| match instruction {
+            Instruction::Binary {
+                dest,
+                operator: operator @ (BinaryOperator::Add | BinaryOperator::Subtract),
+                left,
+                right,
+                ..
+            } if dest == next => match (operator, left, right) {
+                (_, Operand::Register(register), Operand::Constant(step))
+                | (BinaryOperator::Add, Operand::Constant(step), Operand::Register(register))
+                    if *register == variable =>
+                {
+                    Some((*operator, step))
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+}
+
+/// The product of two constant integers, if it fits in their type.
+fn multiply(left: &Constant, right: &Constant) -> Option<Constant> {
+    let (Constant::Number(left @ Number::Int(_)), Constant::Number(right @ Number::Int(_))) =
+        (left, right)
+    else {
+        return None;
+    };
+    match fold_binary(
+        BinaryOperator::Multiply,
+        &Expression::Number(left.clone()),
+        &Expression::Number(right.clone()),
+        Span::default(),
+    ) {
+        Ok(Some(Expression::Number(product))) => Some(Constant::Number(product)),
+        _ => None,
+    }
+}
+
+/// Computes the first product before the loop, and steps it along with the induction variable
+/// in the latch, replacing the multiplication with a copy of it.
+fn reduce(function: &mut Function, candidate: Candidate) {
+    let Candidate {
+        found,
+        latch,
+        position,
+        operator,
+        factor,
+        step,
+    } = candidate;
+    let header = found.header;
+    let preheader = preheader(function, &found).expect("loops not starting the function have one");
+
+    let Instruction::Binary {
+        dest,
+        left,
+        right,
+        span,
+        ..
+    } = function.blocks[header.0].instructions[position].clone()
+    else {
+        unreachable!("candidates are multiplications");
+    };
+    let variable = match (left, right) {
+        (Operand::Register(variable), _) | (_, Operand::Register(variable)) => variable,
+        _ => unreachable!("candidates multiply a register"),
+    };
+    let initial = function.blocks[header.0]
+        .instructions
+        .iter()
+        .find_map(|instruction| match instruction {
+            Instruction::Phi { dest, incoming } if *dest == variable => incoming
+                .iter()
+                .find(|(from, _)| *from == preheader)
+                .map(|(_, value)| value.clone()),
+            _ => None,
+        })
+        .expect("induction variables come from the preheader");
+
+    let first = Register(function.registers);
+    let current = Register(function.registers + 1);
+    let next = Register(function.registers + 2);
+    function.registers += 3;
+
+    function.blocks[preheader.0]
+        .instructions
+        .push(Instruction::Binary {
+            dest: first,
+            operator: BinaryOperator::Multiply,
+            left: initial,
+            right: Operand::Constant(factor),
+            span,
+        });
+    let instructions = &mut function.blocks[header.0].instructions;
+    instructions[position] = Instruction::Copy {
+        dest,
+        value: Operand::Register(current),
+    };
+    instructions.insert(
+        0,
+        Instruction::Phi {
+            dest: current,
+            incoming: vec![
+                (preheader, Operand::Register(first)),
+                (latch, Operand::Register(next)),
+            ],
+        },
+    );
+    function.blocks[latch.0]
+        .instructions
+        .push(Instruction::Binary {
+            dest: next,
+            operator,
+            left: Operand::Register(current),
+            right: Operand::Constant(step),
+            span,
+        });
+}
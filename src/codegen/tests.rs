@@ -0,0 +1,1101 @@
+use crate::{
+    analysis::{
+        lexical::lex,
+        semantic::{folding::fold_binary, Validator},
+        syntactic::parser::Parser,
+    },
+    structures::{
+        ir::{
+            BasicBlock, BlockId, Callee, Constant, Function, Instruction, Module, Operand,
+            Register, Terminator,
+        },
+        parse_tree::{BinaryOperator, Expression, Integer, Number, Signed, Span, Unsigned},
+    },
+};
+
+use super::{
+    dce::eliminate_dead_code,
+    destruct_ssa,
+    dominators::Dominators,
+    error::IrError,
+    inlining::inline_calls,
+    licm::hoist_invariants,
+    loops::natural_loops,
+    lowering::lower,
+    passes::{Pass, PassManager},
+    ssa,
+    strength::reduce_strength,
+    verifier,
+};
+
+/// Lowers a program, which must be valid, to three-address code.
+fn lower_source(source: &str) -> Module {
+    let program = Parser::new(lex(source))
+        .parse_program()
+        .expect("the program parses");
+    let program = match Validator::new().validate(&program) {
+        Ok(program) => program,
+        Err(e) => panic!("the program is invalid: {}", e),
+    };
+    let module = lower(&program);
+    verifier::verify(&module).expect("the lowered module is well formed");
+    module
+}
+
+/// Lowers a program and converts it to SSA form, checking that it is.
+fn ssa_source(source: &str) -> Module {
+    let mut module = lower_source(source);
+    ssa::construct_module(&mut module);
+    if let Err(e) = verifier::verify_ssa(&module) {
+        panic!("not in SSA form: {}\n{}", e, module);
+    }
+    module
+}
+
+fn function<'m>(module: &'m Module, name: &str) -> &'m Function {
+    module
+        .function(name)
+        .unwrap_or_else(|| panic!("no function '{}' in\n{}", name, module))
+}
+
+fn phis(function: &Function) -> Vec<&Instruction> {
+    function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter(|instruction| instruction.is_phi())
+        .collect()
+}
+
+const IF_ELSE: &str = "
+fn choose(c: bool): int {
+    let x: int = 1;
+    let r: nil = if c { x = 2; } else { x = 3; };
+    x
+}
+fn main(): nil { const y: int = choose(true); }
+";
+
+#[test]
+fn if_else_reassignment_merges_with_a_phi() {
+    let module = ssa_source(IF_ELSE);
+    let choose = function(&module, "choose");
+
+    // the value returned is the one x has after either branch
+    let returned = choose
+        .blocks
+        .iter()
+        .find_map(|block| match &block.terminator {
+            Terminator::Return(Operand::Register(register)) => Some(*register),
+            _ => None,
+        })
+        .expect("choose returns a register");
+    let phi = phis(choose)
+        .into_iter()
+        .find(|phi| phi.dest() == Some(returned))
+        .unwrap_or_else(|| panic!("x isn't merged by a phi in\n{}", choose));
+    let Instruction::Phi { incoming, .. } = phi else {
+        unreachable!();
+    };
+    assert_eq!(incoming.len(), 2);
+    assert_ne!(incoming[0].1, incoming[1].1);
+}
+
+#[test]
+fn straight_line_reassignment_needs_no_phis() {
+    let module = ssa_source(
+        "
+fn count(n: int): int {
+    let x: int = n;
+    x = x + 1;
+    x = x * 2;
+    x
+}
+fn main(): nil { const y: int = count(1); }
+",
+    );
+    let count = function(&module, "count");
+    assert!(phis(count).is_empty(), "{}", count);
+}
+
+#[test]
+fn else_if_chains_merge_every_branch() {
+    let module = ssa_source(
+        "
+fn sign(n: int): int {
+    let s: int = 0;
+    let r: nil = if n < 0 { s = 0 - 1; } else if n > 0 { s = 1; } else { s = 0; };
+    s
+}
+fn main(): nil { const y: int = sign(5); }
+",
+    );
+    let sign = function(&module, "sign");
+    let incoming: usize = phis(sign)
+        .iter()
+        .map(|phi| match phi {
+            Instruction::Phi { incoming, .. } => incoming.len(),
+            _ => unreachable!(),
+        })
+        .sum();
+    // every branch contributes to a phi, the inner if merging before the outer one
+    assert!(incoming >= 3, "{}", sign);
+}
+
+#[test]
+fn branches_without_assignments_need_no_phis() {
+    let module = ssa_source(
+        "
+fn pick(c: bool): int {
+    let x: int = 1;
+    x = 4;
+    const y: int = if c { 2 } else { 3 };
+    x + y
+}
+fn main(): nil { const z: int = pick(false); }
+",
+    );
+    let pick = function(&module, "pick");
+    let variables = phis(pick)
+        .iter()
+        .filter(|phi| match phi {
+            Instruction::Phi { incoming, .. } => {
+                incoming.windows(2).all(|pair| pair[0].1 == pair[1].1)
+            }
+            _ => false,
+        })
+        .count();
+    // x keeps its one value through the if, so no phi merges a single value
+    assert_eq!(variables, 0, "{}", pick);
+}
+
+#[test]
+fn dominators_of_a_diamond() {
+    let module = lower_source(IF_ELSE);
+    let choose = function(&module, "choose");
+    let dominators = Dominators::new(choose);
+    let join = choose
+        .predecessors()
+        .iter()
+        .position(|predecessors| predecessors.len() == 2)
+        .map(BlockId)
+        .expect("the branches join");
+    for block in dominators.order() {
+        assert!(dominators.dominates(BlockId(0), *block));
+    }
+    // neither branch dominates the join, which is in both their frontiers
+    for predecessor in &choose.predecessors()[join.0] {
+        assert!(!dominators.dominates(*predecessor, join));
+        assert!(dominators.frontier(*predecessor).contains(&join));
+    }
+}
+
+#[test]
+fn destruction_removes_every_phi() {
+    let mut module = ssa_source(IF_ELSE);
+    ssa::destruct_module(&mut module);
+    verifier::verify(&module).expect("the destructed module is well formed");
+    for function in &module.functions {
+        assert!(phis(function).is_empty(), "{}", function);
+    }
+}
+
+#[test]
+fn optimized_code_is_destructed_for_emitting() {
+    let mut module = ssa_source(TAIL_CALLS);
+    PassManager::new(Pass::level(2), false).run(&mut module);
+    let count = function(&module, "count");
+    assert!(!phis(count).is_empty(), "{}", count);
+
+    let module = destruct_ssa(module);
+    for function in &module.functions {
+        assert!(phis(function).is_empty(), "{}", function);
+    }
+}
+
+#[test]
+fn non_ssa_code_is_rejected() {
+    let module = lower_source(IF_ELSE);
+    assert!(verifier::verify_ssa(&module).is_err());
+}
+
+#[test]
+fn unused_bindings_without_side_effects_are_removed() {
+    let mut module = lower_source(
+        "
+fn f(n: int): int {
+    const dead: int = n;
+    let pair: (int, int) = (n, n);
+    const less: bool = n < 3;
+    let checked: int = n + 1;
+    n
+}
+fn main(): nil { const y: int = f(2); }
+",
+    );
+    let removals = eliminate_dead_code(&mut module);
+    verifier::verify(&module).expect("the module stays well formed");
+
+    let f = function(&module, "f");
+    let instructions: Vec<&Instruction> = f
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .collect();
+    // the addition may overflow, so it stays
+    assert_eq!(instructions.len(), 1, "{}", f);
+    assert!(matches!(instructions[0], Instruction::Binary { .. }));
+    assert!(removals
+        .instructions
+        .iter()
+        .any(|(function, _)| function == "f"));
+}
+
+#[test]
+fn only_checked_indices_are_kept_when_unused() {
+    let mut module = lower_source(
+        "
+fn f(xs: [3]int, i: u64): int {
+    const known: int = xs[1];
+    const unknown: int = xs[i];
+    xs[0]
+}
+fn main(): nil { const y: int = f([1, 2, 3], 2); }
+",
+    );
+    let indices = |module: &Module| -> Vec<bool> {
+        function(module, "f")
+            .blocks
+            .iter()
+            .flat_map(|block| &block.instructions)
+            .filter_map(|instruction| match instruction {
+                Instruction::Index { bounds_check, .. } => Some(*bounds_check),
+                _ => None,
+            })
+            .collect()
+    };
+    assert_eq!(indices(&module), vec![false, true, false]);
+    assert!(module.to_string().contains("unchecked index"), "{}", module);
+
+    eliminate_dead_code(&mut module);
+    verifier::verify(&module).expect("the module stays well formed");
+    // an index out of bounds fails, so only the unchecked one goes
+    assert_eq!(indices(&module), vec![true, false]);
+}
+
+#[test]
+fn unused_private_functions_are_removed() {
+    let mut module = lower_source(
+        "
+fn helper(n: int): int { n * 2 }
+fn unused(n: int): int { helper(n) + 1 }
+fn called(n: int): int { n }
+pub fn api(n: int): int { n }
+fn main(): nil { const y: int = called(1); }
+",
+    );
+    let removals = eliminate_dead_code(&mut module);
+    verifier::verify(&module).expect("the module stays well formed");
+
+    let mut removed = removals.functions.clone();
+    removed.sort();
+    assert_eq!(removed, ["helper", "unused"]);
+    for kept in ["api", "called", "main", Module::INIT] {
+        assert!(module.function(kept).is_some(), "'{}' was removed", kept);
+    }
+}
+
+#[test]
+fn branches_on_constants_drop_the_other_branch() {
+    let mut module = ssa_source(IF_ELSE);
+    let choose = module
+        .functions
+        .iter_mut()
+        .find(|function| function.name == "choose")
+        .expect("choose is lowered");
+    for block in &mut choose.blocks {
+        if let Terminator::Branch { condition, .. } = &mut block.terminator {
+            *condition = Operand::Constant(Constant::Boolean(true));
+        }
+    }
+
+    let removals = eliminate_dead_code(&mut module);
+    if let Err(e) = verifier::verify_ssa(&module) {
+        panic!("not in SSA form: {}\n{}", e, module);
+    }
+    let choose = function(&module, "choose");
+    assert_eq!(removals.blocks, [("choose".to_string(), 1)]);
+    // what is left runs straight through
+    assert_eq!(choose.blocks.len(), 1, "{}", choose);
+    assert!(phis(choose).is_empty(), "{}", choose);
+}
+
+const FOLDABLE: &str = "
+const k: int = 3;
+pub fn f(n: int): int {
+    let x: int = 2;
+    let y: int = x;
+    x = 5;
+    let z: int = y * k;
+    let r: nil = if z > 4 { x = x + 1; } else { x = 0; };
+    x + z
+}
+fn main(): nil { const y: int = f(2); }
+";
+
+#[test]
+fn passes_run_to_a_fixpoint() {
+    let mut module = ssa_source(FOLDABLE);
+    PassManager::new(Pass::level(2), false).run(&mut module);
+    if let Err(e) = verifier::verify_ssa(&module) {
+        panic!("not in SSA form: {}\n{}", e, module);
+    }
+
+    // folding the condition makes one branch unreachable, whose removal folds the rest
+    let f = function(&module, "f");
+    let returned: Vec<&Terminator> = f.blocks.iter().map(|block| &block.terminator).collect();
+    assert!(
+        returned.contains(&&Terminator::Return(Operand::Constant(Constant::Number(
+            Number::Int(Integer::Signed(Signed::Int64(12)))
+        )))),
+        "{}",
+        f
+    );
+    assert!(phis(f).is_empty(), "{}", f);
+}
+
+#[test]
+fn no_passes_leave_the_module_alone() {
+    let lowered = ssa_source(FOLDABLE);
+    let mut module = lowered.clone();
+    let removals = PassManager::new(Pass::level(0), false).run(&mut module);
+    assert_eq!(module, lowered);
+    assert!(removals.is_empty());
+}
+
+#[test]
+fn failing_arithmetic_is_not_folded() {
+    let mut module = lower_source(
+        "
+fn main(): nil {
+    let one: int = 1;
+    let zero: int = 0;
+    const y: int = one / zero;
+}
+",
+    );
+    PassManager::new(Pass::level(2), false).run(&mut module);
+    let main = function(&module, "main");
+    let divides = main
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .any(|instruction| matches!(instruction, Instruction::Binary { .. }));
+    assert!(divides, "{}", main);
+}
+
+/// How many instructions of a function compute the given binary operation.
+fn count_binary(function: &Function, operator: BinaryOperator) -> usize {
+    function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter(|instruction| {
+            matches!(instruction, Instruction::Binary { operator: found, .. } if *found == operator)
+        })
+        .count()
+}
+
+#[test]
+fn repeated_computations_in_dominated_blocks_are_done_once() {
+    let mut module = ssa_source(
+        "
+pub fn f(c: bool, x: int, y: int): int {
+    const a: int = x * 2 + y * 2;
+    let r: int = 0;
+    let u: nil = if c { r = y * 2 + x * 2; } else { r = x * 2; };
+    a + r
+}
+fn main(): nil { const z: int = f(true, 1, 2); }
+",
+    );
+    PassManager::new(Pass::level(2), false).run(&mut module);
+    if let Err(e) = verifier::verify_ssa(&module) {
+        panic!("not in SSA form: {}\n{}", e, module);
+    }
+    let f = function(&module, "f");
+    assert_eq!(count_binary(f, BinaryOperator::Multiply), 2, "{}", f);
+    // the sum in the branch is the same as the first one, with its operands swapped
+    assert_eq!(count_binary(f, BinaryOperator::Add), 2, "{}", f);
+}
+
+#[test]
+fn computations_starting_both_branches_are_hoisted() {
+    let mut module = ssa_source(
+        "
+pub fn f(c: bool, x: int, y: int): int {
+    if c { x * 2 + y * 2 } else { x * 2 + y * 2 + 1 }
+}
+fn main(): nil { const z: int = f(true, 1, 2); }
+",
+    );
+    PassManager::new(Pass::level(2), false).run(&mut module);
+    if let Err(e) = verifier::verify_ssa(&module) {
+        panic!("not in SSA form: {}\n{}", e, module);
+    }
+    let f = function(&module, "f");
+    assert_eq!(count_binary(f, BinaryOperator::Multiply), 2, "{}", f);
+    assert_eq!(count_binary(f, BinaryOperator::Add), 2, "{}", f);
+    // both products are computed before branching
+    let entry = &f.blocks[0].instructions;
+    assert!(
+        entry
+            .iter()
+            .all(|instruction| matches!(instruction, Instruction::Binary { .. }))
+            && entry.len() >= 2,
+        "{}",
+        f
+    );
+}
+
+/// The functions called directly by a function.
+fn callees(function: &Function) -> Vec<&str> {
+    function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter_map(|instruction| match instruction {
+            Instruction::Call {
+                callee: Callee::Function(name),
+                ..
+            } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn small_functions_and_those_marked_inline_are_inlined() {
+    let mut module = lower_source(
+        "
+fn double_me(x: int): int { x * 2 }
+inline fn big(x: int): int {
+    const a: int = x + 1; const b: int = a * 2; const c: int = b + 3; const d: int = c * 4;
+    const e: int = d + 5; const f: int = e * 6; const g: int = f + 7; const h: int = g * 8;
+    const i: int = h + 9; const j: int = i * 10; const k: int = j + 11; const l: int = k * 12;
+    const m: int = l + 13; const n: int = m * 14; const o: int = n + 15; const p: int = o * 16;
+    p
+}
+fn huge(x: int): int {
+    const a: int = x + 1; const b: int = a * 2; const c: int = b + 3; const d: int = c * 4;
+    const e: int = d + 5; const f: int = e * 6; const g: int = f + 7; const h: int = g * 8;
+    const i: int = h + 9; const j: int = i * 10; const k: int = j + 11; const l: int = k * 12;
+    const m: int = l + 13; const n: int = m * 14; const o: int = n + 15; const p: int = o * 16;
+    p
+}
+pub fn f(x: int): int { double_me(x) + big(x) + huge(x) }
+fn main(): nil { const y: int = f(1); }
+",
+    );
+    inline_calls(&mut module);
+    verifier::verify(&module).expect("the module stays well formed");
+
+    let f = function(&module, "f");
+    assert_eq!(callees(f), ["huge"], "{}", f);
+}
+
+#[test]
+fn recursive_functions_are_not_inlined() {
+    let mut module = lower_source(
+        "
+inline fn fact(n: int): int { if n < 2 { 1 } else { n * fact(n - 1) } }
+fn even(n: int): bool { if n == 0 { true } else { odd(n - 1) } }
+fn odd(n: int): bool { if n == 0 { false } else { even(n - 1) } }
+fn main(): nil { const a: int = fact(5); const b: bool = even(4); }
+",
+    );
+    inline_calls(&mut module);
+    verifier::verify(&module).expect("the module stays well formed");
+
+    let main = function(&module, "main");
+    assert_eq!(callees(main), ["fact", "even"], "{}", main);
+    assert_eq!(callees(function(&module, "fact")), ["fact"]);
+    assert_eq!(callees(function(&module, "even")), ["odd"]);
+}
+
+#[test]
+fn inlined_code_keeps_its_spans() {
+    let source = "
+fn divide(x: int, y: int): int {
+    x / y
+}
+fn main(): nil { const z: int = divide(1, 2); }
+";
+    let lowered = lower_source(source);
+    let span = |function: &Function| {
+        function
+            .blocks
+            .iter()
+            .flat_map(|block| &block.instructions)
+            .find_map(|instruction| match instruction {
+                Instruction::Binary { span, .. } => Some(*span),
+                _ => None,
+            })
+    };
+    let original = span(function(&lowered, "divide")).expect("divide divides");
+
+    let mut module = lowered.clone();
+    inline_calls(&mut module);
+    let main = function(&module, "main");
+    assert!(callees(main).is_empty(), "{}", main);
+    assert_eq!(span(main), Some(original));
+    assert_eq!(original.line, 3);
+}
+
+fn int(value: i64) -> Operand {
+    Operand::Constant(Constant::Number(Number::Int(Integer::Signed(
+        Signed::Int64(value),
+    ))))
+}
+
+fn register(register: usize) -> Operand {
+    Operand::Register(Register(register))
+}
+
+fn binary(dest: usize, operator: BinaryOperator, left: Operand, right: Operand) -> Instruction {
+    Instruction::Binary {
+        dest: Register(dest),
+        operator,
+        left,
+        right,
+        span: Span::default(),
+    }
+}
+
+fn phi(dest: usize, incoming: Vec<(usize, Operand)>) -> Instruction {
+    Instruction::Phi {
+        dest: Register(dest),
+        incoming: incoming
+            .into_iter()
+            .map(|(block, value)| (BlockId(block), value))
+            .collect(),
+    }
+}
+
+/// A function in SSA form summing `i * 4 + k * 3` for `i` from 0 while it is below `n + k`.
+fn summing_loop() -> Function {
+    Function {
+        name: "sum".to_string(),
+        public: true,
+        inline: false,
+        parameters: vec![Register(0), Register(1)],
+        blocks: vec![
+            BasicBlock {
+                instructions: vec![],
+                terminator: Terminator::Jump(BlockId(1)),
+            },
+            BasicBlock {
+                instructions: vec![
+                    phi(2, vec![(0, int(0)), (2, register(4))]),
+                    phi(3, vec![(0, int(0)), (2, register(8))]),
+                    binary(5, BinaryOperator::Add, register(0), register(1)),
+                    binary(6, BinaryOperator::Multiply, register(2), int(4)),
+                    binary(7, BinaryOperator::LessThan, register(2), register(5)),
+                ],
+                terminator: Terminator::Branch {
+                    condition: register(7),
+                    then: BlockId(2),
+                    otherwise: BlockId(3),
+                },
+            },
+            BasicBlock {
+                instructions: vec![
+                    binary(9, BinaryOperator::LessThan, register(1), register(0)),
+                    binary(10, BinaryOperator::Multiply, register(1), int(3)),
+                    binary(11, BinaryOperator::Add, register(6), register(10)),
+                    binary(8, BinaryOperator::Add, register(3), register(11)),
+                    binary(4, BinaryOperator::Add, register(2), int(1)),
+                ],
+                terminator: Terminator::Jump(BlockId(1)),
+            },
+            BasicBlock {
+                instructions: vec![],
+                terminator: Terminator::Return(register(3)),
+            },
+        ],
+        registers: 12,
+    }
+}
+
+/// A function in SSA form entering a loop of a single block from two places.
+fn loop_with_two_entries() -> Function {
+    Function {
+        name: "count".to_string(),
+        public: true,
+        inline: false,
+        parameters: vec![Register(0)],
+        blocks: vec![
+            BasicBlock {
+                instructions: vec![],
+                terminator: Terminator::Branch {
+                    condition: register(0),
+                    then: BlockId(1),
+                    otherwise: BlockId(2),
+                },
+            },
+            BasicBlock {
+                instructions: vec![],
+                terminator: Terminator::Jump(BlockId(2)),
+            },
+            BasicBlock {
+                instructions: vec![
+                    phi(1, vec![(0, int(1)), (1, int(2)), (2, register(2))]),
+                    binary(2, BinaryOperator::Add, register(1), int(1)),
+                    binary(3, BinaryOperator::Equal, register(0), register(0)),
+                    binary(4, BinaryOperator::LessThan, register(2), int(10)),
+                ],
+                terminator: Terminator::Branch {
+                    condition: register(4),
+                    then: BlockId(2),
+                    otherwise: BlockId(3),
+                },
+            },
+            BasicBlock {
+                instructions: vec![],
+                terminator: Terminator::Return(register(3)),
+            },
+        ],
+        registers: 5,
+    }
+}
+
+fn module_of(function: Function) -> Module {
+    Module {
+        globals: vec![],
+        functions: vec![function],
+    }
+}
+
+#[test]
+fn natural_loops_are_found_from_back_edges() {
+    let sum = summing_loop();
+    let loops = natural_loops(&sum, &Dominators::new(&sum));
+    assert_eq!(loops.len(), 1);
+    assert_eq!(loops[0].header, BlockId(1));
+    assert_eq!(loops[0].latches, [BlockId(2)]);
+    assert_eq!(
+        loops[0].blocks.iter().copied().collect::<Vec<_>>(),
+        [BlockId(1), BlockId(2)]
+    );
+
+    // a block jumping to itself is a loop of its own
+    let count = loop_with_two_entries();
+    let loops = natural_loops(&count, &Dominators::new(&count));
+    assert_eq!(loops.len(), 1);
+    assert_eq!(loops[0].latches, [BlockId(2)]);
+    assert_eq!(loops[0].blocks.len(), 1);
+
+    let straight = lower_source(IF_ELSE);
+    let choose = function(&straight, "choose");
+    assert!(natural_loops(choose, &Dominators::new(choose)).is_empty());
+}
+
+#[test]
+fn loop_invariants_are_hoisted_before_the_loop() {
+    let mut module = module_of(summing_loop());
+    hoist_invariants(&mut module);
+    verifier::verify_ssa(&module).expect("hoisting keeps SSA form");
+
+    // n + k may overflow but starts the header, k < n can't fail, and k * 3 may fail but is
+    // only done when the loop goes around
+    let expected = "\
+pub fn @sum(%0, %1) {
+bb0:
+    %5 = add %0, %1
+    %9 = lt %1, %0
+    jmp bb1
+bb1:
+    %2 = phi [bb0: 0i64], [bb2: %4]
+    %3 = phi [bb0: 0i64], [bb2: %8]
+    %6 = mul %2, 4i64
+    %7 = lt %2, %5
+    br %7, bb2, bb3
+bb2:
+    %10 = mul %1, 3i64
+    %11 = add %6, %10
+    %8 = add %3, %11
+    %4 = add %2, 1i64
+    jmp bb1
+bb3:
+    ret %3
+}
+";
+    assert_eq!(module.to_string(), expected);
+}
+
+#[test]
+fn loops_entered_from_several_blocks_get_a_preheader() {
+    let mut module = module_of(loop_with_two_entries());
+    hoist_invariants(&mut module);
+    verifier::verify_ssa(&module).expect("hoisting keeps SSA form");
+
+    let expected = "\
+pub fn @count(%0) {
+bb0:
+    br %0, bb1, bb4
+bb1:
+    jmp bb4
+bb2:
+    %1 = phi [bb4: %5], [bb2: %2]
+    %2 = add %1, 1i64
+    %4 = lt %2, 10i64
+    br %4, bb2, bb3
+bb3:
+    ret %3
+bb4:
+    %5 = phi [bb0: 1i64], [bb1: 2i64]
+    %3 = eq %0, %0
+    jmp bb2
+}
+";
+    assert_eq!(module.to_string(), expected);
+}
+
+#[test]
+fn multiplications_of_induction_variables_become_additions() {
+    let mut module = module_of(summing_loop());
+    let before = module.to_string();
+    assert!(before.contains("    %6 = mul %2, 4i64\n"), "{}", before);
+
+    // n + k may fail before i * 4 is done, so it has to go first
+    reduce_strength(&mut module);
+    assert_eq!(module.to_string(), before);
+    hoist_invariants(&mut module);
+    reduce_strength(&mut module);
+    verifier::verify_ssa(&module).expect("reduction keeps SSA form");
+
+    // i * 4 starts at 0 * 4 and goes up by 4 along with i, while k * 3 is left alone
+    let expected = "\
+pub fn @sum(%0, %1) {
+bb0:
+    %5 = add %0, %1
+    %9 = lt %1, %0
+    %12 = mul 0i64, 4i64
+    jmp bb1
+bb1:
+    %13 = phi [bb0: %12], [bb2: %14]
+    %2 = phi [bb0: 0i64], [bb2: %4]
+    %3 = phi [bb0: 0i64], [bb2: %8]
+    %6 = copy %13
+    %7 = lt %2, %5
+    br %7, bb2, bb3
+bb2:
+    %10 = mul %1, 3i64
+    %11 = add %6, %10
+    %8 = add %3, %11
+    %4 = add %2, 1i64
+    %14 = add %13, 4i64
+    jmp bb1
+bb3:
+    ret %3
+}
+";
+    assert_eq!(module.to_string(), expected);
+    assert_eq!(
+        count_binary(function(&module, "sum"), BinaryOperator::Multiply),
+        2
+    );
+}
+
+#[test]
+fn multiplications_after_side_effects_are_not_reduced() {
+    let mut sum = summing_loop();
+    // a call before the multiplication could do something the multiplication failing early
+    // would skip
+    sum.blocks[1].instructions[2] = Instruction::Call {
+        dest: Register(5),
+        callee: Callee::Function("limit".to_string()),
+        arguments: vec![register(0), register(1)],
+        span: Span::default(),
+    };
+    let mut module = module_of(sum);
+    let before = module.to_string();
+    reduce_strength(&mut module);
+    assert_eq!(module.to_string(), before);
+}
+
+/// Runs a function of a module on constant arguments, as far as the instructions arithmetic on
+/// numbers and calls lower to go. Calls nest as the calls they run do, up to `depth` of them.
+fn execute(module: &Module, name: &str, arguments: Vec<Constant>, depth: usize) -> Constant {
+    assert!(depth > 0, "calls nest too deep to reach @{}", name);
+    let function = function(module, name);
+    let mut registers: Vec<Option<Constant>> = vec![None; function.registers];
+    for (parameter, argument) in function.parameters.iter().zip(arguments) {
+        registers[parameter.0] = Some(argument);
+    }
+    let read = |registers: &[Option<Constant>], operand: &Operand| match operand {
+        Operand::Register(register) => registers[register.0]
+            .clone()
+            .expect("registers are assigned before they are read"),
+        Operand::Constant(constant) => constant.clone(),
+    };
+    let number = |constant: Constant| match constant {
+        Constant::Number(number) => Expression::Number(number),
+        Constant::Boolean(boolean) => Expression::Boolean(boolean),
+        constant => panic!("can't compute with {}", constant),
+    };
+
+    let (mut previous, mut block) = (BlockId(0), BlockId(0));
+    loop {
+        let current = &function.blocks[block.0];
+        // phis all choose their value before any of them is assigned
+        let chosen: Vec<(Register, Constant)> = current
+            .instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::Phi { dest, incoming } => {
+                    let (_, value) = incoming
+                        .iter()
+                        .find(|(from, _)| *from == previous)
+                        .expect("phis choose a value for every predecessor");
+                    Some((*dest, read(&registers, value)))
+                }
+                _ => None,
+            })
+            .collect();
+        for (dest, value) in chosen {
+            registers[dest.0] = Some(value);
+        }
+
+        for instruction in current.instructions.iter().filter(|i| !i.is_phi()) {
+            let value = match instruction {
+                Instruction::Copy { value, .. } => read(&registers, value),
+                Instruction::Binary {
+                    operator,
+                    left,
+                    right,
+                    ..
+                } => {
+                    let left = number(read(&registers, left));
+                    let right = number(read(&registers, right));
+                    match fold_binary(*operator, &left, &right, Span::default()) {
+                        Ok(Some(Expression::Number(number))) => Constant::Number(number),
+                        Ok(Some(Expression::Boolean(boolean))) => Constant::Boolean(boolean),
+                        _ => panic!("{} fails", instruction),
+                    }
+                }
+                Instruction::Call {
+                    callee: Callee::Function(callee),
+                    arguments,
+                    ..
+                } => {
+                    let arguments = arguments
+                        .iter()
+                        .map(|argument| read(&registers, argument))
+                        .collect();
+                    execute(module, callee, arguments, depth - 1)
+                }
+                _ => panic!("can't run {}", instruction),
+            };
+            let dest = instruction.dest().expect("instructions run assign");
+            registers[dest.0] = Some(value);
+        }
+
+        previous = block;
+        block = match &current.terminator {
+            Terminator::Jump(next) => *next,
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => match read(&registers, condition) {
+                Constant::Boolean(true) => *then,
+                _ => *otherwise,
+            },
+            Terminator::Return(value) => return read(&registers, value),
+            Terminator::Unreachable => panic!("reached unreachable code in @{}", name),
+        };
+    }
+}
+
+fn u64(value: u64) -> Constant {
+    Constant::Number(Number::Int(Integer::Unsigned(Unsigned::UInt64(value))))
+}
+
+const TAIL_CALLS: &str = "
+pub fn count(n: u64, total: u64): u64 {
+    if n == 0 { total } else { count(n - 1, total + 1) }
+}
+pub fn swap(a: u64, b: u64, n: u64): u64 {
+    if n == 0 { a * 10 + b } else { swap(b, a, n - 1) }
+}
+pub fn depth(n: u64): u64 {
+    if n == 0 { 0 } else { 1 + depth(n - 1) }
+}
+fn main(): nil {}
+";
+
+#[test]
+fn self_calls_returning_their_value_become_jumps() {
+    let module = lower_source(TAIL_CALLS);
+    for name in ["count", "swap"] {
+        assert!(callees(function(&module, name)).is_empty(), "{}", module);
+    }
+    // the value of the call is added to before it is returned
+    assert_eq!(callees(function(&module, "depth")), ["depth"]);
+
+    // arguments are given the parameters all at once, so swapping them swaps them
+    assert_eq!(
+        execute(&module, "swap", vec![u64(1), u64(2), u64(3)], 1),
+        u64(21)
+    );
+}
+
+#[test]
+fn tail_calls_recurse_in_constant_stack() {
+    let lowered = lower_source(TAIL_CALLS);
+    let mut optimized = ssa_source(TAIL_CALLS);
+    PassManager::new(Pass::level(2), false).run(&mut optimized);
+    verifier::verify_ssa(&optimized).expect("the optimized module is in SSA form");
+
+    // a single call runs a million times around the loop the recursion became
+    for module in [&lowered, &optimized] {
+        let total = execute(module, "count", vec![u64(1_000_000), u64(0)], 1);
+        assert_eq!(total, u64(1_000_000));
+    }
+}
+
+/// Verifies a module which must be rejected, returning why.
+fn malformed(module: &Module) -> IrError {
+    match verifier::verify(module) {
+        Ok(()) => panic!("the module is well formed:\n{}", module),
+        Err(e) => e,
+    }
+}
+
+/// The summing loop with an instruction added to the end of its first block.
+fn summing_loop_with(instruction: Instruction) -> Module {
+    let mut sum = summing_loop();
+    sum.blocks[0].instructions.push(instruction);
+    module_of(sum)
+}
+
+#[test]
+fn globals_and_functions_have_names_of_their_own() {
+    let mut module = module_of(summing_loop());
+    verifier::verify(&module).expect("the summing loop is well formed");
+
+    module.globals = vec!["total".to_string(), "total".to_string()];
+    assert!(matches!(
+        malformed(&module),
+        IrError::DuplicateGlobal { global } if global == "total"
+    ));
+    module.globals = vec!["sum".to_string()];
+    assert!(matches!(
+        malformed(&module),
+        IrError::NameClash { name } if name == "sum"
+    ));
+    module.globals = vec![];
+    module.functions.push(summing_loop());
+    assert!(matches!(
+        malformed(&module),
+        IrError::DuplicateFunction { function } if function == "sum"
+    ));
+
+    // functions in blocks may hide globals in the source, but not in the module
+    let module = lower_source(
+        "
+let y: int = 1;
+let z: int = { fn y(): int { 2 } y() };
+fn main(): nil {}
+",
+    );
+    assert_eq!(module.globals, ["y", "z"]);
+    assert!(module.function("y.1").is_some(), "{}", module);
+}
+
+#[test]
+fn malformed_functions_are_rejected() {
+    let mut empty = summing_loop();
+    empty.blocks.clear();
+    assert!(matches!(
+        malformed(&module_of(empty)),
+        IrError::EmptyFunction { .. }
+    ));
+
+    let mut jumping = summing_loop();
+    jumping.blocks[0].terminator = Terminator::Jump(BlockId(4));
+    assert!(matches!(
+        malformed(&module_of(jumping)),
+        IrError::UndefinedBlock { block, .. } if block == BlockId(4)
+    ));
+
+    let module = summing_loop_with(binary(12, BinaryOperator::Add, register(0), int(1)));
+    assert!(matches!(
+        malformed(&module),
+        IrError::UndefinedRegister { register, .. } if register == Register(12)
+    ));
+
+    // %4 is only assigned going around the loop
+    let module = summing_loop_with(binary(9, BinaryOperator::Add, register(4), int(1)));
+    assert!(matches!(
+        malformed(&module),
+        IrError::UseBeforeDefinition { block, register, .. }
+            if block == BlockId(0) && register == Register(4)
+    ));
+
+    let module = summing_loop_with(Instruction::Global {
+        dest: Register(9),
+        name: "total".to_string(),
+    });
+    assert!(matches!(
+        malformed(&module),
+        IrError::UndefinedGlobal { global, .. } if global == "total"
+    ));
+}
+
+#[test]
+fn calls_and_closures_match_their_functions() {
+    let call = |callee: &str, arguments: Vec<Operand>| Instruction::Call {
+        dest: Register(9),
+        callee: Callee::Function(callee.to_string()),
+        arguments,
+        span: Span::default(),
+    };
+    assert!(matches!(
+        malformed(&summing_loop_with(call("limit", vec![register(0)]))),
+        IrError::UndefinedFunction { callee, .. } if callee == "limit"
+    ));
+    assert!(matches!(
+        malformed(&summing_loop_with(call("sum", vec![register(0)]))),
+        IrError::ArgumentCount {
+            expected: 2,
+            found: 1,
+            ..
+        }
+    ));
+    assert!(matches!(
+        malformed(&summing_loop_with(Instruction::Copy {
+            dest: Register(9),
+            value: Operand::Constant(Constant::Function("limit".to_string())),
+        })),
+        IrError::UndefinedFunction { callee, .. } if callee == "limit"
+    ));
+
+    // closures bind some of the parameters, but no more than there are
+    let closure = |captures: Vec<Operand>| Instruction::Closure {
+        dest: Register(9),
+        function: "sum".to_string(),
+        captures,
+    };
+    verifier::verify(&summing_loop_with(closure(vec![register(0)])))
+        .expect("closures may bind fewer parameters");
+    assert!(matches!(
+        malformed(&summing_loop_with(closure(vec![register(0); 3]))),
+        IrError::ArgumentCount {
+            expected: 2,
+            found: 3,
+            ..
+        }
+    ));
+}
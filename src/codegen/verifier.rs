@@ -0,0 +1,390 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::structures::ir::{
+    BlockId, Callee, Constant, Function, Instruction, Module, Operand, Register,
+};
+
+use super::{dominators::Dominators, error::IrError};
+
+/// Checks that a module is well formed: globals and functions have names of their own, every
+/// function has blocks, every block jumped to and every register used exists, registers are
+/// assigned on every path before they are read, and calls and closures refer to functions of
+/// the module with the right number of arguments.
+pub fn verify(module: &Module) -> Result<(), IrError> {
+    let mut globals = HashSet::new();
+    for global in &module.globals {
+        if !globals.insert(global.as_str()) {
+            return Err(IrError::DuplicateGlobal {
+                global: global.clone(),
+            });
+        }
+    }
+
+    let mut parameters = HashMap::new();
+    for function in &module.functions {
+        if globals.contains(function.name.as_str()) {
+            return Err(IrError::NameClash {
+                name: function.name.clone(),
+            });
+        }
+        let count = function.parameters.len();
+        if parameters.insert(function.name.as_str(), count).is_some() {
+            return Err(IrError::DuplicateFunction {
+                function: function.name.clone(),
+            });
+        }
+    }
+
+    for function in &module.functions {
+        Verifier {
+            module,
+            function,
+            parameters: &parameters,
+        }
+        .verify()?;
+    }
+    Ok(())
+}
+
+struct Verifier<'m> {
+    module: &'m Module,
+    function: &'m Function,
+    /// The number of parameters of every function of the module.
+    parameters: &'m HashMap<&'m str, usize>,
+}
+
+impl Verifier<'_> {
+    fn verify(&self) -> Result<(), IrError> {
+        let function = self.function;
+        if function.blocks.is_empty() {
+            return Err(IrError::EmptyFunction {
+                function: function.name.clone(),
+            });
+        }
+
+        for &parameter in &function.parameters {
+            self.check_register(parameter)?;
+        }
+        for block in &function.blocks {
+            for instruction in &block.instructions {
+                self.check_instruction(instruction)?;
+            }
+            for operand in block.terminator.operands() {
+                self.check_operand(operand)?;
+            }
+            let incoming = block
+                .instructions
+                .iter()
+                .flat_map(|instruction| match instruction {
+                    Instruction::Phi { incoming, .. } => {
+                        incoming.iter().map(|(from, _)| *from).collect()
+                    }
+                    _ => vec![],
+                });
+            for target in block.terminator.successors().into_iter().chain(incoming) {
+                if target.0 >= function.blocks.len() {
+                    return Err(IrError::UndefinedBlock {
+                        function: function.name.clone(),
+                        block: target,
+                    });
+                }
+            }
+        }
+
+        self.check_definitions()
+    }
+
+    fn check_register(&self, register: Register) -> Result<(), IrError> {
+        match register.0 < self.function.registers {
+            true => Ok(()),
+            false => Err(IrError::UndefinedRegister {
+                function: self.function.name.clone(),
+                register,
+            }),
+        }
+    }
+
+    fn check_function(&self, callee: &str, arguments: Option<usize>) -> Result<(), IrError> {
+        let Some(&expected) = self.parameters.get(callee) else {
+            return Err(IrError::UndefinedFunction {
+                function: self.function.name.clone(),
+                callee: callee.to_string(),
+            });
+        };
+
+        // closures are given their captures first, so they may take more
+        match arguments {
+            Some(found) if found != expected => Err(IrError::ArgumentCount {
+                function: self.function.name.clone(),
+                callee: callee.to_string(),
+                expected,
+                found,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_operand(&self, operand: &Operand) -> Result<(), IrError> {
+        match operand {
+            Operand::Register(register) => self.check_register(*register),
+            Operand::Constant(Constant::Function(name)) => self.check_function(name, None),
+            Operand::Constant(_) => Ok(()),
+        }
+    }
+
+    fn check_instruction(&self, instruction: &Instruction) -> Result<(), IrError> {
+        if let Some(dest) = instruction.dest() {
+            self.check_register(dest)?;
+        }
+        for operand in instruction.operands() {
+            self.check_operand(operand)?;
+        }
+
+        match instruction {
+            Instruction::Call {
+                callee: Callee::Function(callee),
+                arguments,
+                ..
+            } => self.check_function(callee, Some(arguments.len())),
+            Instruction::Closure {
+                function, captures, ..
+            } => {
+                self.check_function(function, None)?;
+                match self.parameters[function.as_str()] >= captures.len() {
+                    true => Ok(()),
+                    false => Err(IrError::ArgumentCount {
+                        function: self.function.name.clone(),
+                        callee: function.clone(),
+                        expected: self.parameters[function.as_str()],
+                        found: captures.len(),
+                    }),
+                }
+            }
+            Instruction::Global { name, .. } if !self.module.globals.contains(name) => {
+                Err(IrError::UndefinedGlobal {
+                    function: self.function.name.clone(),
+                    global: name.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that every register read is assigned on every path leading to the read, by
+    /// finding the registers assigned on all paths into each block.
+    fn check_definitions(&self) -> Result<(), IrError> {
+        let function = self.function;
+        let predecessors = function.predecessors();
+        let entry: HashSet<Register> = function.parameters.iter().copied().collect();
+
+        // `None` stands for every register, for blocks not reached so far
+        let mut assigned_out: Vec<Option<HashSet<Register>>> = vec![None; function.blocks.len()];
+        let assigned_in = |block: usize, assigned_out: &[Option<HashSet<Register>>]| {
+            if block == 0 {
+                return Some(entry.clone());
+            }
+            predecessors[block]
+                .iter()
+                .filter_map(|predecessor| assigned_out[predecessor.0].as_ref())
+                .fold(
+                    None,
+                    |assigned: Option<HashSet<Register>>, out| match assigned {
+                        Some(assigned) => Some(assigned.intersection(out).copied().collect()),
+                        None => Some(out.clone()),
+                    },
+                )
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (id, block) in function.blocks.iter().enumerate() {
+                let Some(mut assigned) = assigned_in(id, &assigned_out) else {
+                    continue;
+                };
+                assigned.extend(block.instructions.iter().filter_map(Instruction::dest));
+                if assigned_out[id].as_ref() != Some(&assigned) {
+                    assigned_out[id] = Some(assigned);
+                    changed = true;
+                }
+            }
+        }
+
+        for (id, block) in function.blocks.iter().enumerate() {
+            // unreachable blocks are never run
+            let Some(mut assigned) = assigned_in(id, &assigned_out) else {
+                continue;
+            };
+            // phis read at the end of the block control comes from
+            for instruction in &block.instructions {
+                let Instruction::Phi { incoming, .. } = instruction else {
+                    continue;
+                };
+                for (from, operand) in incoming {
+                    let Some(out) = &assigned_out[from.0] else {
+                        continue;
+                    };
+                    if let Operand::Register(register) = operand {
+                        if !out.contains(register) {
+                            return Err(IrError::UseBeforeDefinition {
+                                function: function.name.clone(),
+                                block: *from,
+                                register: *register,
+                            });
+                        }
+                    }
+                }
+            }
+
+            let reads = block
+                .instructions
+                .iter()
+                .filter(|instruction| !instruction.is_phi())
+                .map(|instruction| (instruction.operands(), instruction.dest()))
+                .chain(std::iter::once((block.terminator.operands(), None)));
+            assigned.extend(
+                block
+                    .instructions
+                    .iter()
+                    .filter(|instruction| instruction.is_phi())
+                    .filter_map(Instruction::dest),
+            );
+            for (operands, dest) in reads {
+                for operand in operands {
+                    if let Operand::Register(register) = operand {
+                        if !assigned.contains(register) {
+                            return Err(IrError::UseBeforeDefinition {
+                                function: function.name.clone(),
+                                block: BlockId(id),
+                                register: *register,
+                            });
+                        }
+                    }
+                }
+                assigned.extend(dest);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that a well formed module is in SSA form: every register is assigned once, phis come
+/// first in their blocks with a value for every predecessor, and every read is dominated by the
+/// assignment of the register read.
+pub fn verify_ssa(module: &Module) -> Result<(), IrError> {
+    verify(module)?;
+    module.functions.iter().try_for_each(verify_function_ssa)
+}
+
+fn verify_function_ssa(function: &Function) -> Result<(), IrError> {
+    let dominators = Dominators::new(function);
+    let predecessors = function.predecessors();
+
+    // where every register is assigned, as a block and a position in it
+    let mut definitions: HashMap<Register, (BlockId, usize)> = HashMap::new();
+    let mut define =
+        |register: Register, at: (BlockId, usize)| match definitions.insert(register, at) {
+            Some(_) => Err(IrError::MultipleDefinitions {
+                function: function.name.clone(),
+                register,
+            }),
+            None => Ok(()),
+        };
+    for &parameter in &function.parameters {
+        define(parameter, (BlockId(0), 0))?;
+    }
+    for (id, block) in function.blocks.iter().enumerate() {
+        for (position, instruction) in block.instructions.iter().enumerate() {
+            if let Some(dest) = instruction.dest() {
+                define(dest, (BlockId(id), position))?;
+            }
+        }
+    }
+
+    // whether the assignment of a register happens before the given point
+    let dominated = |register: Register, block: BlockId, position: usize| {
+        let Some(&(defined, at)) = definitions.get(&register) else {
+            return false;
+        };
+        match defined == block {
+            true => at < position || function.parameters.contains(&register),
+            false => dominators.dominates(defined, block),
+        }
+    };
+    let undominated = |block: BlockId, register: Register| IrError::UndominatedUse {
+        function: function.name.clone(),
+        block,
+        register,
+    };
+
+    for (id, block) in function.blocks.iter().enumerate() {
+        let block_id = BlockId(id);
+        if !dominators.is_reachable(block_id) {
+            continue;
+        }
+
+        let phis = block
+            .instructions
+            .iter()
+            .take_while(|instruction| instruction.is_phi())
+            .count();
+        if block.instructions[phis..].iter().any(Instruction::is_phi) {
+            return Err(IrError::MisplacedPhi {
+                function: function.name.clone(),
+                block: block_id,
+            });
+        }
+
+        let mut expected: Vec<BlockId> = predecessors[id].clone();
+        expected.sort();
+        expected.dedup();
+        for instruction in &block.instructions[..phis] {
+            let Instruction::Phi { incoming, .. } = instruction else {
+                unreachable!("phis come first");
+            };
+            let mut found: Vec<BlockId> = incoming.iter().map(|(from, _)| *from).collect();
+            found.sort();
+            if found != expected {
+                return Err(IrError::PhiPredecessors {
+                    function: function.name.clone(),
+                    block: block_id,
+                });
+            }
+            for (from, operand) in incoming {
+                if let Operand::Register(register) = operand {
+                    let end = function.blocks[from.0].instructions.len();
+                    if !dominated(*register, *from, end) {
+                        return Err(undominated(*from, *register));
+                    }
+                }
+            }
+        }
+
+        let reads = block
+            .instructions
+            .iter()
+            .enumerate()
+            .skip(phis)
+            .flat_map(|(position, instruction)| {
+                instruction
+                    .operands()
+                    .into_iter()
+                    .map(move |operand| (position, operand))
+            })
+            .chain(
+                block
+                    .terminator
+                    .operands()
+                    .into_iter()
+                    .map(|operand| (block.instructions.len(), operand)),
+            );
+        for (position, operand) in reads {
+            if let Operand::Register(register) = operand {
+                if !dominated(*register, block_id, position) {
+                    return Err(undominated(block_id, *register));
+                }
+            }
+        }
+    }
+    Ok(())
+}
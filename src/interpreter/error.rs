@@ -0,0 +1,106 @@
+use colored::Colorize;
+
+use crate::{
+    analysis::semantic::error::write_stack,
+    structures::parse_tree::{BinaryOperator, Span},
+};
+
+pub enum RuntimeError {
+    MissingMain,
+    InvalidMain,
+    DivisionByZero {
+        span: Span,
+    },
+    IntegerOverflow {
+        operator: BinaryOperator,
+        span: Span,
+    },
+    IndexOutOfBounds {
+        index: usize,
+        length: usize,
+        span: Span,
+    },
+    StackOverflow {
+        limit: usize,
+        span: Span,
+    },
+    DanglingPointer,
+    /// An error along with the calls that led to it, innermost first.
+    Failure {
+        error: Box<RuntimeError>,
+        stack: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::MissingMain => {
+                write!(f, "{}", "no function 'main' to run".bold().red())
+            }
+            RuntimeError::InvalidMain => {
+                write!(
+                    f,
+                    "{} '{}'",
+                    "'main' has to be declared as".bold().red(),
+                    "fn main(): nil".bold()
+                )
+            }
+            RuntimeError::DivisionByZero { span } => {
+                write!(f, "{} at {}", "division by zero".bold().red(), span)
+            }
+            RuntimeError::IntegerOverflow { operator, span } => {
+                write!(
+                    f,
+                    "{} in '{}' at {}",
+                    "integer overflow".bold().red(),
+                    operator.to_string().bold(),
+                    span
+                )
+            }
+            RuntimeError::IndexOutOfBounds {
+                index,
+                length,
+                span,
+            } => {
+                write!(
+                    f,
+                    "{} {} {} {} at {}",
+                    "index".bold().red(),
+                    index.to_string().bold(),
+                    "is out of bounds for length".bold().red(),
+                    length.to_string().bold(),
+                    span
+                )
+            }
+            RuntimeError::StackOverflow { limit, span } => {
+                write!(
+                    f,
+                    "{} {} {} at {}",
+                    "exceeded the limit of".bold().red(),
+                    limit.to_string().bold(),
+                    "nested calls".bold().red(),
+                    span
+                )
+            }
+            RuntimeError::DanglingPointer => {
+                write!(
+                    f,
+                    "{}",
+                    "used a pointer to a variable which no longer exists"
+                        .bold()
+                        .red()
+                )
+            }
+            RuntimeError::Failure { error, stack } => {
+                write!(f, "{}", error)?;
+                write_stack(f, stack.iter())
+            }
+        }
+    }
+}
+
+pub fn err_msg(error: RuntimeError) -> String {
+    let prefix = "Failed while running the program:";
+    format!("{} {}", prefix, error)
+}
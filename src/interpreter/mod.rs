@@ -0,0 +1,774 @@
+// INTERPRETATION //
+
+use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+use crate::{
+    analysis::semantic::{
+        error::SemanticError,
+        folding::{cast_number, fold_binary, fold_literal, integer, unsigned_value},
+        patterns::bindings,
+    },
+    structures::parse_tree::{
+        BinaryOperator, Expression, If, NumericType, Param, Pattern, Program, Span, Statement,
+        TupleElement, Type, TypeDefinition,
+    },
+};
+
+pub mod error;
+pub mod repl;
+pub mod value;
+use error::{err_msg, RuntimeError};
+use value::{Captured, Closure, Field, Function, Functions, Pointer, Value};
+
+#[cfg(test)]
+mod tests;
+
+/// The number of nested calls a program may make before it is stopped.
+pub const DEPTH_LIMIT: usize = 10_000;
+/// The stack a call takes, measured on a recursive function nesting its call in an `if` and a
+/// few operators, which takes about 33 KiB unoptimized and 4 KiB optimized, with room to spare.
+const CALL_STACK: usize = if cfg!(debug_assertions) {
+    64 * 1024
+} else {
+    8 * 1024
+};
+/// The stack size of the thread running programs, which fits `DEPTH_LIMIT` nested calls.
+const STACK_SIZE: usize = DEPTH_LIMIT * CALL_STACK;
+
+thread_local! {
+    /// The lowest address calls may start at on a thread made by `with_stack`, past which the
+    /// program is stopped rather than overflow the stack with calls taking more than usual.
+    static STACK_END: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Runs the `main` function of a validated program.
+pub fn run(program: &Program) {
+    if let Err(e) = execute(program) {
+        println!("{}", err_msg(e));
+        std::process::exit(1);
+    }
+}
+
+/// Initialises the globals of a validated program and then runs its `main` function.
+pub fn execute(program: &Program) -> Result<(), RuntimeError> {
+    with_stack(|| {
+        let mut interpreter = Interpreter::new();
+        let result = interpreter
+            .load(program)
+            .and_then(|_| interpreter.run_main(program));
+        result.map_err(|error| interpreter.failure(error))
+    })
+}
+
+/// Runs `f` on a thread with a stack large enough for deeply recursive programs.
+pub fn with_stack<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, || {
+                let start = stack_address();
+                STACK_END.with(|end| end.set(Some(start - STACK_SIZE + 4 * CALL_STACK)));
+                f()
+            })
+            .expect("failed to spawn the interpreter")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+/// The address of the top of the stack, which grows downwards.
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::ptr::addr_of!(marker) as usize
+}
+
+/// Whether the stack is too full for another call.
+fn stack_exhausted() -> bool {
+    STACK_END.with(|end| end.get().is_some_and(|end| stack_address() < end))
+}
+
+/// A function being run, with the scopes of its variables, innermost last.
+#[derive(Clone)]
+struct Call {
+    name: String,
+    /// Where the function was called from.
+    span: Span,
+    scopes: Vec<HashMap<String, usize>>,
+    /// The functions the body of the function may call.
+    functions: Rc<Functions>,
+}
+
+/// A tree-walking interpreter over validated programs.
+///
+/// Variables live in slots, which pointers and closures capturing by reference refer to. The
+/// slots of a scope are freed once it is left, and start a new generation when reused. Like
+/// scopes, calls are only left on success, so the call stack is still intact when an error is
+/// returned.
+#[derive(Clone)]
+pub struct Interpreter {
+    slots: Vec<Value>,
+    /// How many times each slot has been taken, kept for freed slots too.
+    generations: Vec<usize>,
+    /// The calls being run, innermost last. The first one runs the top level of the program,
+    /// whose outermost scope holds the globals.
+    calls: Vec<Call>,
+    /// The arguments of the tail call just made, with which the innermost call runs its
+    /// function again instead of nesting another call.
+    tail: Option<Vec<Value>>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter {
+            slots: vec![],
+            generations: vec![],
+            calls: vec![Call {
+                name: "top level".to_string(),
+                span: Span::default(),
+                scopes: vec![HashMap::new()],
+                functions: Rc::new(Functions::default()),
+            }],
+            tail: None,
+        }
+    }
+
+    /// Declares the functions of a program and runs its top level, declaring its globals.
+    pub fn load(&mut self, program: &Program) -> Result<(), RuntimeError> {
+        self.declare_functions(&program.statements);
+        for statement in &program.statements {
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    /// Evaluates an expression at the top level of the program.
+    pub fn evaluate(&mut self, expression: &Expression) -> Result<Value, RuntimeError> {
+        self.expression(expression)
+    }
+
+    /// Runs `fn main(): nil`.
+    pub fn run_main(&mut self, program: &Program) -> Result<(), RuntimeError> {
+        let main = program
+            .statements
+            .iter()
+            .find_map(|statement| match statement {
+                Statement::FuncDeclaration {
+                    name,
+                    parameters,
+                    return_type,
+                    ..
+                } if name == "main" => Some(parameters.is_empty() && *return_type == Type::Nil),
+                _ => None,
+            });
+        match main {
+            Some(true) => {}
+            Some(false) => return Err(RuntimeError::InvalidMain),
+            None => return Err(RuntimeError::MissingMain),
+        }
+
+        let main = self.function("main").expect("main is declared");
+        self.call(&main, vec![], Span::default())?;
+        Ok(())
+    }
+
+    /// Attaches the calls that led to an error to it.
+    pub fn failure(&self, error: RuntimeError) -> RuntimeError {
+        let stack = self.calls[1..]
+            .iter()
+            .rev()
+            .map(|call| match call.span == Span::default() {
+                true => call.name.clone(),
+                false => format!("{}, called at {}", call.name, call.span),
+            })
+            .collect();
+        RuntimeError::Failure {
+            error: Box::new(error),
+            stack,
+        }
+    }
+
+    fn call_frame(&mut self) -> &mut Call {
+        self.calls.last_mut().expect("there is always a call")
+    }
+
+    /// The slot of a variable, which is either local to the current call or global.
+    fn slot(&self, name: &str) -> Option<usize> {
+        let call = self.calls.last().expect("there is always a call");
+        call.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.calls[0].scopes[0].get(name))
+            .copied()
+    }
+
+    fn function(&self, name: &str) -> Option<Closure> {
+        let call = self.calls.last().expect("there is always a call");
+        call.functions.lookup(name)
+    }
+
+    /// Puts a value in the next free slot, starting a new generation of it.
+    fn allocate(&mut self, value: Value) -> usize {
+        let slot = self.slots.len();
+        self.slots.push(value);
+        match self.generations.get_mut(slot) {
+            Some(generation) => *generation += 1,
+            None => self.generations.push(0),
+        }
+        slot
+    }
+
+    fn declare(&mut self, name: &str, value: Value) {
+        let slot = self.allocate(value);
+        let scope = self.call_frame().scopes.last_mut();
+        scope
+            .expect("there is always a scope")
+            .insert(name.to_string(), slot);
+    }
+
+    /// Makes the functions and methods declared in a list of statements callable, returning
+    /// the functions which were callable before if there are any.
+    fn declare_functions(&mut self, statements: &[Statement]) -> Option<Rc<Functions>> {
+        let mut declared = HashMap::new();
+        for statement in statements {
+            let methods = match statement {
+                Statement::TypeDeclaration {
+                    definition:
+                        TypeDefinition::Struct { methods, .. } | TypeDefinition::Enum { methods, .. },
+                    ..
+                } => methods.as_slice(),
+                function => std::slice::from_ref(function),
+            };
+            for method in methods {
+                if let Statement::FuncDeclaration {
+                    name,
+                    parameters,
+                    body,
+                    ..
+                } = method
+                {
+                    let function = Function {
+                        name: name.clone(),
+                        parameters: parameter_names(parameters),
+                        body: body.clone(),
+                        captures: vec![],
+                    };
+                    declared.insert(name.clone(), Rc::new(function));
+                }
+            }
+        }
+
+        if declared.is_empty() {
+            return None;
+        }
+        let call = self.call_frame();
+        let outer = call.functions.clone();
+        call.functions = Rc::new(Functions {
+            declared,
+            parent: Some(outer.clone()),
+        });
+        Some(outer)
+    }
+
+    fn block(
+        &mut self,
+        statements: &[Statement],
+        return_value: &Expression,
+    ) -> Result<Value, RuntimeError> {
+        let base = self.slots.len();
+        self.call_frame().scopes.push(HashMap::new());
+        let outer = self.declare_functions(statements);
+
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        let value = self.expression(return_value)?;
+
+        if let Some(outer) = outer {
+            self.call_frame().functions = outer;
+        }
+        self.call_frame().scopes.pop();
+        self.slots.truncate(base);
+        Ok(value)
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), RuntimeError> {
+        match statement {
+            Statement::Declaration { name, value, .. } => {
+                let value = self.expression(value)?;
+                self.declare(name, value);
+            }
+            Statement::Destructuring { names, value, .. } => {
+                let Value::Tuple(fields) = self.expression(value)? else {
+                    unreachable!("only tuples are destructured")
+                };
+                for (name, field) in names.iter().zip(fields) {
+                    self.declare(name, field.value);
+                }
+            }
+            Statement::ReAssignment { name, value, .. } => {
+                let value = self.expression(value)?;
+                let slot = self.slot(name).expect("assigned variables are declared");
+                self.slots[slot] = value;
+            }
+            Statement::PlaceAssignment { target, value } => {
+                let value = self.expression(value)?;
+                let place = self.place(target)?;
+                *self.place_mut(&place)? = value;
+            }
+            Statement::ExpressionStatement(expression) => {
+                self.expression(expression)?;
+            }
+            Statement::IfStatement(if_statement) => {
+                self.if_expression(if_statement)?;
+            }
+            // functions are declared when entering the block, types don't do anything at runtime
+            Statement::FuncDeclaration { .. } | Statement::TypeDeclaration { .. } => {}
+            Statement::Import { .. } => unreachable!("imports are resolved by the module loader"),
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<Value, RuntimeError> {
+        if let Some((operator, left, right)) = expression.as_binary() {
+            let left = self.expression(left)?;
+            let right = self.expression(right)?;
+            return binary(operator, left, right, expression.span());
+        }
+
+        match expression {
+            Expression::NumberLiteral(literal) => match fold_literal(literal) {
+                Ok(Expression::Number(number)) => Ok(Value::Number(number)),
+                _ => unreachable!("the validator checks literals"),
+            },
+            Expression::Number(number) => Ok(Value::Number(number.clone())),
+            Expression::Boolean(boolean) => Ok(Value::Boolean(*boolean)),
+            Expression::Nil => Ok(Value::Nil),
+            Expression::Identifier { name, .. } => match self.slot(name) {
+                Some(slot) => Ok(self.slots[slot].clone()),
+                None => Ok(Value::Function(
+                    self.function(name).expect("the validator checks names"),
+                )),
+            },
+            Expression::Cast { value, datatype } => match (self.expression(value)?, datatype) {
+                (Value::Number(number), Type::Numeric(to)) => {
+                    Ok(Value::Number(cast_number(&number, *to)))
+                }
+                (Value::Boolean(boolean), Type::Numeric(to)) => Ok(Value::Number(
+                    integer(*to, boolean as i128).expect("booleans fit any integer"),
+                )),
+                (value, _) => Ok(value),
+            },
+            Expression::Default { value, fallback } => match self.expression(value)? {
+                Value::Nil => self.expression(fallback),
+                value => Ok(value),
+            },
+            Expression::Tuple(elements) => Ok(Value::Tuple(self.fields(elements)?)),
+            Expression::StructLiteral { name, fields } => Ok(Value::Struct {
+                name: name.clone().expect("the validator names struct literals"),
+                fields: self.fields(fields)?,
+            }),
+            Expression::Array(elements) => elements
+                .iter()
+                .map(|element| self.expression(element))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            Expression::Index {
+                value,
+                index,
+                bounds_check,
+                span,
+            } => {
+                let Value::Array(mut elements) = self.expression(value)? else {
+                    unreachable!("only arrays are indexed")
+                };
+                let index = self.index(index)?;
+                if *bounds_check {
+                    check_bounds(index, elements.len(), *span)?;
+                }
+                Ok(elements.swap_remove(index))
+            }
+            Expression::FieldAccess { value, field } => match self.expression(value)? {
+                Value::Array(elements) => Ok(Value::Number(
+                    integer(NumericType::USize, elements.len() as i128).expect("lengths fit usize"),
+                )),
+                Value::Tuple(mut fields) | Value::Struct { mut fields, .. } => {
+                    Ok(fields.swap_remove(field_index(field)).value)
+                }
+                _ => unreachable!("fields are only accessed on tuples, structs and arrays"),
+            },
+            Expression::AddressOf(place) => Ok(Value::Pointer(self.place(place)?)),
+            Expression::Deref(pointer) => {
+                let Value::Pointer(pointer) = self.expression(pointer)? else {
+                    unreachable!("only pointers are dereferenced")
+                };
+                self.place_ref(&pointer).cloned()
+            }
+            Expression::FunctionCall {
+                name,
+                arguments,
+                span,
+                tail,
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.expression(argument))
+                    .collect::<Result<Vec<_>, _>>()?;
+                // nothing is done after a tail call but returning, up to the call running
+                if *tail {
+                    self.tail = Some(arguments);
+                    return Ok(Value::Nil);
+                }
+
+                let callee = match self.slot(name) {
+                    Some(slot) => match &self.slots[slot] {
+                        Value::Function(closure) => closure.clone(),
+                        _ => unreachable!("only functions are called"),
+                    },
+                    None => self.function(name).expect("the validator checks calls"),
+                };
+                self.call(&callee, arguments, *span)
+            }
+            Expression::Block {
+                statements,
+                return_value,
+            } => self.block(statements, return_value),
+            Expression::Function {
+                parameters,
+                body,
+                captures,
+                ..
+            } => {
+                let captures = captures
+                    .iter()
+                    .map(|capture| {
+                        let slot = self.slot(&capture.name).expect("captures are declared");
+                        let captured = match capture.by_reference {
+                            true => Captured::Reference(slot),
+                            false => Captured::Value(self.slots[slot].clone()),
+                        };
+                        (capture.name.clone(), captured)
+                    })
+                    .collect();
+                let function = Function {
+                    name: "closure".to_string(),
+                    parameters: parameter_names(parameters),
+                    body: body.clone(),
+                    captures,
+                };
+                Ok(Value::Function(Closure {
+                    function: Rc::new(function),
+                    functions: self.call_frame().functions.clone(),
+                }))
+            }
+            Expression::If(if_expression) => self.if_expression(if_expression),
+            Expression::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => Ok(Value::Variant {
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                payload: match payload {
+                    Some(payload) => Some(Box::new(self.expression(payload)?)),
+                    None => None,
+                },
+            }),
+            Expression::Match(match_expression) => {
+                let value = self.expression(&match_expression.value)?;
+                for arm in &match_expression.arms {
+                    let Some(values) = matches(&arm.pattern, &value) else {
+                        continue;
+                    };
+
+                    let base = self.slots.len();
+                    self.call_frame().scopes.push(HashMap::new());
+                    for (name, value) in bindings(&arm.pattern).into_iter().zip(values) {
+                        self.declare(name, value);
+                    }
+                    let value = self.expression(&arm.value)?;
+                    self.call_frame().scopes.pop();
+                    self.slots.truncate(base);
+                    return Ok(value);
+                }
+                unreachable!("matches are exhaustive")
+            }
+            Expression::Comptime(block) => self.block(&block.statements, &block.return_value),
+            Expression::Type(_) => unreachable!("types only exist at compile time"),
+            _ => unreachable!("method calls are lowered, binary operations are handled above"),
+        }
+    }
+
+    fn fields(&mut self, elements: &[TupleElement]) -> Result<Vec<Field>, RuntimeError> {
+        elements
+            .iter()
+            .map(|element| {
+                Ok(Field {
+                    name: element.name.clone(),
+                    value: self.expression(&element.value)?,
+                })
+            })
+            .collect()
+    }
+
+    fn index(&mut self, index: &Expression) -> Result<usize, RuntimeError> {
+        match self.expression(index)? {
+            Value::Number(number) => Ok(unsigned_value(&number)
+                .and_then(|index| usize::try_from(index).ok())
+                .expect("indices are converted to usize")),
+            _ => unreachable!("indices are numbers"),
+        }
+    }
+
+    fn if_expression(&mut self, if_expression: &If) -> Result<Value, RuntimeError> {
+        let branches =
+            std::iter::once(&if_expression.if_block).chain(&if_expression.else_if_blocks);
+        for branch in branches {
+            if let Value::Boolean(true) = self.expression(&branch.condition)? {
+                return self.block(&branch.block.statements, &branch.block.return_value);
+            }
+        }
+        match &if_expression.else_block {
+            Some(block) => self.block(&block.statements, &block.return_value),
+            None => Ok(Value::Nil),
+        }
+    }
+
+    /// Finds the variable, element or field a place refers to, checking indices on the way.
+    fn place(&mut self, place: &Expression) -> Result<Pointer, RuntimeError> {
+        match place {
+            Expression::Identifier { name, .. } => {
+                let slot = self.slot(name).expect("the validator checks places");
+                Ok(Pointer {
+                    slot,
+                    generation: self.generations[slot],
+                    path: vec![],
+                })
+            }
+            Expression::Index {
+                value,
+                index,
+                bounds_check,
+                span,
+            } => {
+                let mut pointer = self.place(value)?;
+                let index = self.index(index)?;
+                if *bounds_check {
+                    let Value::Array(elements) = self.place_ref(&pointer)? else {
+                        unreachable!("only arrays are indexed")
+                    };
+                    check_bounds(index, elements.len(), *span)?;
+                }
+                pointer.path.push(index);
+                Ok(pointer)
+            }
+            Expression::FieldAccess { value, field } => {
+                let mut pointer = self.place(value)?;
+                pointer.path.push(field_index(field));
+                Ok(pointer)
+            }
+            Expression::Deref(pointer) => match self.expression(pointer)? {
+                Value::Pointer(pointer) => Ok(pointer),
+                _ => unreachable!("only pointers are dereferenced"),
+            },
+            _ => unreachable!("the validator checks places"),
+        }
+    }
+
+    fn place_ref(&self, pointer: &Pointer) -> Result<&Value, RuntimeError> {
+        let mut value = self
+            .slots
+            .get(pointer.slot)
+            .filter(|_| self.generations[pointer.slot] == pointer.generation)
+            .ok_or(RuntimeError::DanglingPointer)?;
+        for &step in &pointer.path {
+            value = match value {
+                Value::Array(elements) => elements.get(step),
+                Value::Tuple(fields) | Value::Struct { fields, .. } => {
+                    fields.get(step).map(|field| &field.value)
+                }
+                _ => None,
+            }
+            .ok_or(RuntimeError::DanglingPointer)?;
+        }
+        Ok(value)
+    }
+
+    fn place_mut(&mut self, pointer: &Pointer) -> Result<&mut Value, RuntimeError> {
+        let mut value = self
+            .slots
+            .get_mut(pointer.slot)
+            .filter(|_| self.generations[pointer.slot] == pointer.generation)
+            .ok_or(RuntimeError::DanglingPointer)?;
+        for &step in &pointer.path {
+            value = match value {
+                Value::Array(elements) => elements.get_mut(step),
+                Value::Tuple(fields) | Value::Struct { fields, .. } => {
+                    fields.get_mut(step).map(|field| &mut field.value)
+                }
+                _ => None,
+            }
+            .ok_or(RuntimeError::DanglingPointer)?;
+        }
+        Ok(value)
+    }
+
+    /// Calls a function from `span` with already evaluated arguments.
+    fn call(
+        &mut self,
+        closure: &Closure,
+        arguments: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        if self.calls.len() > DEPTH_LIMIT {
+            return Err(RuntimeError::StackOverflow {
+                limit: DEPTH_LIMIT,
+                span,
+            });
+        }
+        // calls nesting many blocks and operators may fill the stack before reaching the limit
+        if stack_exhausted() {
+            return Err(RuntimeError::StackOverflow {
+                limit: self.calls.len() - 1,
+                span,
+            });
+        }
+
+        let base = self.slots.len();
+        let function = &closure.function;
+        let scope = self.bind_arguments(function, arguments);
+        self.calls.push(Call {
+            name: function.name.clone(),
+            span,
+            scopes: vec![scope],
+            functions: closure.functions.clone(),
+        });
+        // tail calls run the function again in place of the call which made them
+        let value = loop {
+            let value = self.block(&function.body.statements, &function.body.return_value)?;
+            let Some(arguments) = self.tail.take() else {
+                break value;
+            };
+            self.slots.truncate(base);
+            let scope = self.bind_arguments(function, arguments);
+            self.call_frame().scopes = vec![scope];
+        };
+        self.calls.pop();
+        self.slots.truncate(base);
+        Ok(value)
+    }
+
+    /// Puts the captures of a function and its arguments in slots, returning the scope of its
+    /// body which they are declared in.
+    fn bind_arguments(
+        &mut self,
+        function: &Function,
+        arguments: Vec<Value>,
+    ) -> HashMap<String, usize> {
+        let mut scope = HashMap::new();
+        for (name, captured) in &function.captures {
+            let slot = match captured {
+                Captured::Value(value) => self.allocate(value.clone()),
+                Captured::Reference(slot) => *slot,
+            };
+            scope.insert(name.clone(), slot);
+        }
+        for (name, argument) in function.parameters.iter().zip(arguments) {
+            let slot = self.allocate(argument);
+            scope.insert(name.clone(), slot);
+        }
+        scope
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parameter_names(parameters: &[Param]) -> Vec<String> {
+    parameters
+        .iter()
+        .map(|Param::Parameter { name, .. }| name.clone())
+        .collect()
+}
+
+fn field_index(field: &str) -> usize {
+    field
+        .parse()
+        .expect("the validator turns fields into positions")
+}
+
+fn check_bounds(index: usize, length: usize, span: Span) -> Result<(), RuntimeError> {
+    match index < length {
+        true => Ok(()),
+        false => Err(RuntimeError::IndexOutOfBounds {
+            index,
+            length,
+            span,
+        }),
+    }
+}
+
+/// Applies a binary operator by folding its operands, which are constants at runtime.
+fn binary(
+    operator: BinaryOperator,
+    left: Value,
+    right: Value,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let operand = |value| match value {
+        Value::Number(number) => Expression::Number(number),
+        Value::Boolean(boolean) => Expression::Boolean(boolean),
+        Value::Nil => Expression::Nil,
+        // other values are only ever compared with nil, which they never equal
+        _ => Expression::Boolean(true),
+    };
+
+    match fold_binary(operator, &operand(left), &operand(right), span) {
+        Ok(Some(Expression::Number(number))) => Ok(Value::Number(number)),
+        Ok(Some(Expression::Boolean(boolean))) => Ok(Value::Boolean(boolean)),
+        Err(SemanticError::DivisionByZero { span }) => Err(RuntimeError::DivisionByZero { span }),
+        Err(SemanticError::IntegerOverflow { operator, span }) => {
+            Err(RuntimeError::IntegerOverflow { operator, span })
+        }
+        _ => unreachable!("the validator checks the operands"),
+    }
+}
+
+/// The values a pattern binds if it matches a value, in the order of `bindings`.
+fn matches(pattern: &Pattern, value: &Value) -> Option<Vec<Value>> {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) => Some(vec![]),
+        (Pattern::Binding(_), value) => Some(vec![value.clone()]),
+        (Pattern::Literal(Expression::Number(literal)), Value::Number(number)) => {
+            (literal == number).then(Vec::new)
+        }
+        (Pattern::Literal(Expression::Boolean(literal)), Value::Boolean(boolean)) => {
+            (literal == boolean).then(Vec::new)
+        }
+        (Pattern::Literal(Expression::Nil), Value::Nil) => Some(vec![]),
+        (
+            Pattern::Variant {
+                variant, payload, ..
+            },
+            Value::Variant {
+                variant: actual,
+                payload: actual_payload,
+                ..
+            },
+        ) if variant == actual => match (payload, actual_payload) {
+            (Some(payload), Some(actual_payload)) => matches(payload, actual_payload),
+            _ => Some(vec![]),
+        },
+        (Pattern::Tuple(elements), Value::Tuple(fields)) => {
+            let mut values = vec![];
+            for (element, field) in elements.iter().zip(fields) {
+                values.extend(matches(element, &field.value)?);
+            }
+            Some(values)
+        }
+        _ => None,
+    }
+}
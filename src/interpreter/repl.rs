@@ -0,0 +1,141 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    analysis::{
+        lexical::{error::err_msg as lexing_err_msg, Lexer},
+        semantic::{error::err_msg as semantic_err_msg, Validator},
+        syntactic::parser::Parser,
+    },
+    structures::{
+        parse_tree::{Program, Span, Statement},
+        tokens::Token,
+    },
+};
+
+use super::{error::err_msg, with_stack, Interpreter};
+
+/// Reads declarations and expressions from the standard input until it ends, running them as
+/// if they were the top level of a program and printing the value of every expression.
+pub fn repl() {
+    with_stack(|| {
+        let mut session = Session::new();
+        let mut input = String::new();
+        loop {
+            prompt(if input.is_empty() { "> " } else { ". " });
+            let mut line = String::new();
+            match io::stdin().lock().read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => input.push_str(&line),
+            }
+
+            let tokens = match Lexer::new(&input).lex() {
+                Ok(tokens) => tokens,
+                Err(e) => {
+                    println!("{}", lexing_err_msg(e));
+                    input.clear();
+                    continue;
+                }
+            };
+            // blocks may span several lines, which are read until they are closed
+            if tokens.is_empty() || is_open(&tokens) {
+                if tokens.is_empty() {
+                    input.clear();
+                }
+                continue;
+            }
+            input.clear();
+
+            match session.input(tokens) {
+                Ok(Some(output)) => println!("{}", output),
+                Ok(None) => {}
+                Err(e) => println!("{}", e),
+            }
+        }
+        println!();
+    })
+}
+
+fn prompt(prompt: &str) {
+    print!("{}", prompt);
+    io::stdout()
+        .flush()
+        .expect("failed to write to the standard output");
+}
+
+/// Whether there are more opening braces, parentheses or brackets than closing ones.
+pub(super) fn is_open(tokens: &[(Token, Span)]) -> bool {
+    let depth = tokens.iter().fold(0, |depth, (token, _)| match token {
+        Token::OpenBrace | Token::OpenParen | Token::OpenBracket => depth + 1,
+        Token::CloseBrace | Token::CloseParen | Token::CloseBracket => depth - 1,
+        _ => depth,
+    });
+    depth > 0
+}
+
+/// Everything declared so far, which later inputs may use.
+pub(super) struct Session {
+    validator: Validator,
+    interpreter: Interpreter,
+}
+
+impl Session {
+    pub(super) fn new() -> Self {
+        Session {
+            validator: Validator::new(),
+            interpreter: Interpreter::new(),
+        }
+    }
+
+    /// Runs an input, which is either a single expression whose value and type are returned, or
+    /// statements. An input which fails leaves the session as it was before.
+    pub(super) fn input(&mut self, tokens: Vec<(Token, Span)>) -> Result<Option<String>, String> {
+        let mut validator = self.validator.clone();
+        let mut interpreter = self.interpreter.clone();
+
+        let output = match Parser::new(tokens.clone()).parse_lone_expression() {
+            Ok(expression) => {
+                let (instances, expression, datatype) = validator
+                    .validate_expression(&expression)
+                    .map_err(semantic_err_msg)?;
+                let program = Program {
+                    statements: instances,
+                };
+                let value = interpreter
+                    .load(&program)
+                    .and_then(|_| interpreter.evaluate(&expression))
+                    .map_err(|e| err_msg(interpreter.failure(e)))?;
+                Some(format!("{}: {}", value, datatype))
+            }
+            Err(expression_error) => {
+                let program = match Parser::new(tokens.clone()).parse_program() {
+                    Ok(program) => program,
+                    // input not ending like a statement was most likely meant as an expression
+                    Err(e) => {
+                        let e = match tokens.last() {
+                            Some((Token::SemiColon | Token::CloseBrace, _)) => e,
+                            _ => expression_error,
+                        };
+                        return Err(format!("Failed to parse the program: {e}"));
+                    }
+                };
+                let imports = |statement: &Statement| matches!(statement, Statement::Import { .. });
+                if program.statements.iter().any(imports) {
+                    return Err(
+                        "Failed to parse the program: modules can only be imported by files"
+                            .to_string(),
+                    );
+                }
+
+                let program = validator.validate(&program).map_err(semantic_err_msg)?;
+                interpreter
+                    .load(&program)
+                    .map_err(|e| err_msg(interpreter.failure(e)))?;
+                None
+            }
+        };
+
+        self.validator = validator;
+        self.interpreter = interpreter;
+        Ok(output)
+    }
+}
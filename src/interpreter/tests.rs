@@ -0,0 +1,327 @@
+use crate::{
+    analysis::{lexical::lex, semantic::Validator, syntactic::parser::Parser},
+    structures::parse_tree::{
+        BinaryOperator, Expression, Floating, Integer, Number, Program, Span, Unsigned,
+    },
+};
+
+use super::{
+    error::RuntimeError,
+    execute,
+    repl::{is_open, Session},
+    value::Value,
+    with_stack, Interpreter, DEPTH_LIMIT,
+};
+
+/// Validates a program, which must be valid.
+fn validate(source: &str) -> Program {
+    let program = Parser::new(lex(source))
+        .parse_program()
+        .expect("the program parses");
+    match Validator::new().validate(&program) {
+        Ok(program) => program,
+        Err(e) => panic!("the program is invalid: {}", e),
+    }
+}
+
+/// Validates a program, which must be valid, and runs its top level.
+fn load(source: &str) -> Interpreter {
+    let program = validate(source);
+    let mut interpreter = Interpreter::new();
+    if let Err(e) = interpreter.load(&program) {
+        panic!("the program fails to load: {}", e);
+    }
+    interpreter
+}
+
+fn u64(value: u64) -> Expression {
+    Expression::Number(Number::Int(Integer::Unsigned(Unsigned::UInt64(value))))
+}
+
+fn call(name: &str, arguments: Vec<Expression>) -> Expression {
+    Expression::FunctionCall {
+        name: name.to_string(),
+        arguments,
+        span: Span::default(),
+        tail: false,
+    }
+}
+
+const COUNTING: &str = "
+fn count(n: u64, total: u64): u64 {
+    if n == 0 { total } else { count(n - 1, total + 1) }
+}
+fn depth(n: u64): u64 {
+    if n == 0 { 0 } else { 1 + depth(n - 1) }
+}
+fn main(): nil {}
+";
+
+#[test]
+fn tail_calls_recurse_in_constant_stack() {
+    // a million nested calls would be far past the limit, and past the stack of the test
+    let mut interpreter = load(COUNTING);
+    match interpreter.evaluate(&call("count", vec![u64(1_000_000), u64(0)])) {
+        Ok(Value::Number(total)) => {
+            assert_eq!(
+                total,
+                Number::Int(Integer::Unsigned(Unsigned::UInt64(1_000_000)))
+            )
+        }
+        Ok(value) => panic!("counted to {:?}", value),
+        Err(e) => panic!("counting failed: {}", e),
+    }
+    assert_eq!(interpreter.calls.len(), 1);
+
+    // the call is not the value of the body when something is done with its result
+    let overflowed = with_stack(|| {
+        let mut interpreter = load(COUNTING);
+        let depth = interpreter.evaluate(&call("depth", vec![u64(1_000_000)]));
+        matches!(depth, Err(RuntimeError::StackOverflow { limit, .. }) if limit == DEPTH_LIMIT)
+    });
+    assert!(overflowed);
+}
+
+#[test]
+fn calls_filling_the_stack_are_stopped() {
+    // each call nests many operators, so the stack may run out before the depth limit
+    let deep = execute(&validate(
+        "
+fn deep(n: u64): u64 {
+    if n == 0 { 0 } else { 1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + deep(n - 1)))))))))))) }
+}
+fn main(): nil { const y: u64 = deep(1000000); }
+",
+    ));
+    assert!(matches!(
+        deep,
+        Err(RuntimeError::Failure { error, .. })
+            if matches!(*error, RuntimeError::StackOverflow { limit, .. } if limit <= DEPTH_LIMIT)
+    ));
+}
+
+#[test]
+fn functions_are_bound_to_function_typed_names() {
+    let mut interpreter = load(
+        "
+fn dbl(n: u64): u64 { n * 2 }
+fn mk(): (x: u64) -> u64 { dbl }
+fn f(): u64 {
+    const g: (x: u64) -> u64 = dbl;
+    const h: (x: u64) -> u64 = mk();
+    const i: (x: u64) -> u64 = x + 1;
+    i(g(3) + h(4))
+}
+",
+    );
+    match interpreter.evaluate(&call("f", vec![])) {
+        Ok(Value::Number(n)) => assert_eq!(n, Number::Int(Integer::Unsigned(Unsigned::UInt64(15)))),
+        Ok(value) => panic!("called to {:?}", value),
+        Err(e) => panic!("calling failed: {}", e),
+    }
+}
+
+#[test]
+fn payloads_are_matched_field_by_field() {
+    let mut interpreter = load(
+        "
+const Shape = enum { Dot, Circle(f64), Rect(w: f64, h: f64) };
+fn area(s: Shape): f64 {
+    match s { .Dot => 0.0, .Circle(r) => r * r, .Rect(1.0, h) => h, .Rect(w, h) => w * h }
+}
+fn f(): f64 { area(Shape.Rect(2.0, 3.0)) + area(Shape.Rect(1.0, 5.0)) }
+",
+    );
+    match interpreter.evaluate(&call("f", vec![])) {
+        Ok(Value::Number(area)) => assert_eq!(area, Number::Float(Floating::Double(11.0))),
+        Ok(value) => panic!("matched to {:?}", value),
+        Err(e) => panic!("matching failed: {}", e),
+    }
+}
+
+#[test]
+fn optionals_are_compared_by_value() {
+    let mut interpreter = load(
+        "
+fn set(p: (u64, u64)?): bool { p != nil }
+fn same(a: u64?, b: u64?): bool { a == b }
+fn bit(b: bool, value: u64): u64 { if b { value } else { 0 } }
+fn f(): u64 {
+    bit(set((1, 2)), 1) + bit(set(nil), 2) + bit(same(3, 3), 4) + bit(same(3, nil), 8)
+        + bit(same(nil, nil), 16) + bit(same(3, 4), 32)
+}
+",
+    );
+    match interpreter.evaluate(&call("f", vec![])) {
+        Ok(Value::Number(bits)) => {
+            assert_eq!(bits, Number::Int(Integer::Unsigned(Unsigned::UInt64(21))))
+        }
+        Ok(value) => panic!("compared to {:?}", value),
+        Err(e) => panic!("comparing failed: {}", e),
+    }
+}
+
+/// Runs a program which must fail, returning the error and the calls that led to it.
+fn failure(source: &str) -> (RuntimeError, Vec<String>) {
+    match execute(&validate(source)) {
+        Ok(()) => panic!("the program runs"),
+        Err(RuntimeError::Failure { error, stack }) => (*error, stack),
+        Err(e) => panic!("the error has no stack: {}", e),
+    }
+}
+
+#[test]
+fn indices_unknown_statically_are_checked_at_runtime() {
+    let (error, _) = failure(
+        "
+fn get(xs: []u64, i: usize): u64 { xs[i] }
+fn main(): nil { let xs: [3]u64 = [1, 2, 3]; const y: u64 = get(xs, 3); }
+",
+    );
+    assert!(
+        matches!(
+            error,
+            RuntimeError::IndexOutOfBounds { index: 3, length: 3, span } if span.line == 2
+        ),
+        "{}",
+        error
+    );
+    assert!(execute(&validate(
+        "
+fn get(xs: []u64, i: usize): u64 { xs[i] }
+fn main(): nil { let xs: [3]u64 = [1, 2, 3]; const y: u64 = get(xs, 2); }
+"
+    ))
+    .is_ok());
+}
+
+#[test]
+fn pointers_to_freed_variables_are_not_followed() {
+    // the variable's slot is taken by those of `g` before the pointer is read
+    let (error, stack) = failure(
+        "
+fn id(p: *u64): *u64 { p }
+fn f(): *u64 { let x: u64 = 1; id(&x) }
+fn g(n: u64): u64 { let a: u64 = n; a + n }
+fn main(): nil { const p: *u64 = f(); const z: u64 = g(5); const y: u64 = p.*; }
+",
+    );
+    assert!(matches!(error, RuntimeError::DanglingPointer), "{}", error);
+    assert_eq!(stack, ["main"]);
+}
+
+#[test]
+fn runtime_errors_are_located_with_their_calls() {
+    let (error, stack) = failure(
+        "
+fn divide(a: u64, b: u64): u64 { a / b }
+fn twice(a: u64, b: u64): u64 {
+    divide(a, b) * 2
+}
+fn main(): nil {
+    const x: u64 = twice(4, 0);
+}
+",
+    );
+    assert!(
+        matches!(error, RuntimeError::DivisionByZero { span } if span == Span { line: 2, column: 36 }),
+        "{}",
+        error
+    );
+    assert_eq!(
+        stack,
+        [
+            "divide, called at line 4, column 5",
+            "twice, called at line 7, column 20",
+            "main"
+        ]
+    );
+
+    let (error, stack) = failure(
+        "
+fn add(a: u8, b: u8): u8 { a + b }
+fn main(): nil { const x: u8 = add(200, 100); }
+",
+    );
+    assert!(
+        matches!(
+            error,
+            RuntimeError::IntegerOverflow { operator: BinaryOperator::Add, span }
+                if span == Span { line: 2, column: 30 }
+        ),
+        "{}",
+        error
+    );
+    assert_eq!(stack, ["add, called at line 3, column 32", "main"]);
+
+    assert!(matches!(
+        execute(&validate("fn f(): nil {}")),
+        Err(RuntimeError::Failure { error, .. }) if matches!(*error, RuntimeError::MissingMain)
+    ));
+    assert!(matches!(
+        execute(&validate("fn main(a: u64): nil {}")),
+        Err(RuntimeError::Failure { error, .. }) if matches!(*error, RuntimeError::InvalidMain)
+    ));
+}
+
+#[test]
+fn deep_runtime_stacks_are_shown_by_their_ends() {
+    let source = "
+fn depth(n: u64): u64 { if n == 0 { 1 / n } else { 1 + depth(n - 1) } }
+fn main(): nil { const x: u64 = depth(100); }
+";
+    let message = execute(&validate(source))
+        .expect_err("the program fails")
+        .to_string();
+    let calls: Vec<&str> = message
+        .lines()
+        .filter(|line| line.starts_with("    in "))
+        .collect();
+    assert_eq!(calls.len(), 10, "{}", message);
+    assert!(message.contains("\n    ... 92 more calls\n"), "{}", message);
+    assert_eq!(calls[9], "    in main", "{}", message);
+}
+
+/// Gives an input to a REPL session, returning what it prints.
+fn enter(session: &mut Session, input: &str) -> Result<Option<String>, String> {
+    session.input(lex(input))
+}
+
+#[test]
+fn repl_sessions_keep_what_was_declared() {
+    with_stack(|| {
+        let mut session = Session::new();
+        assert_eq!(enter(&mut session, "let a: u64 = 2;"), Ok(None));
+        assert_eq!(
+            enter(&mut session, "fn twice(n: u64): u64 { n * 2 }"),
+            Ok(None)
+        );
+        assert_eq!(
+            enter(&mut session, "twice(a) + 1"),
+            Ok(Some("5: u64".to_string()))
+        );
+        assert_eq!(enter(&mut session, "a = 10;"), Ok(None));
+        assert_eq!(
+            enter(&mut session, "twice(a)"),
+            Ok(Some("20: u64".to_string()))
+        );
+
+        // an input which fails leaves the session as it was
+        assert!(enter(&mut session, "let b: u64 = 1; let c: u64 = b / 0;").is_err());
+        assert!(enter(&mut session, "b").is_err());
+        assert!(enter(&mut session, "let a: u64 = 3;").is_err());
+        assert_eq!(enter(&mut session, "a"), Ok(Some("10: u64".to_string())));
+        assert!(enter(&mut session, "import \"a.hon\" as a;").is_err());
+    });
+}
+
+#[test]
+fn repl_inputs_are_read_until_their_brackets_close() {
+    let open = |input: &str| is_open(&lex(input));
+    assert!(open("fn f(): u64 {"));
+    assert!(open("fn f(): u64 { [1, (2"));
+    assert!(!open("fn f(): u64 { 1 }"));
+    assert!(!open("a"));
+    // too many closing brackets are left for the parser to complain about
+    assert!(!open("}"));
+}
@@ -0,0 +1,126 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::structures::parse_tree::{Block, Number};
+
+/// A value of a running program.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(Number),
+    Boolean(bool),
+    /// Nil, which is also the value of an empty optional.
+    Nil,
+    Tuple(Vec<Field>),
+    Struct {
+        name: String,
+        fields: Vec<Field>,
+    },
+    /// An array or a slice.
+    Array(Vec<Value>),
+    Variant {
+        enum_name: String,
+        variant: String,
+        payload: Option<Box<Value>>,
+    },
+    Pointer(Pointer),
+    Function(Closure),
+}
+
+/// A component of a tuple or a struct, named if it was declared with a name.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: Option<String>,
+    pub value: Value,
+}
+
+/// The location of a variable, or of an element or field of one by its position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pointer {
+    pub slot: usize,
+    /// The generation of the slot when the variable was in it, which tells whether the slot
+    /// has since been freed and reused.
+    pub generation: usize,
+    pub path: Vec<usize>,
+}
+
+/// A function along with the functions declared around it, which it may call.
+#[derive(Debug, Clone)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub functions: Rc<Functions>,
+}
+
+/// A declared function or an anonymous one, along with what it captured.
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: Block,
+    pub captures: Vec<(String, Captured)>,
+}
+
+/// The functions declared in a block, and through `parent` those declared around it.
+#[derive(Debug, Default)]
+pub struct Functions {
+    pub declared: HashMap<String, Rc<Function>>,
+    pub parent: Option<Rc<Functions>>,
+}
+
+impl Functions {
+    /// Finds a function by name, along with the functions it may call.
+    pub fn lookup(self: &Rc<Self>, name: &str) -> Option<Closure> {
+        let mut functions = self;
+        loop {
+            if let Some(function) = functions.declared.get(name) {
+                return Some(Closure {
+                    function: function.clone(),
+                    functions: functions.clone(),
+                });
+            }
+            functions = functions.parent.as_ref()?;
+        }
+    }
+}
+
+/// A variable captured by a closure, either copied or referred to by its slot.
+#[derive(Debug, Clone)]
+pub enum Captured {
+    Value(Value),
+    Reference(usize),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = |fields: &[Field]| {
+            fields
+                .iter()
+                .map(|field| match &field.name {
+                    Some(name) => format!("{} = {}", name, field.value),
+                    None => field.value.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match self {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Boolean(boolean) => write!(f, "{}", boolean),
+            Value::Nil => write!(f, "nil"),
+            Value::Tuple(elements) => write!(f, "({})", fields(elements)),
+            Value::Struct { name, fields: all } => write!(f, "{}.{{ {} }}", name, fields(all)),
+            Value::Array(values) => {
+                let values = values.iter().map(|value| value.to_string());
+                write!(f, "[{}]", values.collect::<Vec<_>>().join(", "))
+            }
+            Value::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => match payload {
+                Some(payload) => write!(f, "{}.{}({})", enum_name, variant, payload),
+                None => write!(f, "{}.{}", enum_name, variant),
+            },
+            Value::Pointer(pointer) => write!(f, "a pointer to slot {}", pointer.slot),
+            Value::Function(closure) => write!(f, "fn {}", closure.function.name),
+        }
+    }
+}
@@ -1,6 +1,13 @@
 pub mod analysis;
+pub mod codegen;
+pub mod interpreter;
 pub mod structures;
 
 pub mod prelude {
-    pub use crate::analysis::{lexical::lex, syntactic::parse, semantic::validate};
+    pub use crate::analysis::{lexical::lex, modules::link, semantic::validate, syntactic::parse};
+    pub use crate::codegen::{
+        destruct_ssa, generate, generate_ssa, optimize,
+        passes::{Pass, PassManager},
+    };
+    pub use crate::interpreter::{repl::repl, run};
 }
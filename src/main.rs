@@ -2,7 +2,7 @@ use std::fs::File;
 use std::io::prelude::*;
 
 use colored::Colorize;
-use structopt::StructOpt;
+use structopt::{clap, StructOpt};
 
 use honey::prelude::*;
 
@@ -14,7 +14,10 @@ use honey::prelude::*;
 struct Opt {
     /// The input file to compile.
     #[structopt()]
-    file: String,
+    file: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
 
     /// Prints a representation of all steps of the compilation (as specified by flags).
     #[structopt(short = "i", long = "info")]
@@ -35,12 +38,67 @@ struct Opt {
     /// Turns on semantic analysis (requires both lexical and syntactic).
     #[structopt(short = "v", long = "validate")]
     validate: bool,
+
+    /// Prints the generated code in the given form (requires all analyses).
+    #[structopt(
+        long = "emit",
+        possible_values = &["ir", "ssa"],
+        requires_all = &["lex", "parse", "validate"]
+    )]
+    emit: Option<String>,
+
+    /// Optimizes the generated code at the given level, from none at 0 to every pass at 2.
+    #[structopt(
+        short = "O",
+        default_value = "1",
+        possible_values = &["0", "1", "2"],
+        hide_default_value = true
+    )]
+    level: u8,
+
+    /// Runs the given optimization passes in order instead of those of the level.
+    #[structopt(long = "passes", use_delimiter = true, possible_values = Pass::NAMES)]
+    passes: Option<Vec<Pass>>,
+
+    /// Prints the generated code to stderr after every pass which changes it.
+    #[structopt(long = "dump-passes")]
+    dump_passes: bool,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Validates a program and runs its `main` function.
+    Run {
+        /// The program to run.
+        file: String,
+    },
+    /// Reads declarations and expressions line by line, printing the value of every expression.
+    Repl,
 }
 
 fn main() {
     let opt = Opt::from_args();
 
-    let mut file = match File::open(opt.file) {
+    match &opt.command {
+        Some(Command::Run { file }) => {
+            let source = read_source(file);
+            let program = validate(&link(file, &parse(&lex(&source))));
+            run(&program);
+        }
+        Some(Command::Repl) => repl(),
+        None => match &opt.file {
+            Some(file) => compile(&opt, file),
+            None => clap::Error::with_description(
+                "a file to compile or a subcommand is required",
+                clap::ErrorKind::MissingRequiredArgument,
+            )
+            .exit(),
+        },
+    }
+}
+
+fn read_source(path: &str) -> String {
+    let mut file = match File::open(path) {
         Ok(file) => file,
         Err(e) => {
             println!("{} {}", "Failed to open given file.".red(), e);
@@ -55,6 +113,12 @@ fn main() {
         std::process::exit(2);
     }
 
+    source
+}
+
+fn compile(opt: &Opt, path: &str) {
+    let source = read_source(path);
+
     let tokens = if opt.lex { Some(lex(&source)) } else { None };
 
     let program = if tokens.is_some() && opt.parse {
@@ -63,10 +127,10 @@ fn main() {
         None
     };
 
-    let validator = if program.is_some() && opt.validate {
-        Some(validate(&program.clone().unwrap()))
-    } else {
-        None
+    // the modules the program imports are loaded and linked in before validation
+    let validator = match &program {
+        Some(program) if opt.validate => Some(validate(&link(path, program))),
+        _ => None,
     };
 
     if opt.verbose {
@@ -88,6 +152,34 @@ fn main() {
         }
     }
 
+    if let (Some(program), Some(form)) = (&validator, &opt.emit) {
+        let passes = match &opt.passes {
+            Some(passes) => passes.clone(),
+            None => Pass::level(opt.level),
+        };
+        // the passes are run on SSA form, which is converted back when asked for plain code
+        let optimizing = !passes.is_empty();
+        let mut module = match (form.as_str(), optimizing) {
+            ("ir", false) => generate(program),
+            _ => generate_ssa(program),
+        };
+        let removals = optimize(&mut module, &PassManager::new(passes, opt.dump_passes));
+        if form == "ir" && optimizing {
+            module = destruct_ssa(module);
+        }
+
+        if opt.verbose {
+            println!("------------\n");
+            println!(
+                "{}\n{}",
+                "Dead code elimination:".bold().magenta(),
+                removals
+            );
+        }
+
+        print!("{}", module);
+    }
+
     if opt.save_structures {
         std::fs::write("debug/generated/tokens", format!("{:#?}", &tokens))
             .expect("failed to write tokens to file");
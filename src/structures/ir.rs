@@ -0,0 +1,644 @@
+use super::parse_tree::{BinaryOperator, Number, NumericType, Span};
+
+/// A virtual register, of which a function may use as many as it needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Register(pub usize);
+
+/// A basic block, referred to by its position in its function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Number(Number),
+    Boolean(bool),
+    Nil,
+    /// A named function used as a value.
+    Function(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Register(Register),
+    Constant(Constant),
+}
+
+/// What a call calls, either a function known by name or a closure held by an operand.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Callee {
+    Function(String),
+    Value(Operand),
+}
+
+/// The kind of value an `Aggregate` instruction builds from its elements.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Aggregate {
+    Tuple,
+    Struct(String),
+    Array,
+}
+
+/// An instruction computing at most one register from at most a few operands, which can't
+/// change the control flow.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Copy {
+        dest: Register,
+        value: Operand,
+    },
+    /// An arithmetic operation or a comparison, where arithmetic may fail at `span`.
+    Binary {
+        dest: Register,
+        operator: BinaryOperator,
+        left: Operand,
+        right: Operand,
+        span: Span,
+    },
+    /// A conversion of a number or a boolean to a numeric type.
+    Cast {
+        dest: Register,
+        value: Operand,
+        to: NumericType,
+    },
+    Aggregate {
+        dest: Register,
+        kind: Aggregate,
+        elements: Vec<Operand>,
+    },
+    Variant {
+        dest: Register,
+        enum_name: String,
+        variant: String,
+        payload: Option<Operand>,
+    },
+    /// A component of a tuple or a struct, by its position.
+    Extract {
+        dest: Register,
+        value: Operand,
+        index: usize,
+    },
+    /// The length of an array.
+    Length {
+        dest: Register,
+        value: Operand,
+    },
+    /// An element of an array, checked against its length unless the index is known to be in
+    /// bounds.
+    Index {
+        dest: Register,
+        value: Operand,
+        index: Operand,
+        bounds_check: bool,
+        span: Span,
+    },
+    /// Whether a value of an enum is the given variant.
+    IsVariant {
+        dest: Register,
+        value: Operand,
+        variant: String,
+    },
+    /// The payload of a value of an enum.
+    Payload {
+        dest: Register,
+        value: Operand,
+    },
+    /// A pointer to a new variable, which lives until the function returns.
+    Alloc {
+        dest: Register,
+    },
+    /// A pointer to a global variable.
+    Global {
+        dest: Register,
+        name: String,
+    },
+    /// A pointer to a component of the tuple or struct `pointer` points to.
+    FieldAddress {
+        dest: Register,
+        pointer: Operand,
+        index: usize,
+    },
+    /// A pointer to an element of the array `pointer` points to, checked against its length
+    /// unless the index is known to be in bounds.
+    ElementAddress {
+        dest: Register,
+        pointer: Operand,
+        index: Operand,
+        bounds_check: bool,
+        span: Span,
+    },
+    Load {
+        dest: Register,
+        pointer: Operand,
+    },
+    Store {
+        pointer: Operand,
+        value: Operand,
+    },
+    Call {
+        dest: Register,
+        callee: Callee,
+        arguments: Vec<Operand>,
+        span: Span,
+    },
+    /// A closure of a function whose first parameters are bound to `captures`.
+    Closure {
+        dest: Register,
+        function: String,
+        captures: Vec<Operand>,
+    },
+    /// The value coming in from the block control came from, only found at the start of
+    /// blocks in SSA form.
+    Phi {
+        dest: Register,
+        incoming: Vec<(BlockId, Operand)>,
+    },
+}
+
+/// How a basic block is left.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        condition: Operand,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    Return(Operand),
+    /// The end of a block control never reaches, like the end of an exhaustive match.
+    Unreachable,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+}
+
+/// A function in three-address code, whose first block is where it starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// Whether other modules may call the function.
+    pub public: bool,
+    /// Whether the function was asked to be inlined wherever it is called.
+    pub inline: bool,
+    pub parameters: Vec<Register>,
+    pub blocks: Vec<BasicBlock>,
+    /// The number of registers the function uses, which are numbered from zero.
+    pub registers: usize,
+}
+
+/// A whole program in three-address code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub globals: Vec<String>,
+    pub functions: Vec<Function>,
+}
+
+impl Module {
+    /// The function initialising the globals, which runs before `main`.
+    pub const INIT: &'static str = "$init";
+
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+}
+
+impl Instruction {
+    /// The register the instruction assigns to, if any.
+    pub fn dest(&self) -> Option<Register> {
+        match self {
+            Instruction::Copy { dest, .. }
+            | Instruction::Binary { dest, .. }
+            | Instruction::Cast { dest, .. }
+            | Instruction::Aggregate { dest, .. }
+            | Instruction::Variant { dest, .. }
+            | Instruction::Extract { dest, .. }
+            | Instruction::Length { dest, .. }
+            | Instruction::Index { dest, .. }
+            | Instruction::IsVariant { dest, .. }
+            | Instruction::Payload { dest, .. }
+            | Instruction::Alloc { dest }
+            | Instruction::Global { dest, .. }
+            | Instruction::FieldAddress { dest, .. }
+            | Instruction::ElementAddress { dest, .. }
+            | Instruction::Load { dest, .. }
+            | Instruction::Call { dest, .. }
+            | Instruction::Closure { dest, .. }
+            | Instruction::Phi { dest, .. } => Some(*dest),
+            Instruction::Store { .. } => None,
+        }
+    }
+
+    /// The operands the instruction reads.
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Instruction::Copy { value, .. }
+            | Instruction::Cast { value, .. }
+            | Instruction::Extract { value, .. }
+            | Instruction::Length { value, .. }
+            | Instruction::IsVariant { value, .. }
+            | Instruction::Payload { value, .. } => vec![value],
+            Instruction::Binary { left, right, .. } => vec![left, right],
+            Instruction::Aggregate { elements, .. } => elements.iter().collect(),
+            Instruction::Variant { payload, .. } => payload.iter().collect(),
+            Instruction::Index { value, index, .. } => vec![value, index],
+            Instruction::Alloc { .. } | Instruction::Global { .. } => vec![],
+            Instruction::FieldAddress { pointer, .. } | Instruction::Load { pointer, .. } => {
+                vec![pointer]
+            }
+            Instruction::ElementAddress { pointer, index, .. } => vec![pointer, index],
+            Instruction::Store { pointer, value } => vec![pointer, value],
+            Instruction::Call {
+                callee, arguments, ..
+            } => match callee {
+                Callee::Function(_) => arguments.iter().collect(),
+                Callee::Value(value) => std::iter::once(value).chain(arguments).collect(),
+            },
+            Instruction::Closure { captures, .. } => captures.iter().collect(),
+            Instruction::Phi { incoming, .. } => incoming.iter().map(|(_, value)| value).collect(),
+        }
+    }
+
+    /// The operands the instruction reads, to be changed.
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Instruction::Copy { value, .. }
+            | Instruction::Cast { value, .. }
+            | Instruction::Extract { value, .. }
+            | Instruction::Length { value, .. }
+            | Instruction::IsVariant { value, .. }
+            | Instruction::Payload { value, .. } => vec![value],
+            Instruction::Binary { left, right, .. } => vec![left, right],
+            Instruction::Aggregate { elements, .. } => elements.iter_mut().collect(),
+            Instruction::Variant { payload, .. } => payload.iter_mut().collect(),
+            Instruction::Index { value, index, .. } => vec![value, index],
+            Instruction::Alloc { .. } | Instruction::Global { .. } => vec![],
+            Instruction::FieldAddress { pointer, .. } | Instruction::Load { pointer, .. } => {
+                vec![pointer]
+            }
+            Instruction::ElementAddress { pointer, index, .. } => vec![pointer, index],
+            Instruction::Store { pointer, value } => vec![pointer, value],
+            Instruction::Call {
+                callee, arguments, ..
+            } => match callee {
+                Callee::Function(_) => arguments.iter_mut().collect(),
+                Callee::Value(value) => std::iter::once(value).chain(arguments).collect(),
+            },
+            Instruction::Closure { captures, .. } => captures.iter_mut().collect(),
+            Instruction::Phi { incoming, .. } => {
+                incoming.iter_mut().map(|(_, value)| value).collect()
+            }
+        }
+    }
+
+    pub fn dest_mut(&mut self) -> Option<&mut Register> {
+        match self {
+            Instruction::Copy { dest, .. }
+            | Instruction::Binary { dest, .. }
+            | Instruction::Cast { dest, .. }
+            | Instruction::Aggregate { dest, .. }
+            | Instruction::Variant { dest, .. }
+            | Instruction::Extract { dest, .. }
+            | Instruction::Length { dest, .. }
+            | Instruction::Index { dest, .. }
+            | Instruction::IsVariant { dest, .. }
+            | Instruction::Payload { dest, .. }
+            | Instruction::Alloc { dest }
+            | Instruction::Global { dest, .. }
+            | Instruction::FieldAddress { dest, .. }
+            | Instruction::ElementAddress { dest, .. }
+            | Instruction::Load { dest, .. }
+            | Instruction::Call { dest, .. }
+            | Instruction::Closure { dest, .. }
+            | Instruction::Phi { dest, .. } => Some(dest),
+            Instruction::Store { .. } => None,
+        }
+    }
+
+    pub fn is_phi(&self) -> bool {
+        matches!(self, Instruction::Phi { .. })
+    }
+
+    /// Whether running the instruction may do more than assign its register, by failing,
+    /// changing memory or calling a function. Arithmetic may overflow or divide by zero,
+    /// checked indices may be out of bounds and loads may go through dangling pointers.
+    pub fn has_side_effects(&self) -> bool {
+        match self {
+            Instruction::Binary { operator, .. } => !operator.is_comparison(),
+            Instruction::Index { bounds_check, .. }
+            | Instruction::ElementAddress { bounds_check, .. } => *bounds_check,
+            Instruction::Load { .. } | Instruction::Store { .. } | Instruction::Call { .. } => true,
+            Instruction::Copy { .. }
+            | Instruction::Cast { .. }
+            | Instruction::Aggregate { .. }
+            | Instruction::Variant { .. }
+            | Instruction::Extract { .. }
+            | Instruction::Length { .. }
+            | Instruction::IsVariant { .. }
+            | Instruction::Payload { .. }
+            | Instruction::Alloc { .. }
+            | Instruction::Global { .. }
+            | Instruction::FieldAddress { .. }
+            | Instruction::Closure { .. }
+            | Instruction::Phi { .. } => false,
+        }
+    }
+}
+
+impl Terminator {
+    /// The blocks control may continue with.
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![*then, *otherwise],
+            Terminator::Return(_) | Terminator::Unreachable => vec![],
+        }
+    }
+
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Terminator::Branch { condition, .. } => vec![condition],
+            Terminator::Return(value) => vec![value],
+            Terminator::Jump(_) | Terminator::Unreachable => vec![],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Terminator::Branch { condition, .. } => vec![condition],
+            Terminator::Return(value) => vec![value],
+            Terminator::Jump(_) | Terminator::Unreachable => vec![],
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![target],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![then, otherwise],
+            Terminator::Return(_) | Terminator::Unreachable => vec![],
+        }
+    }
+}
+
+impl Function {
+    /// The blocks control may reach each block from.
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![vec![]; self.blocks.len()];
+        for (id, block) in self.blocks.iter().enumerate() {
+            for successor in block.terminator.successors() {
+                predecessors[successor.0].push(BlockId(id));
+            }
+        }
+        predecessors
+    }
+
+    /// Whether control may reach each of the blocks from the start of the function.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut pending = vec![BlockId(0)];
+        while let Some(block) = pending.pop() {
+            if !std::mem::replace(&mut reachable[block.0], true) {
+                pending.extend(self.blocks[block.0].terminator.successors());
+            }
+        }
+        reachable
+    }
+
+    /// Removes the blocks control never reaches, renumbering the others and dropping the
+    /// values phis would get from removed blocks. Returns the number of removed blocks.
+    pub fn remove_unreachable_blocks(&mut self) -> usize {
+        let reachable = self.reachable();
+        let mut renumbered = vec![None; self.blocks.len()];
+        let mut count = 0;
+        for (id, &reachable) in reachable.iter().enumerate() {
+            if reachable {
+                renumbered[id] = Some(BlockId(count));
+                count += 1;
+            }
+        }
+        let removed = self.blocks.len() - count;
+        if removed == 0 {
+            return 0;
+        }
+
+        let blocks = std::mem::take(&mut self.blocks);
+        for (id, mut block) in blocks.into_iter().enumerate() {
+            if !reachable[id] {
+                continue;
+            }
+            for successor in block.terminator.successors_mut() {
+                *successor =
+                    renumbered[successor.0].expect("successors of reachable blocks are reachable");
+            }
+            for instruction in &mut block.instructions {
+                if let Instruction::Phi { incoming, .. } = instruction {
+                    incoming.retain(|(from, _)| reachable[from.0]);
+                    for (from, _) in incoming.iter_mut() {
+                        *from = renumbered[from.0].expect("kept blocks are renumbered");
+                    }
+                }
+            }
+            self.blocks.push(block);
+        }
+        removed
+    }
+}
+
+impl std::fmt::Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+impl std::fmt::Display for BlockId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl std::fmt::Display for Constant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constant::Number(number) => write!(f, "{}{}", number, number.datatype()),
+            Constant::Boolean(boolean) => write!(f, "{}", boolean),
+            Constant::Nil => write!(f, "nil"),
+            Constant::Function(name) => write!(f, "@{}", name),
+        }
+    }
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Register(register) => write!(f, "{}", register),
+            Operand::Constant(constant) => write!(f, "{}", constant),
+        }
+    }
+}
+
+fn list(operands: &[Operand]) -> String {
+    let operands = operands.iter().map(|operand| operand.to_string());
+    operands.collect::<Vec<_>>().join(", ")
+}
+
+/// Marks the accesses to elements of arrays whose index is known to be in bounds.
+fn unchecked(bounds_check: bool) -> &'static str {
+    match bounds_check {
+        true => "",
+        false => "unchecked ",
+    }
+}
+
+/// The mnemonic of a binary operator in the textual form of the IR.
+fn mnemonic(operator: BinaryOperator) -> &'static str {
+    match operator {
+        BinaryOperator::Add => "add",
+        BinaryOperator::Subtract => "sub",
+        BinaryOperator::Multiply => "mul",
+        BinaryOperator::Divide => "div",
+        BinaryOperator::LessThan => "lt",
+        BinaryOperator::GreaterThan => "gt",
+        BinaryOperator::LessThanOrEqual => "le",
+        BinaryOperator::GreaterThanOrEqual => "ge",
+        BinaryOperator::Equal => "eq",
+        BinaryOperator::NotEqual => "ne",
+    }
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(dest) = self.dest() {
+            write!(f, "{} = ", dest)?;
+        }
+
+        match self {
+            Instruction::Copy { value, .. } => write!(f, "copy {}", value),
+            Instruction::Binary {
+                operator,
+                left,
+                right,
+                ..
+            } => write!(f, "{} {}, {}", mnemonic(*operator), left, right),
+            Instruction::Cast { value, to, .. } => write!(f, "cast {} to {}", value, to),
+            Instruction::Aggregate { kind, elements, .. } => match kind {
+                Aggregate::Tuple => write!(f, "tuple ({})", list(elements)),
+                Aggregate::Struct(name) => write!(f, "struct {} {{ {} }}", name, list(elements)),
+                Aggregate::Array => write!(f, "array [{}]", list(elements)),
+            },
+            Instruction::Variant {
+                enum_name,
+                variant,
+                payload,
+                ..
+            } => match payload {
+                Some(payload) => write!(f, "variant {}.{}({})", enum_name, variant, payload),
+                None => write!(f, "variant {}.{}", enum_name, variant),
+            },
+            Instruction::Extract { value, index, .. } => write!(f, "extract {}, {}", value, index),
+            Instruction::Length { value, .. } => write!(f, "length {}", value),
+            Instruction::Index {
+                value,
+                index,
+                bounds_check,
+                ..
+            } => write!(f, "{}index {}, {}", unchecked(*bounds_check), value, index),
+            Instruction::IsVariant { value, variant, .. } => write!(f, "is {}, {}", value, variant),
+            Instruction::Payload { value, .. } => write!(f, "payload {}", value),
+            Instruction::Alloc { .. } => write!(f, "alloc"),
+            Instruction::Global { name, .. } => write!(f, "global @{}", name),
+            Instruction::FieldAddress { pointer, index, .. } => {
+                write!(f, "field {}, {}", pointer, index)
+            }
+            Instruction::ElementAddress {
+                pointer,
+                index,
+                bounds_check,
+                ..
+            } => write!(
+                f,
+                "{}element {}, {}",
+                unchecked(*bounds_check),
+                pointer,
+                index
+            ),
+            Instruction::Load { pointer, .. } => write!(f, "load {}", pointer),
+            Instruction::Store { pointer, value } => write!(f, "store {}, {}", pointer, value),
+            Instruction::Call {
+                callee, arguments, ..
+            } => match callee {
+                Callee::Function(name) => write!(f, "call @{}({})", name, list(arguments)),
+                Callee::Value(value) => write!(f, "call {}({})", value, list(arguments)),
+            },
+            Instruction::Closure {
+                function, captures, ..
+            } => write!(f, "closure @{} [{}]", function, list(captures)),
+            Instruction::Phi { incoming, .. } => {
+                let incoming = incoming
+                    .iter()
+                    .map(|(block, value)| format!("[{}: {}]", block, value));
+                write!(f, "phi {}", incoming.collect::<Vec<_>>().join(", "))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Terminator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Terminator::Jump(target) => write!(f, "jmp {}", target),
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => write!(f, "br {}, {}, {}", condition, then, otherwise),
+            Terminator::Return(value) => write!(f, "ret {}", value),
+            Terminator::Unreachable => write!(f, "unreachable"),
+        }
+    }
+}
+
+impl std::fmt::Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parameters = self.parameters.iter().map(|register| register.to_string());
+        let visibility = if self.public { "pub " } else { "" };
+        let inline = if self.inline { "inline " } else { "" };
+        writeln!(
+            f,
+            "{}{}fn @{}({}) {{",
+            visibility,
+            inline,
+            self.name,
+            parameters.collect::<Vec<_>>().join(", ")
+        )?;
+
+        for (id, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "{}:", BlockId(id))?;
+            for instruction in &block.instructions {
+                writeln!(f, "    {}", instruction)?;
+            }
+            writeln!(f, "    {}", block.terminator)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl std::fmt::Display for Module {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for global in &self.globals {
+            writeln!(f, "global @{}", global)?;
+        }
+
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 || !self.globals.is_empty() {
+                writeln!(f)?;
+            }
+            writeln!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
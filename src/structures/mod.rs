@@ -1,2 +1,3 @@
+pub mod ir;
 pub mod parse_tree;
 pub mod tokens;
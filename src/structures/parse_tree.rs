@@ -1,14 +1,154 @@
-use crate::analysis::semantic::parser::Visitor;
+use crate::analysis::semantic::{error::SemanticError, parser::Visitor};
 
 pub trait AstNode {
-    fn accept(&self, visitor: &mut dyn Visitor);
+    fn accept(&self, visitor: &mut dyn Visitor) -> Result<(), SemanticError>;
 }
 
-#[derive(Debug, Clone)]
+/// The position in the source code a token starts at, kept on the nodes that may fail at
+/// runtime or be found wrong by the validator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumericType {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    ISize,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    USize,
+    F32,
+    F64,
+}
+
+impl NumericType {
+    pub const ALL: [NumericType; 14] = [
+        NumericType::I8,
+        NumericType::I16,
+        NumericType::I32,
+        NumericType::I64,
+        NumericType::I128,
+        NumericType::ISize,
+        NumericType::U8,
+        NumericType::U16,
+        NumericType::U32,
+        NumericType::U64,
+        NumericType::U128,
+        NumericType::USize,
+        NumericType::F32,
+        NumericType::F64,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|numeric| numeric.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NumericType::I8 => "i8",
+            NumericType::I16 => "i16",
+            NumericType::I32 => "i32",
+            NumericType::I64 => "i64",
+            NumericType::I128 => "i128",
+            NumericType::ISize => "isize",
+            NumericType::U8 => "u8",
+            NumericType::U16 => "u16",
+            NumericType::U32 => "u32",
+            NumericType::U64 => "u64",
+            NumericType::U128 => "u128",
+            NumericType::USize => "usize",
+            NumericType::F32 => "f32",
+            NumericType::F64 => "f64",
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        match self {
+            NumericType::I8 | NumericType::U8 => 8,
+            NumericType::I16 | NumericType::U16 => 16,
+            NumericType::I32 | NumericType::U32 | NumericType::F32 => 32,
+            NumericType::I64 | NumericType::U64 | NumericType::F64 => 64,
+            NumericType::ISize | NumericType::USize => usize::BITS,
+            NumericType::I128 | NumericType::U128 => 128,
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, NumericType::F32 | NumericType::F64)
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            NumericType::I8
+                | NumericType::I16
+                | NumericType::I32
+                | NumericType::I64
+                | NumericType::I128
+                | NumericType::ISize
+        )
+    }
+}
+
+impl std::fmt::Display for NumericType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A component of a tuple type, optionally tagged with a name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TupleField {
+    pub name: Option<String>,
+    pub datatype: Type,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
-    Number,
-    Int,
+    /// Any of the fixed-width numeric types, `number` and `int` being `f64` and `i64`.
+    Numeric(NumericType),
+    Bool,
     Nil,
+    Tuple(Vec<TupleField>),
+    /// Either a value of the wrapped type or `nil`.
+    Optional(Box<Type>),
+    /// An array `[N]T` of fixed length, or a slice `[]T` if its length isn't known statically.
+    Array {
+        element: Box<Type>,
+        length: Option<usize>,
+    },
+    /// A pointer `*T`, or `*const T` if what it points to can't be changed through it.
+    Pointer {
+        pointee: Box<Type>,
+        mutable: bool,
+    },
+    /// A struct, referred to by the name it was declared with.
+    Struct(String),
+    /// An enum, referred to by the name it was declared with.
+    Enum(String),
+    /// A type referred to by name, resolved by the validator.
+    Named(String),
+    /// The type created by calling a type factory, like `Maybe(int)`.
+    Instance(Box<FactoryCall>),
+    /// The type of types, which only exist at compile time.
+    Type,
+    /// A parameter whose argument must be known at compile time.
+    Comptime(Box<Type>),
     FuncType {
         parameters: Vec<Param>,
         return_type: Box<Type>,
@@ -16,22 +156,197 @@ pub enum Type {
 }
 
 impl AstNode for Type {
-    fn accept(&self, visitor: &mut dyn Visitor) {
-        visitor.visit_type(self);
+    fn accept(&self, visitor: &mut dyn Visitor) -> Result<(), SemanticError> {
+        visitor.visit_type(self).map(|_| ())
     }
 }
 
-#[derive(Debug, Clone)]
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Numeric(numeric) => write!(f, "{}", numeric),
+            Type::Bool => write!(f, "bool"),
+            Type::Nil => write!(f, "nil"),
+            Type::Optional(datatype) => write!(f, "{}?", datatype),
+            Type::Array { element, length } => match length {
+                Some(length) => write!(f, "[{}]{}", length, element),
+                None => write!(f, "[]{}", element),
+            },
+            Type::Pointer { pointee, mutable } => match mutable {
+                true => write!(f, "*{}", pointee),
+                false => write!(f, "*const {}", pointee),
+            },
+            Type::Struct(name) | Type::Enum(name) | Type::Named(name) => write!(f, "{}", name),
+            Type::Instance(call) => {
+                let FactoryCall { factory, arguments } = &**call;
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| argument.to_string())
+                    .collect::<Vec<_>>();
+                write!(f, "{}({})", factory, arguments.join(", "))
+            }
+            Type::Type => write!(f, "type"),
+            Type::Comptime(datatype) => write!(f, "comptime {}", datatype),
+            Type::Tuple(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|field| match &field.name {
+                        Some(name) => format!("{name}: {}", field.datatype),
+                        None => field.datatype.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                write!(f, "({})", fields.join(", "))
+            }
+            Type::FuncType {
+                parameters,
+                return_type,
+            } => {
+                let parameters = parameters
+                    .iter()
+                    .map(|Param::Parameter { name, datatype }| format!("{name}: {datatype}"))
+                    .collect::<Vec<_>>();
+                write!(f, "({}) -> {}", parameters.join(", "), return_type)
+            }
+        }
+    }
+}
+
+/// A type factory along with the types it is called with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FactoryCall {
+    pub factory: String,
+    pub arguments: Vec<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Param {
     Parameter { name: String, datatype: Type },
 }
 
 impl AstNode for Param {
-    fn accept(&self, visitor: &mut dyn Visitor) {
-        visitor.visit_param(self);
+    fn accept(&self, visitor: &mut dyn Visitor) -> Result<(), SemanticError> {
+        visitor.visit_param(self).map(|_| ())
+    }
+}
+
+/// A variable from an enclosing scope used by a closure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    pub name: String,
+    pub by_reference: bool,
+}
+
+/// A component of a tuple literal, optionally tagged with a name.
+#[derive(Debug, Clone)]
+pub struct TupleElement {
+    pub name: Option<String>,
+    pub value: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructField {
+    pub name: String,
+    pub datatype: Type,
+}
+
+/// What a name bound with `const Name = ...;` stands for: a new struct or enum type, or an
+/// alias of another type.
+#[derive(Debug, Clone)]
+pub enum TypeDefinition {
+    Struct {
+        fields: Vec<StructField>,
+        methods: Vec<Statement>,
+    },
+    /// An enum may have methods just like a struct.
+    Enum {
+        variants: Vec<Variant>,
+        methods: Vec<Statement>,
+    },
+    Alias(Type),
+}
+
+/// A variant of an enum, carrying a value of the payload type if it has one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub name: String,
+    pub payload: Option<Type>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard,
+    Binding(String),
+    Literal(Expression),
+    /// A variant like `Shape.Circle(radius)`, or `.Circle(radius)` with the enum left implicit.
+    Variant {
+        enum_name: Option<String>,
+        variant: String,
+        payload: Option<Box<Pattern>>,
+    },
+    /// A tuple like `(w, h)`, matching each component with a pattern of its own, as in the
+    /// payload of `Shape.Rect(w, h)`.
+    Tuple(Vec<Pattern>),
+}
+
+impl Pattern {
+    /// Whether the pattern matches any value.
+    pub fn is_irrefutable(&self) -> bool {
+        match self {
+            Pattern::Wildcard | Pattern::Binding(_) => true,
+            Pattern::Tuple(elements) => elements.iter().all(Pattern::is_irrefutable),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Binding(name) => write!(f, "{}", name),
+            Pattern::Literal(Expression::Number(number)) => write!(f, "{}", number),
+            Pattern::Literal(Expression::NumberLiteral(literal)) => write!(f, "{}", literal),
+            Pattern::Literal(Expression::Boolean(boolean)) => write!(f, "{}", boolean),
+            Pattern::Literal(_) => write!(f, "nil"),
+            Pattern::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                write!(f, "{}.{}", enum_name.as_deref().unwrap_or(""), variant)?;
+                match payload.as_deref() {
+                    // the components of a payload go in the parentheses of the variant
+                    Some(payload @ Pattern::Tuple(elements)) if elements.len() > 1 => {
+                        write!(f, "{}", payload)
+                    }
+                    Some(payload) => write!(f, "({})", payload),
+                    None => Ok(()),
+                }
+            }
+            Pattern::Tuple(elements) => {
+                let elements: Vec<_> = elements.iter().map(Pattern::to_string).collect();
+                match elements.len() {
+                    1 => write!(f, "({},)", elements[0]),
+                    _ => write!(f, "({})", elements.join(", ")),
+                }
+            }
+        }
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub value: Expression,
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub value: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub struct If {
     pub if_block: ConditionalBlock,
@@ -40,8 +355,8 @@ pub struct If {
 }
 
 impl AstNode for If {
-    fn accept(&self, visitor: &mut dyn Visitor) {
-        visitor.visit_if(self);
+    fn accept(&self, visitor: &mut dyn Visitor) -> Result<(), SemanticError> {
+        visitor.visit_if(self).map(|_| ())
     }
 }
 
@@ -52,8 +367,8 @@ pub struct ConditionalBlock {
 }
 
 impl AstNode for ConditionalBlock {
-    fn accept(&self, visitor: &mut dyn Visitor) {
-        visitor.visit_conditional_block(self);
+    fn accept(&self, visitor: &mut dyn Visitor) -> Result<(), SemanticError> {
+        visitor.visit_conditional_block(self).map(|_| ())
     }
 }
 
@@ -64,12 +379,12 @@ pub struct Block {
 }
 
 impl AstNode for Block {
-    fn accept(&self, visitor: &mut dyn Visitor) {
-        visitor.visit_block(self);
+    fn accept(&self, visitor: &mut dyn Visitor) -> Result<(), SemanticError> {
+        visitor.visit_block(self).map(|_| ())
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Signed {
     Int8(i8),
     Int16(i16),
@@ -79,7 +394,7 @@ pub enum Signed {
     ISize(isize),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Unsigned {
     UInt8(u8),
     UInt16(u16),
@@ -89,26 +404,76 @@ pub enum Unsigned {
     USize(usize),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Integer {
     Signed(Signed),
     Unsigned(Unsigned),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Floating {
     Float(f32),
     Double(f64),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Number {
     Int(Integer),
     Float(Floating),
 }
 
+impl Number {
+    pub fn datatype(&self) -> NumericType {
+        match self {
+            Number::Int(Integer::Signed(signed)) => match signed {
+                Signed::Int8(_) => NumericType::I8,
+                Signed::Int16(_) => NumericType::I16,
+                Signed::Int32(_) => NumericType::I32,
+                Signed::Int64(_) => NumericType::I64,
+                Signed::Int128(_) => NumericType::I128,
+                Signed::ISize(_) => NumericType::ISize,
+            },
+            Number::Int(Integer::Unsigned(unsigned)) => match unsigned {
+                Unsigned::UInt8(_) => NumericType::U8,
+                Unsigned::UInt16(_) => NumericType::U16,
+                Unsigned::UInt32(_) => NumericType::U32,
+                Unsigned::UInt64(_) => NumericType::U64,
+                Unsigned::UInt128(_) => NumericType::U128,
+                Unsigned::USize(_) => NumericType::USize,
+            },
+            Number::Float(Floating::Float(_)) => NumericType::F32,
+            Number::Float(Floating::Double(_)) => NumericType::F64,
+        }
+    }
+}
+
+impl std::fmt::Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Int(Integer::Signed(signed)) => match signed {
+                Signed::Int8(v) => write!(f, "{}", v),
+                Signed::Int16(v) => write!(f, "{}", v),
+                Signed::Int32(v) => write!(f, "{}", v),
+                Signed::Int64(v) => write!(f, "{}", v),
+                Signed::Int128(v) => write!(f, "{}", v),
+                Signed::ISize(v) => write!(f, "{}", v),
+            },
+            Number::Int(Integer::Unsigned(unsigned)) => match unsigned {
+                Unsigned::UInt8(v) => write!(f, "{}", v),
+                Unsigned::UInt16(v) => write!(f, "{}", v),
+                Unsigned::UInt32(v) => write!(f, "{}", v),
+                Unsigned::UInt64(v) => write!(f, "{}", v),
+                Unsigned::UInt128(v) => write!(f, "{}", v),
+                Unsigned::USize(v) => write!(f, "{}", v),
+            },
+            Number::Float(Floating::Float(v)) => write!(f, "{:?}", v),
+            Number::Float(Floating::Double(v)) => write!(f, "{:?}", v),
+        }
+    }
+}
+
 // impl AstNode for Number {
-//     fn accept(&self, visitor: &mut dyn Visitor) {
+//     fn accept(&self, visitor: &mut dyn Visitor) -> Result<(), SemanticError> {
 //         visitor.visit_number(self);
 //     }
 // }
@@ -117,22 +482,30 @@ pub enum Number {
 pub enum Expression {
     NumberLiteral(String),
     Number(Number),
-    Identifier(String),
+    Boolean(bool),
+    Identifier {
+        name: String,
+        span: Span,
+    },
     Addition {
         augend: Box<Expression>,
         addend: Box<Expression>,
+        span: Span,
     },
     Subtraction {
         minuend: Box<Expression>,
         subtrahend: Box<Expression>,
+        span: Span,
     },
     Multiplication {
         multiplicant: Box<Expression>,
         multiplier: Box<Expression>,
+        span: Span,
     },
     Division {
         dividend: Box<Expression>,
         divisor: Box<Expression>,
+        span: Span,
     },
     LessThan {
         left: Box<Expression>,
@@ -158,50 +531,309 @@ pub enum Expression {
         left: Box<Expression>,
         right: Box<Expression>,
     },
+    Cast {
+        value: Box<Expression>,
+        datatype: Type,
+    },
+    /// The value of an optional, or `fallback` if it is `nil`, written `value ! fallback`.
+    Default {
+        value: Box<Expression>,
+        fallback: Box<Expression>,
+    },
+    Tuple(Vec<TupleElement>),
+    Array(Vec<Expression>),
+    Index {
+        value: Box<Expression>,
+        index: Box<Expression>,
+        /// Whether the index has to be checked against the length at runtime, which the
+        /// validator rules out for constant indices into arrays of known length.
+        bounds_check: bool,
+        span: Span,
+    },
+    /// The address of a variable or an element of one, written `&place`.
+    AddressOf(Box<Expression>),
+    /// The value a pointer points to, written `pointer.*`.
+    Deref(Box<Expression>),
+    /// Access of a named field, or of a positional one like `pair.0`.
+    FieldAccess {
+        value: Box<Expression>,
+        field: String,
+    },
+    /// A struct literal like `.{ name, age = 0 }`, named after its struct once validated.
+    StructLiteral {
+        name: Option<String>,
+        fields: Vec<TupleElement>,
+    },
+    /// A call like `pet.rename(name)`, lowered to a plain call by the validator.
+    MethodCall {
+        receiver: Box<Expression>,
+        method: String,
+        arguments: Vec<Expression>,
+        span: Span,
+    },
     FunctionCall {
         name: String,
         arguments: Vec<Expression>,
+        span: Span,
+        /// Whether the call is a function calling itself for the value it returns, marked by
+        /// the validator so that it is run as a jump back to the start of the function.
+        tail: bool,
     },
     Block {
         statements: Vec<Statement>,
         return_value: Box<Expression>,
     },
+    Function {
+        parameters: Vec<Param>,
+        return_type: Box<Type>,
+        body: Block,
+        /// Whether mutable variables are captured by reference rather than by value.
+        by_reference: bool,
+        /// Filled in by the validator.
+        captures: Vec<Capture>,
+    },
     If(If),
+    /// A value of an enum, like `Shape.Circle(1.5)`.
+    Variant {
+        enum_name: String,
+        variant: String,
+        payload: Option<Box<Expression>>,
+    },
+    Match(Match),
+    /// A block evaluated during compilation, written `comptime { ... }`.
+    Comptime(Block),
+    /// A type used as a value, which is what type factories return.
+    Type(Box<TypeDefinition>),
     Nil,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    LessThan,
+    GreaterThan,
+    LessThanOrEqual,
+    GreaterThanOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl BinaryOperator {
+    pub fn is_comparison(&self) -> bool {
+        !matches!(
+            self,
+            BinaryOperator::Add
+                | BinaryOperator::Subtract
+                | BinaryOperator::Multiply
+                | BinaryOperator::Divide
+        )
+    }
+}
+
+impl std::fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::LessThanOrEqual => "<=",
+            BinaryOperator::GreaterThanOrEqual => ">=",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl Expression {
+    /// Builds the binary expression corresponding to `operator`, where `span` is only kept by
+    /// the arithmetic operations which may fail.
+    pub fn binary(
+        operator: BinaryOperator,
+        left: Expression,
+        right: Expression,
+        span: Span,
+    ) -> Expression {
+        let (left, right) = (Box::new(left), Box::new(right));
+        match operator {
+            BinaryOperator::Add => Expression::Addition {
+                augend: left,
+                addend: right,
+                span,
+            },
+            BinaryOperator::Subtract => Expression::Subtraction {
+                minuend: left,
+                subtrahend: right,
+                span,
+            },
+            BinaryOperator::Multiply => Expression::Multiplication {
+                multiplicant: left,
+                multiplier: right,
+                span,
+            },
+            BinaryOperator::Divide => Expression::Division {
+                dividend: left,
+                divisor: right,
+                span,
+            },
+            BinaryOperator::LessThan => Expression::LessThan { left, right },
+            BinaryOperator::GreaterThan => Expression::GreaterThan { left, right },
+            BinaryOperator::LessThanOrEqual => Expression::LessThanOrEqual { left, right },
+            BinaryOperator::GreaterThanOrEqual => Expression::GreaterThanOrEqual { left, right },
+            BinaryOperator::Equal => Expression::Equal { left, right },
+            BinaryOperator::NotEqual => Expression::NotEqual { left, right },
+        }
+    }
+
+    /// Splits a binary expression into its operator and operands.
+    pub fn as_binary(&self) -> Option<(BinaryOperator, &Expression, &Expression)> {
+        match self {
+            Expression::Addition { augend, addend, .. } => {
+                Some((BinaryOperator::Add, augend, addend))
+            }
+            Expression::Subtraction {
+                minuend,
+                subtrahend,
+                ..
+            } => Some((BinaryOperator::Subtract, minuend, subtrahend)),
+            Expression::Multiplication {
+                multiplicant,
+                multiplier,
+                ..
+            } => Some((BinaryOperator::Multiply, multiplicant, multiplier)),
+            Expression::Division {
+                dividend, divisor, ..
+            } => Some((BinaryOperator::Divide, dividend, divisor)),
+            Expression::LessThan { left, right } => Some((BinaryOperator::LessThan, left, right)),
+            Expression::GreaterThan { left, right } => {
+                Some((BinaryOperator::GreaterThan, left, right))
+            }
+            Expression::LessThanOrEqual { left, right } => {
+                Some((BinaryOperator::LessThanOrEqual, left, right))
+            }
+            Expression::GreaterThanOrEqual { left, right } => {
+                Some((BinaryOperator::GreaterThanOrEqual, left, right))
+            }
+            Expression::Equal { left, right } => Some((BinaryOperator::Equal, left, right)),
+            Expression::NotEqual { left, right } => Some((BinaryOperator::NotEqual, left, right)),
+            _ => None,
+        }
+    }
+
+    /// Where the expression is in the source code, if it is one that keeps track of that.
+    /// Expressions made of another one are where that one is.
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Identifier { span, .. }
+            | Expression::Addition { span, .. }
+            | Expression::Subtraction { span, .. }
+            | Expression::Multiplication { span, .. }
+            | Expression::Division { span, .. }
+            | Expression::Index { span, .. }
+            | Expression::MethodCall { span, .. }
+            | Expression::FunctionCall { span, .. }
+            | Expression::Match(Match { span, .. }) => *span,
+            Expression::Cast { value, .. }
+            | Expression::Default { value, .. }
+            | Expression::FieldAccess { value, .. }
+            | Expression::AddressOf(value)
+            | Expression::Deref(value)
+            | Expression::Block {
+                return_value: value,
+                ..
+            } => value.span(),
+            Expression::If(if_expression) => if_expression.if_block.block.return_value.span(),
+            Expression::Comptime(block) => block.return_value.span(),
+            _ => Span::default(),
+        }
+    }
+
+    /// Whether the expression is a value known at compile time.
+    pub fn is_constant(&self) -> bool {
+        match self {
+            Expression::Number(_) | Expression::Boolean(_) | Expression::Nil => true,
+            Expression::Tuple(elements)
+            | Expression::StructLiteral {
+                fields: elements, ..
+            } => elements.iter().all(|element| element.value.is_constant()),
+            Expression::Array(elements) => elements.iter().all(|element| element.is_constant()),
+            Expression::Variant { payload, .. } => {
+                payload.as_ref().is_none_or(|payload| payload.is_constant())
+            }
+            _ => false,
+        }
+    }
+}
+
 impl AstNode for Expression {
-    fn accept(&self, visitor: &mut dyn Visitor) {
-        visitor.visit_expression(self);
+    fn accept(&self, visitor: &mut dyn Visitor) -> Result<(), SemanticError> {
+        visitor.visit_expression(self).map(|_| ())
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum Statement {
     Declaration {
+        /// Whether other modules may use the variable, which only matters at the top level.
+        public: bool,
         mutable: bool,
         name: String,
         datatype: Type,
         value: Expression,
     },
+    /// Declaration of one variable per component of a tuple, as in `let (a, b): ... = ...;`.
+    Destructuring {
+        mutable: bool,
+        names: Vec<String>,
+        datatype: Type,
+        value: Expression,
+    },
+    /// Assignment to a place other than a variable, like `items[0] = item;`,
+    /// `pet.name = name;` or `pointer.* = value;`.
+    PlaceAssignment {
+        target: Expression,
+        value: Expression,
+    },
     ReAssignment {
         name: String,
         value: Expression,
+        span: Span,
     },
     FuncDeclaration {
         public: bool,
+        /// Whether the function was asked to be inlined wherever it is called.
+        inline: bool,
         name: String,
         parameters: Vec<Param>,
         return_type: Type,
         body: Block,
     },
+    /// Binding of a type to a name; methods of structs and enums are functions, taking `self`
+    /// or not.
+    TypeDeclaration {
+        public: bool,
+        name: String,
+        definition: TypeDefinition,
+    },
     ExpressionStatement(Expression),
     IfStatement(If),
+    /// An `import "path.hon";` of another module, accessed through `name`, which is the name
+    /// of the file unless given with `as name`. Resolved by the module loader.
+    Import {
+        path: String,
+        name: String,
+    },
 }
 
 impl AstNode for Statement {
-    fn accept(&self, visitor: &mut dyn Visitor) {
-        visitor.visit_statement(self);
+    fn accept(&self, visitor: &mut dyn Visitor) -> Result<(), SemanticError> {
+        visitor.visit_statement(self).map(|_| ())
     }
 }
 
@@ -213,7 +845,7 @@ pub struct Program {
 }
 
 impl AstNode for Program {
-    fn accept(&self, visitor: &mut dyn Visitor) {
-        visitor.visit_program(self);
+    fn accept(&self, visitor: &mut dyn Visitor) -> Result<(), SemanticError> {
+        visitor.visit_program(self).map(|_| ())
     }
 }
@@ -1,16 +1,32 @@
 use colored::Colorize;
 
+use super::parse_tree::NumericType;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Nil,
     Let,
     Const,
+    Capture,
+    Where,
     If,
     Else,
+    True,
+    False,
     NumberKeyword,
     IntKeyword,
+    BoolKeyword,
+    NumericKeyword(NumericType),
     PubKeyword,
+    Inline,
+    Import,
+    As,
     FnKeyword,
+    Struct,
+    Enum,
+    Match,
+    Comptime,
+    TypeKeyword,
     Assignment,
     Equal,
     NotEqual,
@@ -18,21 +34,28 @@ pub enum Token {
     GreaterEqual,
     Negate,
     Colon,
+    QuestionMark,
     SemiColon,
     Comma,
+    Dot,
     Plus,
     Minus,
     Asterisk,
+    Ampersand,
     Slash,
     Arrow,
+    FatArrow,
     OpenBrace,
     CloseBrace,
     OpenParen,
     CloseParen,
+    OpenBracket,
+    CloseBracket,
     OpenAngle,
     CloseAngle,
     Identifier(String),
     NumberLiteral(String),
+    StringLiteral(String),
 }
 
 impl std::fmt::Display for Token {
@@ -41,12 +64,28 @@ impl std::fmt::Display for Token {
             Token::Nil => write!(f, "keyword '{}'", "nil".bold()),
             Token::Let => write!(f, "keyword '{}'", "let".bold()),
             Token::Const => write!(f, "keyword '{}'", "const".bold()),
+            Token::Capture => write!(f, "keyword '{}'", "capture".bold()),
+            Token::Where => write!(f, "keyword '{}'", "where".bold()),
             Token::If => write!(f, "keyword '{}'", "if".bold()),
             Token::Else => write!(f, "keyword '{}'", "else".bold()),
+            Token::True => write!(f, "keyword '{}'", "true".bold()),
+            Token::False => write!(f, "keyword '{}'", "false".bold()),
             Token::NumberKeyword => write!(f, "keyword '{}'", "number".bold()),
             Token::IntKeyword => write!(f, "keyword '{}'", "int".bold()),
+            Token::BoolKeyword => write!(f, "keyword '{}'", "bool".bold()),
+            Token::NumericKeyword(numeric) => {
+                write!(f, "keyword '{}'", numeric.name().bold())
+            }
+            Token::As => write!(f, "keyword '{}'", "as".bold()),
             Token::PubKeyword => write!(f, "keyword '{}'", "pub".bold()),
+            Token::Inline => write!(f, "keyword '{}'", "inline".bold()),
+            Token::Import => write!(f, "keyword '{}'", "import".bold()),
             Token::FnKeyword => write!(f, "keyword '{}'", "fn".bold()),
+            Token::Struct => write!(f, "keyword '{}'", "struct".bold()),
+            Token::Enum => write!(f, "keyword '{}'", "enum".bold()),
+            Token::Match => write!(f, "keyword '{}'", "match".bold()),
+            Token::Comptime => write!(f, "keyword '{}'", "comptime".bold()),
+            Token::TypeKeyword => write!(f, "keyword '{}'", "type".bold()),
             Token::Assignment => write!(f, "assignment '{}'", "=".bold()),
             Token::Negate => write!(f, "negation '{}'", "!".bold()),
             Token::Equal => write!(f, "equal '{}'", "==".bold()),
@@ -54,22 +93,31 @@ impl std::fmt::Display for Token {
             Token::LessEqual => write!(f, "less-than-or-equal-to '{}'", "=".bold()),
             Token::GreaterEqual => write!(f, "greater-than-or-equal-to '{}'", "=".bold()),
             Token::Colon => write!(f, "colon '{}'", ":".bold()),
+            Token::QuestionMark => write!(f, "question mark '{}'", "?".bold()),
             Token::SemiColon => write!(f, "semi colon '{}'", ";".bold()),
             Token::Comma => write!(f, "comma '{}'", ",".bold()),
+            Token::Dot => write!(f, "dot '{}'", ".".bold()),
             Token::Plus => write!(f, "plus '{}'", "+".bold()),
             Token::Minus => write!(f, "minus '{}'", "-".bold()),
             Token::Asterisk => write!(f, "asterisk '{}'", "*".bold()),
+            Token::Ampersand => write!(f, "ampersand '{}'", "&".bold()),
             Token::Slash => write!(f, "slash '{}'", "/".bold()),
             Token::Arrow => write!(f, "arrow '{}'", "->".bold()),
+            Token::FatArrow => write!(f, "arrow '{}'", "=>".bold()),
             Token::OpenBrace => write!(f, "open brace '{}'", "{".bold()),
             Token::CloseBrace => write!(f, "closing brace '{}'", "}".bold()),
             Token::OpenParen => write!(f, "open parenthesis '{}'", "(".bold()),
             Token::CloseParen => write!(f, "closing parenthesis '{}'", ")".bold()),
+            Token::OpenBracket => write!(f, "open bracket '{}'", "[".bold()),
+            Token::CloseBracket => write!(f, "closing bracket '{}'", "]".bold()),
             Token::OpenAngle => write!(f, "open angle bracket / less than '{}'", "<".bold()),
             Token::CloseAngle => write!(f, "closing angle bracket / greater than '{}'", ">".bold()),
-            Token::Identifier(ident) => write!(f, "identifier '{}'", format!("{}", ident).bold()),
+            Token::Identifier(ident) => write!(f, "identifier '{}'", ident.bold()),
             Token::NumberLiteral(literal) => {
-                write!(f, "number '{}'", format!("{}", literal).bold())
+                write!(f, "number '{}'", literal.bold())
+            }
+            Token::StringLiteral(literal) => {
+                write!(f, "string \"{}\"", literal.bold())
             }
         }
     }